@@ -0,0 +1,265 @@
+// Benchmarks of the proxy CPU cost on the hot path.
+// The backends are in-memory so that only the proxy itself is measured.
+//
+// cargo bench --bench proxy_bench [filter]
+extern crate undermoon;
+
+#[path = "../tests/connection.rs"]
+mod connection;
+#[path = "../tests/redis_client.rs"]
+mod redis_client;
+
+use arc_swap::ArcSwap;
+use bytes::BytesMut;
+use connection::DummyOkConnFactory;
+use futures::channel::mpsc;
+use redis_client::DummyClientFactory;
+use std::env;
+use std::hint::black_box;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use undermoon::common::batch::BatchStrategy;
+use undermoon::common::proto::SET_CLUSTER_API_VERSION;
+use undermoon::common::response::{ERR_BACKEND_CONNECTION, ERR_MAINTENANCE};
+use undermoon::common::track::TrackedFutureRegistry;
+use undermoon::common::utils::{HashTagDelimiters, SocketBufferSizes};
+use undermoon::protocol::{encode_resp, Array, BulkStr, DecodedPacket, Resp, RespPacket, RespVec};
+use undermoon::proxy::command::{new_command_pair, Command};
+use undermoon::proxy::executor::ForwardHandler;
+use undermoon::proxy::manager::MetaMap;
+use undermoon::proxy::rename::CommandRenames;
+use undermoon::proxy::service::{
+    ClusterNodesVersion, SelectPolicy, ServerProxyConfig, UnknownCommandPolicy,
+};
+use undermoon::proxy::session::{AuthState, ClientInfo, CmdCtx, CmdCtxHandler};
+use undermoon::proxy::slowlog::SlowRequestLogger;
+use undermoon::proxy::stats::{DbConnCounter, ProxyStats};
+
+type BenchForwardHandler = ForwardHandler<DummyClientFactory, DummyOkConnFactory>;
+
+const PIPELINE_SIZE: usize = 16;
+
+fn gen_config() -> ServerProxyConfig {
+    ServerProxyConfig {
+        address: "127.0.0.1:5299".to_string(),
+        extra_listen_addresses: vec![],
+        listen_backlog: NonZeroU32::new(1024).unwrap(),
+        listen_reuseport: false,
+        client_socket_buffer: SocketBufferSizes::default(),
+        announce_address: "127.0.0.1:5299".to_string(),
+        announce_host: "127.0.0.1".to_string(),
+        slowlog_len: NonZeroUsize::new(1024).unwrap(),
+        slowlog_log_slower_than: AtomicI64::new(-1),
+        slowlog_sample_rate: AtomicU64::new(1),
+        thread_number: NonZeroUsize::new(1).unwrap(),
+        backend_conn_num: NonZeroUsize::new(1).unwrap(),
+        backend_socket_buffer: SocketBufferSizes::default(),
+        active_redirection: false,
+        max_redirections: None,
+        default_redirection_address: None,
+        backend_batch_strategy: BatchStrategy::Disabled,
+        backend_flush_size: NonZeroUsize::new(1024).unwrap(),
+        backend_low_flush_interval: Duration::from_nanos(200_000),
+        backend_high_flush_interval: Duration::from_nanos(800_000),
+        backend_timeout: Duration::from_secs(3),
+        password: None,
+        auth_admin_commands: false,
+        admin_password: None,
+        command_cluster_nodes_version: ClusterNodesVersion::V2,
+        proto_max_bulk_len: 512 * 1024 * 1024,
+        proto_max_multibulk_len: 1024 * 1024,
+        proto_max_inline_len: 64 * 1024,
+        session_idle_timeout: None,
+        client_output_buffer_hard_limit: 0,
+        client_output_buffer_soft_limit: 0,
+        client_output_buffer_soft_seconds: 0,
+        reply_stream_threshold: 64 * 1024,
+        init_meta_file: None,
+        umctl_max_meta_args: 100_000,
+        umctl_max_meta_bytes: 64 * 1024 * 1024,
+        unknown_command_policy: UnknownCommandPolicy::Forward,
+        select_policy: SelectPolicy::IndexOnly,
+        max_connections_per_db: AtomicUsize::new(0),
+        maxclients: AtomicUsize::new(0),
+        expose_backend_address: AtomicBool::new(false),
+        backend_warmup: false,
+        cluster_reset_hard: false,
+        ping_requires_meta: false,
+        enable_debug: false,
+        redis_version: "7.0.0".to_string(),
+        maintenance_error: ERR_MAINTENANCE.to_string(),
+        rename_command: CommandRenames::default(),
+        command_timeout: AtomicU64::new(0),
+        hash_tag: HashTagDelimiters::default(),
+    }
+}
+
+fn always_ok(_: Vec<String>) -> RespVec {
+    Resp::Simple(b"OK".to_vec())
+}
+
+fn gen_forward_handler() -> BenchForwardHandler {
+    let handle_func = Arc::new(always_ok);
+    let config = Arc::new(gen_config());
+    let client_factory = Arc::new(DummyClientFactory::new(handle_func.clone()));
+    let conn_factory = Arc::new(DummyOkConnFactory::new(handle_func));
+    let meta_map = Arc::new(ArcSwap::new(Arc::new(MetaMap::empty())));
+    let future_registry = Arc::new(TrackedFutureRegistry::default());
+    let (stopped, _) = mpsc::unbounded();
+    ForwardHandler::new(
+        config.clone(),
+        client_factory,
+        Arc::new(SlowRequestLogger::new(config)),
+        meta_map,
+        conn_factory,
+        future_registry,
+        stopped,
+        Arc::new(ProxyStats::default()),
+        Arc::new(DbConnCounter::default()),
+    )
+}
+
+fn gen_cmd_resp(args: &str) -> RespVec {
+    Resp::Arr(Array::Arr(
+        args.split(' ')
+            .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+            .collect(),
+    ))
+}
+
+async fn send_cmd(
+    handler: &BenchForwardHandler,
+    auth_state: &AuthState,
+    client_info: &ClientInfo,
+    args: &str,
+) -> RespVec {
+    let command = Command::new(Box::new(RespPacket::Data(gen_cmd_resp(args))));
+    let (s, r) = new_command_pair(&command);
+    let cmd_ctx = CmdCtx::new(command, s, 233, true);
+    let (_, response, _) = handler
+        .handle_cmd_ctx(cmd_ctx, r, auth_state, client_info)
+        .await
+        .unwrap()
+        .into_inner();
+    response.into_resp_vec()
+}
+
+struct Bench {
+    filter: Option<String>,
+}
+
+impl Bench {
+    // Runs `f` for about one second and prints the average time of each iteration.
+    fn run<F: FnMut()>(&self, name: &str, mut f: F) {
+        if let Some(filter) = self.filter.as_ref() {
+            if !name.contains(filter.as_str()) {
+                return;
+            }
+        }
+
+        // Warm up and estimate the iteration number.
+        let mut iters: u64 = 1;
+        loop {
+            let start = Instant::now();
+            for _ in 0..iters {
+                f();
+            }
+            if start.elapsed() > Duration::from_millis(100) {
+                break;
+            }
+            iters *= 2;
+        }
+
+        iters *= 10;
+        let start = Instant::now();
+        for _ in 0..iters {
+            f();
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "{:<32} {:>12} ns/iter ({} iterations)",
+            name,
+            elapsed.as_nanos() / u128::from(iters),
+            iters
+        );
+    }
+}
+
+fn bench_resp(bench: &Bench) {
+    let cmd = gen_cmd_resp("SET somekey somevalue");
+    let mut encoded = vec![];
+    encode_resp(&mut encoded, &cmd).unwrap();
+    let pipelined = encoded.repeat(PIPELINE_SIZE);
+
+    bench.run("resp_decode_single", || {
+        let mut buf = BytesMut::from(encoded.as_slice());
+        black_box(RespPacket::decode(&mut buf, ()).unwrap().unwrap());
+    });
+    bench.run("resp_decode_pipeline", || {
+        let mut buf = BytesMut::from(pipelined.as_slice());
+        while let Some(packet) = RespPacket::decode(&mut buf, ()).unwrap() {
+            black_box(packet);
+        }
+    });
+    bench.run("resp_encode_single", || {
+        let mut buf = Vec::with_capacity(encoded.len());
+        black_box(encode_resp(&mut buf, &cmd).unwrap());
+    });
+    bench.run("resp_encode_pipeline", || {
+        let mut buf = Vec::with_capacity(pipelined.len());
+        for _ in 0..PIPELINE_SIZE {
+            encode_resp(&mut buf, &cmd).unwrap();
+        }
+        black_box(buf);
+    });
+}
+
+fn bench_routing(bench: &Bench) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let handler = gen_forward_handler();
+    let auth_state = AuthState::default();
+    let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+
+    runtime.block_on(async {
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        send_cmd(&handler, &auth_state, &client_info, &set_cluster).await;
+        // Wait for the backend connection.
+        loop {
+            match send_cmd(&handler, &auth_state, &client_info, "GET somekey").await {
+                Resp::Error(err) if err.starts_with(ERR_BACKEND_CONNECTION.as_bytes()) => {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                _ => break,
+            }
+        }
+    });
+
+    bench.run("routing_single", || {
+        runtime.block_on(async {
+            black_box(send_cmd(&handler, &auth_state, &client_info, "GET somekey").await);
+        });
+    });
+    bench.run("routing_pipeline", || {
+        runtime.block_on(async {
+            let futs = (0..PIPELINE_SIZE)
+                .map(|_| send_cmd(&handler, &auth_state, &client_info, "GET somekey"));
+            black_box(futures::future::join_all(futs).await);
+        });
+    });
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`.
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let bench = Bench { filter };
+    bench_resp(&bench);
+    bench_routing(&bench);
+}
@@ -61,6 +61,15 @@ fn gen_conf() -> CoordinatorConfig {
     let enable_compression = s.get::<bool>("enable_compression").unwrap_or(false);
     let disable_failover = s.get::<bool>("disable_failover").unwrap_or(false);
 
+    let min_ping_retry = s.get::<usize>("min_ping_retry").unwrap_or(2);
+    let max_ping_retry = s.get::<usize>("max_ping_retry").unwrap_or(5);
+    let heartbeat_interval = s.get::<u64>("heartbeat_interval").unwrap_or(0);
+    let isolate_detector_conn = s.get::<bool>("isolate_detector_conn").unwrap_or(true);
+    let detector_interval = s.get::<u64>("detector_interval").unwrap_or(1000);
+    let detector_stagger = s.get::<u64>("detector_stagger").unwrap_or(0);
+    let detector_jitter = s.get::<bool>("detector_jitter").unwrap_or(true);
+    let proxy_admin_password = s.get::<String>("proxy_admin_password").ok();
+
     CoordinatorConfig {
         address,
         broker_addresses: Arc::new(ArcSwap::new(Arc::new(broker_address_list))),
@@ -69,6 +78,14 @@ fn gen_conf() -> CoordinatorConfig {
         proxy_timeout,
         enable_compression,
         disable_failover,
+        min_ping_retry,
+        max_ping_retry,
+        heartbeat_interval,
+        isolate_detector_conn,
+        detector_interval,
+        detector_stagger,
+        detector_jitter,
+        proxy_admin_password,
     }
 }
 
@@ -88,9 +105,28 @@ fn gen_service(
 
     let timeout = Duration::new(config.proxy_timeout as u64, 0);
     let pool_size = 2;
-    let client_factory = PooledRedisClientFactory::new(pool_size, timeout);
-
-    CoordinatorService::new(config, data_broker, mani_broker, client_factory)
+    let password = config.proxy_admin_password.clone();
+    let client_factory =
+        PooledRedisClientFactory::new_with_password(pool_size, timeout, password.clone());
+    // Only PING is sent through it.
+    let detector_client_factory = if config.isolate_detector_conn {
+        Some(PooledRedisClientFactory::new_with_password(
+            1, timeout, password,
+        ))
+    } else {
+        None
+    };
+    // The backend nodes do not use the proxy admin password.
+    let backend_client_factory = PooledRedisClientFactory::new(pool_size, timeout);
+
+    CoordinatorService::new(
+        config,
+        data_broker,
+        mani_broker,
+        client_factory,
+        detector_client_factory,
+        backend_client_factory,
+    )
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
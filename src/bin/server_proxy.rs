@@ -9,22 +9,32 @@ extern crate env_logger;
 use arc_swap::ArcSwap;
 use futures::channel::mpsc;
 use std::cmp::min;
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
-use std::num::{NonZeroU64, NonZeroUsize};
-use std::sync::atomic::{AtomicI64, AtomicU64};
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize};
 use std::sync::Arc;
 use std::time::Duration;
 use string_error::into_err;
 use undermoon::common::batch::BatchStrategy;
+use undermoon::common::response::ERR_MAINTENANCE;
 use undermoon::common::track::TrackedFutureRegistry;
-use undermoon::common::utils::extract_host_from_address;
-use undermoon::protocol::SimpleRedisClientFactory;
+use undermoon::common::utils::{
+    extract_host_from_address, set_hash_tag_delimiters, HashTagDelimiters, SocketBufferSizes,
+};
+use undermoon::common::version::DEFAULT_REDIS_VERSION;
+use undermoon::protocol::{ProtocolLimits, SimpleRedisClientFactory};
 use undermoon::proxy::backend::DefaultConnFactory;
 use undermoon::proxy::executor::SharedForwardHandler;
 use undermoon::proxy::manager::MetaMap;
-use undermoon::proxy::service::{ClusterNodesVersion, ServerProxyConfig, ServerProxyService};
+use undermoon::proxy::rename::CommandRenames;
+use undermoon::proxy::service::{
+    ClusterNodesVersion, SelectPolicy, ServerProxyConfig, ServerProxyService, UnknownCommandPolicy,
+    DEFAULT_LISTEN_BACKLOG,
+};
 use undermoon::proxy::slowlog::SlowRequestLogger;
+use undermoon::proxy::stats::{DbConnCounter, ProxyStats};
 use undermoon::MAX_REDIRECTIONS;
 
 #[global_allocator]
@@ -46,6 +56,19 @@ fn gen_conf() -> Result<ServerProxyConfig, &'static str> {
     let address = s
         .get::<String>("address")
         .unwrap_or_else(|_| "127.0.0.1:5299".to_string());
+    let extra_listen_addresses = s
+        .get::<Vec<String>>("extra_listen_addresses")
+        .unwrap_or_default();
+    let listen_backlog = NonZeroU32::new(
+        s.get::<u32>("listen_backlog")
+            .unwrap_or(DEFAULT_LISTEN_BACKLOG),
+    )
+    .ok_or("listen_backlog")?;
+    let client_socket_buffer = SocketBufferSizes::new(
+        s.get::<u32>("client_send_buffer_size").unwrap_or(0),
+        s.get::<u32>("client_recv_buffer_size").unwrap_or(0),
+    )
+    .ok_or("client_send_buffer_size or client_recv_buffer_size")?;
     let announce_address = s
         .get::<String>("announce_address")
         .unwrap_or_else(|_| address.clone());
@@ -59,6 +82,11 @@ fn gen_conf() -> Result<ServerProxyConfig, &'static str> {
         NonZeroUsize::new(s.get::<usize>("thread_number").unwrap_or(2)).ok_or("thread_number")?;
     let backend_conn_num = NonZeroUsize::new(s.get::<usize>("backend_conn_num").unwrap_or(2))
         .ok_or("backend_conn_num")?;
+    let backend_socket_buffer = SocketBufferSizes::new(
+        s.get::<u32>("backend_send_buffer_size").unwrap_or(0),
+        s.get::<u32>("backend_recv_buffer_size").unwrap_or(0),
+    )
+    .ok_or("backend_send_buffer_size or backend_recv_buffer_size")?;
     let backend_batch_strategy = s
         .get::<String>("backend_batch_strategy")
         .map(|strategy| match strategy.to_lowercase().as_str() {
@@ -85,6 +113,8 @@ fn gen_conf() -> Result<ServerProxyConfig, &'static str> {
         .ok_or("backend_timeout")?;
 
     let password = s.get::<String>("password").ok();
+    let auth_admin_commands = s.get::<bool>("auth_admin_commands").unwrap_or(false);
+    let admin_password = s.get::<String>("admin_password").ok();
 
     let mut max_redirections = s.get::<usize>("max_redirections").unwrap_or(0);
     if max_redirections != 0 {
@@ -115,8 +145,94 @@ fn gen_conf() -> Result<ServerProxyConfig, &'static str> {
         Err(_) => ClusterNodesVersion::V2,
     };
 
+    let default_limits = ProtocolLimits::default();
+    let proto_max_bulk_len = s
+        .get::<usize>("proto_max_bulk_len")
+        .unwrap_or(default_limits.max_bulk_len);
+    let proto_max_multibulk_len = s
+        .get::<usize>("proto_max_multibulk_len")
+        .unwrap_or(default_limits.max_multibulk_len);
+    let proto_max_inline_len = s
+        .get::<usize>("proto_max_inline_len")
+        .unwrap_or(default_limits.max_inline_len);
+
+    let session_idle_timeout = match s.get::<u64>("session_idle_timeout").unwrap_or(0) {
+        0 => None,
+        t => Some(Duration::from_secs(t)),
+    };
+
+    let client_output_buffer_hard_limit = s
+        .get::<usize>("client_output_buffer_hard_limit")
+        .unwrap_or(0);
+    let client_output_buffer_soft_limit = s
+        .get::<usize>("client_output_buffer_soft_limit")
+        .unwrap_or(0);
+    let client_output_buffer_soft_seconds = s
+        .get::<u64>("client_output_buffer_soft_seconds")
+        .unwrap_or(0);
+    let reply_stream_threshold = s
+        .get::<usize>("reply_stream_threshold")
+        .unwrap_or(64 * 1024);
+
+    let redis_version = match s.get::<String>("redis_version") {
+        Ok(version) if !version.is_empty() => version,
+        _ => DEFAULT_REDIS_VERSION.to_string(),
+    };
+
+    // It's sent as an error reply so it can't contain any line break.
+    let maintenance_error = match s.get::<String>("maintenance_error") {
+        Ok(err) if err.contains(['\r', '\n']) => return Err("maintenance_error"),
+        Ok(err) if !err.is_empty() => err,
+        _ => ERR_MAINTENANCE.to_string(),
+    };
+
+    let rename_command = s
+        .get::<HashMap<String, String>>("rename_command")
+        .unwrap_or_default();
+    let rename_command = CommandRenames::new(rename_command).map_err(|err| {
+        error!("invalid rename_command: {}", err);
+        "rename_command"
+    })?;
+
+    let hash_tag = match s.get::<String>("hash_tag") {
+        Ok(hash_tag) => HashTagDelimiters::parse(&hash_tag).ok_or("hash_tag")?,
+        Err(_) => HashTagDelimiters::default(),
+    };
+
+    let unknown_command_policy = s.get::<String>("unknown_command_policy");
+    let unknown_command_policy = match unknown_command_policy.as_ref().map(|s| s.as_str()) {
+        Ok("forward-unknown") => UnknownCommandPolicy::Forward,
+        Ok("reject-unknown") => UnknownCommandPolicy::Reject,
+        Ok(others) => {
+            error!(
+                "Unexpected unknown command policy {}. Make it default forward-unknown",
+                others
+            );
+            UnknownCommandPolicy::Forward
+        }
+        Err(_) => UnknownCommandPolicy::Forward,
+    };
+
+    let select_policy = s.get::<String>("select_policy");
+    let select_policy = match select_policy.as_ref().map(|s| s.as_str()) {
+        Ok("index-only") => SelectPolicy::IndexOnly,
+        Ok("cluster-name") => SelectPolicy::ClusterName,
+        Ok(others) => {
+            error!(
+                "Unexpected select policy {}. Make it default index-only",
+                others
+            );
+            SelectPolicy::IndexOnly
+        }
+        Err(_) => SelectPolicy::IndexOnly,
+    };
+
     let config = ServerProxyConfig {
         address,
+        extra_listen_addresses,
+        listen_backlog,
+        listen_reuseport: s.get::<bool>("listen_reuseport").unwrap_or(false),
+        client_socket_buffer,
         announce_address,
         announce_host,
         slowlog_len,
@@ -126,6 +242,7 @@ fn gen_conf() -> Result<ServerProxyConfig, &'static str> {
         slowlog_sample_rate: AtomicU64::new(s.get::<u64>("slowlog_sample_rate").unwrap_or(1000)),
         thread_number,
         backend_conn_num,
+        backend_socket_buffer,
         active_redirection: s.get::<bool>("active_redirection").unwrap_or(false),
         max_redirections,
         default_redirection_address,
@@ -135,7 +252,40 @@ fn gen_conf() -> Result<ServerProxyConfig, &'static str> {
         backend_high_flush_interval: Duration::from_nanos(backend_high_flush_interval.get()),
         backend_timeout: Duration::from_millis(backend_timeout.get()),
         password,
+        auth_admin_commands,
+        admin_password,
         command_cluster_nodes_version,
+        proto_max_bulk_len,
+        proto_max_multibulk_len,
+        proto_max_inline_len,
+        session_idle_timeout,
+        client_output_buffer_hard_limit,
+        client_output_buffer_soft_limit,
+        client_output_buffer_soft_seconds,
+        reply_stream_threshold,
+        unknown_command_policy,
+        select_policy,
+        max_connections_per_db: AtomicUsize::new(
+            s.get::<usize>("max_connections_per_db").unwrap_or(0),
+        ),
+        maxclients: AtomicUsize::new(s.get::<usize>("maxclients").unwrap_or(0)),
+        expose_backend_address: AtomicBool::new(
+            s.get::<bool>("expose_backend_address").unwrap_or(false),
+        ),
+        backend_warmup: s.get::<bool>("backend_warmup").unwrap_or(false),
+        cluster_reset_hard: s.get::<bool>("cluster_reset_hard").unwrap_or(false),
+        ping_requires_meta: s.get::<bool>("ping_requires_meta").unwrap_or(false),
+        enable_debug: s.get::<bool>("enable_debug").unwrap_or(false),
+        redis_version,
+        maintenance_error,
+        rename_command,
+        command_timeout: AtomicU64::new(s.get::<u64>("command_timeout").unwrap_or(0)),
+        hash_tag,
+        init_meta_file: s.get::<String>("init_meta_file").ok(),
+        umctl_max_meta_args: s.get::<usize>("umctl_max_meta_args").unwrap_or(100_000),
+        umctl_max_meta_bytes: s
+            .get::<usize>("umctl_max_meta_bytes")
+            .unwrap_or(64 * 1024 * 1024),
     };
 
     Ok(config)
@@ -149,6 +299,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     })?;
 
     info!("config: {:?}", config);
+    set_hash_tag_delimiters(config.hash_tag);
     let config = Arc::new(config);
 
     let timeout = Duration::new(1, 0);
@@ -157,6 +308,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     let slow_request_logger = Arc::new(SlowRequestLogger::new(config.clone()));
     let meta_map = Arc::new(ArcSwap::new(Arc::new(MetaMap::empty())));
     let future_registry = Arc::new(TrackedFutureRegistry::default());
+    let stats = Arc::new(ProxyStats::default());
+    let db_conn_counter = Arc::new(DbConnCounter::default());
 
     let (service_stopped_sender, service_stopped_receiver) = mpsc::unbounded();
 
@@ -165,15 +318,19 @@ fn main() -> Result<(), Box<dyn Error>> {
         Arc::new(client_factory),
         slow_request_logger.clone(),
         meta_map,
-        Arc::new(DefaultConnFactory::default()),
+        Arc::new(DefaultConnFactory::new(config.backend_socket_buffer)),
         future_registry.clone(),
         service_stopped_sender,
+        stats.clone(),
+        db_conn_counter.clone(),
     );
     let server = ServerProxyService::new(
         config.clone(),
         forward_handler,
         slow_request_logger,
         future_registry,
+        stats,
+        db_conn_counter,
     );
 
     let runtime = tokio::runtime::Builder::new_multi_thread()
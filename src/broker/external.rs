@@ -3,8 +3,11 @@ use super::storage::MetaStorage;
 use super::store::{ClusterInfo, MetaStore, ScaleOp, NODES_PER_PROXY};
 use super::MetaStoreError;
 use crate::common::atomic_lock::{AtomicLock, AtomicLockGuard};
-use crate::common::cluster::{Cluster, ClusterName, MigrationTaskMeta, Node, Proxy};
+use crate::common::cluster::{
+    Cluster, ClusterName, MigrationTaskMeta, Node, Proxy, ReplaceProxyHint,
+};
 use crate::common::config::ClusterConfig;
+use crate::coordinator::http_meta_broker::FailureReport;
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use std::collections::HashMap;
@@ -321,6 +324,14 @@ impl MetaStorage for ExternalHttpStorage {
         Ok(failures)
     }
 
+    async fn get_failure_reports(
+        &self,
+        failure_ttl: chrono::Duration,
+    ) -> Result<Vec<FailureReport>, MetaStoreError> {
+        let store = self.cached_store.lease();
+        Ok(store.get_failure_reports(failure_ttl))
+    }
+
     async fn add_failure(
         &self,
         address: String,
@@ -338,16 +349,39 @@ impl MetaStorage for ExternalHttpStorage {
         Ok(())
     }
 
+    async fn add_failures(
+        &self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Result<(), MetaStoreError> {
+        let _guard = self.try_lock()?;
+
+        let ExternalStore { mut store, version } =
+            self.get_external_store_and_update_cache().await?;
+        let mut changed = false;
+        for address in addresses.into_iter() {
+            changed |= store.add_failure(address, reporter_id.clone());
+        }
+        if !changed {
+            return Ok(());
+        }
+        self.update_external_store_and_cache(ExternalStore { store, version })
+            .await?;
+        Ok(())
+    }
+
     async fn replace_failed_proxy(
         &self,
         failed_proxy_address: String,
         migration_limit: u64,
-    ) -> Result<Option<Proxy>, MetaStoreError> {
+        hint: ReplaceProxyHint,
+    ) -> Result<(Option<Proxy>, bool), MetaStoreError> {
         let _guard = self.try_lock()?;
 
         let ExternalStore { mut store, version } =
             self.get_external_store_and_update_cache().await?;
-        let res = store.replace_failed_proxy(failed_proxy_address, migration_limit);
+        let res =
+            store.replace_failed_proxy_with_hint(failed_proxy_address, migration_limit, &hint);
         // It may change the store even on error.
         self.update_external_store_and_cache(ExternalStore { store, version })
             .await?;
@@ -358,12 +392,13 @@ impl MetaStorage for ExternalHttpStorage {
         &self,
         task: MigrationTaskMeta,
         clear_free_nodes: bool,
+        expected_epoch: Option<u64>,
     ) -> Result<(), MetaStoreError> {
         let _guard = self.try_lock()?;
 
         let ExternalStore { mut store, version } =
             self.get_external_store_and_update_cache().await?;
-        store.commit_migration(task, clear_free_nodes)?;
+        store.commit_migration(task, clear_free_nodes, expected_epoch)?;
         self.update_external_store_and_cache(ExternalStore { store, version })
             .await?;
         Ok(())
@@ -0,0 +1,120 @@
+use crate::coordinator::http_mani_broker::ProxyHeartbeat;
+use std::collections::BTreeMap;
+
+// Forget the heartbeats of the removed proxies or coordinators after this.
+const HEARTBEAT_TTL_SECS: i64 = 3600;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ProxyHeartbeatRecord {
+    #[serde(flatten)]
+    pub heartbeat: ProxyHeartbeat,
+    // The unix timestamp in seconds when the broker received it.
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyHeartbeatsPayload {
+    pub heartbeats: Vec<ProxyHeartbeatRecord>,
+}
+
+// The latest heartbeat of each proxy from each coordinator.
+// It is only kept in memory and is neither persisted nor replicated
+// since it will be refreshed by the coordinators soon.
+#[derive(Debug, Default)]
+pub struct HeartbeatStore {
+    // (proxy_address, reporter_id) => record
+    records: parking_lot::Mutex<BTreeMap<(String, String), ProxyHeartbeatRecord>>,
+}
+
+impl HeartbeatStore {
+    pub fn add(&self, heartbeat: ProxyHeartbeat) {
+        self.add_at(heartbeat, chrono::Utc::now().timestamp())
+    }
+
+    fn add_at(&self, heartbeat: ProxyHeartbeat, now: i64) {
+        let mut records = self.records.lock();
+        records.retain(|_, record| now - record.last_seen < HEARTBEAT_TTL_SECS);
+        let key = (
+            heartbeat.proxy_address.clone(),
+            heartbeat.reporter_id.clone(),
+        );
+        records.insert(
+            key,
+            ProxyHeartbeatRecord {
+                heartbeat,
+                last_seen: now,
+            },
+        );
+    }
+
+    // Sorted by the proxy addresses and then the reporters.
+    pub fn get_all(&self) -> Vec<ProxyHeartbeatRecord> {
+        self.records.lock().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::health::HealthStatus;
+
+    fn gen_heartbeat(proxy_address: &str, reporter_id: &str, epoch: u64) -> ProxyHeartbeat {
+        ProxyHeartbeat {
+            proxy_address: proxy_address.to_string(),
+            reporter_id: reporter_id.to_string(),
+            epoch,
+            connected_clients: 1,
+            status: HealthStatus::Ok,
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_store() {
+        let store = HeartbeatStore::default();
+        store.add_at(gen_heartbeat("127.0.0.1:5300", "coordinator1", 1), 100);
+        store.add_at(gen_heartbeat("127.0.0.1:5299", "coordinator2", 1), 100);
+        store.add_at(gen_heartbeat("127.0.0.1:5299", "coordinator1", 1), 100);
+        // Replace the older one.
+        store.add_at(gen_heartbeat("127.0.0.1:5299", "coordinator1", 2), 110);
+
+        let records = store.get_all();
+        let keys: Vec<(&str, &str, u64, i64)> = records
+            .iter()
+            .map(|r| {
+                (
+                    r.heartbeat.proxy_address.as_str(),
+                    r.heartbeat.reporter_id.as_str(),
+                    r.heartbeat.epoch,
+                    r.last_seen,
+                )
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("127.0.0.1:5299", "coordinator1", 2, 110),
+                ("127.0.0.1:5299", "coordinator2", 1, 100),
+                ("127.0.0.1:5300", "coordinator1", 1, 100),
+            ]
+        );
+
+        // The expired ones are removed.
+        store.add_at(
+            gen_heartbeat("127.0.0.1:5299", "coordinator1", 2),
+            100 + HEARTBEAT_TTL_SECS,
+        );
+        assert_eq!(store.get_all().len(), 1);
+    }
+
+    #[test]
+    fn test_heartbeat_record_json() {
+        let record = ProxyHeartbeatRecord {
+            heartbeat: gen_heartbeat("127.0.0.1:5299", "coordinator1", 7),
+            last_seen: 100,
+        };
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["proxy_address"], "127.0.0.1:5299");
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["last_seen"], 100);
+    }
+}
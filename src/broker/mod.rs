@@ -1,5 +1,6 @@
 mod epoch;
 mod external;
+mod heartbeat;
 mod migrate;
 mod persistence;
 mod query;
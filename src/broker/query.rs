@@ -5,6 +5,8 @@ use super::store::{
 use crate::broker::store::ProxyResource;
 use crate::common::cluster::{Cluster, Node, PeerProxy, Proxy, ReplMeta, ReplPeer};
 use crate::common::cluster::{ClusterName, Role};
+use crate::coordinator::http_meta_broker::FailureReport;
+use chrono::Utc;
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
@@ -262,6 +264,30 @@ impl<'a> MetaStoreQuery<'a> {
         )
     }
 
+    // Sorted by the proxy addresses and then the reporters.
+    pub fn get_failure_reports(&self, failure_ttl: chrono::Duration) -> Vec<FailureReport> {
+        let now = Utc::now().timestamp();
+        let mut reports: Vec<FailureReport> = self
+            .store
+            .failures
+            .iter()
+            .flat_map(|(address, reporter_map)| {
+                reporter_map
+                    .iter()
+                    .map(move |(reporter_id, report_time)| FailureReport {
+                        proxy_address: address.clone(),
+                        reporter_id: reporter_id.clone(),
+                        report_time: *report_time,
+                    })
+            })
+            .filter(|report| chrono::Duration::seconds(now - report.report_time) < failure_ttl)
+            .collect();
+        reports.sort_by(|a, b| {
+            (&a.proxy_address, &a.reporter_id).cmp(&(&b.proxy_address, &b.reporter_id))
+        });
+        reports
+    }
+
     pub fn get_free_proxy_resource(&self) -> Vec<ProxyResource> {
         let failed_proxies = self.store.failed_proxies.clone();
         let failures = self.store.failures.clone();
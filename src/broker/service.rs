@@ -1,3 +1,4 @@
+use super::heartbeat::{HeartbeatStore, ProxyHeartbeatsPayload};
 use super::persistence::MetaPersistence;
 use super::replication::MetaReplicator;
 use super::resource::ResourceChecker;
@@ -6,13 +7,15 @@ use super::store::{ClusterInfo, MetaStore, MetaStoreError, ScaleOp, CHUNK_HALF_N
 use crate::broker::epoch::{fetch_max_epoch, wait_for_proxy_epoch, EpochFetchResult};
 use crate::broker::external::ExternalHttpStorage;
 use crate::common::atomic_lock::AtomicLock;
-use crate::common::cluster::{Cluster, ClusterName, MigrationTaskMeta, Node, Proxy};
+use crate::common::cluster::{
+    Cluster, ClusterName, MigrationTaskMeta, Node, Proxy, ReplaceProxyHint,
+};
 use crate::common::config::ClusterConfig;
 use crate::common::version::UNDERMOON_VERSION;
-use crate::coordinator::http_mani_broker::ReplaceProxyResponse;
+use crate::coordinator::http_mani_broker::{ProxyHeartbeat, ReplaceProxyResponse};
 use crate::coordinator::http_meta_broker::{
-    ClusterNamesPayload, ClusterPayload, FailedProxiesPayload, FailuresPayload,
-    ProxyAddressesPayload, ProxyPayload,
+    AddFailuresPayload, ClusterNamesPayload, ClusterPayload, FailedProxiesPayload, FailureReport,
+    FailureReportsPayload, FailuresPayload, ProxyAddressesPayload, ProxyPayload,
 };
 use arc_swap::ArcSwap;
 use std::collections::HashMap;
@@ -80,6 +83,11 @@ pub async fn run_server(service: Arc<MemBrokerService>, address: std::net::Socke
         .and_then(get_proxy_by_address)
         .with(warp::compression::gzip());
 
+    let get_failure_reports_hdl = warp::get()
+        .and(warp::path!("failures" / "reports"))
+        .and(svc.clone())
+        .and_then(get_failure_reports);
+
     let get_failures_hdl = warp::get()
         .and(warp::path("failures"))
         .and(svc.clone())
@@ -90,17 +98,36 @@ pub async fn run_server(service: Arc<MemBrokerService>, address: std::net::Socke
         .and(svc.clone())
         .and_then(add_failure);
 
+    let add_failures_hdl = warp::post()
+        .and(warp::path!("failures"))
+        .and(warp::body::json())
+        .and(svc.clone())
+        .and_then(add_failures);
+
     let replace_failed_node_hdl = warp::post()
         .and(warp::path!("proxies" / "failover" / String))
+        .and(warp::query::<ReplaceProxyQuery>())
         .and(svc.clone())
         .and_then(replace_failed_node);
 
     let commit_migration_hdl = warp::put()
         .and(warp::path!("clusters" / "migrations"))
+        .and(warp::query::<CommitMigrationQuery>())
         .and(warp::body::json())
         .and(svc.clone())
         .and_then(commit_migration);
 
+    let add_heartbeat_hdl = warp::post()
+        .and(warp::path!("proxies" / "heartbeats"))
+        .and(warp::body::json())
+        .and(svc.clone())
+        .map(add_heartbeat);
+
+    let get_heartbeats_hdl = warp::get()
+        .and(warp::path!("proxies" / "heartbeats"))
+        .and(svc.clone())
+        .map(get_heartbeats);
+
     let get_failed_proxies_hdl = warp::get()
         .and(warp::path!("proxies" / "failed" / "addresses"))
         .and(svc.clone())
@@ -222,11 +249,15 @@ pub async fn run_server(service: Arc<MemBrokerService>, address: std::net::Socke
                 .or(get_cluster_by_name_hdl)
                 .or(get_proxy_addresses_hdl)
                 .or(get_proxy_by_address_hdl)
+                .or(get_failure_reports_hdl)
                 .or(get_failures_hdl)
                 .or(add_failure_hdl)
+                .or(add_failures_hdl)
                 .or(replace_failed_node_hdl)
                 .or(commit_migration_hdl)
                 .or(get_failed_proxies_hdl)
+                .or(add_heartbeat_hdl)
+                .or(get_heartbeats_hdl)
                 // Additional api
                 .or(get_cluster_info_by_name_hdl)
                 .or(add_cluster_hdl)
@@ -326,6 +357,7 @@ pub struct MemBrokerService {
     meta_persistence: Arc<dyn MetaPersistence + Send + Sync + 'static>,
     meta_replicator: Arc<dyn MetaReplicator + Send + Sync + 'static>,
     scale_lock: AtomicLock,
+    heartbeats: HeartbeatStore,
 }
 
 impl MemBrokerService {
@@ -380,6 +412,7 @@ impl MemBrokerService {
             meta_persistence,
             meta_replicator,
             scale_lock: AtomicLock::default(),
+            heartbeats: HeartbeatStore::default(),
         };
         Ok(service)
     }
@@ -632,6 +665,11 @@ impl MemBrokerService {
         self.storage.get_failures(failure_ttl, failure_quorum).await
     }
 
+    pub async fn get_failure_reports(&self) -> Result<Vec<FailureReport>, MetaStoreError> {
+        let failure_ttl = chrono::Duration::seconds(self.config.failure_ttl as i64);
+        self.storage.get_failure_reports(failure_ttl).await
+    }
+
     pub async fn add_failure(
         &self,
         address: String,
@@ -640,18 +678,33 @@ impl MemBrokerService {
         self.storage.add_failure(address, reporter_id).await
     }
 
-    pub async fn commit_migration(&self, task: MigrationTaskMeta) -> Result<(), MetaStoreError> {
+    pub async fn add_failures(
+        &self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Result<(), MetaStoreError> {
+        self.storage.add_failures(addresses, reporter_id).await
+    }
+
+    pub async fn commit_migration(
+        &self,
+        task: MigrationTaskMeta,
+        expected_epoch: Option<u64>,
+    ) -> Result<(), MetaStoreError> {
         // TODO: Maybe we need to make `clear_free_nodes` of `commit_migration` configurable.
-        self.storage.commit_migration(task, false).await
+        self.storage
+            .commit_migration(task, false, expected_epoch)
+            .await
     }
 
     pub async fn replace_failed_proxy(
         &self,
         failed_proxy_address: String,
-    ) -> Result<Option<Proxy>, MetaStoreError> {
+        hint: ReplaceProxyHint,
+    ) -> Result<(Option<Proxy>, bool), MetaStoreError> {
         let migration_limit = self.config.migration_limit;
         self.storage
-            .replace_failed_proxy(failed_proxy_address, migration_limit)
+            .replace_failed_proxy(failed_proxy_address, migration_limit, hint)
             .await
     }
 
@@ -659,6 +712,16 @@ impl MemBrokerService {
         self.storage.get_failed_proxies().await
     }
 
+    pub fn add_heartbeat(&self, heartbeat: ProxyHeartbeat) {
+        self.heartbeats.add(heartbeat)
+    }
+
+    pub fn get_heartbeats(&self) -> ProxyHeartbeatsPayload {
+        ProxyHeartbeatsPayload {
+            heartbeats: self.heartbeats.get_all(),
+        }
+    }
+
     pub async fn force_bump_all_epoch(&self, new_epoch: u64) -> Result<(), MetaStoreError> {
         self.storage.force_bump_all_epoch(new_epoch).await
     }
@@ -776,6 +839,14 @@ async fn get_failures(state: ServiceState) -> Result<impl warp::reply::Reply, In
     Ok(warp_json(res.map(WarpRes::Json)))
 }
 
+async fn get_failure_reports(state: ServiceState) -> Result<impl warp::reply::Reply, Infallible> {
+    let res = state
+        .get_failure_reports()
+        .await
+        .map(|reports| FailureReportsPayload { reports });
+    Ok(warp_json(res.map(WarpRes::Json)))
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct ProxyResourcePayload {
     proxy_address: String,
@@ -1024,12 +1095,35 @@ async fn add_failure(
     Ok(warp_json(res.map(warp_empty_res)))
 }
 
+async fn add_failures(
+    payload: AddFailuresPayload,
+    state: ServiceState,
+) -> Result<impl warp::reply::Reply, Infallible> {
+    let res = async move {
+        let AddFailuresPayload {
+            addresses,
+            reporter_id,
+        } = payload;
+        state.add_failures(addresses, reporter_id).await?;
+        state.trigger_update().await?;
+        Ok(())
+    }
+    .await;
+    Ok(warp_json(res.map(warp_empty_res)))
+}
+
+#[derive(Deserialize)]
+struct CommitMigrationQuery {
+    epoch: Option<u64>,
+}
+
 async fn commit_migration(
+    query: CommitMigrationQuery,
     task: MigrationTaskMeta,
     state: ServiceState,
 ) -> Result<impl warp::reply::Reply, Infallible> {
     let res = async move {
-        state.commit_migration(task).await?;
+        state.commit_migration(task, query.epoch).await?;
         state.trigger_update().await?;
         Ok(())
     }
@@ -1037,15 +1131,49 @@ async fn commit_migration(
     Ok(warp_json(res.map(warp_empty_res)))
 }
 
+// Hosts are separated by commas:
+// /proxies/failover/<address>?preferred_hosts=host1,host2&excluded_hosts=host3
+#[derive(Deserialize)]
+struct ReplaceProxyQuery {
+    preferred_hosts: Option<String>,
+    excluded_hosts: Option<String>,
+}
+
+impl ReplaceProxyQuery {
+    fn split_hosts(hosts: Option<String>) -> Vec<String> {
+        hosts
+            .map(|hosts| {
+                hosts
+                    .split(',')
+                    .map(|host| host.trim())
+                    .filter(|host| !host.is_empty())
+                    .map(|host| host.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn into_hint(self) -> ReplaceProxyHint {
+        ReplaceProxyHint {
+            preferred_hosts: Self::split_hosts(self.preferred_hosts),
+            excluded_hosts: Self::split_hosts(self.excluded_hosts),
+        }
+    }
+}
+
 async fn replace_failed_node(
     proxy_address: String,
+    query: ReplaceProxyQuery,
     state: ServiceState,
 ) -> Result<impl warp::reply::Reply, Infallible> {
     let res = async {
         let res = state
-            .replace_failed_proxy(proxy_address)
+            .replace_failed_proxy(proxy_address, query.into_hint())
             .await
-            .map(|proxy| ReplaceProxyResponse { proxy });
+            .map(|(proxy, hint_satisfied)| ReplaceProxyResponse {
+                proxy,
+                hint_satisfied,
+            });
         let sync_res = state.trigger_update().await;
         let res = res?;
         sync_res?;
@@ -1063,6 +1191,15 @@ async fn get_failed_proxies(state: ServiceState) -> Result<impl warp::reply::Rep
     Ok(warp_json(res.map(WarpRes::Json)))
 }
 
+fn add_heartbeat(heartbeat: ProxyHeartbeat, state: ServiceState) -> impl warp::reply::Reply {
+    state.add_heartbeat(heartbeat);
+    warp_json::<()>(Ok(WarpRes::Empty))
+}
+
+fn get_heartbeats(state: ServiceState) -> impl warp::reply::Reply {
+    warp_json(Ok(WarpRes::Json(state.get_heartbeats())))
+}
+
 async fn get_epoch(state: ServiceState) -> Result<impl warp::reply::Reply, Infallible> {
     let res = state.get_epoch().await;
     Ok(warp_json(res.map(WarpRes::Json)))
@@ -1115,6 +1252,8 @@ impl MetaStoreError {
             MetaStoreError::Retry => http::StatusCode::CONFLICT,
             MetaStoreError::EmptyExternalVersion => http::StatusCode::INTERNAL_SERVER_ERROR,
             MetaStoreError::ExternalTimeout => http::StatusCode::GATEWAY_TIMEOUT,
+            // Distinguished from CONFLICT so that the coordinator could refresh the epoch.
+            MetaStoreError::EpochChanged => http::StatusCode::PRECONDITION_FAILED,
         }
     }
 }
@@ -1,8 +1,11 @@
 use super::store::{ClusterInfo, MetaStoreError};
 use super::store::{MetaStore, NODES_PER_PROXY};
 use crate::broker::store::ScaleOp;
-use crate::common::cluster::{Cluster, ClusterName, MigrationTaskMeta, Node, Proxy};
+use crate::common::cluster::{
+    Cluster, ClusterName, MigrationTaskMeta, Node, Proxy, ReplaceProxyHint,
+};
 use crate::common::config::ClusterConfig;
+use crate::coordinator::http_meta_broker::FailureReport;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -36,17 +39,28 @@ pub trait MetaStorage: Send + Sync + 'static {
         failure_ttl: chrono::Duration,
         failure_quorum: u64,
     ) -> Result<Vec<String>, MetaStoreError>;
+    async fn get_failure_reports(
+        &self,
+        failure_ttl: chrono::Duration,
+    ) -> Result<Vec<FailureReport>, MetaStoreError>;
     async fn add_failure(&self, address: String, reporter_id: String)
         -> Result<(), MetaStoreError>;
+    async fn add_failures(
+        &self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Result<(), MetaStoreError>;
     async fn replace_failed_proxy(
         &self,
         failed_proxy_address: String,
         migration_limit: u64,
-    ) -> Result<Option<Proxy>, MetaStoreError>;
+        hint: ReplaceProxyHint,
+    ) -> Result<(Option<Proxy>, bool), MetaStoreError>;
     async fn commit_migration(
         &self,
         task: MigrationTaskMeta,
         clear_free_nodes: bool,
+        expected_epoch: Option<u64>,
     ) -> Result<(), MetaStoreError>;
     async fn get_failed_proxies(&self) -> Result<Vec<String>, MetaStoreError>;
     async fn get_cluster_info_by_name(
@@ -181,6 +195,13 @@ impl MetaStorage for MemoryStorage {
         Ok(failures)
     }
 
+    async fn get_failure_reports(
+        &self,
+        failure_ttl: chrono::Duration,
+    ) -> Result<Vec<FailureReport>, MetaStoreError> {
+        Ok(self.store.read().get_failure_reports(failure_ttl))
+    }
+
     async fn add_failure(
         &self,
         address: String,
@@ -190,22 +211,40 @@ impl MetaStorage for MemoryStorage {
         Ok(())
     }
 
+    async fn add_failures(
+        &self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Result<(), MetaStoreError> {
+        let mut store = self.store.write();
+        for address in addresses.into_iter() {
+            store.add_failure(address, reporter_id.clone());
+        }
+        Ok(())
+    }
+
     async fn replace_failed_proxy(
         &self,
         failed_proxy_address: String,
         migration_limit: u64,
-    ) -> Result<Option<Proxy>, MetaStoreError> {
-        self.store
-            .write()
-            .replace_failed_proxy(failed_proxy_address, migration_limit)
+        hint: ReplaceProxyHint,
+    ) -> Result<(Option<Proxy>, bool), MetaStoreError> {
+        self.store.write().replace_failed_proxy_with_hint(
+            failed_proxy_address,
+            migration_limit,
+            &hint,
+        )
     }
 
     async fn commit_migration(
         &self,
         task: MigrationTaskMeta,
         clear_free_nodes: bool,
+        expected_epoch: Option<u64>,
     ) -> Result<(), MetaStoreError> {
-        self.store.write().commit_migration(task, clear_free_nodes)
+        self.store
+            .write()
+            .commit_migration(task, clear_free_nodes, expected_epoch)
     }
 
     async fn get_failed_proxies(&self) -> Result<Vec<String>, MetaStoreError> {
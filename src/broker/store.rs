@@ -4,10 +4,12 @@ use super::query::MetaStoreQuery;
 use super::update::MetaStoreUpdate;
 use crate::common::cluster::ClusterName;
 use crate::common::cluster::{
-    Cluster, MigrationMeta, MigrationTaskMeta, Node, Proxy, RangeList, SlotRange, SlotRangeTag,
+    Cluster, MigrationMeta, MigrationTaskMeta, Node, Proxy, RangeList, ReplaceProxyHint, SlotRange,
+    SlotRangeTag,
 };
 use crate::common::config::ClusterConfig;
 use crate::common::version::UNDERMOON_MEM_BROKER_META_VERSION;
+use crate::coordinator::http_meta_broker::FailureReport;
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use std::cmp::{max, Ordering};
 use std::collections::{HashMap, HashSet};
@@ -407,6 +409,10 @@ impl MetaStore {
         MetaStoreUpdate::new(self).get_failures(failure_ttl, failure_quorum)
     }
 
+    pub fn get_failure_reports(&self, failure_ttl: chrono::Duration) -> Vec<FailureReport> {
+        MetaStoreQuery::new(self).get_failure_reports(failure_ttl)
+    }
+
     pub fn cleanup_failures(&mut self, failure_ttl: chrono::Duration, failure_quorum: u64) -> bool {
         MetaStoreUpdate::new(self).cleanup_failures(failure_ttl, failure_quorum)
     }
@@ -470,11 +476,23 @@ impl MetaStore {
         MetaStoreMigrate::new(self).migrate_slots_to_scale_down(cluster_name, new_node_num)
     }
 
+    // When `expected_epoch` is specified, the commit will be rejected
+    // if the epoch of the cluster has changed since then.
     pub fn commit_migration(
         &mut self,
         task: MigrationTaskMeta,
         clear_free_nodes: bool,
+        expected_epoch: Option<u64>,
     ) -> Result<(), MetaStoreError> {
+        if let Some(expected_epoch) = expected_epoch {
+            let cluster = self
+                .clusters
+                .get(&task.cluster_name)
+                .ok_or(MetaStoreError::ClusterNotFound)?;
+            if cluster.epoch != expected_epoch {
+                return Err(MetaStoreError::EpochChanged);
+            }
+        }
         let cluster_name = task.cluster_name.to_string();
         MetaStoreMigrate::new(self).commit_migration(task)?;
         if clear_free_nodes {
@@ -577,6 +595,19 @@ impl MetaStore {
         MetaStoreUpdate::new(self).replace_failed_proxy(failed_proxy_address, migration_limit)
     }
 
+    pub fn replace_failed_proxy_with_hint(
+        &mut self,
+        failed_proxy_address: String,
+        migration_limit: u64,
+        hint: &ReplaceProxyHint,
+    ) -> Result<(Option<Proxy>, bool), MetaStoreError> {
+        MetaStoreUpdate::new(self).replace_failed_proxy_with_hint(
+            failed_proxy_address,
+            migration_limit,
+            hint,
+        )
+    }
+
     pub fn change_config(
         &mut self,
         cluster_name: String,
@@ -660,6 +691,7 @@ pub enum MetaStoreError {
     Retry,
     EmptyExternalVersion,
     ExternalTimeout,
+    EpochChanged,
 }
 
 impl MetaStoreError {
@@ -696,6 +728,7 @@ impl MetaStoreError {
             Self::Retry => "RETRY",
             Self::EmptyExternalVersion => "EMPTY_EXTERNAL_VERSION",
             Self::ExternalTimeout => "EXTERNAL_TIMEOUT",
+            Self::EpochChanged => "EPOCH_CHANGED",
         }
     }
 }
@@ -1171,6 +1204,143 @@ mod tests {
         check_cluster_and_proxy(&store);
     }
 
+    #[test]
+    fn test_failure_reports() {
+        let mut store = MetaStore::new(false);
+        add_testing_proxies(&mut store, 4, 3);
+
+        store.add_failure("127.0.0.1:7001".to_string(), "reporter2".to_string());
+        store.add_failure("127.0.0.1:7001".to_string(), "reporter1".to_string());
+        store.add_failure("127.0.0.1:7000".to_string(), "reporter2".to_string());
+
+        let reports = store.get_failure_reports(chrono::Duration::max_value());
+        let reporters: Vec<(&str, &str)> = reports
+            .iter()
+            .map(|r| (r.proxy_address.as_str(), r.reporter_id.as_str()))
+            .collect();
+        assert_eq!(
+            reporters,
+            vec![
+                ("127.0.0.1:7000", "reporter2"),
+                ("127.0.0.1:7001", "reporter1"),
+                ("127.0.0.1:7001", "reporter2"),
+            ]
+        );
+        assert!(reports.iter().all(|r| r.report_time > 0));
+
+        // The expired ones are not included.
+        assert!(store
+            .get_failure_reports(chrono::Duration::zero())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_replace_failed_proxy_with_hint() {
+        let migration_limit = 0;
+
+        let mut store = MetaStore::new(false);
+        add_testing_proxies(&mut store, 4, 3);
+        store
+            .add_cluster(CLUSTER_NAME.to_string(), 4, ClusterConfig::default())
+            .unwrap();
+        let cluster = store
+            .get_cluster_by_name(CLUSTER_NAME, migration_limit)
+            .unwrap();
+        let failed_proxy_address = cluster.get_nodes()[0].get_proxy_address().to_string();
+        let failed_host = store
+            .all_proxies
+            .get(&failed_proxy_address)
+            .unwrap()
+            .host
+            .clone();
+        store.add_failure(failed_proxy_address.clone(), "reporter_id".to_string());
+
+        let other_hosts: Vec<String> = (1..=4)
+            .map(|i| format!("127.0.0.{}", i))
+            .filter(|host| host != &failed_host)
+            .collect();
+        let get_host = |store: &MetaStore, proxy: &Proxy| {
+            store
+                .all_proxies
+                .get(proxy.get_address())
+                .unwrap()
+                .host
+                .clone()
+        };
+
+        // The first available preferred host wins.
+        let mut s = store.clone();
+        let hint = ReplaceProxyHint {
+            preferred_hosts: vec!["unknown_host".to_string(), other_hosts[2].clone()],
+            excluded_hosts: vec![],
+        };
+        let (proxy, hint_satisfied) = s
+            .replace_failed_proxy_with_hint(failed_proxy_address.clone(), migration_limit, &hint)
+            .unwrap();
+        let proxy = proxy.unwrap();
+        assert!(hint_satisfied);
+        assert_eq!(get_host(&s, &proxy), other_hosts[2]);
+        check_cluster_and_proxy(&s);
+
+        // Excluded hosts are never chosen even if they are preferred.
+        let mut s = store.clone();
+        let hint = ReplaceProxyHint {
+            preferred_hosts: vec![other_hosts[0].clone()],
+            excluded_hosts: vec![other_hosts[0].clone(), other_hosts[1].clone()],
+        };
+        let (proxy, hint_satisfied) = s
+            .replace_failed_proxy_with_hint(failed_proxy_address.clone(), migration_limit, &hint)
+            .unwrap();
+        let proxy = proxy.unwrap();
+        assert!(!hint_satisfied);
+        assert_eq!(get_host(&s, &proxy), other_hosts[2]);
+
+        // No candidate left.
+        let mut s = store.clone();
+        let hint = ReplaceProxyHint {
+            preferred_hosts: vec![],
+            excluded_hosts: other_hosts.clone(),
+        };
+        let err = s
+            .replace_failed_proxy_with_hint(failed_proxy_address.clone(), migration_limit, &hint)
+            .unwrap_err();
+        assert_eq!(err, MetaStoreError::NoAvailableResource);
+
+        // No hint
+        let (proxy, hint_satisfied) = store
+            .replace_failed_proxy_with_hint(
+                failed_proxy_address,
+                migration_limit,
+                &ReplaceProxyHint::default(),
+            )
+            .unwrap();
+        assert!(proxy.is_some());
+        assert!(hint_satisfied);
+    }
+
+    #[test]
+    fn test_commit_migration_with_changed_epoch() {
+        let mut store = MetaStore::new(false);
+        add_testing_proxies(&mut store, 4, 3);
+        store
+            .add_cluster(CLUSTER_NAME.to_string(), 4, ClusterConfig::default())
+            .unwrap();
+        let cluster_name = ClusterName::try_from(CLUSTER_NAME).unwrap();
+        let epoch = store.clusters.get(&cluster_name).unwrap().epoch;
+
+        let task = MigrationTaskMeta {
+            cluster_name,
+            slot_range: SlotRange {
+                range_list: RangeList::try_from("1 0-100").unwrap(),
+                tag: SlotRangeTag::None,
+            },
+        };
+        let err = store
+            .commit_migration(task, false, Some(epoch - 1))
+            .unwrap_err();
+        assert_eq!(err, MetaStoreError::EpochChanged);
+    }
+
     const CLUSTER_NAME: &'static str = "testcluster";
 
     #[test]
@@ -1394,7 +1564,7 @@ mod tests {
                     cluster_name: ClusterName::try_from(cluster_name.as_str()).unwrap(),
                     slot_range,
                 };
-                store.commit_migration(task_meta, false).unwrap();
+                store.commit_migration(task_meta, false, None).unwrap();
             }
         }
 
@@ -1648,7 +1818,7 @@ mod tests {
                     cluster_name: ClusterName::try_from(cluster_name.as_str()).unwrap(),
                     slot_range,
                 };
-                store.commit_migration(task_meta, false).unwrap();
+                store.commit_migration(task_meta, false, None).unwrap();
             }
         }
 
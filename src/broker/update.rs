@@ -4,7 +4,9 @@ use super::store::{
     ProxyResource, CHUNK_HALF_NODE_NUM, CHUNK_PARTS, NODES_PER_PROXY,
 };
 use crate::common::cluster::ClusterName;
-use crate::common::cluster::{Node, Proxy, Range, RangeList, SlotRange, SlotRangeTag};
+use crate::common::cluster::{
+    Node, Proxy, Range, RangeList, ReplaceProxyHint, SlotRange, SlotRangeTag,
+};
 use crate::common::config::ClusterConfig;
 use crate::common::utils::SLOT_NUM;
 use chrono::{DateTime, NaiveDateTime, Utc};
@@ -646,6 +648,7 @@ impl<'a> MetaStoreUpdate<'a> {
     fn generate_new_free_proxy(
         &self,
         failed_proxy_address: String,
+        hint: &ReplaceProxyHint,
     ) -> Result<ProxyResource, MetaStoreError> {
         let free_host_proxies = self.generate_free_host_proxies();
         info!(
@@ -666,20 +669,33 @@ impl<'a> MetaStoreUpdate<'a> {
         let link_count_table = link_table
             .get(&failed_proxy_host)
             .expect("consume_new_proxy: cannot find failed proxy");
-        let peer_host = link_count_table
+        let candidates: Vec<(&String, &usize)> = link_count_table
             .iter()
             .filter(|(peer_host, _)| free_host_proxies.contains_key(*peer_host))
-            .min_by(|(host1, count1), (host2, count2)| {
-                Self::second_host_cmp(
-                    host1.as_str(),
-                    **count1,
-                    host2.as_str(),
-                    **count2,
-                    &free_host_proxies,
-                )
-            })
-            .map(|(peer_host, _)| peer_host)
-            .ok_or(MetaStoreError::NoAvailableResource)?;
+            .filter(|(peer_host, _)| !hint.excluded_hosts.contains(*peer_host))
+            .collect();
+
+        let preferred_host = hint.preferred_hosts.iter().find(|preferred_host| {
+            candidates
+                .iter()
+                .any(|(peer_host, _)| peer_host == preferred_host)
+        });
+        let peer_host = match preferred_host {
+            Some(preferred_host) => preferred_host,
+            None => candidates
+                .into_iter()
+                .min_by(|(host1, count1), (host2, count2)| {
+                    Self::second_host_cmp(
+                        host1.as_str(),
+                        **count1,
+                        host2.as_str(),
+                        **count2,
+                        &free_host_proxies,
+                    )
+                })
+                .map(|(peer_host, _)| peer_host)
+                .ok_or(MetaStoreError::NoAvailableResource)?,
+        };
 
         let peer_proxy = MetaStoreQuery::new(self.store)
             .get_free_proxies()
@@ -765,6 +781,21 @@ impl<'a> MetaStoreUpdate<'a> {
         failed_proxy_address: String,
         migration_limit: u64,
     ) -> Result<Option<Proxy>, MetaStoreError> {
+        self.replace_failed_proxy_with_hint(
+            failed_proxy_address,
+            migration_limit,
+            &ReplaceProxyHint::default(),
+        )
+        .map(|(proxy, _)| proxy)
+    }
+
+    // Returns the new proxy and whether it's chosen from the preferred hosts of the hint.
+    pub fn replace_failed_proxy_with_hint(
+        &mut self,
+        failed_proxy_address: String,
+        migration_limit: u64,
+        hint: &ReplaceProxyHint,
+    ) -> Result<(Option<Proxy>, bool), MetaStoreError> {
         let cluster_name = match self.store.all_proxies.get(&failed_proxy_address) {
             None => return Err(MetaStoreError::ProxyNotFound),
             Some(proxy) => proxy.cluster.clone(),
@@ -774,7 +805,7 @@ impl<'a> MetaStoreUpdate<'a> {
             None => {
                 self.store.failures.remove(&failed_proxy_address);
                 self.store.failed_proxies.insert(failed_proxy_address);
-                return Ok(None);
+                return Ok((None, hint.is_satisfied_by(None)));
             }
             Some(cluster_name) => cluster_name,
         };
@@ -784,14 +815,14 @@ impl<'a> MetaStoreUpdate<'a> {
         // If enable_ordered_proxy is true, we won't replace the proxy.
         if self.store.enable_ordered_proxy {
             self.store.bump_global_epoch();
-            return Ok(None);
+            return Ok((None, hint.is_satisfied_by(None)));
         }
 
         self.store
             .failed_proxies
             .insert(failed_proxy_address.clone());
 
-        let proxy_resource = self.generate_new_free_proxy(failed_proxy_address.clone())?;
+        let proxy_resource = self.generate_new_free_proxy(failed_proxy_address.clone(), hint)?;
         let new_epoch = self.store.bump_global_epoch();
         {
             let cluster = self
@@ -833,7 +864,8 @@ impl<'a> MetaStoreUpdate<'a> {
         let proxy = MetaStoreQuery::new(self.store)
             .get_proxy_by_address(&proxy_resource.proxy_address, migration_limit)
             .expect("replace_failed_proxy");
-        Ok(Some(proxy))
+        let hint_satisfied = hint.is_satisfied_by(Some(proxy_resource.host.as_str()));
+        Ok((Some(proxy), hint_satisfied))
     }
 
     fn takeover_master(
@@ -0,0 +1,166 @@
+use crate::common::utils::ThreadSafe;
+use futures::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+// Abstracts the time so that the timing logic can be tested
+// without real sleeps.
+pub trait Clock: ThreadSafe {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub use self::fake::FakeClock;
+
+#[cfg(any(test, feature = "test-support"))]
+mod fake {
+    use super::*;
+    use crate::common::yield_now::YieldNow;
+    use futures::channel::oneshot;
+    use futures::task::Poll;
+
+    struct FakeClockState {
+        elapsed: Duration,
+        // (deadline, wake-up sender) of the pending sleeps
+        sleepers: Vec<(Duration, oneshot::Sender<()>)>,
+        sleep_history: Vec<Duration>,
+    }
+
+    // The time only goes forward when `advance` is called.
+    // Sleeping for zero duration finishes immediately.
+    pub struct FakeClock {
+        start: Instant,
+        state: parking_lot::Mutex<FakeClockState>,
+    }
+
+    impl Default for FakeClock {
+        fn default() -> Self {
+            Self {
+                start: Instant::now(),
+                state: parking_lot::Mutex::new(FakeClockState {
+                    elapsed: Duration::from_secs(0),
+                    sleepers: vec![],
+                    sleep_history: vec![],
+                }),
+            }
+        }
+    }
+
+    impl FakeClock {
+        // Wakes up all the sleeps whose deadlines have been reached.
+        pub fn advance(&self, duration: Duration) {
+            let mut state = self.state.lock();
+            state.elapsed += duration;
+            let elapsed = state.elapsed;
+            let (ready, pending) = state
+                .sleepers
+                .drain(..)
+                .partition(|(deadline, _)| *deadline <= elapsed);
+            state.sleepers = pending;
+            for (_, sender) in ready.into_iter() {
+                // The sleep future might have been dropped.
+                let _ = sender.send(());
+            }
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            self.state.lock().elapsed
+        }
+
+        pub fn pending_sleeps(&self) -> usize {
+            self.state.lock().sleepers.len()
+        }
+
+        // Returns the durations of all the sleeps in order.
+        pub fn get_sleep_history(&self) -> Vec<Duration> {
+            self.state.lock().sleep_history.clone()
+        }
+
+        // Polls the future and advances the clock by `step`
+        // whenever the future is pending, until it finishes.
+        // The future should only be blocked by the sleeps of this clock.
+        pub async fn drive<F: Future>(&self, step: Duration, fut: F) -> F::Output {
+            futures::pin_mut!(fut);
+            loop {
+                if let Poll::Ready(output) = futures::poll!(fut.as_mut()) {
+                    return output;
+                }
+                self.advance(step);
+                YieldNow::default().await;
+            }
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed()
+        }
+
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            let mut state = self.state.lock();
+            state.sleep_history.push(duration);
+            if duration == Duration::from_secs(0) {
+                return Box::pin(async {});
+            }
+            let (sender, receiver) = oneshot::channel();
+            let deadline = state.elapsed + duration;
+            state.sleepers.push((deadline, sender));
+            Box::pin(async move {
+                // The sender is only dropped along with the clock.
+                let _ = receiver.await;
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn test_fake_clock() {
+        let clock = FakeClock::default();
+        let start = clock.now();
+
+        clock.sleep(Duration::from_secs(0)).await;
+
+        let mut sleep1 = clock.sleep(Duration::from_secs(1));
+        let mut sleep2 = clock.sleep(Duration::from_secs(3));
+        assert_eq!(clock.pending_sleeps(), 2);
+        assert!((&mut sleep1).now_or_never().is_none());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(clock.pending_sleeps(), 1);
+        assert!((&mut sleep1).now_or_never().is_some());
+        assert!((&mut sleep2).now_or_never().is_none());
+
+        clock.advance(Duration::from_secs(2));
+        assert!((&mut sleep2).now_or_never().is_some());
+        assert_eq!(clock.pending_sleeps(), 0);
+
+        assert_eq!(clock.now() - start, Duration::from_secs(3));
+        assert_eq!(
+            clock.get_sleep_history(),
+            vec![
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                Duration::from_secs(3)
+            ]
+        );
+    }
+}
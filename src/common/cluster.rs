@@ -241,6 +241,31 @@ impl RangeList {
             .map(|range| range.end() - range.start() + 1)
             .sum()
     }
+
+    // Returns the slots inside `self` but not inside `other`.
+    pub fn difference(&self, other: &RangeList) -> RangeList {
+        let mut other = other.clone();
+        other.compact();
+
+        let mut ranges = vec![];
+        for range in self.0.iter() {
+            let mut start = range.start();
+            let end = range.end();
+            for other_range in other.get_ranges().iter() {
+                if other_range.end() < start || other_range.start() > end {
+                    continue;
+                }
+                if other_range.start() > start {
+                    ranges.push(Range(start, other_range.start() - 1));
+                }
+                start = other_range.end() + 1;
+            }
+            if start <= end {
+                ranges.push(Range(start, end));
+            }
+        }
+        Self::new(ranges)
+    }
 }
 
 #[derive(Clone)]
@@ -792,6 +817,33 @@ impl Proxy {
     }
 }
 
+// Candidate hints for choosing the replacement of a failed proxy.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ReplaceProxyHint {
+    // Hosts in priority order. The first available one wins.
+    #[serde(default)]
+    pub preferred_hosts: Vec<String>,
+    // Hosts which should never be chosen.
+    #[serde(default)]
+    pub excluded_hosts: Vec<String>,
+}
+
+impl ReplaceProxyHint {
+    pub fn is_empty(&self) -> bool {
+        self.preferred_hosts.is_empty() && self.excluded_hosts.is_empty()
+    }
+
+    pub fn is_satisfied_by(&self, host: Option<&str>) -> bool {
+        if self.preferred_hosts.is_empty() {
+            return true;
+        }
+        match host {
+            Some(host) => self.preferred_hosts.iter().any(|h| h == host),
+            None => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::config::CompressionStrategy;
@@ -1059,6 +1111,21 @@ mod tests {
         assert_eq!(range_list.get_ranges()[1].end(), 1000);
     }
 
+    #[test]
+    fn test_range_list_difference() {
+        let range_list = RangeList::try_from("2 0-100 200-300").unwrap();
+        let empty = RangeList::new(vec![]);
+        assert_eq!(range_list.difference(&empty), range_list);
+        assert_eq!(empty.difference(&range_list), empty);
+        assert_eq!(range_list.difference(&range_list), empty);
+
+        let other = RangeList::try_from("3 50-60 90-210 300-400").unwrap();
+        let expected = RangeList::try_from("3 0-49 61-89 211-299").unwrap();
+        assert_eq!(range_list.difference(&other), expected);
+        let expected = RangeList::try_from("2 101-199 301-400").unwrap();
+        assert_eq!(other.difference(&range_list), expected);
+    }
+
     #[test]
     fn test_range_list_contains_slot() {
         let range_list = RangeList::try_from("0 233-666").unwrap();
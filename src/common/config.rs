@@ -1,6 +1,6 @@
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 
@@ -10,6 +10,9 @@ pub struct ClusterConfig {
     pub compression_strategy: CompressionStrategy,
     #[serde(default)]
     pub migration_config: MigrationConfig,
+    // Prepended to the keys sent to the backends. Empty means disabled.
+    #[serde(default)]
+    pub key_prefix: String,
 }
 
 impl Default for ClusterConfig {
@@ -17,10 +20,22 @@ impl Default for ClusterConfig {
         Self {
             compression_strategy: CompressionStrategy::default(),
             migration_config: MigrationConfig::default(),
+            key_prefix: String::new(),
         }
     }
 }
 
+const MAX_KEY_PREFIX_LEN: usize = 64;
+
+// The prefix should not change the hash tags of the keys
+// and should be usable in the MATCH pattern of SCAN.
+fn is_valid_key_prefix(prefix: &str) -> bool {
+    prefix.len() <= MAX_KEY_PREFIX_LEN
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"{}*?[]\\".contains(c))
+}
+
 impl ClusterConfig {
     pub fn set_field(&mut self, field: &str, value: &str) -> Result<(), ConfigError> {
         let field = field.to_lowercase();
@@ -30,6 +45,12 @@ impl ClusterConfig {
                     CompressionStrategy::from_str(value).map_err(|_| ConfigError::InvalidValue)?;
                 self.compression_strategy = strategy;
             }
+            "key_prefix" => {
+                if !is_valid_key_prefix(value) {
+                    return Err(ConfigError::InvalidValue);
+                }
+                self.key_prefix = value.to_string();
+            }
             _ => {
                 if field.starts_with("migration_") {
                     let f = field
@@ -46,7 +67,7 @@ impl ClusterConfig {
     }
 
     pub fn to_str_map(&self) -> HashMap<String, String> {
-        vec![
+        let mut map: HashMap<String, String> = vec![
             (
                 "compression_strategy",
                 self.compression_strategy.to_str().to_string(),
@@ -70,7 +91,12 @@ impl ClusterConfig {
         ]
         .into_iter()
         .map(|(k, v)| (k.to_string(), v))
-        .collect()
+        .collect();
+        // Only sent when enabled so that the proxies without this field still accept the config.
+        if !self.key_prefix.is_empty() {
+            map.insert("key_prefix".to_string(), self.key_prefix.clone());
+        }
+        map
     }
 }
 
@@ -146,7 +172,7 @@ pub struct MigrationConfig {
 }
 
 impl MigrationConfig {
-    fn set_field(&mut self, field: &str, value: &str) -> Result<(), ConfigError> {
+    pub fn set_field(&mut self, field: &str, value: &str) -> Result<(), ConfigError> {
         let field = field.to_lowercase();
         match field.as_str() {
             "max_migration_time" => {
@@ -193,6 +219,53 @@ impl Default for MigrationConfig {
     }
 }
 
+impl MigrationConfig {
+    pub fn to_fields(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("max_migration_time", self.max_migration_time),
+            ("max_blocking_time", self.max_blocking_time),
+            ("scan_interval", self.scan_interval),
+            ("scan_count", self.scan_count),
+        ]
+    }
+}
+
+// The fields set at runtime by `UMCTL MGRCONFIG SET`
+// override the migration config from `UMCTL SETCLUSTER`.
+#[derive(Debug, Default)]
+pub struct MigrationConfigOverrides {
+    cluster_config: MigrationConfig,
+    // field => value, all validated.
+    overrides: BTreeMap<String, String>,
+}
+
+impl MigrationConfigOverrides {
+    pub fn set_cluster_config(&mut self, config: MigrationConfig) {
+        self.cluster_config = config;
+    }
+
+    pub fn set_field(&mut self, field: &str, value: &str) -> Result<(), ConfigError> {
+        let mut config = self.get_config();
+        config.set_field(field, value)?;
+        self.overrides
+            .insert(field.to_lowercase(), value.to_string());
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> MigrationConfig {
+        let mut config = self.cluster_config.clone();
+        for (field, value) in self.overrides.iter() {
+            if let Err(err) = config.set_field(field, value) {
+                error!(
+                    "invalid migration config override {} {}: {:?}",
+                    field, value, err
+                );
+            }
+        }
+        config
+    }
+}
+
 pub struct AtomicMigrationConfig {
     max_migration_time: AtomicU64,
     max_blocking_time: AtomicU64,
@@ -268,4 +341,54 @@ mod tests {
             .unwrap();
         assert_eq!(cluster_config.migration_config.scan_count, 666);
     }
+
+    #[test]
+    fn test_key_prefix_config() {
+        let mut cluster_config = ClusterConfig::default();
+        assert!(!cluster_config.to_str_map().contains_key("key_prefix"));
+
+        cluster_config.set_field("key_prefix", "tenant1:").unwrap();
+        assert_eq!(cluster_config.key_prefix, "tenant1:");
+        assert_eq!(
+            cluster_config.to_str_map().get("key_prefix"),
+            Some(&"tenant1:".to_string())
+        );
+
+        for prefix in &["{tag}", "a*", "a b", "a\\"] {
+            assert!(matches!(
+                cluster_config.set_field("key_prefix", prefix),
+                Err(ConfigError::InvalidValue)
+            ));
+        }
+        let long_prefix = "a".repeat(MAX_KEY_PREFIX_LEN + 1);
+        assert!(cluster_config
+            .set_field("key_prefix", &long_prefix)
+            .is_err());
+
+        cluster_config.set_field("key_prefix", "").unwrap();
+        assert!(cluster_config.key_prefix.is_empty());
+    }
+
+    #[test]
+    fn test_migration_config_overrides() {
+        let mut overrides = MigrationConfigOverrides::default();
+        overrides.set_field("SCAN_COUNT", "32").unwrap();
+        assert!(matches!(
+            overrides.set_field("scan_count", "0"),
+            Err(ConfigError::InvalidValue)
+        ));
+        assert!(matches!(
+            overrides.set_field("concurrency", "2"),
+            Err(ConfigError::FieldNotFound)
+        ));
+
+        overrides.set_cluster_config(MigrationConfig {
+            scan_count: 64,
+            scan_interval: 1000,
+            ..MigrationConfig::default()
+        });
+        let config = overrides.get_config();
+        assert_eq!(config.scan_count, 32);
+        assert_eq!(config.scan_interval, 1000);
+    }
 }
@@ -90,6 +90,16 @@ pub struct FutureAutoStopHandle {
     signal_sender: Option<oneshot::Sender<()>>,
 }
 
+impl FutureAutoStopHandle {
+    // Returns true if the future has finished or has been dropped.
+    pub fn is_stopped(&self) -> bool {
+        self.signal_sender
+            .as_ref()
+            .map(|sender| sender.is_canceled())
+            .unwrap_or(true)
+    }
+}
+
 impl Drop for FutureAutoStopHandle {
     fn drop(&mut self) {
         self.signal_sender
@@ -1,6 +1,7 @@
 pub mod atomic_lock;
 pub mod batch;
 pub mod biatomic;
+pub mod clock;
 pub mod cluster;
 pub mod config;
 pub mod future_group;
@@ -8,6 +9,7 @@ pub mod proto;
 pub mod resp_execution;
 pub mod response;
 pub mod slot_lock;
+pub mod stats;
 pub mod track;
 pub mod try_chunks;
 pub mod utils;
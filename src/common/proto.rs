@@ -2,7 +2,7 @@ use super::cluster::SlotRange;
 use super::utils::{has_flags, CmdParseError};
 use crate::common::cluster::ClusterName;
 use crate::common::config::ClusterConfig;
-use crate::common::utils::extract_host_from_address;
+use crate::common::utils::{extract_host_from_address, is_valid_address};
 use crate::protocol::{Array, BulkStr, Resp};
 use flate2::Compression;
 use std::collections::HashMap;
@@ -13,24 +13,6 @@ use std::io::Write;
 use std::iter::Peekable;
 use std::str;
 
-macro_rules! try_parse {
-    ($expression:expr) => {{
-        match $expression {
-            Ok(v) => (v),
-            Err(_) => return Err(CmdParseError::InvalidArgs),
-        }
-    }};
-}
-
-macro_rules! try_get {
-    ($expression:expr) => {{
-        match $expression {
-            Some(v) => (v),
-            None => return Err(CmdParseError::InvalidArgs),
-        }
-    }};
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClusterMapFlags {
     pub force: bool,
@@ -195,14 +177,22 @@ impl ProxyClusterMeta {
 
     pub fn from_resp<T: AsRef<[u8]>>(
         resp: &Resp<T>,
+    ) -> Result<(Self, Result<(), ParseExtendedMetaError>), CmdParseError> {
+        // Skip the "UMCTL SETCLUSTER"
+        Self::from_resp_skipping(resp, 2)
+    }
+
+    // For the commands with more elements before the metadata such as "UMCTL SETCLUSTER ASYNC".
+    pub fn from_resp_skipping<T: AsRef<[u8]>>(
+        resp: &Resp<T>,
+        skipped: usize,
     ) -> Result<(Self, Result<(), ParseExtendedMetaError>), CmdParseError> {
         let arr = match resp {
             Resp::Arr(Array::Arr(ref arr)) => arr,
             _ => return Err(CmdParseError::InvalidArgs),
         };
 
-        // Skip the "UMCTL SETCLUSTER"
-        let it = arr.iter().skip(2).flat_map(|resp| match resp {
+        let it = arr.iter().skip(skipped).flat_map(|resp| match resp {
             Resp::Bulk(BulkStr::Str(safe_str)) => match str::from_utf8(safe_str.as_ref()) {
                 Ok(s) => Some(s.to_string()),
                 _ => None,
@@ -220,20 +210,23 @@ impl ProxyClusterMeta {
     where
         It: Iterator<Item = String>,
     {
-        let version = try_get!(it.next());
+        let version = it.next().ok_or(CmdParseError::MissingField("version"))?;
         if version != SET_CLUSTER_API_VERSION {
             return Err(CmdParseError::InvalidVersion);
         }
 
-        let epoch_str = try_get!(it.next());
-        let epoch = try_parse!(epoch_str.parse::<u64>());
+        let epoch_str = it.next().ok_or(CmdParseError::MissingField("epoch"))?;
+        let epoch = epoch_str
+            .parse::<u64>()
+            .map_err(|_| CmdParseError::InvalidEpoch)?;
 
-        let flags = ClusterMapFlags::from_arg(&try_get!(it.next()));
+        let flags =
+            ClusterMapFlags::from_arg(&it.next().ok_or(CmdParseError::MissingField("flags"))?);
 
         if flags.compress {
             let compressed_data = it.next().ok_or_else(|| {
                 error!("failed to get compressed data for UMCTL SETCLUSTER");
-                CmdParseError::InvalidArgs
+                CmdParseError::MissingField("compressed data")
             })?;
             let data =
                 ProxyClusterMetaData::from_compressed_data(compressed_data).map_err(|err| {
@@ -263,7 +256,9 @@ impl ProxyClusterMeta {
             ));
         }
 
-        let cluster_name = try_get!(it.next());
+        let cluster_name = it
+            .next()
+            .ok_or(CmdParseError::MissingField("cluster name"))?;
         let cluster_name = ClusterName::try_from(cluster_name.as_str())
             .map_err(|_| CmdParseError::InvalidClusterName)?;
 
@@ -277,9 +272,9 @@ impl ProxyClusterMeta {
                 PEER_PREFIX => peer = NodeMap::parse(it)?,
                 CONFIG_PREFIX => match ClusterConfigData::parse(it) {
                     Ok(c) => cluster_config = c,
-                    Err(_) => {
+                    Err(err) => {
                         if local.is_empty() || peer.get_map().is_empty() {
-                            return Err(CmdParseError::InvalidArgs);
+                            return Err(err);
                         } else {
                             error!("invalid cluster config from UMCTL SETCLUSTER but the local and peer metadata are complete. Ignore this error to protect the core functionality.");
                             extended_meta_result = Err(ParseExtendedMetaError {})
@@ -390,7 +385,7 @@ impl NodeMap {
                 None => break,
             }
 
-            let (address, slot_range) = try_parse!(Self::parse_node(it));
+            let (address, slot_range) = Self::parse_node(it)?;
             let slots = node_map.entry(address).or_insert_with(Vec::new);
             slots.push(slot_range);
         }
@@ -402,8 +397,13 @@ impl NodeMap {
     where
         It: Iterator<Item = String>,
     {
-        let addr = try_get!(it.next());
-        let slot_range = try_parse!(Self::parse_tagged_slot_range(it));
+        let addr = it
+            .next()
+            .ok_or(CmdParseError::MissingField("node address"))?;
+        if !is_valid_address(&addr) {
+            return Err(CmdParseError::InvalidAddress(addr));
+        }
+        let slot_range = Self::parse_tagged_slot_range(it)?;
         Ok((addr, slot_range))
     }
 
@@ -476,8 +476,8 @@ impl ClusterConfigData {
                 None => break,
             }
 
-            let field = try_get!(it.next());
-            let value = try_get!(it.next());
+            let field = it.next().ok_or(CmdParseError::InvalidConfig)?;
+            let value = it.next().ok_or(CmdParseError::InvalidConfig)?;
             if let Err(err) = config.set_field(&field, &value) {
                 warn!("failed to set config field {:?}", err);
                 return Err(CmdParseError::InvalidConfig);
@@ -872,4 +872,107 @@ mod tests {
 
         assert!(ProxyClusterMeta::parse(&mut it).is_err());
     }
+
+    fn parse_cluster_meta_err(arguments: &[&str]) -> CmdParseError {
+        let mut it = arguments.iter().map(|s| s.to_string()).peekable();
+        ProxyClusterMeta::parse(&mut it).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_cluster_meta_missing_field() {
+        assert_eq!(
+            parse_cluster_meta_err(&[]),
+            CmdParseError::MissingField("version")
+        );
+        assert_eq!(
+            parse_cluster_meta_err(&[SET_CLUSTER_API_VERSION]),
+            CmdParseError::MissingField("epoch")
+        );
+        assert_eq!(
+            parse_cluster_meta_err(&[SET_CLUSTER_API_VERSION, "233"]),
+            CmdParseError::MissingField("flags")
+        );
+        assert_eq!(
+            parse_cluster_meta_err(&[SET_CLUSTER_API_VERSION, "233", "FORCE"]),
+            CmdParseError::MissingField("cluster name")
+        );
+        assert_eq!(
+            parse_cluster_meta_err(&[SET_CLUSTER_API_VERSION, "233", "COMPRESS"]),
+            CmdParseError::MissingField("compressed data")
+        );
+    }
+
+    #[test]
+    fn test_parse_cluster_meta_invalid_version() {
+        assert_eq!(
+            parse_cluster_meta_err(&["v0", "233", "FORCE", "cluster_name"]),
+            CmdParseError::InvalidVersion
+        );
+    }
+
+    #[test]
+    fn test_parse_cluster_meta_invalid_epoch() {
+        assert_eq!(
+            parse_cluster_meta_err(&[SET_CLUSTER_API_VERSION, "-1", "FORCE", "cluster_name"]),
+            CmdParseError::InvalidEpoch
+        );
+    }
+
+    #[test]
+    fn test_parse_cluster_meta_invalid_slot_range() {
+        let err = parse_cluster_meta_err(&[
+            SET_CLUSTER_API_VERSION,
+            "233",
+            "FORCE",
+            "cluster_name",
+            "127.0.0.1:7000",
+            "1",
+            "0~1000",
+        ]);
+        assert_eq!(err, CmdParseError::InvalidSlots);
+
+        let err = parse_cluster_meta_err(&[
+            SET_CLUSTER_API_VERSION,
+            "233",
+            "FORCE",
+            "cluster_name",
+            "127.0.0.1:7000",
+            "2",
+            "0-1000",
+        ]);
+        assert_eq!(err, CmdParseError::InvalidSlots);
+    }
+
+    #[test]
+    fn test_parse_cluster_meta_invalid_address() {
+        let err = parse_cluster_meta_err(&[
+            SET_CLUSTER_API_VERSION,
+            "233",
+            "FORCE",
+            "cluster_name",
+            "127.0.0.1",
+            "1",
+            "0-1000",
+        ]);
+        assert_eq!(err, CmdParseError::InvalidAddress("127.0.0.1".to_string()));
+        assert_eq!(err.to_string(), "invalid address: 127.0.0.1");
+
+        let err = parse_cluster_meta_err(&[
+            SET_CLUSTER_API_VERSION,
+            "233",
+            "FORCE",
+            "cluster_name",
+            "127.0.0.1:7000",
+            "1",
+            "0-1000",
+            "PEER",
+            "127.0.0.2:port",
+            "1",
+            "1001-2000",
+        ]);
+        assert_eq!(
+            err,
+            CmdParseError::InvalidAddress("127.0.0.2:port".to_string())
+        );
+    }
 }
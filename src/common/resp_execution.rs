@@ -1,3 +1,4 @@
+use crate::common::clock::{Clock, SystemClock};
 use crate::common::utils::pretty_print_bytes;
 use crate::protocol::{
     BinSafeStr, OptionalMulti, RedisClient, RedisClientError, RedisClientFactory, Resp, RespVec,
@@ -10,15 +11,63 @@ use std::str;
 use std::sync::atomic;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::watch;
 
+// What to do when the connection is broken after the commands are sent.
+// The commands could have been partially written or even applied by then,
+// so only the idempotent commands should be sent again.
+// Failing to create the connection is always retried since nothing has been sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetPolicy {
+    Retry,
+    Abort,
+}
+
+// Only used for the idempotent commands. See `ResetPolicy`.
 pub async fn keep_connecting_and_sending_cmd_with_cached_client<F: RedisClientFactory, Func>(
     client: Option<F::Client>,
     client_factory: Arc<F>,
     address: String,
     opt_multi_cmd: OptionalMulti<Vec<BinSafeStr>>,
     interval: Duration,
+    clock: &dyn Clock,
     handle_result: Func,
 ) -> F::Client
+where
+    Func: Clone + Fn(OptionalMulti<RespVec>) -> Result<(), RedisClientError>,
+{
+    let mut client = client;
+    loop {
+        match keep_connecting_and_sending_cmd_with_policy(
+            client.take(),
+            client_factory.clone(),
+            address.clone(),
+            opt_multi_cmd.clone(),
+            interval,
+            clock,
+            ResetPolicy::Retry,
+            handle_result.clone(),
+        )
+        .await
+        {
+            Ok(c) => return c,
+            Err(err) => error!("unexpected error when retrying on reset: {:?}", err),
+        }
+    }
+}
+
+// Returns the error on a broken connection if `reset_policy` is `ResetPolicy::Abort`.
+#[allow(clippy::too_many_arguments)]
+pub async fn keep_connecting_and_sending_cmd_with_policy<F: RedisClientFactory, Func>(
+    client: Option<F::Client>,
+    client_factory: Arc<F>,
+    address: String,
+    opt_multi_cmd: OptionalMulti<Vec<BinSafeStr>>,
+    interval: Duration,
+    clock: &dyn Clock,
+    reset_policy: ResetPolicy,
+    handle_result: Func,
+) -> Result<F::Client, RedisClientError>
 where
     Func: Clone + Fn(OptionalMulti<RespVec>) -> Result<(), RedisClientError>,
 {
@@ -31,7 +80,7 @@ where
                 Ok(c) => c,
                 Err(err) => {
                     error!("failed to create client: {:?}", err);
-                    tokio::time::sleep(interval).await;
+                    clock.sleep(interval).await;
                     continue;
                 }
             }
@@ -41,6 +90,7 @@ where
             &mut c,
             opt_multi_cmd.clone(),
             interval,
+            clock,
             handle_result.clone(),
         )
         .await
@@ -48,7 +98,11 @@ where
             Ok(()) => {
                 client = Some(c);
             }
-            Err(RedisClientError::Done) => return c,
+            Err(RedisClientError::Done) => return Ok(c),
+            Err(err) if reset_policy == ResetPolicy::Abort => {
+                error!("failed to send commands {:?}. Abort without retrying.", err);
+                return Err(err);
+            }
             Err(err) => {
                 let debug_cmd = opt_multi_cmd.clone().map(|cmd| {
                     cmd.iter()
@@ -61,15 +115,17 @@ where
                 );
             }
         }
-        tokio::time::sleep(interval).await;
+        clock.sleep(interval).await;
     }
 }
 
+// Only used for the idempotent commands. See `ResetPolicy`.
 pub async fn keep_connecting_and_sending_cmd<F: RedisClientFactory, Func>(
     client_factory: Arc<F>,
     address: String,
-    cmd: Vec<Vec<u8>>,
+    cmd: Vec<BinSafeStr>,
     interval: Duration,
+    clock: &dyn Clock,
     handle_result: Func,
 ) where
     Func: Clone + Fn(RespVec) -> Result<(), RedisClientError>,
@@ -87,6 +143,7 @@ pub async fn keep_connecting_and_sending_cmd<F: RedisClientFactory, Func>(
         address,
         OptionalMulti::Single(cmd),
         interval,
+        clock,
         handler,
     )
     .await;
@@ -96,6 +153,7 @@ pub async fn keep_sending_cmd<C: RedisClient, Func>(
     client: &mut C,
     opt_mul_cmd: OptionalMulti<Vec<BinSafeStr>>,
     interval: Duration,
+    clock: &dyn Clock,
     handle_result: Func,
 ) -> Result<(), RedisClientError>
 where
@@ -107,10 +165,68 @@ where
             Err(err) => return Err(err),
         };
         handle_result(response)?;
-        tokio::time::sleep(interval).await;
+        clock.sleep(interval).await;
+    }
+}
+
+// Similar to `keep_sending_cmd` but the next command is generated from the last reply,
+// starting from `init_cmd`.
+pub async fn keep_sending_cmd_with<C: RedisClient, Func, NextCmdFunc>(
+    client: &mut C,
+    init_cmd: Vec<BinSafeStr>,
+    interval: Duration,
+    clock: &dyn Clock,
+    next_cmd: NextCmdFunc,
+    handle_result: Func,
+) -> Result<(), RedisClientError>
+where
+    NextCmdFunc: Fn(&RespVec) -> Vec<BinSafeStr>,
+    Func: Fn(RespVec) -> Result<(), RedisClientError>,
+{
+    let mut cmd = init_cmd;
+    loop {
+        let response = client.execute_single(cmd).await?;
+        cmd = next_cmd(&response);
+        handle_result(response)?;
+        clock.sleep(interval).await;
     }
 }
 
+// The reconnecting version of `keep_sending_cmd_with`.
+// After reconnecting, it continues from the last command instead of `init_cmd`,
+// so the last command is sent again and should be idempotent.
+pub async fn keep_connecting_and_sending_cmd_with<F: RedisClientFactory, Func, NextCmdFunc>(
+    client_factory: Arc<F>,
+    address: String,
+    init_cmd: Vec<BinSafeStr>,
+    interval: Duration,
+    clock: &dyn Clock,
+    next_cmd: NextCmdFunc,
+    handle_result: Func,
+) where
+    NextCmdFunc: Fn(&RespVec) -> Vec<BinSafeStr> + Clone + Send + 'static,
+    Func: Fn(RespVec) -> Result<(), RedisClientError> + Clone + Send + 'static,
+{
+    keep_connecting_and_sending(
+        init_cmd,
+        client_factory,
+        address,
+        interval,
+        clock,
+        move |cmd, client| {
+            let next_cmd = next_cmd.clone();
+            let handle_result = handle_result.clone();
+            Box::pin(async move {
+                let response = client.execute_single(cmd).await?;
+                let cmd = next_cmd(&response);
+                handle_result(response)?;
+                Ok(cmd)
+            })
+        },
+    )
+    .await;
+}
+
 pub fn retry_handle_func(response: OptionalMulti<RespVec>) -> Result<(), RedisClientError> {
     response.map(|resp| {
         if let Resp::Error(err) = resp {
@@ -128,10 +244,42 @@ pub async fn keep_connecting_and_sending<T: Send + Clone, F: RedisClientFactory,
     client_factory: Arc<F>,
     address: String,
     interval: Duration,
+    clock: &dyn Clock,
     send_func: Func,
 ) -> T
 // dyn Trait has default 'static lifetime.
 // '_ would use the lifetime of &mut F::Client instead.
+where
+    Func: Clone
+        + Send
+        + Fn(
+            T,
+            &mut F::Client,
+        ) -> Pin<Box<dyn Future<Output = Result<T, RedisClientError>> + Send + '_>>,
+{
+    keep_connecting_and_sending_to_any(
+        data,
+        client_factory,
+        vec![address],
+        interval,
+        clock,
+        send_func,
+    )
+    .await
+}
+
+// Similar to `keep_connecting_and_sending` but for the data that could be
+// retrieved from any of the `addresses`.
+// It tries the next address on connection failure
+// and only sleeps after all the addresses have failed in turn.
+pub async fn keep_connecting_and_sending_to_any<T: Send + Clone, F: RedisClientFactory, Func>(
+    data: T,
+    client_factory: Arc<F>,
+    addresses: Vec<String>,
+    interval: Duration,
+    clock: &dyn Clock,
+    send_func: Func,
+) -> T
 where
     Func: Clone
         + Send
@@ -141,15 +289,28 @@ where
         ) -> Pin<Box<dyn Future<Output = Result<T, RedisClientError>> + Send + '_>>,
 {
     let mut data = data;
+    let mut address_iter = addresses.iter().cycle();
+    let mut failures = 0;
     loop {
+        let address = match address_iter.next() {
+            Some(address) => address,
+            None => {
+                error!("no address to connect");
+                return data;
+            }
+        };
         let mut client = match client_factory.create_client(address.clone()).await {
             Ok(client) => client,
             Err(err) => {
-                error!("failed to create redis client: {:?}", err);
-                tokio::time::sleep(interval).await;
+                error!("failed to create redis client {}: {:?}", address, err);
+                failures += 1;
+                if failures % addresses.len() == 0 {
+                    clock.sleep(interval).await;
+                }
                 continue;
             }
         };
+        failures = 0;
         loop {
             data = match send_func(data.clone(), &mut client).await {
                 Ok(d) => d,
@@ -159,9 +320,9 @@ where
                     break;
                 }
             };
-            tokio::time::sleep(interval).await;
+            clock.sleep(interval).await;
         }
-        tokio::time::sleep(interval).await;
+        clock.sleep(interval).await;
     }
 }
 
@@ -169,6 +330,9 @@ type RetrieverFut = Pin<Box<dyn Future<Output = Result<(), RedisClientError>> +
 
 pub struct I64Retriever<F: RedisClientFactory> {
     data: Arc<atomic::AtomicI64>,
+    // Notified whenever the handle func changes the data.
+    data_sender: Arc<watch::Sender<i64>>,
+    data_receiver: watch::Receiver<i64>,
     stop_signal_sender: AtomicOption<oneshot::Sender<()>>,
     stop_signal_receiver: AtomicOption<oneshot::Receiver<()>>,
     client_factory: Arc<F>,
@@ -178,11 +342,14 @@ impl<F: RedisClientFactory> I64Retriever<F> {
     pub fn new(init_data: i64, client_factory: Arc<F>) -> Self {
         let (sender, receiver) = oneshot::channel();
         let data = Arc::new(atomic::AtomicI64::new(init_data));
+        let (data_sender, data_receiver) = watch::channel(init_data);
 
         let stop_signal_sender = AtomicOption::new(Box::new(sender));
         let stop_signal_receiver = AtomicOption::new(Box::new(receiver));
         Self {
             data,
+            data_sender: Arc::new(data_sender),
+            data_receiver,
             stop_signal_sender,
             stop_signal_receiver,
             client_factory,
@@ -193,11 +360,16 @@ impl<F: RedisClientFactory> I64Retriever<F> {
         self.data.load(atomic::Ordering::SeqCst)
     }
 
+    // For awaiting the next change of the data instead of polling `get_data`.
+    pub fn subscribe(&self) -> watch::Receiver<i64> {
+        self.data_receiver.clone()
+    }
+
     pub fn start<Func>(
         &self,
         handle_func: Func,
         address: String,
-        cmd: Vec<String>,
+        cmd: Vec<BinSafeStr>,
         interval: Duration,
     ) -> Option<RetrieverFut>
     where
@@ -210,15 +382,22 @@ impl<F: RedisClientFactory> I64Retriever<F> {
         if let Some(stop_signal_receiver) = self.stop_signal_receiver.take(atomic::Ordering::SeqCst)
         {
             let data_clone = self.data.clone();
+            let data_sender = self.data_sender.clone();
             let handle_result = move |resp: RespVec| -> Result<(), RedisClientError> {
-                handle_func(resp, &data_clone)
+                let res = handle_func(resp, &data_clone);
+                let data = data_clone.load(atomic::Ordering::SeqCst);
+                if *data_sender.borrow() != data {
+                    // `self.data_receiver` is always alive so it won't fail.
+                    let _ = data_sender.send(data);
+                }
+                res
             };
-            let cmd: Vec<Vec<u8>> = cmd.into_iter().map(|e| e.into_bytes()).collect();
             let sending = keep_connecting_and_sending_cmd(
                 self.client_factory.clone(),
                 address,
                 cmd,
                 interval,
+                &SystemClock,
                 handle_result,
             );
             // For `select!`
@@ -261,6 +440,7 @@ impl<F: RedisClientFactory> Drop for I64Retriever<F> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::clock::FakeClock;
     use crate::protocol::BinSafeStr;
     use crate::protocol::{OptionalMulti, Resp};
     use futures::future;
@@ -338,23 +518,276 @@ mod tests {
 
     #[tokio::test]
     async fn test_keep_sending_cmd() {
-        let interval = Duration::new(0, 0);
+        let interval = Duration::from_secs(1);
+        let clock = FakeClock::default();
         let counter = Arc::new(Counter::new(3));
         let mut client = DummyRedisClient::new(counter.clone());
-        let res = keep_sending_cmd(
+        let fut = keep_sending_cmd(
             &mut client,
             OptionalMulti::Single(vec![]),
             interval,
+            &clock,
             retry_handle_func,
-        )
-        .await;
+        );
+        let res = clock.drive(interval, fut).await;
         assert!(res.is_err());
         assert_eq!(counter.count.load(Ordering::SeqCst), 3);
+        assert_eq!(clock.get_sleep_history(), vec![interval; 3]);
+        assert_eq!(clock.elapsed(), interval * 3);
+    }
+
+    #[derive(Debug)]
+    struct IncrRedisClient {
+        sent_cmds: Vec<Vec<BinSafeStr>>,
+    }
+
+    impl RedisClient for IncrRedisClient {
+        fn execute<'s>(
+            &'s mut self,
+            command: OptionalMulti<Vec<BinSafeStr>>,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<OptionalMulti<RespVec>, RedisClientError>> + Send + 's>,
+        > {
+            let cmd = match command {
+                OptionalMulti::Single(cmd) => cmd,
+                OptionalMulti::Multi(_) => {
+                    return Box::pin(async { Err(RedisClientError::InvalidState) })
+                }
+            };
+            let offset = cmd
+                .get(1)
+                .and_then(|n| btoi::btoi::<i64>(n).ok())
+                .unwrap_or(0);
+            self.sent_cmds.push(cmd);
+            Box::pin(async move {
+                Ok(OptionalMulti::Single(Resp::Integer(
+                    (offset + 1).to_string().into_bytes(),
+                )))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_sending_cmd_with() {
+        let interval = Duration::from_secs(1);
+        let clock = FakeClock::default();
+        let mut client = IncrRedisClient { sent_cmds: vec![] };
+        let next_cmd = |resp: &RespVec| match resp {
+            Resp::Integer(offset) => vec![b"GET".to_vec(), offset.clone()],
+            _ => vec![b"GET".to_vec(), b"0".to_vec()],
+        };
+        let handler = |resp: RespVec| match resp {
+            Resp::Integer(offset) if offset.as_slice() == b"3" => Err(RedisClientError::Done),
+            _ => Ok(()),
+        };
+        let fut = keep_sending_cmd_with(
+            &mut client,
+            vec![b"GET".to_vec(), b"0".to_vec()],
+            interval,
+            &clock,
+            next_cmd,
+            handler,
+        );
+        let res = clock.drive(interval, fut).await;
+        assert!(matches!(res, Err(RedisClientError::Done)));
+        assert_eq!(clock.get_sleep_history(), vec![interval; 2]);
+        assert_eq!(
+            client.sent_cmds,
+            vec![
+                vec![b"GET".to_vec(), b"0".to_vec()],
+                vec![b"GET".to_vec(), b"1".to_vec()],
+                vec![b"GET".to_vec(), b"2".to_vec()],
+            ]
+        );
+    }
+
+    // Each connection is broken after `max_cmds` commands.
+    struct ResetClientFactory {
+        max_cmds: usize,
+        sent_cmds: Arc<parking_lot::Mutex<Vec<Vec<BinSafeStr>>>>,
+        connections: AtomicUsize,
+    }
+
+    struct ResetRedisClient {
+        remaining: usize,
+        sent_cmds: Arc<parking_lot::Mutex<Vec<Vec<BinSafeStr>>>>,
+    }
+
+    impl RedisClient for ResetRedisClient {
+        fn execute<'s>(
+            &'s mut self,
+            command: OptionalMulti<Vec<BinSafeStr>>,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<OptionalMulti<RespVec>, RedisClientError>> + Send + 's>,
+        > {
+            let cmd = match command {
+                OptionalMulti::Single(cmd) if self.remaining > 0 => cmd,
+                _ => return Box::pin(async { Err(RedisClientError::Closed) }),
+            };
+            self.remaining -= 1;
+            let offset = cmd
+                .get(1)
+                .and_then(|n| btoi::btoi::<i64>(n).ok())
+                .unwrap_or(0);
+            self.sent_cmds.lock().push(cmd);
+            Box::pin(async move {
+                Ok(OptionalMulti::Single(Resp::Integer(
+                    (offset + 1).to_string().into_bytes(),
+                )))
+            })
+        }
+    }
+
+    impl RedisClientFactory for ResetClientFactory {
+        type Client = ResetRedisClient;
+
+        fn create_client(
+            &self,
+            _address: String,
+        ) -> Pin<Box<dyn Future<Output = Result<Self::Client, RedisClientError>> + Send>> {
+            self.connections.fetch_add(1, Ordering::SeqCst);
+            Box::pin(future::ok(ResetRedisClient {
+                remaining: self.max_cmds,
+                sent_cmds: self.sent_cmds.clone(),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_connecting_and_sending_cmd_with() {
+        let interval = Duration::from_secs(1);
+        let clock = FakeClock::default();
+        let factory = Arc::new(ResetClientFactory {
+            max_cmds: 2,
+            sent_cmds: Arc::new(parking_lot::Mutex::new(vec![])),
+            connections: AtomicUsize::new(0),
+        });
+        let next_cmd = |resp: &RespVec| match resp {
+            Resp::Integer(offset) => vec![b"GET".to_vec(), offset.clone()],
+            _ => vec![b"GET".to_vec(), b"0".to_vec()],
+        };
+        let handler = |resp: RespVec| match resp {
+            Resp::Integer(offset) if offset.as_slice() == b"4" => Err(RedisClientError::Done),
+            _ => Ok(()),
+        };
+        let fut = keep_connecting_and_sending_cmd_with(
+            factory.clone(),
+            "host:port".to_string(),
+            vec![b"GET".to_vec(), b"0".to_vec()],
+            interval,
+            &clock,
+            next_cmd,
+            handler,
+        );
+        clock.drive(interval, fut).await;
+        // Continues from the last command after reconnecting.
+        assert_eq!(factory.connections.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            factory.sent_cmds.lock().clone(),
+            (0..4)
+                .map(|i| vec![b"GET".to_vec(), i.to_string().into_bytes()])
+                .collect::<Vec<_>>()
+        );
+    }
+
+    struct FailoverClientFactory {
+        counter: Arc<Counter>,
+        failed_address: String,
+        connected: parking_lot::Mutex<Vec<String>>,
+    }
+
+    impl RedisClientFactory for FailoverClientFactory {
+        type Client = DummyRedisClient;
+
+        fn create_client(
+            &self,
+            address: String,
+        ) -> Pin<Box<dyn Future<Output = Result<Self::Client, RedisClientError>> + Send>> {
+            self.connected.lock().push(address.clone());
+            if address == self.failed_address {
+                return Box::pin(future::err(RedisClientError::Closed));
+            }
+            Box::pin(future::ok(DummyRedisClient::new(self.counter.clone())))
+        }
+    }
+
+    fn send_ping(
+        sent: usize,
+        client: &mut DummyRedisClient,
+    ) -> Pin<Box<dyn Future<Output = Result<usize, RedisClientError>> + Send + '_>> {
+        Box::pin(async move {
+            client.execute_single(vec![b"PING".to_vec()]).await?;
+            if sent + 1 == 2 {
+                return Err(RedisClientError::Done);
+            }
+            Ok(sent + 1)
+        })
+    }
+
+    #[tokio::test]
+    async fn test_keep_connecting_and_sending_to_any() {
+        let interval = Duration::from_secs(1);
+        let clock = FakeClock::default();
+        let counter = Arc::new(Counter::new(3));
+        let factory = Arc::new(FailoverClientFactory {
+            counter: counter.clone(),
+            failed_address: "host1:port".to_string(),
+            connected: parking_lot::Mutex::new(vec![]),
+        });
+        let fut = keep_connecting_and_sending_to_any(
+            0,
+            factory.clone(),
+            vec!["host1:port".to_string(), "host2:port".to_string()],
+            interval,
+            &clock,
+            send_ping,
+        );
+        let sent = clock.drive(interval, fut).await;
+        assert_eq!(sent, 1);
+        assert_eq!(counter.count.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            factory.connected.lock().clone(),
+            vec!["host1:port".to_string(), "host2:port".to_string()]
+        );
+        // No sleep for switching to the second address.
+        assert_eq!(clock.get_sleep_history(), vec![interval]);
+    }
+
+    #[tokio::test]
+    async fn test_keep_connecting_and_sending_cmd_with_abort_policy() {
+        let interval = Duration::from_secs(1);
+        let clock = FakeClock::default();
+        // The connection is broken after the first command.
+        let counter = Arc::new(Counter::new(1));
+        let factory = Arc::new(FailoverClientFactory {
+            counter: counter.clone(),
+            failed_address: "".to_string(),
+            connected: parking_lot::Mutex::new(vec![]),
+        });
+        let fut = keep_connecting_and_sending_cmd_with_policy(
+            None,
+            factory.clone(),
+            "host:port".to_string(),
+            OptionalMulti::Single(vec![]),
+            interval,
+            &clock,
+            ResetPolicy::Abort,
+            retry_handle_func,
+        );
+        let res = clock.drive(interval, fut).await;
+        assert!(matches!(res, Err(RedisClientError::Closed)));
+        assert_eq!(counter.count.load(Ordering::SeqCst), 1);
+        // No reconnection.
+        assert_eq!(
+            factory.connected.lock().clone(),
+            vec!["host:port".to_string()]
+        );
     }
 
     #[tokio::test]
     async fn test_keep_connecting_and_sending() {
-        let interval = Duration::new(0, 0);
+        let interval = Duration::from_secs(1);
+        let clock = FakeClock::default();
         let counter = Arc::new(Counter::new(3));
         let retry_counter = Arc::new(Counter::new(2));
         let retry_counter_clone = retry_counter.clone();
@@ -367,15 +800,47 @@ mod tests {
             }
         };
         let factory = Arc::new(DummyClientFactory::new(counter.clone()));
-        keep_connecting_and_sending_cmd(
+        let fut = keep_connecting_and_sending_cmd(
             factory,
             "host:port".to_string(),
             vec![],
             interval,
+            &clock,
             handler,
-        )
-        .await;
+        );
+        clock.drive(interval, fut).await;
         assert_eq!(counter.count.load(Ordering::SeqCst), 3);
         assert_eq!(retry_counter_clone.count.load(Ordering::SeqCst), 2);
+        assert_eq!(clock.get_sleep_history(), vec![interval; 2]);
+    }
+
+    #[tokio::test]
+    async fn test_i64_retriever_notification() {
+        let counter = Arc::new(Counter::new(3));
+        let factory = Arc::new(DummyClientFactory::new(counter));
+        let retriever = I64Retriever::new(0, factory);
+        let mut receiver = retriever.subscribe();
+        let handle_func = |_resp: RespVec, data: &Arc<atomic::AtomicI64>| {
+            data.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+        let fut = retriever
+            .start(
+                handle_func,
+                "host:port".to_string(),
+                vec![],
+                Duration::from_millis(1),
+            )
+            .unwrap();
+        tokio::spawn(fut);
+
+        receiver.changed().await.unwrap();
+        assert!(*receiver.borrow() >= 1);
+        // Await the data crossing a threshold.
+        while *receiver.borrow() < 3 {
+            receiver.changed().await.unwrap();
+        }
+        assert_eq!(retriever.get_data(), 3);
+        assert!(retriever.stop());
     }
 }
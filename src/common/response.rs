@@ -1,4 +1,5 @@
 pub const OK_REPLY: &str = "OK";
+pub const PONG_REPLY: &str = "PONG";
 pub const OLD_EPOCH_REPLY: &str = "OLD_EPOCH";
 pub const TRY_AGAIN_REPLY: &str = "TRY_AGAIN";
 pub const NOT_READY_FOR_SWITCHING_REPLY: &str = "NOT_READY_FOR_SWITCHING";
@@ -7,9 +8,23 @@ pub const ERR_NOT_THE_SAME_SLOT: &str = "ERR_MULTI_SLOTS slots of the keys are n
 pub const ERR_CLUSTER_NOT_FOUND: &str = "ERR_CLUSTER_NOT_FOUND";
 pub const ERR_BACKEND_CONNECTION: &str = "ERR_BACKEND_CONNECTION";
 pub const ERR_MOVED: &str = "MOVED";
+pub const ERR_ASK: &str = "ASK";
+pub const ERR_CLUSTER_DOWN: &str = "CLUSTERDOWN";
 pub const CMD_NOT_SUPPORTED: &str = "ERR_COMMAND_NOT_SUPPORTED";
 pub const ERR_TOO_MANY_REDIRECTIONS: &str = "ERR_TOO_MANY_REDIRECTIONS";
 pub const MIGRATING_FINISHED: &str = "MIGRATING_FINISHED";
 pub const MIGRATION_TASK_NOT_FOUND: &str = "MIGRATION_TASK_NOT_FOUND";
 pub const ERR_MULTI_KEY_PARTIAL_ERROR: &str = "ERR_MULTI_KEY_PARTIAL_ERROR";
 pub const ERR_NOT_MY_META: &str = "ERR_NOT_MY_META";
+pub const ERR_INVALID_COMMAND: &str = "ERR invalid command: the command name is missing or empty";
+pub const ERR_COMMAND_TIMEOUT: &str = "ERR proxy command timeout";
+pub const ERR_PROTOCOL: &str =
+    "ERR Protocol error: invalid request or exceeding the protocol limits";
+pub const ERR_META_TOO_LARGE: &str = "ERR metadata payload too large";
+pub const ERR_LOADING_META: &str = "LOADING cluster metadata is not set yet";
+pub const ERR_READONLY: &str = "READONLY You can't write against a read only database.";
+pub const ERR_MAINTENANCE: &str = "TRYAGAIN maintenance in progress";
+// Clients match this exact message to start their AUTH flow.
+pub const ERR_NOAUTH: &str = "NOAUTH Authentication required.";
+pub const ERR_INVALID_CLIENT_NAME: &str =
+    "ERR Client names cannot contain spaces, newlines or special characters.";
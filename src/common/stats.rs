@@ -0,0 +1,30 @@
+macro_rules! atomic_usize_stats {
+    (pub struct $struct_name:ident {
+        $(pub $field_name:ident: AtomicUsize,)*
+    }) => {
+        pub struct $struct_name {
+            $(pub $field_name: AtomicUsize,)*
+        }
+
+        impl Default for $struct_name {
+            fn default() -> Self {
+                Self {
+                    $($field_name: AtomicUsize::new(0),)*
+                }
+            }
+        }
+
+        impl $struct_name {
+            pub fn to_lines_str(&self) -> Vec<(String, usize)> {
+                vec![
+                    $((
+                        stringify!($field_name).to_string(),
+                        self.$field_name.load(std::sync::atomic::Ordering::Relaxed),
+                    )),*
+                ]
+            }
+        }
+    }
+}
+
+pub(crate) use atomic_usize_stats;
@@ -1,19 +1,23 @@
-use super::response::ERR_MOVED;
+use super::response::{ERR_ASK, ERR_MOVED};
 use crate::protocol::{Array, BulkStr, Resp};
 use crate::protocol::{BinSafeStr, RespVec};
+use arc_swap::ArcSwap;
 use crc16::{State, ARC, XMODEM};
 use futures::{stream, Stream};
 use std::cmp::min;
 use std::fmt;
+use std::io;
 use std::net::SocketAddr;
 use std::str;
-use tokio::net::lookup_host;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use tokio::net::{lookup_host, TcpSocket};
 
 pub trait ThreadSafe: Send + Sync + 'static {}
 
 impl<T: Send + Sync + 'static> ThreadSafe for T {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CmdParseError {
     InvalidVersion,
     InvalidEpoch,
@@ -21,9 +25,27 @@ pub enum CmdParseError {
     InvalidSlots,
     InvalidConfig,
     InvalidRole,
+    InvalidAddress(String),
+    MissingField(&'static str),
     InvalidArgs,
 }
 
+impl fmt::Display for CmdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidVersion => write!(f, "unsupported version"),
+            Self::InvalidEpoch => write!(f, "invalid epoch"),
+            Self::InvalidClusterName => write!(f, "invalid cluster name"),
+            Self::InvalidSlots => write!(f, "invalid slot range"),
+            Self::InvalidConfig => write!(f, "invalid cluster config"),
+            Self::InvalidRole => write!(f, "invalid role"),
+            Self::InvalidAddress(address) => write!(f, "invalid address: {}", address),
+            Self::MissingField(field) => write!(f, "missing {}", field),
+            Self::InvalidArgs => write!(f, "invalid arguments"),
+        }
+    }
+}
+
 pub fn has_flags(s: &str, delimiter: char, flag: &'static str) -> bool {
     s.split(delimiter)
         .any(|s| str_ascii_case_insensitive_eq(s, flag))
@@ -45,6 +67,54 @@ pub async fn resolve_first_address(address: &str) -> Option<SocketAddr> {
     }
 }
 
+// `SO_SNDBUF` and `SO_RCVBUF` of the sockets. 0 keeps the default of the OS.
+// Note that setting them disables the buffer autotuning of Linux.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SocketBufferSizes {
+    send: u32,
+    recv: u32,
+}
+
+impl SocketBufferSizes {
+    pub const MIN_SIZE: u32 = 4096;
+    pub const MAX_SIZE: u32 = 256 * 1024 * 1024;
+
+    // Returns None if any of the non-zero sizes is out of range.
+    pub fn new(send: u32, recv: u32) -> Option<Self> {
+        let is_valid = |size: u32| size == 0 || (Self::MIN_SIZE..=Self::MAX_SIZE).contains(&size);
+        if !is_valid(send) || !is_valid(recv) {
+            return None;
+        }
+        Some(Self { send, recv })
+    }
+
+    pub fn get_send(&self) -> u32 {
+        self.send
+    }
+
+    pub fn get_recv(&self) -> u32 {
+        self.recv
+    }
+
+    // The sizes are set before connecting or listening
+    // so that the TCP window scale is negotiated with them.
+    // The accepted sockets inherit them from the listener.
+    pub fn new_socket(&self, address: SocketAddr) -> io::Result<TcpSocket> {
+        let socket = if address.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        if self.send != 0 {
+            socket.set_send_buffer_size(self.send)?;
+        }
+        if self.recv != 0 {
+            socket.set_recv_buffer_size(self.recv)?;
+        }
+        Ok(socket)
+    }
+}
+
 pub fn get_resp_bytes(resp: &RespVec) -> Option<Vec<Vec<u8>>> {
     match resp {
         Resp::Arr(Array::Arr(ref resps)) => {
@@ -149,11 +219,102 @@ pub fn gen_moved(slot: usize, addr: String) -> String {
     format!("{} {} {}", ERR_MOVED, slot, addr)
 }
 
+pub fn gen_ask(slot: usize, addr: String) -> String {
+    format!("{} {} {}", ERR_ASK, slot, addr)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedirectionType {
+    Moved,
+    Ask,
+}
+
+// The target of a `-MOVED` or `-ASK` reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedirectionTarget {
+    pub redirection_type: RedirectionType,
+    pub slot: usize,
+    pub address: String,
+}
+
+// Parses the error reply like `MOVED 3999 127.0.0.1:6381`.
+pub fn parse_redirection(err: &[u8]) -> Option<RedirectionTarget> {
+    let err = str::from_utf8(err).ok()?;
+    let mut it = err.split(' ');
+    let redirection_type = match it.next()? {
+        ERR_MOVED => RedirectionType::Moved,
+        ERR_ASK => RedirectionType::Ask,
+        _ => return None,
+    };
+    let slot = it.next()?.parse::<usize>().ok()?;
+    let address = it.next()?;
+    if it.next().is_some() || !address.contains(':') {
+        return None;
+    }
+    Some(RedirectionTarget {
+        redirection_type,
+        slot,
+        address: address.to_string(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HashTagDelimiters {
+    pub open: u8,
+    pub close: u8,
+}
+
+impl Default for HashTagDelimiters {
+    fn default() -> Self {
+        Self {
+            open: b'{',
+            close: b'}',
+        }
+    }
+}
+
+impl HashTagDelimiters {
+    // Parses the two ASCII characters like "{}".
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.as_bytes() {
+            &[open, close] if open.is_ascii() && close.is_ascii() => Some(Self { open, close }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashTagDelimiters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.open as char, self.close as char)
+    }
+}
+
+// Should only be set at startup.
+// Changing them at runtime would move the existing keys to other slots.
+static HASH_TAG_OPEN: AtomicU8 = AtomicU8::new(b'{');
+static HASH_TAG_CLOSE: AtomicU8 = AtomicU8::new(b'}');
+
+pub fn set_hash_tag_delimiters(delimiters: HashTagDelimiters) {
+    HASH_TAG_OPEN.store(delimiters.open, Ordering::Relaxed);
+    HASH_TAG_CLOSE.store(delimiters.close, Ordering::Relaxed);
+}
+
+pub fn get_hash_tag_delimiters() -> HashTagDelimiters {
+    HashTagDelimiters {
+        open: HASH_TAG_OPEN.load(Ordering::Relaxed),
+        close: HASH_TAG_CLOSE.load(Ordering::Relaxed),
+    }
+}
+
 pub fn get_hash_tag(key: &[u8]) -> &[u8] {
-    if let Some(begin) = key.iter().position(|x| *x as char == '{') {
+    get_hash_tag_with(key, get_hash_tag_delimiters())
+}
+
+fn get_hash_tag_with(key: &[u8], delimiters: HashTagDelimiters) -> &[u8] {
+    if let Some(begin) = key.iter().position(|x| *x == delimiters.open) {
         if let Some(end_offset) = key
             .get(begin + 1..)
-            .and_then(|t| t.iter().position(|x| *x as char == '}'))
+            .and_then(|t| t.iter().position(|x| *x == delimiters.close))
         {
             if end_offset == 0 {
                 return key;
@@ -166,9 +327,42 @@ pub fn get_hash_tag(key: &[u8]) -> &[u8] {
     key
 }
 
+// Maps the keys to the slots for request routing and `CLUSTER KEYSLOT`.
+// The returned slot must be less than `SLOT_NUM`.
+pub trait SlotHasher: Send + Sync {
+    fn hash_slot(&self, key: &[u8]) -> usize;
+}
+
+// The same as Redis Cluster.
+pub struct Crc16SlotHasher;
+
+impl SlotHasher for Crc16SlotHasher {
+    fn hash_slot(&self, key: &[u8]) -> usize {
+        State::<XMODEM>::calculate(get_hash_tag(key)) as usize % SLOT_NUM
+    }
+}
+
+// Same as the hash tag delimiters, it should only be set at startup.
+// The flag keeps the default `Crc16SlotHasher` free of the dynamic dispatch.
+static CUSTOM_SLOT_HASHER: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref SLOT_HASHER: ArcSwap<Box<dyn SlotHasher>> =
+        ArcSwap::new(Arc::new(Box::new(Crc16SlotHasher)));
+}
+
+pub fn set_slot_hasher(hasher: Box<dyn SlotHasher>) {
+    SLOT_HASHER.store(Arc::new(hasher));
+    CUSTOM_SLOT_HASHER.store(true, Ordering::Release);
+}
+
 // Hash function for request routing.
 pub fn generate_slot(key: &[u8]) -> usize {
-    State::<XMODEM>::calculate(get_hash_tag(key)) as usize % SLOT_NUM
+    if CUSTOM_SLOT_HASHER.load(Ordering::Acquire) {
+        SLOT_HASHER.lease().hash_slot(key)
+    } else {
+        Crc16SlotHasher.hash_slot(key)
+    }
 }
 
 // Hash function for locking in migration.
@@ -291,10 +485,37 @@ pub fn extract_host_from_address(address: &str) -> Option<&str> {
     Some(host)
 }
 
+// Only checks the `<host>:<port>` format without resolving it.
+pub fn is_valid_address(address: &str) -> bool {
+    match address.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_socket_buffer_sizes() {
+        assert_eq!(
+            SocketBufferSizes::new(0, 0),
+            Some(SocketBufferSizes::default())
+        );
+        assert!(SocketBufferSizes::new(65536, 0).is_some());
+        assert!(SocketBufferSizes::new(0, SocketBufferSizes::MAX_SIZE).is_some());
+        assert!(SocketBufferSizes::new(1024, 0).is_none());
+        assert!(SocketBufferSizes::new(0, SocketBufferSizes::MAX_SIZE + 1).is_none());
+
+        let address: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let sizes = SocketBufferSizes::new(65536, 65536).unwrap();
+        let socket = sizes.new_socket(address).unwrap();
+        // The OS could adjust the sizes, e.g. Linux doubles them.
+        assert!(socket.send_buffer_size().unwrap() >= 65536);
+        assert!(socket.recv_buffer_size().unwrap() >= 65536);
+    }
+
     #[test]
     fn test_get_hash_tag() {
         assert_eq!(
@@ -311,6 +532,52 @@ mod tests {
         assert_eq!(get_hash_tag("{".as_bytes()), "{".as_bytes());
     }
 
+    #[test]
+    fn test_crc16_slot_hasher() {
+        // Same as `CLUSTER KEYSLOT` of Redis.
+        assert_eq!(Crc16SlotHasher.hash_slot(b"foo"), 12182);
+        assert_eq!(Crc16SlotHasher.hash_slot(b"bar"), 5061);
+        assert_eq!(
+            Crc16SlotHasher.hash_slot(b"{foo}.bar"),
+            Crc16SlotHasher.hash_slot(b"foo")
+        );
+        assert_eq!(generate_slot(b"foo"), 12182);
+    }
+
+    #[test]
+    fn test_get_hash_tag_with_custom_delimiters() {
+        let delimiters = HashTagDelimiters::parse("<>").unwrap();
+        assert_eq!(
+            get_hash_tag_with(b"<user1000>.following", delimiters),
+            b"user1000"
+        );
+        assert_eq!(
+            get_hash_tag_with(b"{user1000}.following", delimiters),
+            b"{user1000}.following"
+        );
+        assert_eq!(get_hash_tag_with(b"foo<>xx", delimiters), b"foo<>xx");
+
+        let delimiters = HashTagDelimiters::parse("%%").unwrap();
+        assert_eq!(get_hash_tag_with(b"a%tag%b%c%", delimiters), b"tag");
+    }
+
+    #[test]
+    fn test_parse_hash_tag_delimiters() {
+        let default = HashTagDelimiters::default();
+        assert_eq!(HashTagDelimiters::parse("{}"), Some(default));
+        assert_eq!(default.to_string(), "{}");
+        assert_eq!(
+            HashTagDelimiters::parse("[]"),
+            Some(HashTagDelimiters {
+                open: b'[',
+                close: b']'
+            })
+        );
+        assert!(HashTagDelimiters::parse("{").is_none());
+        assert!(HashTagDelimiters::parse("{}}").is_none());
+        assert!(HashTagDelimiters::parse("《》").is_none());
+    }
+
     #[test]
     fn test_bytes_ascii_case_insensitive_eq() {
         assert!(bytes_ascii_case_insensitive_eq(b"a", b"a"));
@@ -344,4 +611,37 @@ mod tests {
         assert!(extract_host_from_address("").is_none());
         assert!(extract_host_from_address("localhost").is_none());
     }
+
+    #[test]
+    fn test_is_valid_address() {
+        assert!(is_valid_address("127.0.0.1:6379"));
+        assert!(is_valid_address("localhost:6379"));
+        assert!(!is_valid_address("127.0.0.1"));
+        assert!(!is_valid_address(":6379"));
+        assert!(!is_valid_address("127.0.0.1:port"));
+        assert!(!is_valid_address("127.0.0.1:65536"));
+    }
+
+    #[test]
+    fn test_parse_redirection() {
+        let target = parse_redirection(b"MOVED 3999 127.0.0.1:6381").unwrap();
+        assert_eq!(target.redirection_type, RedirectionType::Moved);
+        assert_eq!(target.slot, 3999);
+        assert_eq!(target.address, "127.0.0.1:6381");
+        let moved = gen_moved(233, "localhost:5299".to_string());
+        assert_eq!(
+            parse_redirection(moved.as_bytes()).unwrap().address,
+            "localhost:5299"
+        );
+
+        let target = parse_redirection(b"ASK 0 localhost:6000").unwrap();
+        assert_eq!(target.redirection_type, RedirectionType::Ask);
+        assert_eq!(target.slot, 0);
+
+        assert!(parse_redirection(b"ERR MOVED 1 localhost:6000").is_none());
+        assert!(parse_redirection(b"MOVED slot localhost:6000").is_none());
+        assert!(parse_redirection(b"MOVED 1").is_none());
+        assert!(parse_redirection(b"MOVED 1 localhost").is_none());
+        assert!(parse_redirection(b"ASK 1 localhost:6000 extra").is_none());
+    }
 }
@@ -1,3 +1,5 @@
 pub const UNDERMOON_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const UNDERMOON_MIGRATION_VERSION: &str = "mgr-0.2";
 pub const UNDERMOON_MEM_BROKER_META_VERSION: &str = "mem-broker-0.2";
+// The `redis_version` reported in INFO by default.
+pub const DEFAULT_REDIS_VERSION: &str = "7.0.0";
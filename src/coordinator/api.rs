@@ -1,29 +1,42 @@
+use super::broker::{MetaDataBroker, MetaDataBrokerError, MetaManipulationBroker};
 use super::core::CoordinateError;
+use super::http_meta_broker::FailureReport;
+use super::recover::ManualFailoverHandler;
 use super::service::CoordinatorConfig;
 use crate::common::response;
 use crate::common::track::TrackedFutureRegistry;
 use crate::common::utils::resolve_first_address;
-use crate::protocol::{Array, BulkStr, Resp, RespPacket, RespVec};
+use crate::protocol::{
+    Array, BulkStr, ProtocolLimits, RedisClientFactory, Resp, RespPacket, RespVec,
+};
 use crate::proxy::command::{new_command_pair, CmdType, Command, TaskReply};
-use crate::proxy::session::{handle_session, CmdHandler, CmdReplyFuture};
+use crate::proxy::session::{handle_session, CmdHandler, CmdReplyFuture, OutputBufferLimits};
 use crate::proxy::slowlog::Slowlog;
-use futures::{FutureExt, StreamExt};
+use futures::{FutureExt, StreamExt, TryStreamExt};
 use std::str;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 
-pub struct ApiService {
+pub struct ApiService<DB: MetaDataBroker, MB: MetaManipulationBroker, F: RedisClientFactory> {
     config: Arc<CoordinatorConfig>,
     future_registry: Arc<TrackedFutureRegistry>,
+    data_broker: Arc<DB>,
+    failover_handler: Arc<ManualFailoverHandler<DB, MB, F>>,
 }
 
-impl ApiService {
-    pub fn new(config: Arc<CoordinatorConfig>) -> ApiService {
+impl<DB: MetaDataBroker, MB: MetaManipulationBroker, F: RedisClientFactory> ApiService<DB, MB, F> {
+    pub fn new(
+        config: Arc<CoordinatorConfig>,
+        data_broker: Arc<DB>,
+        failover_handler: Arc<ManualFailoverHandler<DB, MB, F>>,
+    ) -> Self {
         let future_registry = Arc::new(TrackedFutureRegistry::default());
         Self {
             config,
             future_registry,
+            data_broker,
+            failover_handler,
         }
     }
 
@@ -68,8 +81,13 @@ impl ApiService {
                 Arc::new(CoordCmdHandler::new(
                     config.clone(),
                     future_registry.clone(),
+                    self.data_broker.clone(),
+                    self.failover_handler.clone(),
                 )),
                 sock,
+                ProtocolLimits::default(),
+                None,
+                OutputBufferLimits::default(),
             );
 
             let desc = format!("session: session_id={} peer={}", curr_session_id, peer);
@@ -84,16 +102,30 @@ impl ApiService {
     }
 }
 
-struct CoordCmdHandler {
+// The default maximum replication offset lag in bytes for UMCTL FAILOVER.
+const DEFAULT_FAILOVER_MAX_LAG: u64 = 0;
+
+struct CoordCmdHandler<DB: MetaDataBroker, MB: MetaManipulationBroker, F: RedisClientFactory> {
     config: Arc<CoordinatorConfig>,
     future_registry: Arc<TrackedFutureRegistry>,
+    data_broker: Arc<DB>,
+    failover_handler: Arc<ManualFailoverHandler<DB, MB, F>>,
 }
 
-impl CoordCmdHandler {
-    fn new(config: Arc<CoordinatorConfig>, future_registry: Arc<TrackedFutureRegistry>) -> Self {
+impl<DB: MetaDataBroker, MB: MetaManipulationBroker, F: RedisClientFactory>
+    CoordCmdHandler<DB, MB, F>
+{
+    fn new(
+        config: Arc<CoordinatorConfig>,
+        future_registry: Arc<TrackedFutureRegistry>,
+        data_broker: Arc<DB>,
+        failover_handler: Arc<ManualFailoverHandler<DB, MB, F>>,
+    ) -> Self {
         Self {
             config,
             future_registry,
+            data_broker,
+            failover_handler,
         }
     }
 
@@ -177,6 +209,72 @@ impl CoordCmdHandler {
         Resp::Arr(Array::Arr(elements))
     }
 
+    // UMCTL FAILOVER <proxy address> [max lag]
+    async fn handle_umctl_failover(&self, cmd: &Command) -> RespVec {
+        let proxy_address = match Self::get_sub_arg(cmd, 2) {
+            Ok(address) => address,
+            Err(err) => {
+                return Resp::Error(err.into_bytes());
+            }
+        };
+        let max_lag = match Self::get_sub_arg(cmd, 3) {
+            Ok(max_lag) => match max_lag.parse::<u64>() {
+                Ok(max_lag) => max_lag,
+                Err(_) => {
+                    return Resp::Error(b"Invalid max lag".to_vec());
+                }
+            },
+            Err(_) => DEFAULT_FAILOVER_MAX_LAG,
+        };
+
+        match self
+            .failover_handler
+            .failover(proxy_address.clone(), max_lag)
+            .await
+        {
+            Ok(res) => match res.proxy {
+                Some(new_proxy) => Resp::Bulk(BulkStr::Str(
+                    new_proxy.get_address().to_string().into_bytes(),
+                )),
+                None => Resp::Bulk(BulkStr::Nil),
+            },
+            Err(err) => {
+                warn!(
+                    "failed to fail over proxy address={} error={}",
+                    proxy_address, err
+                );
+                Resp::Error(format!("ERR failover rejected: {}", err).into_bytes())
+            }
+        }
+    }
+
+    // UMCTL FAILURES
+    // Lists the unexpired failures reported by all the coordinators as
+    // <proxy address> <reporter id> <report time>
+    async fn handle_umctl_failures(&self) -> RespVec {
+        let res: Result<Vec<FailureReport>, MetaDataBrokerError> =
+            self.data_broker.get_failure_reports().try_collect().await;
+        match res {
+            Ok(reports) => {
+                let elements = reports
+                    .into_iter()
+                    .map(|report| {
+                        let line = format!(
+                            "{} {} {}",
+                            report.proxy_address, report.reporter_id, report.report_time
+                        );
+                        Resp::Bulk(BulkStr::Str(line.into_bytes()))
+                    })
+                    .collect();
+                Resp::Arr(Array::Arr(elements))
+            }
+            Err(err) => {
+                warn!("failed to get failure reports: {}", err);
+                Resp::Error(format!("ERR failed to get failure reports: {}", err).into_bytes())
+            }
+        }
+    }
+
     fn get_sub_arg(cmd: &Command, index: usize) -> Result<String, String> {
         let sub_cmd = match cmd.get_command_element(index) {
             None => return Err("Missing sub argument".to_string()),
@@ -189,11 +287,33 @@ impl CoordCmdHandler {
     }
 }
 
-impl CmdHandler for CoordCmdHandler {
+impl<DB: MetaDataBroker, MB: MetaManipulationBroker, F: RedisClientFactory> CmdHandler
+    for CoordCmdHandler<DB, MB, F>
+{
     fn handle_cmd(&self, cmd: Command) -> CmdReplyFuture {
-        let (mut reply_sender, reply_receiver) = new_command_pair(&cmd);
-
         let cmd_type = cmd.get_type();
+        // These sub commands need to access the brokers asynchronously.
+        let async_sub_cmd = match cmd_type {
+            CmdType::UmCtl => Self::get_sub_arg(&cmd, 1)
+                .ok()
+                .map(|sub_cmd| sub_cmd.to_uppercase())
+                .filter(|sub_cmd| sub_cmd == "FAILOVER" || sub_cmd == "FAILURES"),
+            _ => None,
+        };
+        if let Some(sub_cmd) = async_sub_cmd {
+            return CmdReplyFuture::Right(Box::pin(async move {
+                let resp = match sub_cmd.as_str() {
+                    "FAILOVER" => self.handle_umctl_failover(&cmd).await,
+                    _ => self.handle_umctl_failures().await,
+                };
+                let request = cmd.into_packet();
+                let response = Box::new(RespPacket::Data(resp));
+                let slowlog = Slowlog::new(0, false); // not used
+                Ok(Box::new(TaskReply::new(request, response, slowlog)))
+            }));
+        }
+
+        let (mut reply_sender, reply_receiver) = new_command_pair(&cmd);
         let resp = match cmd_type {
             CmdType::Config => self.handle_config(&cmd),
             CmdType::UmCtl => self.handle_umctl(&cmd),
@@ -218,4 +338,87 @@ impl CmdHandler for CoordCmdHandler {
     }
 
     fn handle_slowlog(&self, _request: Box<RespPacket>, _slowlog: Slowlog) {}
+    fn set_output_buffer_size(&self, _size: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::broker::MockMetaManipulationBroker;
+    use super::super::test_support::InMemoryMetaBroker;
+    use super::*;
+    use crate::protocol::{DummyRedisClientFactory, MockRedisClient};
+    use arc_swap::ArcSwap;
+
+    fn gen_config() -> CoordinatorConfig {
+        CoordinatorConfig {
+            address: "127.0.0.1:6699".to_string(),
+            broker_addresses: Arc::new(ArcSwap::new(Arc::new(vec![]))),
+            reporter_id: "coordinator1".to_string(),
+            thread_number: 1,
+            proxy_timeout: 2,
+            enable_compression: false,
+            disable_failover: false,
+            min_ping_retry: 1,
+            max_ping_retry: 1,
+            heartbeat_interval: 0,
+            isolate_detector_conn: false,
+            detector_interval: 1000,
+            detector_stagger: 0,
+            detector_jitter: false,
+            proxy_admin_password: None,
+        }
+    }
+
+    fn gen_cmd(args: &[&str]) -> Command {
+        let resp = Resp::Arr(Array::Arr(
+            args.iter()
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+                .collect(),
+        ));
+        Command::new(Box::new(RespPacket::from_resp_vec(resp)))
+    }
+
+    #[tokio::test]
+    async fn test_umctl_failures() {
+        let data_broker = Arc::new(InMemoryMetaBroker::default());
+        data_broker
+            .add_failures(
+                vec!["127.0.0.1:5299".to_string(), "127.0.0.1:5300".to_string()],
+                "coordinator2".to_string(),
+            )
+            .await
+            .unwrap();
+        let failover_handler = Arc::new(ManualFailoverHandler::new(
+            data_broker.clone(),
+            Arc::new(MockMetaManipulationBroker::new()),
+            Arc::new(DummyRedisClientFactory::new(
+                |_| MockRedisClient::new(),
+                false,
+            )),
+        ));
+        let handler = CoordCmdHandler::new(
+            Arc::new(gen_config()),
+            Arc::new(TrackedFutureRegistry::default()),
+            data_broker,
+            failover_handler,
+        );
+
+        let reply = handler
+            .handle_cmd(gen_cmd(&["UMCTL", "FAILURES"]))
+            .await
+            .unwrap();
+        let lines: Vec<String> = match reply.into_inner().1.into_resp_vec() {
+            Resp::Arr(Array::Arr(elements)) => elements
+                .into_iter()
+                .map(|element| match element {
+                    Resp::Bulk(BulkStr::Str(line)) => String::from_utf8(line).unwrap(),
+                    other => panic!("unexpected element {:?}", other),
+                })
+                .collect(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("127.0.0.1:5299 coordinator2 "));
+        assert!(lines[1].starts_with("127.0.0.1:5300 coordinator2 "));
+    }
 }
@@ -1,4 +1,6 @@
-use crate::common::cluster::{Cluster, ClusterName, MigrationTaskMeta, Proxy};
+use super::http_mani_broker::{ProxyHeartbeat, ReplaceProxyResponse};
+use super::http_meta_broker::FailureReport;
+use crate::common::cluster::{Cluster, ClusterName, MigrationTaskMeta, Proxy, ReplaceProxyHint};
 use crate::common::utils::ThreadSafe;
 use futures::{Future, Stream};
 use mockall::automock;
@@ -44,10 +46,22 @@ mod trait_mod {
             reporter_id: String,
         ) -> Pin<Box<dyn Future<Output = Result<(), MetaDataBrokerError>> + Send + 's>>;
 
+        // Reports the failures found in the same round with one request.
+        fn add_failures<'s>(
+            &'s self,
+            addresses: Vec<String>,
+            reporter_id: String,
+        ) -> Pin<Box<dyn Future<Output = Result<(), MetaDataBrokerError>> + Send + 's>>;
+
         fn get_failures<'s>(
             &'s self,
         ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>>;
 
+        // All the unexpired failures with their reporters for diagnosis.
+        fn get_failure_reports<'s>(
+            &'s self,
+        ) -> Pin<Box<dyn Stream<Item = Result<FailureReport, MetaDataBrokerError>> + Send + 's>>;
+
         fn get_failed_proxies<'s>(
             &'s self,
         ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>>;
@@ -60,15 +74,27 @@ mod trait_mod {
         fn replace_proxy<'s>(
             &'s self,
             failed_proxy_address: String,
+            hint: ReplaceProxyHint,
         ) -> Pin<
             Box<
-                dyn Future<Output = Result<Option<Proxy>, MetaManipulationBrokerError>> + Send + 's,
+                dyn Future<Output = Result<ReplaceProxyResponse, MetaManipulationBrokerError>>
+                    + Send
+                    + 's,
             >,
         >;
 
+        // The commit will be rejected with `EpochChanged`
+        // if the cluster epoch is not `expected_epoch` anymore.
         fn commit_migration<'s>(
             &'s self,
             meta: MigrationTaskMeta,
+            expected_epoch: Option<u64>,
+        ) -> Pin<Box<dyn Future<Output = Result<(), MetaManipulationBrokerError>> + Send + 's>>;
+
+        // Reports a proxy reachable from this coordinator.
+        fn heartbeat<'s>(
+            &'s self,
+            heartbeat: ProxyHeartbeat,
         ) -> Pin<Box<dyn Future<Output = Result<(), MetaManipulationBrokerError>> + Send + 's>>;
     }
 }
@@ -112,6 +138,7 @@ pub enum MetaManipulationBrokerError {
     InvalidReply,
     NoBroker,
     Retry,
+    EpochChanged,
 }
 
 impl fmt::Display for MetaManipulationBrokerError {
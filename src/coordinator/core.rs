@@ -1,11 +1,16 @@
 use super::broker::{MetaDataBrokerError, MetaManipulationBrokerError};
+use crate::common::clock::{Clock, SystemClock};
 use crate::common::cluster::{MigrationTaskMeta, Proxy};
 use crate::protocol::RedisClientError;
+use futures::channel::mpsc;
 use futures::{future, stream, Future, FutureExt, Stream, StreamExt, TryFutureExt};
 use futures_batch::ChunksTimeoutStreamExt;
 use mockall::automock;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
+use std::hash::BuildHasher;
 use std::io;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -19,20 +24,152 @@ pub trait ProxiesRetriever: Sync + Send + 'static {
 
 pub type ProxyFailure = String; // proxy address
 
+// Different kinds of failures imply different remediation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    // The connection is refused or reset. The proxy process is probably down.
+    Refused,
+    // No response in time. The host or the network might be down or overloaded.
+    Timeout,
+    // The proxy is reachable but replies something unexpected.
+    Protocol,
+    Other,
+}
+
+impl FailureKind {
+    pub fn from_client_error(err: &RedisClientError) -> Self {
+        match err {
+            RedisClientError::Timeout => Self::Timeout,
+            RedisClientError::Io(err) => match err.kind() {
+                io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe => Self::Refused,
+                io::ErrorKind::TimedOut => Self::Timeout,
+                _ => Self::Other,
+            },
+            RedisClientError::Closed => Self::Refused,
+            RedisClientError::InvalidReply | RedisClientError::EncodeError => Self::Protocol,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Refused => "refused",
+            Self::Timeout => "timeout",
+            Self::Protocol => "protocol",
+            Self::Other => "other",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedFailure {
+    pub address: String,
+    pub kind: FailureKind,
+}
+
 pub trait FailureChecker: Sync + Send + 'static {
     fn check<'s>(
         &'s self,
         address: String,
-    ) -> Pin<Box<dyn Future<Output = Result<Option<String>, CoordinateError>> + Send + 's>>;
+    ) -> Pin<Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send + 's>>;
+
+    // Called with all the checked addresses after a complete round
+    // to clean up the states of the removed proxies.
+    fn retain_addresses(&self, _addresses: &HashSet<String>) {}
 }
 
 pub trait FailureReporter: Sync + Send + 'static {
     fn report<'s>(
         &'s self,
-        address: String,
+        failure: DetectedFailure,
+    ) -> Pin<Box<dyn Future<Output = Result<(), CoordinateError>> + Send + 's>>;
+
+    // Reports the failures together to reduce the load of the broker
+    // when many proxies fail at the same time.
+    fn report_batch<'s>(
+        &'s self,
+        failures: Vec<DetectedFailure>,
     ) -> Pin<Box<dyn Future<Output = Result<(), CoordinateError>> + Send + 's>>;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectorEventKind {
+    Failure,
+    Recovery,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectorEvent {
+    pub address: String,
+    pub kind: DetectorEventKind,
+}
+
+pub type FailureCallback =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+// Side effects like alerting run at the moment a proxy is detected failed or recovered.
+// The callbacks are awaited before reporting to the broker.
+// This should live longer than the detectors to remember the failed proxies.
+#[derive(Default)]
+pub struct FailureHooks {
+    on_failure: Option<FailureCallback>,
+    on_recovery: Option<FailureCallback>,
+    failed_proxies: parking_lot::Mutex<HashSet<String>>,
+    subscribers: parking_lot::Mutex<Vec<mpsc::UnboundedSender<DetectorEvent>>>,
+}
+
+impl FailureHooks {
+    pub fn new(on_failure: Option<FailureCallback>, on_recovery: Option<FailureCallback>) -> Self {
+        Self {
+            on_failure,
+            on_recovery,
+            failed_proxies: parking_lot::Mutex::new(HashSet::new()),
+            subscribers: parking_lot::Mutex::new(vec![]),
+        }
+    }
+
+    // Receives the same events as the callbacks without blocking the detectors.
+    // Dropping the stream unsubscribes it.
+    pub fn subscribe(&self) -> impl Stream<Item = DetectorEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers.lock().push(sender);
+        receiver
+    }
+
+    fn publish(&self, address: String, kind: DetectorEventKind) {
+        let event = DetectorEvent { address, kind };
+        self.subscribers
+            .lock()
+            .retain(|sender| sender.unbounded_send(event.clone()).is_ok());
+    }
+
+    async fn notify_failure(&self, address: String) {
+        if !self.failed_proxies.lock().insert(address.clone()) {
+            return;
+        }
+        self.publish(address.clone(), DetectorEventKind::Failure);
+        if let Some(on_failure) = self.on_failure.as_ref() {
+            on_failure(address).await;
+        }
+    }
+
+    async fn notify_recovery(&self, address: String) {
+        if !self.failed_proxies.lock().remove(&address) {
+            return;
+        }
+        self.publish(address.clone(), DetectorEventKind::Recovery);
+        if let Some(on_recovery) = self.on_recovery.as_ref() {
+            on_recovery(address).await;
+        }
+    }
+}
+
 pub trait FailureDetector {
     type Retriever: ProxiesRetriever;
     type Checker: FailureChecker;
@@ -50,64 +187,150 @@ pub struct ParFailureDetector<
     retriever: Retriever,
     checker: Arc<Checker>,
     reporter: Arc<Reporter>,
+    hooks: Option<Arc<FailureHooks>>,
+    stagger: Duration,
+    jitter_window: Duration,
+    clock: Arc<dyn Clock>,
 }
 
 impl<T: ProxiesRetriever, C: FailureChecker, P: FailureReporter> ParFailureDetector<T, C, P> {
-    async fn check_and_report(
+    pub fn with_hooks(mut self, hooks: Arc<FailureHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    // Delay the checks in the same batch one after another by `stagger`
+    // instead of sending all the PINGs at once.
+    pub fn with_stagger(mut self, stagger: Duration) -> Self {
+        self.stagger = stagger;
+        self
+    }
+
+    // Spread the start of the checks of a round across `jitter_window`
+    // to avoid the connection spike at the beginning of every round.
+    pub fn with_jitter_window(mut self, jitter_window: Duration) -> Self {
+        self.jitter_window = jitter_window;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    async fn check(
         checker: &C,
-        reporter: &P,
+        hooks: Option<&FailureHooks>,
         address: String,
-    ) -> Result<(), CoordinateError> {
-        let address = match checker.check(address).await? {
-            Some(addr) => addr,
-            None => return Ok(()),
+    ) -> Result<Option<DetectedFailure>, CoordinateError> {
+        let failure = match checker.check(address.clone()).await? {
+            Some(failure) => failure,
+            None => {
+                if let Some(hooks) = hooks {
+                    hooks.notify_recovery(address).await;
+                }
+                return Ok(None);
+            }
         };
-        if let Err(err) = reporter.report(address).await {
-            error!("failed to report failure: {:?}", err);
-            return Err(err);
+        if let Some(hooks) = hooks {
+            hooks.notify_failure(failure.address.clone()).await;
         }
-        Ok(())
+        Ok(Some(failure))
     }
 
     async fn run_impl(&self) -> Result<(), CoordinateError> {
         let checker = self.checker.clone();
         let reporter = self.reporter.clone();
         const BATCH_SIZE: usize = 30;
-        const BATCH_TIME: Duration = Duration::from_millis(1);
 
         let mut res = Ok(());
-        let mut s = self
-            .retriever
-            .retrieve_proxies()
-            .chunks_timeout(BATCH_SIZE, BATCH_TIME);
-
-        while let Some(results) = s.next().await {
-            let mut proxies = vec![];
-            for r in results {
-                match r {
-                    Ok(proxy) => proxies.push(proxy),
-                    Err(err) => {
-                        error!("failed to get proxy: {:?}", err);
-                        res = Err(err);
-                    }
+        let mut failed_proxies = vec![];
+        let mut retrieve_failed = false;
+        let mut s = self.retriever.retrieve_proxies();
+
+        // Get all the proxies first since the jitter depends on the proxy number.
+        let mut proxies = vec![];
+        while let Some(r) = s.next().await {
+            match r {
+                Ok(proxy) => proxies.push(proxy),
+                Err(err) => {
+                    error!("failed to get proxy: {:?}", err);
+                    retrieve_failed = true;
+                    res = Err(err);
                 }
             }
-            let futs: Vec<_> = proxies
-                .into_iter()
-                .map(|address| Self::check_and_report(&checker, &reporter, address))
+        }
+        let checked_proxies: HashSet<String> = proxies.iter().cloned().collect();
+        let jitter_slot = match proxies.len() {
+            0 => Duration::from_secs(0),
+            proxy_num => self.jitter_window / proxy_num as u32,
+        };
+
+        let round_start = self.clock.now();
+        for (batch_index, batch) in proxies.chunks(BATCH_SIZE).enumerate() {
+            // The jitter is based on the start of the round
+            // while the stagger is based on the start of the batch.
+            let round_elapsed = self.clock.now().saturating_duration_since(round_start);
+            let futs: Vec<_> = batch
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(i, address)| {
+                    let jitter = gen_jitter(jitter_slot, batch_index * BATCH_SIZE + i, &address);
+                    let delay = self.stagger.saturating_mul(i as u32)
+                        + jitter.saturating_sub(round_elapsed);
+                    let checker = &checker;
+                    let hooks = self.hooks.as_deref();
+                    let clock = &self.clock;
+                    async move {
+                        if delay != Duration::from_secs(0) {
+                            clock.sleep(delay).await;
+                        }
+                        Self::check(checker, hooks, address).await
+                    }
+                })
                 .collect();
             let results = future::join_all(futs).await;
             for r in results.into_iter() {
-                if let Err(err) = r {
-                    error!("faild to check and report error: {:?}", err);
-                    res = Err(err);
+                match r {
+                    Ok(Some(failure)) => failed_proxies.push(failure),
+                    Ok(None) => (),
+                    Err(err) => {
+                        error!("faild to check error: {:?}", err);
+                        res = Err(err);
+                    }
                 }
             }
         }
+        // Report all the failures of this round together.
+        let report_res = match failed_proxies.len() {
+            0 => Ok(()),
+            1 => reporter.report(failed_proxies.swap_remove(0)).await,
+            _ => reporter.report_batch(failed_proxies).await,
+        };
+        if let Err(err) = report_res {
+            error!("failed to report failures: {:?}", err);
+            res = Err(err);
+        }
+        // The proxy list is incomplete on retrieval errors.
+        if !retrieve_failed {
+            checker.retain_addresses(&checked_proxies);
+        }
         res
     }
 }
 
+// The i-th check starts at a random time within [i * slot, (i + 1) * slot)
+// so that the checks are spread evenly.
+fn gen_jitter(slot: Duration, i: usize, address: &str) -> Duration {
+    let slot_nanos = slot.as_nanos() as u64;
+    if slot_nanos == 0 {
+        return Duration::from_secs(0);
+    }
+    let random = RandomState::new().hash_one(address);
+    slot.saturating_mul(i as u32) + Duration::from_nanos(random % slot_nanos)
+}
+
 impl<T: ProxiesRetriever, C: FailureChecker, P: FailureReporter> FailureDetector
     for ParFailureDetector<T, C, P>
 {
@@ -120,6 +343,10 @@ impl<T: ProxiesRetriever, C: FailureChecker, P: FailureReporter> FailureDetector
             retriever,
             checker: Arc::new(checker),
             reporter: Arc::new(reporter),
+            hooks: None,
+            stagger: Duration::from_secs(0),
+            jitter_window: Duration::from_secs(0),
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -601,7 +828,8 @@ mod tests {
         fn check(
             &self,
             _address: String,
-        ) -> Pin<Box<dyn Future<Output = Result<Option<String>, CoordinateError>> + Send>> {
+        ) -> Pin<Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send>>
+        {
             Box::pin(future::ok(None))
         }
     }
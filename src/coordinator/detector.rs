@@ -1,11 +1,15 @@
 use super::broker::MetaDataBroker;
-use super::core::{CoordinateError, FailureChecker, FailureReporter, ProxiesRetriever};
+use super::core::{
+    CoordinateError, DetectedFailure, FailureChecker, FailureKind, FailureReporter,
+    ProxiesRetriever,
+};
+use crate::common::clock::Clock;
 use crate::common::cluster::Cluster;
-use crate::protocol::{RedisClient, RedisClientFactory};
+use crate::protocol::{CmdBuilder, RedisClient, RedisClientFactory};
 use futures::{future, stream, Future, FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use futures_batch::ChunksTimeoutStreamExt;
 use std::cmp;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
@@ -212,52 +216,139 @@ impl<B: MetaDataBroker> ProxiesRetriever for BrokerOrderedProxiesRetriever<B> {
     }
 }
 
+const DEFAULT_PING_RETRY: usize = 3;
+// After this number of consecutive clean checks, a proxy is considered reliable.
+const PING_CLEAN_ROUNDS: usize = 30;
+// A proxy is considered flaky for this number of checks after a failed ping.
+const PING_FLAKY_ROUNDS: usize = 10;
+// Give the proxy a moment to recover before the next ping.
+const PING_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct PingHistory {
+    clean_rounds: usize,
+    flaky_rounds: usize,
+}
+
+// Adjusts the ping retries of each proxy by its recent ping results.
+// Reliable proxies use `min_retry` to detect failures faster
+// while recently flaky proxies use `max_retry` to avoid false positives.
+// This should live longer than the detectors to remember the history.
+pub struct PingRetryHistory {
+    min_retry: usize,
+    max_retry: usize,
+    history: parking_lot::Mutex<HashMap<String, PingHistory>>,
+}
+
+impl PingRetryHistory {
+    pub fn new(min_retry: usize, max_retry: usize) -> Self {
+        let min_retry = cmp::max(min_retry, 1);
+        let max_retry = cmp::max(max_retry, min_retry);
+        Self {
+            min_retry,
+            max_retry,
+            history: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_retry(&self, address: &str) -> usize {
+        let default_retry = cmp::min(cmp::max(DEFAULT_PING_RETRY, self.min_retry), self.max_retry);
+        match self.history.lock().get(address) {
+            Some(history) if history.flaky_rounds > 0 => self.max_retry,
+            Some(history) if history.clean_rounds >= PING_CLEAN_ROUNDS => self.min_retry,
+            _ => default_retry,
+        }
+    }
+
+    // `clean` is true only when the first ping succeeds.
+    pub fn record(&self, address: String, clean: bool) {
+        let mut history = self.history.lock();
+        let history = history.entry(address).or_default();
+        if clean {
+            history.clean_rounds = history.clean_rounds.saturating_add(1);
+            history.flaky_rounds = history.flaky_rounds.saturating_sub(1);
+        } else {
+            history.clean_rounds = 0;
+            history.flaky_rounds = PING_FLAKY_ROUNDS;
+        }
+    }
+
+    pub fn retain(&self, addresses: &HashSet<String>) {
+        self.history
+            .lock()
+            .retain(|address, _| addresses.contains(address));
+    }
+}
+
 pub struct PingFailureDetector<F: RedisClientFactory> {
     client_factory: Arc<F>,
+    retry_history: Arc<PingRetryHistory>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<F: RedisClientFactory> PingFailureDetector<F> {
-    pub fn new(client_factory: Arc<F>) -> Self {
-        Self { client_factory }
+    pub fn new(
+        client_factory: Arc<F>,
+        retry_history: Arc<PingRetryHistory>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            client_factory,
+            retry_history,
+            clock,
+        }
     }
 
-    async fn ping(&self, address: String) -> Result<Option<String>, CoordinateError> {
+    async fn ping(&self, address: String) -> Result<(), FailureKind> {
         let mut client = match self.client_factory.create_client(address.clone()).await {
             Ok(client) => client,
             Err(err) => {
                 error!(
-                    "PingFailureDetector::check failed to connect: {} {:?}",
+                    "PingFailureDetector::check failed to connect address={} error={:?}",
                     address, err
                 );
-                return Ok(Some(address));
+                return Err(FailureKind::from_client_error(&err));
             }
         };
 
-        // The connection pool might get a stale connection.
-        // Return err instead for retry.
-        let ping_command = vec!["PING".to_string().into_bytes()];
-        match client.execute_single(ping_command).await {
-            Ok(_) => Ok(None),
-            Err(err) => {
-                error!(
-                    "PingFailureDetector::check failed to send PING: {} {:?}",
-                    address, err
-                );
-                Err(CoordinateError::Redis(err))
-            }
-        }
+        let ping_command = CmdBuilder::new("PING").build();
+        // The connection pool might get a stale connection so it will be retried.
+        client.execute_single(ping_command).await.map_err(|err| {
+            error!(
+                "PingFailureDetector::check failed to send PING address={} error={:?}",
+                address, err
+            );
+            FailureKind::from_client_error(&err)
+        })?;
+        Ok(())
     }
 
-    async fn check_impl(&self, address: String) -> Result<Option<String>, CoordinateError> {
-        const RETRY: usize = 3;
-        for i in 1..=RETRY {
+    async fn check_impl(
+        &self,
+        address: String,
+    ) -> Result<Option<DetectedFailure>, CoordinateError> {
+        let retry = self.retry_history.get_retry(&address);
+        let mut kind = FailureKind::Other;
+        for i in 1..=retry {
             match self.ping(address.clone()).await {
-                Ok(None) => return Ok(None),
-                _ if i == RETRY => return Ok(Some(address)),
-                _ => continue,
+                Ok(()) => {
+                    self.retry_history.record(address, i == 1);
+                    return Ok(None);
+                }
+                // Reports the kind of the last failure.
+                Err(last_kind) if i == retry => {
+                    kind = last_kind;
+                    break;
+                }
+                Err(_) => self.clock.sleep(PING_RETRY_INTERVAL).await,
             }
         }
-        Ok(Some(address))
+        self.retry_history.record(address.clone(), false);
+        warn!(
+            "PingFailureDetector::check found failed node address={} retry={} kind={}",
+            address, retry, kind
+        );
+        Ok(Some(DetectedFailure { address, kind }))
     }
 }
 
@@ -265,9 +356,14 @@ impl<F: RedisClientFactory> FailureChecker for PingFailureDetector<F> {
     fn check<'s>(
         &'s self,
         address: String,
-    ) -> Pin<Box<dyn Future<Output = Result<Option<String>, CoordinateError>> + Send + 's>> {
+    ) -> Pin<Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send + 's>>
+    {
         Box::pin(self.check_impl(address))
     }
+
+    fn retain_addresses(&self, addresses: &HashSet<String>) {
+        self.retry_history.retain(addresses);
+    }
 }
 
 pub struct BrokerFailureReporter<B: MetaDataBroker> {
@@ -284,14 +380,40 @@ impl<B: MetaDataBroker> BrokerFailureReporter<B> {
     }
 }
 
+// The broker only records the failed addresses so the kinds are only logged here.
 impl<B: MetaDataBroker> FailureReporter for BrokerFailureReporter<B> {
     fn report<'s>(
         &'s self,
-        address: String,
+        failure: DetectedFailure,
+    ) -> Pin<Box<dyn Future<Output = Result<(), CoordinateError>> + Send + 's>> {
+        info!(
+            "reporting failure address={} kind={}",
+            failure.address, failure.kind
+        );
+        Box::pin(
+            self.meta_data_broker
+                .add_failure(failure.address, self.reporter_id.clone())
+                .map_err(CoordinateError::MetaData),
+        )
+    }
+
+    fn report_batch<'s>(
+        &'s self,
+        failures: Vec<DetectedFailure>,
     ) -> Pin<Box<dyn Future<Output = Result<(), CoordinateError>> + Send + 's>> {
+        let addresses = failures
+            .into_iter()
+            .map(|failure| {
+                info!(
+                    "reporting failure address={} kind={}",
+                    failure.address, failure.kind
+                );
+                failure.address
+            })
+            .collect();
         Box::pin(
             self.meta_data_broker
-                .add_failure(address, self.reporter_id.clone())
+                .add_failures(addresses, self.reporter_id.clone())
                 .map_err(CoordinateError::MetaData),
         )
     }
@@ -300,10 +422,14 @@ impl<B: MetaDataBroker> FailureReporter for BrokerFailureReporter<B> {
 #[cfg(test)]
 mod tests {
     use super::super::broker::{MetaDataBrokerError, MockMetaDataBroker};
-    use super::super::core::{FailureDetector, ParFailureDetector};
+    use super::super::core::{
+        DetectorEvent, DetectorEventKind, FailureDetector, FailureHooks, ParFailureDetector,
+    };
+    use super::super::test_support::InMemoryMetaBroker;
     use super::*;
+    use crate::common::clock::FakeClock;
     use crate::common::cluster::{
-        ClusterName, MigrationMeta, Node, RangeList, ReplMeta, Role, SlotRange, SlotRangeTag,
+        ClusterName, MigrationMeta, Node, Proxy, RangeList, ReplMeta, Role, SlotRange, SlotRangeTag,
     };
     use crate::common::config::ClusterConfig;
     use crate::protocol::{
@@ -311,12 +437,15 @@ mod tests {
     };
     use futures::{future, stream, StreamExt};
     use std::convert::TryFrom;
+    use std::io;
     use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::sync::Arc;
     use tokio;
 
     const NODE1: &'static str = "127.0.0.1:7000";
     const NODE2: &'static str = "127.0.0.1:7001";
+    const NODE2_TIMEOUT: &'static str = "127.0.0.1:7002";
 
     #[derive(Debug)]
     struct DummyClient {
@@ -335,6 +464,8 @@ mod tests {
                 Box::pin(future::ok(OptionalMulti::Single(
                     Resp::Arr(Array::Nil).into(),
                 )))
+            } else if self.address == NODE2_TIMEOUT {
+                Box::pin(future::err(RedisClientError::Timeout))
             } else {
                 Box::pin(future::err(RedisClientError::InvalidReply))
             }
@@ -354,21 +485,51 @@ mod tests {
         }
     }
 
+    // NODE1 refuses the connections, NODE2 times out the PING,
+    // and the others reply invalid data.
+    struct FailingClientFactory;
+
+    impl RedisClientFactory for FailingClientFactory {
+        type Client = DummyClient;
+
+        fn create_client(
+            &self,
+            address: String,
+        ) -> Pin<Box<dyn Future<Output = Result<Self::Client, RedisClientError>> + Send>> {
+            if address == NODE1 {
+                let err = io::Error::from(io::ErrorKind::ConnectionRefused);
+                return Box::pin(future::err(RedisClientError::Io(err)));
+            }
+            Box::pin(future::ok(DummyClient { address }))
+        }
+    }
+
+    struct CountedClientFactory {
+        count: Arc<AtomicUsize>,
+    }
+
+    impl RedisClientFactory for CountedClientFactory {
+        type Client = DummyClient;
+
+        fn create_client(
+            &self,
+            address: String,
+        ) -> Pin<Box<dyn Future<Output = Result<Self::Client, RedisClientError>> + Send>> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Box::pin(future::ok(DummyClient { address }))
+        }
+    }
+
     #[tokio::test]
     async fn test_proxy_retriever() {
-        let mut mock_broker = MockMetaDataBroker::new();
+        let broker = Arc::new(InMemoryMetaBroker::default());
         let addresses: Vec<String> = vec!["host1:port1", "host2:port2"]
             .into_iter()
             .map(|s| s.to_string())
             .collect();
-        let addresses_clone = addresses.clone();
-        mock_broker
-            .expect_get_proxy_addresses()
-            .returning(move || Box::pin(stream::iter(addresses_clone.clone().into_iter().map(Ok))));
-        mock_broker
-            .expect_get_failed_proxies()
-            .returning(|| Box::pin(stream::iter(vec![])));
-        let broker = Arc::new(mock_broker);
+        for address in addresses.iter() {
+            broker.add_proxy(Proxy::new(None, address.clone(), 1, vec![], vec![], None));
+        }
         let retriever = BrokerProxiesRetriever::new(broker);
         let addrs: Vec<Result<String, CoordinateError>> =
             retriever.retrieve_proxies().collect().await;
@@ -504,14 +665,142 @@ mod tests {
             .expect_get_proxy_addresses()
             .returning(move || Box::pin(stream::iter(addresses_clone.clone().into_iter().map(Ok))));
 
-        let checker = PingFailureDetector::new(Arc::new(DummyClientFactory {}));
+        let clock = Arc::new(FakeClock::default());
+        let checker = PingFailureDetector::new(
+            Arc::new(DummyClientFactory {}),
+            Arc::new(PingRetryHistory::new(3, 3)),
+            clock.clone(),
+        );
         let res = checker.check(NODE1.to_string()).await;
         assert!(res.is_ok());
         assert!(res.unwrap().is_none());
+        assert!(clock.get_sleep_history().is_empty());
 
-        let res = checker.check(NODE2.to_string()).await;
+        let res = clock
+            .drive(PING_RETRY_INTERVAL, checker.check(NODE2.to_string()))
+            .await;
         assert!(res.is_ok());
-        assert_eq!(res.unwrap().unwrap(), NODE2);
+        let failure = res.unwrap().unwrap();
+        assert_eq!(failure.address, NODE2);
+        assert_eq!(failure.kind, FailureKind::Protocol);
+        // No need to wait after the last retry.
+        assert_eq!(clock.get_sleep_history(), vec![PING_RETRY_INTERVAL; 2]);
+    }
+
+    #[tokio::test]
+    async fn test_failure_kind() {
+        let clock = Arc::new(FakeClock::default());
+        let checker = PingFailureDetector::new(
+            Arc::new(FailingClientFactory),
+            Arc::new(PingRetryHistory::new(1, 1)),
+            clock,
+        );
+        let cases = vec![
+            (NODE1, FailureKind::Refused),
+            (NODE2_TIMEOUT, FailureKind::Timeout),
+            (NODE2, FailureKind::Protocol),
+        ];
+        for (address, kind) in cases.into_iter() {
+            let failure = checker.check(address.to_string()).await.unwrap().unwrap();
+            assert_eq!(failure.address, address);
+            assert_eq!(failure.kind, kind);
+        }
+    }
+
+    #[test]
+    fn test_failure_kind_from_client_error() {
+        let refused = RedisClientError::Io(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert_eq!(
+            FailureKind::from_client_error(&refused),
+            FailureKind::Refused
+        );
+        let timeout = RedisClientError::Io(io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!(
+            FailureKind::from_client_error(&timeout),
+            FailureKind::Timeout
+        );
+        assert_eq!(
+            FailureKind::from_client_error(&RedisClientError::InitError),
+            FailureKind::Other
+        );
+    }
+
+    #[test]
+    fn test_ping_retry_history() {
+        let history = PingRetryHistory::new(1, 5);
+        assert_eq!(history.get_retry(NODE1), 3);
+
+        for _ in 0..PING_CLEAN_ROUNDS {
+            history.record(NODE1.to_string(), true);
+        }
+        assert_eq!(history.get_retry(NODE1), 1);
+
+        history.record(NODE1.to_string(), false);
+        assert_eq!(history.get_retry(NODE1), 5);
+        for _ in 0..PING_FLAKY_ROUNDS {
+            history.record(NODE1.to_string(), true);
+        }
+        assert_eq!(history.get_retry(NODE1), 3);
+
+        history.record(NODE2.to_string(), false);
+        assert_eq!(history.get_retry(NODE2), 5);
+        history.retain(&vec![NODE1.to_string()].into_iter().collect());
+        assert_eq!(history.get_retry(NODE2), 3);
+
+        // The default retry is bounded by the config.
+        let history = PingRetryHistory::new(4, 6);
+        assert_eq!(history.get_retry(NODE1), 4);
+        let history = PingRetryHistory::new(0, 0);
+        assert_eq!(history.get_retry(NODE1), 1);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_ping_retry() {
+        let mut mock_broker = MockMetaDataBroker::new();
+        mock_broker
+            .expect_get_proxy_addresses()
+            .returning(|| Box::pin(stream::iter(vec![Ok(NODE1.to_string())])));
+        mock_broker
+            .expect_get_failed_proxies()
+            .returning(|| Box::pin(stream::iter(vec![])));
+        let broker = Arc::new(mock_broker);
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let history = Arc::new(PingRetryHistory::new(1, 5));
+        let clock = Arc::new(FakeClock::default());
+        let checker = PingFailureDetector::new(
+            Arc::new(CountedClientFactory {
+                count: count.clone(),
+            }),
+            history.clone(),
+            clock.clone(),
+        );
+
+        let res = clock
+            .drive(PING_RETRY_INTERVAL, checker.check(NODE2.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(res.unwrap().address, NODE2);
+        assert_eq!(count.swap(0, Ordering::SeqCst), 3);
+
+        // Retry more for the flaky node.
+        let res = clock
+            .drive(PING_RETRY_INTERVAL, checker.check(NODE2.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(res.unwrap().address, NODE2);
+        assert_eq!(count.swap(0, Ordering::SeqCst), 5);
+
+        // NODE2 is not in the proxy list so its history is removed.
+        let retriever = BrokerProxiesRetriever::new(broker.clone());
+        let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
+        let detector = ParFailureDetector::new(retriever, checker, reporter);
+        let res = clock
+            .drive(PING_RETRY_INTERVAL, detector.run().into_future())
+            .await;
+        assert!(res.is_ok());
+        assert_eq!(history.get_retry(NODE1), 3);
+        assert_eq!(history.get_retry(NODE2), 3);
     }
 
     #[tokio::test]
@@ -525,7 +814,7 @@ mod tests {
 
         let broker = Arc::new(mock_broker);
         let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
-        let res = reporter.report(NODE2.to_string()).await;
+        let res = reporter.report(gen_failure(NODE2.to_string())).await;
         assert!(res.is_ok());
     }
 
@@ -553,14 +842,315 @@ mod tests {
 
         let broker = Arc::new(mock_broker);
         let retriever = BrokerProxiesRetriever::new(broker.clone());
-        let checker = PingFailureDetector::new(Arc::new(DummyClientFactory {}));
+        let clock = Arc::new(FakeClock::default());
+        let checker = PingFailureDetector::new(
+            Arc::new(DummyClientFactory {}),
+            Arc::new(PingRetryHistory::new(3, 3)),
+            clock.clone(),
+        );
         let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
         let detector = ParFailureDetector::new(retriever, checker, reporter);
 
+        let res = clock
+            .drive(PING_RETRY_INTERVAL, detector.run().into_future())
+            .await;
+        assert!(res.is_ok());
+    }
+
+    fn gen_failure(address: String) -> DetectedFailure {
+        DetectedFailure {
+            address,
+            kind: FailureKind::Refused,
+        }
+    }
+
+    struct ToggledChecker {
+        node2_failed: Arc<AtomicBool>,
+    }
+
+    impl FailureChecker for ToggledChecker {
+        fn check<'s>(
+            &'s self,
+            address: String,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send + 's>,
+        > {
+            let failed = address == NODE2 && self.node2_failed.load(Ordering::SeqCst);
+            Box::pin(future::ok(if failed {
+                Some(gen_failure(address))
+            } else {
+                None
+            }))
+        }
+    }
+
+    struct AllFailedChecker;
+
+    impl FailureChecker for AllFailedChecker {
+        fn check<'s>(
+            &'s self,
+            address: String,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send + 's>,
+        > {
+            Box::pin(future::ok(Some(gen_failure(address))))
+        }
+    }
+
+    struct NoFailureChecker;
+
+    impl FailureChecker for NoFailureChecker {
+        fn check<'s>(
+            &'s self,
+            _address: String,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send + 's>,
+        > {
+            Box::pin(future::ok(None))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_detector_stagger() {
+        let mut mock_broker = MockMetaDataBroker::new();
+        mock_broker.expect_get_proxy_addresses().returning(move || {
+            let addresses = vec![
+                Ok(NODE1.to_string()),
+                Ok(NODE2.to_string()),
+                Ok("127.0.0.1:7002".to_string()),
+            ];
+            Box::pin(stream::iter(addresses))
+        });
+        mock_broker
+            .expect_get_failed_proxies()
+            .returning(|| Box::pin(stream::iter(vec![])));
+
+        let broker = Arc::new(mock_broker);
+        let retriever = BrokerProxiesRetriever::new(broker.clone());
+        let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
+        let stagger = Duration::from_millis(20);
+        let detector =
+            ParFailureDetector::new(retriever, NoFailureChecker, reporter).with_stagger(stagger);
+        let start = std::time::Instant::now();
+        detector.run().await.unwrap();
+        // The third proxy is checked after two staggers.
+        assert!(start.elapsed() >= stagger * 2);
+    }
+
+    struct RecordingChecker {
+        clock: Arc<FakeClock>,
+        // (address, elapsed time of the clock)
+        check_times: Arc<parking_lot::Mutex<Vec<(String, Duration)>>>,
+    }
+
+    impl FailureChecker for RecordingChecker {
+        fn check<'s>(
+            &'s self,
+            address: String,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<Option<DetectedFailure>, CoordinateError>> + Send + 's>,
+        > {
+            self.check_times
+                .lock()
+                .push((address, self.clock.elapsed()));
+            Box::pin(future::ok(None))
+        }
+    }
+
+    async fn check_detector_jitter(proxy_num: usize) {
+        let mut mock_broker = MockMetaDataBroker::new();
+        mock_broker.expect_get_proxy_addresses().returning(move || {
+            let addresses = (0..proxy_num).map(|i| Ok(format!("127.0.0.1:{}", 7000 + i)));
+            Box::pin(stream::iter(addresses.collect::<Vec<_>>()))
+        });
+        mock_broker
+            .expect_get_failed_proxies()
+            .returning(|| Box::pin(stream::iter(vec![])));
+
+        let broker = Arc::new(mock_broker);
+        let retriever = BrokerProxiesRetriever::new(broker.clone());
+        let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
+        let clock = Arc::new(FakeClock::default());
+        let check_times = Arc::new(parking_lot::Mutex::new(vec![]));
+        let checker = RecordingChecker {
+            clock: clock.clone(),
+            check_times: check_times.clone(),
+        };
+        let window = Duration::from_secs(proxy_num as u64);
+        let detector = ParFailureDetector::new(retriever, checker, reporter)
+            .with_jitter_window(window)
+            .with_clock(clock.clone());
+        let step = Duration::from_millis(10);
+        clock.drive(step, detector.run()).await.unwrap();
+
+        let check_times = check_times.lock().clone();
+        assert_eq!(check_times.len(), proxy_num);
+        // The i-th proxy is checked within its own slot of the window
+        // no matter which batch it's in.
+        let slot = window / proxy_num as u32;
+        for (address, elapsed) in check_times.into_iter() {
+            let i = address
+                .strip_prefix("127.0.0.1:")
+                .and_then(|port| port.parse::<usize>().ok())
+                .unwrap()
+                - 7000;
+            assert!(elapsed >= slot * i as u32);
+            assert!(elapsed < slot * (i as u32 + 1) + step);
+        }
+        assert!(clock.elapsed() < window + step);
+    }
+
+    #[tokio::test]
+    async fn test_detector_jitter() {
+        check_detector_jitter(4).await;
+    }
+
+    #[tokio::test]
+    async fn test_detector_jitter_multiple_batches() {
+        // More than one batch of 30 proxies.
+        check_detector_jitter(70).await;
+    }
+
+    #[tokio::test]
+    async fn test_detector_report_batch() {
+        let mut mock_broker = MockMetaDataBroker::new();
+        mock_broker.expect_get_proxy_addresses().returning(move || {
+            let addresses = vec![Ok(NODE1.to_string()), Ok(NODE2.to_string())];
+            Box::pin(stream::iter(addresses))
+        });
+        mock_broker
+            .expect_get_failed_proxies()
+            .returning(|| Box::pin(stream::iter(vec![])));
+        mock_broker.expect_add_failure().times(0);
+        mock_broker
+            .expect_add_failures()
+            .withf(|addresses: &Vec<String>, reporter_id: &String| {
+                let mut addresses = addresses.clone();
+                addresses.sort();
+                addresses == vec![NODE1.to_string(), NODE2.to_string()] && reporter_id == "test_id"
+            })
+            .times(1)
+            .returning(|_, _| Box::pin(future::ok(())));
+
+        let broker = Arc::new(mock_broker);
+        let retriever = BrokerProxiesRetriever::new(broker.clone());
+        let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
+        let detector = ParFailureDetector::new(retriever, AllFailedChecker, reporter);
         let res = detector.run().into_future().await;
         assert!(res.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_failure_detector_hooks() {
+        let events = Arc::new(parking_lot::Mutex::new(vec![]));
+
+        let mut mock_broker = MockMetaDataBroker::new();
+        mock_broker.expect_get_proxy_addresses().returning(move || {
+            let addresses = vec![Ok(NODE1.to_string()), Ok(NODE2.to_string())];
+            Box::pin(stream::iter(addresses))
+        });
+        mock_broker
+            .expect_get_failed_proxies()
+            .returning(|| Box::pin(stream::iter(vec![])));
+        let reported_events = events.clone();
+        mock_broker
+            .expect_add_failure()
+            .withf(|address: &String, _| address == NODE2)
+            .times(2)
+            .returning(move |_, _| {
+                // The hook should be called before reporting.
+                assert!(!reported_events.lock().is_empty());
+                Box::pin(future::ok(()))
+            });
+        let broker = Arc::new(mock_broker);
+
+        let failure_events = events.clone();
+        let recovery_events = events.clone();
+        let hooks = Arc::new(FailureHooks::new(
+            Some(Arc::new(move |address| {
+                failure_events.lock().push(format!("failure {}", address));
+                Box::pin(async {})
+            })),
+            Some(Arc::new(move |address| {
+                recovery_events.lock().push(format!("recovery {}", address));
+                Box::pin(async {})
+            })),
+        ));
+
+        let node2_failed = Arc::new(AtomicBool::new(true));
+        for failed in [true, true, false, false] {
+            node2_failed.store(failed, Ordering::SeqCst);
+            let retriever = BrokerProxiesRetriever::new(broker.clone());
+            let checker = ToggledChecker {
+                node2_failed: node2_failed.clone(),
+            };
+            let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
+            let detector =
+                ParFailureDetector::new(retriever, checker, reporter).with_hooks(hooks.clone());
+            let res = detector.run().into_future().await;
+            assert!(res.is_ok());
+        }
+
+        // Only triggered when the state changes.
+        assert_eq!(
+            *events.lock(),
+            vec![format!("failure {}", NODE2), format!("recovery {}", NODE2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failure_detector_events() {
+        let mut mock_broker = MockMetaDataBroker::new();
+        mock_broker.expect_get_proxy_addresses().returning(move || {
+            let addresses = vec![Ok(NODE1.to_string()), Ok(NODE2.to_string())];
+            Box::pin(stream::iter(addresses))
+        });
+        mock_broker
+            .expect_get_failed_proxies()
+            .returning(|| Box::pin(stream::iter(vec![])));
+        mock_broker
+            .expect_add_failure()
+            .withf(|address: &String, _| address == NODE2)
+            .times(2)
+            .returning(|_, _| Box::pin(future::ok(())));
+        let broker = Arc::new(mock_broker);
+
+        let hooks = Arc::new(FailureHooks::default());
+        let events = hooks.subscribe();
+        let dropped_events = hooks.subscribe();
+        drop(dropped_events);
+
+        let node2_failed = Arc::new(AtomicBool::new(true));
+        for failed in [true, true, false, false] {
+            node2_failed.store(failed, Ordering::SeqCst);
+            let retriever = BrokerProxiesRetriever::new(broker.clone());
+            let checker = ToggledChecker {
+                node2_failed: node2_failed.clone(),
+            };
+            let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
+            let detector =
+                ParFailureDetector::new(retriever, checker, reporter).with_hooks(hooks.clone());
+            let res = detector.run().into_future().await;
+            assert!(res.is_ok());
+        }
+        drop(hooks);
+
+        let events: Vec<DetectorEvent> = events.collect().await;
+        assert_eq!(
+            events,
+            vec![
+                DetectorEvent {
+                    address: NODE2.to_string(),
+                    kind: DetectorEventKind::Failure,
+                },
+                DetectorEvent {
+                    address: NODE2.to_string(),
+                    kind: DetectorEventKind::Recovery,
+                },
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_detector_partial_error() {
         let mut mock_broker = MockMetaDataBroker::new();
@@ -584,11 +1174,18 @@ mod tests {
 
         let broker = Arc::new(mock_broker);
         let retriever = BrokerProxiesRetriever::new(broker.clone());
-        let checker = PingFailureDetector::new(Arc::new(DummyClientFactory {}));
+        let clock = Arc::new(FakeClock::default());
+        let checker = PingFailureDetector::new(
+            Arc::new(DummyClientFactory {}),
+            Arc::new(PingRetryHistory::new(3, 3)),
+            clock.clone(),
+        );
         let reporter = BrokerFailureReporter::new("test_id".to_string(), broker.clone());
         let detector = ParFailureDetector::new(retriever, checker, reporter);
 
-        let res = detector.run().into_future().await;
+        let res = clock
+            .drive(PING_RETRY_INTERVAL, detector.run().into_future())
+            .await;
         assert!(res.is_err());
     }
 }
@@ -0,0 +1,215 @@
+use super::broker::MetaManipulationBroker;
+use super::core::{CoordinateError, ProxiesRetriever};
+use super::http_mani_broker::ProxyHeartbeat;
+use crate::protocol::{BulkStr, CmdBuilder, RedisClient, RedisClientFactory, Resp};
+use crate::proxy::health::ProxyHealth;
+use futures::{future, StreamExt};
+use futures_batch::ChunksTimeoutStreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Reports the proxies reachable from this coordinator to the broker.
+// Unlike the failure reports, this is a positive liveness signal
+// so that the proxies only unreachable from some coordinators could be found
+// by comparing the heartbeats of different reporters.
+pub struct HeartbeatReporter<T: ProxiesRetriever, MB: MetaManipulationBroker, F: RedisClientFactory>
+{
+    reporter_id: String,
+    retriever: T,
+    mani_broker: Arc<MB>,
+    client_factory: Arc<F>,
+}
+
+impl<T: ProxiesRetriever, MB: MetaManipulationBroker, F: RedisClientFactory>
+    HeartbeatReporter<T, MB, F>
+{
+    pub fn new(
+        reporter_id: String,
+        retriever: T,
+        mani_broker: Arc<MB>,
+        client_factory: Arc<F>,
+    ) -> Self {
+        Self {
+            reporter_id,
+            retriever,
+            mani_broker,
+            client_factory,
+        }
+    }
+
+    // Runs one round for all the proxies.
+    pub async fn run(&self) -> Result<(), CoordinateError> {
+        const BATCH_SIZE: usize = 30;
+        const BATCH_TIME: Duration = Duration::from_millis(1);
+
+        let mut res = Ok(());
+        let mut s = self
+            .retriever
+            .retrieve_proxies()
+            .chunks_timeout(BATCH_SIZE, BATCH_TIME);
+        while let Some(results) = s.next().await {
+            let mut proxies = vec![];
+            for r in results {
+                match r {
+                    Ok(proxy) => proxies.push(proxy),
+                    Err(err) => {
+                        error!("failed to get proxy for heartbeat: {:?}", err);
+                        res = Err(err);
+                    }
+                }
+            }
+            let futs: Vec<_> = proxies
+                .into_iter()
+                .map(|address| self.report(address))
+                .collect();
+            for r in future::join_all(futs).await.into_iter() {
+                if let Err(err) = r {
+                    res = Err(err);
+                }
+            }
+        }
+        res
+    }
+
+    async fn report(&self, address: String) -> Result<(), CoordinateError> {
+        // The unreachable proxies are handled by the failure detector.
+        let health = match self.get_health(address.clone()).await {
+            Ok(health) => health,
+            Err(err) => {
+                warn!(
+                    "failed to get health for heartbeat address={} error={:?}",
+                    address, err
+                );
+                return Ok(());
+            }
+        };
+        let heartbeat = ProxyHeartbeat {
+            proxy_address: address,
+            reporter_id: self.reporter_id.clone(),
+            epoch: health.epoch,
+            connected_clients: health.connected_clients,
+            status: health.status,
+        };
+        self.mani_broker.heartbeat(heartbeat).await.map_err(|err| {
+            error!("failed to send heartbeat: {:?}", err);
+            CoordinateError::MetaMani(err)
+        })
+    }
+
+    async fn get_health(&self, address: String) -> Result<ProxyHealth, CoordinateError> {
+        let mut client = self
+            .client_factory
+            .create_client(address)
+            .await
+            .map_err(CoordinateError::Redis)?;
+        let cmd = CmdBuilder::new("UMCTL").arg("HEALTH").build();
+        let json = match client
+            .execute_single(cmd)
+            .await
+            .map_err(CoordinateError::Redis)?
+        {
+            Resp::Bulk(BulkStr::Str(json)) => json,
+            _ => return Err(CoordinateError::InvalidReply),
+        };
+        serde_json::from_slice(&json).map_err(|_| CoordinateError::InvalidReply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::broker::MockMetaManipulationBroker;
+    use super::*;
+    use crate::protocol::{BinSafeStr, DummyRedisClientFactory, MockRedisClient};
+    use crate::proxy::health::HealthStatus;
+    use futures::{stream, Stream};
+    use std::pin::Pin;
+
+    struct StaticProxiesRetriever {
+        addresses: Vec<String>,
+    }
+
+    impl ProxiesRetriever for StaticProxiesRetriever {
+        fn retrieve_proxies<'s>(
+            &'s self,
+        ) -> Pin<Box<dyn Stream<Item = Result<String, CoordinateError>> + Send + 's>> {
+            Box::pin(stream::iter(self.addresses.clone().into_iter().map(Ok)))
+        }
+    }
+
+    fn gen_reporter(
+        health_reply: &'static [u8],
+        mani_broker: MockMetaManipulationBroker,
+    ) -> HeartbeatReporter<
+        StaticProxiesRetriever,
+        MockMetaManipulationBroker,
+        impl RedisClientFactory,
+    > {
+        let create_client_func = move |_| {
+            let mut mock_client = MockRedisClient::new();
+            let health_cmd = vec![b"UMCTL".to_vec(), b"HEALTH".to_vec()];
+            mock_client
+                .expect_execute_single()
+                .withf(move |command: &Vec<BinSafeStr>| command.eq(&health_cmd))
+                .returning(move |_| {
+                    let json = health_reply.to_vec();
+                    Box::pin(async { Ok(Resp::Bulk(BulkStr::Str(json))) })
+                });
+            mock_client
+        };
+        let retriever = StaticProxiesRetriever {
+            addresses: vec!["127.0.0.1:5299".to_string(), "127.0.0.1:5300".to_string()],
+        };
+        HeartbeatReporter::new(
+            "coordinator1".to_string(),
+            retriever,
+            Arc::new(mani_broker),
+            Arc::new(DummyRedisClientFactory::new(create_client_func, false)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_reporter() {
+        let health = br#"{
+            "version": 1,
+            "status": "degraded",
+            "cluster": "mycluster",
+            "epoch": 7,
+            "connected_clients": 3,
+            "backends": [],
+            "migrations": [],
+            "replication": []
+        }"#;
+        let heartbeats = Arc::new(parking_lot::Mutex::new(vec![]));
+        let heartbeats_clone = heartbeats.clone();
+        let mut mani_broker = MockMetaManipulationBroker::new();
+        mani_broker
+            .expect_heartbeat()
+            .times(2)
+            .returning(move |heartbeat| {
+                heartbeats_clone.lock().push(heartbeat);
+                Box::pin(async { Ok(()) })
+            });
+
+        let reporter = gen_reporter(health, mani_broker);
+        reporter.run().await.unwrap();
+
+        let mut heartbeats = heartbeats.lock().clone();
+        heartbeats.sort_by(|a, b| a.proxy_address.cmp(&b.proxy_address));
+        assert_eq!(heartbeats.len(), 2);
+        assert_eq!(heartbeats[0].proxy_address, "127.0.0.1:5299");
+        assert_eq!(heartbeats[1].proxy_address, "127.0.0.1:5300");
+        assert_eq!(heartbeats[0].reporter_id, "coordinator1");
+        assert_eq!(heartbeats[0].epoch, 7);
+        assert_eq!(heartbeats[0].connected_clients, 3);
+        assert_eq!(heartbeats[0].status, HealthStatus::Degraded);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_invalid_health() {
+        let mut mani_broker = MockMetaManipulationBroker::new();
+        mani_broker.expect_heartbeat().never();
+        let reporter = gen_reporter(b"not json", mani_broker);
+        // No heartbeat is sent for the proxies without a valid health.
+        reporter.run().await.unwrap();
+    }
+}
@@ -1,7 +1,8 @@
 use super::broker::{MetaManipulationBroker, MetaManipulationBrokerError};
 use super::service::BrokerAddresses;
 use crate::broker::MEM_BROKER_API_VERSION;
-use crate::common::cluster::{MigrationTaskMeta, Proxy};
+use crate::common::cluster::{MigrationTaskMeta, Proxy, ReplaceProxyHint};
+use crate::proxy::health::HealthStatus;
 use futures::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -35,11 +36,19 @@ impl HttpMetaManipulationBroker {
     async fn replace_proxy_impl(
         &self,
         failed_proxy_address: String,
-    ) -> Result<Option<Proxy>, MetaManipulationBrokerError> {
+        hint: ReplaceProxyHint,
+    ) -> Result<ReplaceProxyResponse, MetaManipulationBrokerError> {
         let url = self
             .gen_url(&format!("/proxies/failover/{}", failed_proxy_address))
             .ok_or(MetaManipulationBrokerError::NoBroker)?;
-        let response = self.client.post(&url).send().await.map_err(|e| {
+        let mut request = self.client.post(&url);
+        if !hint.preferred_hosts.is_empty() {
+            request = request.query(&[("preferred_hosts", hint.preferred_hosts.join(","))]);
+        }
+        if !hint.excluded_hosts.is_empty() {
+            request = request.query(&[("excluded_hosts", hint.excluded_hosts.join(","))]);
+        }
+        let response = request.send().await.map_err(|e| {
             error!("Failed to replace proxy {:?}", e);
             MetaManipulationBrokerError::RequestFailed
         })?;
@@ -47,11 +56,11 @@ impl HttpMetaManipulationBroker {
         let status = response.status();
 
         if status.is_success() {
-            let ReplaceProxyResponse { proxy } = response.json().await.map_err(|e| {
+            let res: ReplaceProxyResponse = response.json().await.map_err(|e| {
                 error!("Failed to get json payload {:?}", e);
                 MetaManipulationBrokerError::InvalidReply
             })?;
-            Ok(proxy)
+            Ok(res)
         } else {
             if status == reqwest::StatusCode::CONFLICT {
                 return Err(MetaManipulationBrokerError::Retry);
@@ -78,21 +87,20 @@ impl HttpMetaManipulationBroker {
     async fn commit_migration_impl(
         &self,
         meta: MigrationTaskMeta,
+        expected_epoch: Option<u64>,
     ) -> Result<(), MetaManipulationBrokerError> {
         let url = self
             .gen_url("/clusters/migrations")
             .ok_or(MetaManipulationBrokerError::NoBroker)?;
 
-        let response = self
-            .client
-            .put(&url)
-            .json(&meta)
-            .send()
-            .await
-            .map_err(|e| {
-                error!("Failed to commit migration {:?}", e);
-                MetaManipulationBrokerError::RequestFailed
-            })?;
+        let mut request = self.client.put(&url);
+        if let Some(epoch) = expected_epoch {
+            request = request.query(&[("epoch", epoch)]);
+        }
+        let response = request.json(&meta).send().await.map_err(|e| {
+            error!("Failed to commit migration {:?}", e);
+            MetaManipulationBrokerError::RequestFailed
+        })?;
 
         let status = response.status();
 
@@ -102,6 +110,9 @@ impl HttpMetaManipulationBroker {
             if status == reqwest::StatusCode::CONFLICT {
                 return Err(MetaManipulationBrokerError::Retry);
             }
+            if status == reqwest::StatusCode::PRECONDITION_FAILED {
+                return Err(MetaManipulationBrokerError::EpochChanged);
+            }
 
             error!("Failed to commit migration status code {:?}", status);
             let result = response.text().await;
@@ -123,26 +134,81 @@ impl HttpMetaManipulationBroker {
             }
         }
     }
+
+    async fn heartbeat_impl(
+        &self,
+        heartbeat: ProxyHeartbeat,
+    ) -> Result<(), MetaManipulationBrokerError> {
+        let url = self
+            .gen_url("/proxies/heartbeats")
+            .ok_or(MetaManipulationBrokerError::NoBroker)?;
+        let response = self
+            .client
+            .post(&url)
+            .json(&heartbeat)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Failed to send heartbeat {:?}", e);
+                MetaManipulationBrokerError::RequestFailed
+            })?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            error!("Failed to send heartbeat status code {:?}", status);
+            Err(MetaManipulationBrokerError::InvalidReply)
+        }
+    }
 }
 
 impl MetaManipulationBroker for HttpMetaManipulationBroker {
     fn replace_proxy<'s>(
         &'s self,
         failed_proxy_address: String,
-    ) -> Pin<Box<dyn Future<Output = Result<Option<Proxy>, MetaManipulationBrokerError>> + Send + 's>>
-    {
-        Box::pin(self.replace_proxy_impl(failed_proxy_address))
+        hint: ReplaceProxyHint,
+    ) -> Pin<
+        Box<
+            dyn Future<Output = Result<ReplaceProxyResponse, MetaManipulationBrokerError>>
+                + Send
+                + 's,
+        >,
+    > {
+        Box::pin(self.replace_proxy_impl(failed_proxy_address, hint))
     }
 
     fn commit_migration<'s>(
         &'s self,
         meta: MigrationTaskMeta,
+        expected_epoch: Option<u64>,
     ) -> Pin<Box<dyn Future<Output = Result<(), MetaManipulationBrokerError>> + Send + 's>> {
-        Box::pin(self.commit_migration_impl(meta))
+        Box::pin(self.commit_migration_impl(meta, expected_epoch))
+    }
+
+    fn heartbeat<'s>(
+        &'s self,
+        heartbeat: ProxyHeartbeat,
+    ) -> Pin<Box<dyn Future<Output = Result<(), MetaManipulationBrokerError>> + Send + 's>> {
+        Box::pin(self.heartbeat_impl(heartbeat))
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ReplaceProxyResponse {
     pub proxy: Option<Proxy>,
+    // Whether the new proxy is chosen from the preferred hosts.
+    // Always true when no preferred host is specified.
+    #[serde(default)]
+    pub hint_satisfied: bool,
+}
+
+// The liveness of a proxy observed by a coordinator.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ProxyHeartbeat {
+    pub proxy_address: String,
+    pub reporter_id: String,
+    pub epoch: u64,
+    pub connected_clients: usize,
+    pub status: HealthStatus,
 }
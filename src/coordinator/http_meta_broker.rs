@@ -143,6 +143,35 @@ impl HttpMetaBroker {
             error!("failed to add failures {:?}", e);
             MetaDataBrokerError::RequestFailed
         })?;
+        Self::check_empty_response(response).await
+    }
+
+    async fn add_failures_impl(
+        &self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Result<(), MetaDataBrokerError> {
+        let url = self
+            .gen_url("/failures")
+            .ok_or(MetaDataBrokerError::NoBroker)?;
+        let payload = AddFailuresPayload {
+            addresses,
+            reporter_id,
+        };
+        let response = self
+            .client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("failed to add failures {:?}", e);
+                MetaDataBrokerError::RequestFailed
+            })?;
+        Self::check_empty_response(response).await
+    }
+
+    async fn check_empty_response(response: reqwest::Response) -> Result<(), MetaDataBrokerError> {
         let status = response.status();
         if status.is_success() {
             Ok(())
@@ -176,6 +205,21 @@ impl HttpMetaBroker {
         Ok(addresses)
     }
 
+    async fn get_failure_reports_impl(&self) -> Result<Vec<FailureReport>, MetaDataBrokerError> {
+        let url = self
+            .gen_url("/failures/reports")
+            .ok_or(MetaDataBrokerError::NoBroker)?;
+        let response = self.client.get(&url).send().await.map_err(|e| {
+            error!("Failed to get failure reports {:?}", e);
+            MetaDataBrokerError::RequestFailed
+        })?;
+        let FailureReportsPayload { reports } = response.json().await.map_err(|e| {
+            error!("Failed to get failure reports from json {:?}", e);
+            MetaDataBrokerError::InvalidReply
+        })?;
+        Ok(reports)
+    }
+
     async fn get_failed_proxies_impl(&self) -> Result<Vec<String>, MetaDataBrokerError> {
         let url = self
             .gen_url("/proxies/failed/addresses")
@@ -252,6 +296,14 @@ impl MetaDataBroker for HttpMetaBroker {
         Box::pin(self.add_failure_impl(address, reporter_id))
     }
 
+    fn add_failures<'s>(
+        &'s self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), MetaDataBrokerError>> + Send + 's>> {
+        Box::pin(self.add_failures_impl(addresses, reporter_id))
+    }
+
     fn get_failures<'s>(
         &'s self,
     ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>> {
@@ -262,6 +314,16 @@ impl MetaDataBroker for HttpMetaBroker {
         )
     }
 
+    fn get_failure_reports<'s>(
+        &'s self,
+    ) -> Pin<Box<dyn Stream<Item = Result<FailureReport, MetaDataBrokerError>> + Send + 's>> {
+        Box::pin(
+            self.get_failure_reports_impl()
+                .map(vec_result_to_stream)
+                .flatten_stream(),
+        )
+    }
+
     fn get_failed_proxies<'s>(
         &'s self,
     ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>> {
@@ -306,6 +368,26 @@ pub struct FailuresPayload {
     pub addresses: Vec<String>,
 }
 
+// One failure reported by one coordinator.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FailureReport {
+    pub proxy_address: String,
+    pub reporter_id: String,
+    // The unix timestamp in seconds when the broker received it.
+    pub report_time: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FailureReportsPayload {
+    pub reports: Vec<FailureReport>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AddFailuresPayload {
+    pub addresses: Vec<String>,
+    pub reporter_id: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct FailedProxiesPayload {
     pub addresses: Vec<String>,
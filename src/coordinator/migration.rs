@@ -1,10 +1,10 @@
-use super::broker::MetaManipulationBroker;
+use super::broker::{MetaDataBroker, MetaManipulationBroker, MetaManipulationBrokerError};
 use super::core::{CoordinateError, MigrationCommitter, MigrationStateChecker};
 use crate::common::cluster::MigrationTaskMeta;
 use crate::common::utils::vec_result_to_stream;
 use crate::protocol::{Array, BulkStr, Resp};
-use crate::protocol::{RedisClient, RedisClientFactory, RespVec};
-use futures::{Future, FutureExt, Stream, TryFutureExt};
+use crate::protocol::{CmdBuilder, RedisClient, RedisClientFactory, RespVec};
+use futures::{Future, FutureExt, Stream};
 use std::pin::Pin;
 use std::str;
 use std::sync::Arc;
@@ -45,10 +45,7 @@ impl<F: RedisClientFactory> MigrationStateRespChecker<F> {
             .create_client(address.clone())
             .await
             .map_err(CoordinateError::Redis)?;
-        let info_mgr_cmd = vec!["UMCTL".to_string(), "INFOMGR".to_string()]
-            .into_iter()
-            .map(String::into_bytes)
-            .collect();
+        let info_mgr_cmd = CmdBuilder::new("UMCTL").arg("INFOMGR").build();
 
         let info_mgr_reply = client
             .execute_single(info_mgr_cmd)
@@ -92,33 +89,64 @@ impl<F: RedisClientFactory> MigrationStateChecker for MigrationStateRespChecker<
     }
 }
 
-pub struct BrokerMigrationCommitter<MB: MetaManipulationBroker> {
+pub struct BrokerMigrationCommitter<MB: MetaManipulationBroker, DB: MetaDataBroker> {
     mani_broker: Arc<MB>,
+    data_broker: Arc<DB>,
 }
 
-impl<MB: MetaManipulationBroker> BrokerMigrationCommitter<MB> {
-    pub fn new(mani_broker: Arc<MB>) -> Self {
-        Self { mani_broker }
+impl<MB: MetaManipulationBroker, DB: MetaDataBroker> BrokerMigrationCommitter<MB, DB> {
+    pub fn new(mani_broker: Arc<MB>, data_broker: Arc<DB>) -> Self {
+        Self {
+            mani_broker,
+            data_broker,
+        }
+    }
+
+    async fn commit_impl(&self, meta: MigrationTaskMeta) -> Result<(), CoordinateError> {
+        const EPOCH_CHANGED_RETRY_TIMES: usize = 3;
+
+        for _ in 0..EPOCH_CHANGED_RETRY_TIMES {
+            // The broker will reject the commit if the cluster has changed after this.
+            let epoch = self
+                .data_broker
+                .get_cluster(meta.cluster_name.clone())
+                .await
+                .map_err(CoordinateError::MetaData)?
+                .map(|cluster| cluster.get_epoch());
+
+            match self.mani_broker.commit_migration(meta.clone(), epoch).await {
+                Ok(()) => {
+                    info!("successfully commit the migration {:?}", meta);
+                    return Ok(());
+                }
+                Err(MetaManipulationBrokerError::EpochChanged) => {
+                    warn!(
+                        "epoch of cluster changed when committing migration {:?}. Retry.",
+                        meta
+                    );
+                }
+                Err(e) => {
+                    error!("failed to commit migration {:?} {:?}", meta, e);
+                    return Err(CoordinateError::MetaMani(e));
+                }
+            }
+        }
+
+        error!("failed to commit migration for epoch changed {:?}", meta);
+        Err(CoordinateError::MetaMani(
+            MetaManipulationBrokerError::EpochChanged,
+        ))
     }
 }
 
-impl<MB: MetaManipulationBroker> MigrationCommitter for BrokerMigrationCommitter<MB> {
+impl<MB: MetaManipulationBroker, DB: MetaDataBroker> MigrationCommitter
+    for BrokerMigrationCommitter<MB, DB>
+{
     fn commit<'s>(
         &'s self,
         meta: MigrationTaskMeta,
     ) -> Pin<Box<dyn Future<Output = Result<(), CoordinateError>> + Send + 's>> {
-        let meta_clone = meta.clone();
-        Box::pin(
-            self.mani_broker
-                .commit_migration(meta.clone())
-                .map_err(move |e| {
-                    error!("failed to commit migration {:?} {:?}", meta, e);
-                    CoordinateError::MetaMani(e)
-                })
-                .map_ok(move |()| {
-                    info!("successfully commit the migration {:?}", meta_clone);
-                }),
-        )
+        Box::pin(self.commit_impl(meta))
     }
 }
 
@@ -130,12 +158,14 @@ mod tests {
     use super::super::sync::BrokerMetaRetriever;
     use super::*;
     use crate::common::cluster::{
-        ClusterName, MigrationMeta, Proxy, RangeList, SlotRange, SlotRangeTag,
+        Cluster, ClusterName, MigrationMeta, Proxy, RangeList, SlotRange, SlotRangeTag,
     };
+    use crate::common::config::ClusterConfig;
     use crate::coordinator::core::MockProxyMetaSender;
     use crate::protocol::{BinSafeStr, DummyRedisClientFactory, MockRedisClient};
     use futures::{stream, StreamExt};
     use std::convert::TryFrom;
+    use std::sync::atomic::{AtomicU64, Ordering};
     use tokio;
 
     fn gen_testing_dummy_proxy(addr: &str) -> Proxy {
@@ -203,6 +233,15 @@ mod tests {
         }
     }
 
+    fn gen_testing_cluster(epoch: u64) -> Cluster {
+        Cluster::new(
+            ClusterName::try_from("mycluster").unwrap(),
+            epoch,
+            vec![],
+            ClusterConfig::default(),
+        )
+    }
+
     #[tokio::test]
     async fn test_migration_committer() {
         let mut mock_broker = MockMetaManipulationBroker::new();
@@ -212,11 +251,52 @@ mod tests {
 
         mock_broker
             .expect_commit_migration()
-            .withf(move |m| m == &meta2)
-            .returning(move |_| Box::pin(async { Ok(()) }));
+            .withf(move |m, epoch| m == &meta2 && *epoch == Some(233))
+            .returning(move |_, _| Box::pin(async { Ok(()) }));
+        let mock_broker = Arc::new(mock_broker);
+
+        let mut mock_data_broker = MockMetaDataBroker::new();
+        mock_data_broker
+            .expect_get_cluster()
+            .returning(|_| Box::pin(async { Ok(Some(gen_testing_cluster(233))) }));
+        let mock_data_broker = Arc::new(mock_data_broker);
+
+        let committer = BrokerMigrationCommitter::new(mock_broker, mock_data_broker);
+        let res = committer.commit(meta).await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_migration_committer_epoch_changed() {
+        let mut mock_broker = MockMetaManipulationBroker::new();
+
+        let meta = gen_testing_migration_task_meta();
+        let meta2 = meta.clone();
+        let meta3 = meta.clone();
+
+        mock_broker
+            .expect_commit_migration()
+            .withf(move |m, epoch| m == &meta2 && *epoch == Some(233))
+            .times(1)
+            .returning(move |_, _| {
+                Box::pin(async { Err(MetaManipulationBrokerError::EpochChanged) })
+            });
+        mock_broker
+            .expect_commit_migration()
+            .withf(move |m, epoch| m == &meta3 && *epoch == Some(234))
+            .times(1)
+            .returning(move |_, _| Box::pin(async { Ok(()) }));
         let mock_broker = Arc::new(mock_broker);
 
-        let committer = BrokerMigrationCommitter::new(mock_broker);
+        let epoch = Arc::new(AtomicU64::new(233));
+        let mut mock_data_broker = MockMetaDataBroker::new();
+        mock_data_broker.expect_get_cluster().returning(move |_| {
+            let epoch = epoch.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { Ok(Some(gen_testing_cluster(epoch))) })
+        });
+        let mock_data_broker = Arc::new(mock_data_broker);
+
+        let committer = BrokerMigrationCommitter::new(mock_broker, mock_data_broker);
         let res = committer.commit(meta).await;
         assert!(res.is_ok());
     }
@@ -232,8 +312,8 @@ mod tests {
         let meta2 = meta.clone();
         mock_mani_broker
             .expect_commit_migration()
-            .withf(move |m| m == &meta2)
-            .returning(move |_| Box::pin(async { Ok(()) }));
+            .withf(move |m, epoch| m == &meta2 && *epoch == Some(233))
+            .returning(move |_, _| Box::pin(async { Ok(()) }));
         let mock_mani_broker = Arc::new(mock_mani_broker);
 
         let mut mock_data_broker = MockMetaDataBroker::new();
@@ -246,6 +326,9 @@ mod tests {
         mock_data_broker
             .expect_get_failed_proxies()
             .returning(|| Box::pin(stream::iter(vec![])));
+        mock_data_broker
+            .expect_get_cluster()
+            .returning(|_| Box::pin(async { Ok(Some(gen_testing_cluster(233))) }));
         mock_data_broker
             .expect_get_proxy()
             .withf(|proxy_addr| proxy_addr == "127.0.0.1:6000")
@@ -258,7 +341,8 @@ mod tests {
 
         let proxies_retriever = BrokerProxiesRetriever::new(mock_data_broker.clone());
 
-        let committer = BrokerMigrationCommitter::new(mock_mani_broker.clone());
+        let committer =
+            BrokerMigrationCommitter::new(mock_mani_broker.clone(), mock_data_broker.clone());
         let meta_retriever = BrokerMetaRetriever::new(mock_data_broker);
 
         let mut mock_meta_sender = MockProxyMetaSender::new();
@@ -4,9 +4,12 @@ mod api;
 pub mod broker;
 mod core;
 mod detector;
+mod heartbeat;
 pub mod http_mani_broker;
 pub mod http_meta_broker;
 mod migration;
 mod recover;
 pub mod service;
 mod sync;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
@@ -1,7 +1,12 @@
 use super::broker::{MetaDataBroker, MetaManipulationBroker};
 use super::core::{CoordinateError, ProxyFailure, ProxyFailureHandler, ProxyFailureRetriever};
+use super::http_mani_broker::ReplaceProxyResponse;
+use crate::common::cluster::{ReplaceProxyHint, Role};
+use crate::protocol::{BulkStr, CmdBuilder, RedisClient, RedisClientFactory, Resp};
 use futures::{Future, Stream, TryFutureExt, TryStreamExt};
+use std::fmt;
 use std::pin::Pin;
+use std::str;
 use std::sync::Arc;
 
 pub struct BrokerProxyFailureRetriever<B: MetaDataBroker> {
@@ -44,7 +49,7 @@ impl<MB: MetaManipulationBroker> ProxyFailureHandler for ReplaceNodeHandler<MB>
         let proxy_failure2 = proxy_failure.clone();
         Box::pin(
             self.mani_broker
-                .replace_proxy(proxy_failure.clone())
+                .replace_proxy(proxy_failure.clone(), ReplaceProxyHint::default())
                 .map_err(move |e| {
                     error!("failed to replace proxy {} {:?}", proxy_failure2, e);
                     CoordinateError::MetaMani(e)
@@ -59,14 +64,214 @@ impl<MB: MetaManipulationBroker> ProxyFailureHandler for ReplaceNodeHandler<MB>
     }
 }
 
+#[derive(Debug)]
+pub enum ManualFailoverError {
+    ProxyNotFound,
+    NoMaster,
+    NoReplica(String),
+    ReplicaFailed(String),
+    ReplicaNotReady(String),
+    ReplicaLagging { node_address: String, lag: u64 },
+    Coordinate(CoordinateError),
+}
+
+impl fmt::Display for ManualFailoverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ProxyNotFound => write!(f, "proxy not found"),
+            Self::NoMaster => write!(f, "no master in the proxy"),
+            Self::NoReplica(node) => write!(f, "master {} has no replica", node),
+            Self::ReplicaFailed(proxy) => write!(f, "replica proxy {} is failed", proxy),
+            Self::ReplicaNotReady(node) => {
+                write!(f, "replicas of master {} are not all online", node)
+            }
+            Self::ReplicaLagging { node_address, lag } => write!(
+                f,
+                "replicas of master {} are lagging behind by {} bytes",
+                node_address, lag
+            ),
+            Self::Coordinate(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+// Triggers the failover of a healthy proxy for planned maintenance.
+// It's the same replacement as the automatic failover
+// except that the replicas are checked to have caught up first
+// so that the promotion won't lose the data.
+pub struct ManualFailoverHandler<
+    DB: MetaDataBroker,
+    MB: MetaManipulationBroker,
+    F: RedisClientFactory,
+> {
+    data_broker: Arc<DB>,
+    mani_broker: Arc<MB>,
+    client_factory: Arc<F>,
+}
+
+impl<DB: MetaDataBroker, MB: MetaManipulationBroker, F: RedisClientFactory>
+    ManualFailoverHandler<DB, MB, F>
+{
+    pub fn new(data_broker: Arc<DB>, mani_broker: Arc<MB>, client_factory: Arc<F>) -> Self {
+        Self {
+            data_broker,
+            mani_broker,
+            client_factory,
+        }
+    }
+
+    // `max_lag` is the maximum replication offset difference in bytes.
+    pub async fn failover(
+        &self,
+        proxy_address: String,
+        max_lag: u64,
+    ) -> Result<ReplaceProxyResponse, ManualFailoverError> {
+        let proxy = self
+            .data_broker
+            .get_proxy(proxy_address.clone())
+            .await
+            .map_err(|err| ManualFailoverError::Coordinate(CoordinateError::MetaData(err)))?
+            .ok_or(ManualFailoverError::ProxyNotFound)?;
+
+        let failures: Vec<String> = self
+            .data_broker
+            .get_failures()
+            .try_collect()
+            .await
+            .map_err(|err| ManualFailoverError::Coordinate(CoordinateError::MetaData(err)))?;
+
+        let masters: Vec<_> = proxy
+            .get_nodes()
+            .into_iter()
+            .filter(|node| node.get_role() == Role::Master)
+            .collect();
+        if masters.is_empty() {
+            return Err(ManualFailoverError::NoMaster);
+        }
+
+        for master in masters.iter() {
+            let replicas = master.get_repl_meta().get_peers();
+            if replicas.is_empty() {
+                return Err(ManualFailoverError::NoReplica(
+                    master.get_address().to_string(),
+                ));
+            }
+            if let Some(replica) = replicas
+                .iter()
+                .find(|replica| failures.contains(&replica.proxy_address))
+            {
+                return Err(ManualFailoverError::ReplicaFailed(
+                    replica.proxy_address.clone(),
+                ));
+            }
+
+            let (online, lag) = self
+                .get_replication_lag(master.get_address().to_string())
+                .await
+                .map_err(ManualFailoverError::Coordinate)?;
+            if online < replicas.len() {
+                return Err(ManualFailoverError::ReplicaNotReady(
+                    master.get_address().to_string(),
+                ));
+            }
+            if lag > max_lag {
+                return Err(ManualFailoverError::ReplicaLagging {
+                    node_address: master.get_address().to_string(),
+                    lag,
+                });
+            }
+        }
+
+        let res = self
+            .mani_broker
+            .replace_proxy(proxy_address.clone(), ReplaceProxyHint::default())
+            .await
+            .map_err(|err| {
+                error!(
+                    "failed to fail over proxy address={} error={:?}",
+                    proxy_address, err
+                );
+                ManualFailoverError::Coordinate(CoordinateError::MetaMani(err))
+            })?;
+        info!(
+            "manual failover done address={} new_proxy={:?}",
+            proxy_address, res.proxy
+        );
+        Ok(res)
+    }
+
+    // Returns (online replica number, max offset lag) from the INFO of the master.
+    async fn get_replication_lag(
+        &self,
+        master_address: String,
+    ) -> Result<(usize, u64), CoordinateError> {
+        let mut client = self
+            .client_factory
+            .create_client(master_address)
+            .await
+            .map_err(CoordinateError::Redis)?;
+        let cmd = CmdBuilder::new("INFO").arg("REPLICATION").build();
+        let info = match client
+            .execute_single(cmd)
+            .await
+            .map_err(CoordinateError::Redis)?
+        {
+            Resp::Bulk(BulkStr::Str(info)) => info,
+            _ => return Err(CoordinateError::InvalidReply),
+        };
+        let info = str::from_utf8(&info).map_err(|_| CoordinateError::InvalidReply)?;
+        parse_replication_lag(info).ok_or(CoordinateError::InvalidReply)
+    }
+}
+
+// Parses the `slaveN:ip=...,state=online,offset=...` lines of the master.
+fn parse_replication_lag(info: &str) -> Option<(usize, u64)> {
+    let mut master_offset = None;
+    let mut replica_offsets = vec![];
+    for line in info.lines() {
+        let (key, value) = match line.trim().split_once(':') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        if key == "master_repl_offset" {
+            master_offset = Some(value.parse::<u64>().ok()?);
+        } else if key
+            .strip_prefix("slave")
+            .is_some_and(|n| n.parse::<usize>().is_ok())
+        {
+            let mut online = false;
+            let mut offset = None;
+            for field in value.split(',') {
+                match field.split_once('=') {
+                    Some(("state", state)) => online = state == "online",
+                    Some(("offset", o)) => offset = Some(o.parse::<u64>().ok()?),
+                    _ => (),
+                }
+            }
+            if online {
+                replica_offsets.push(offset?);
+            }
+        }
+    }
+    let master_offset = master_offset?;
+    let lag = replica_offsets
+        .iter()
+        .map(|offset| master_offset.saturating_sub(*offset))
+        .max()
+        .unwrap_or(0);
+    Some((replica_offsets.len(), lag))
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::broker::{MockMetaDataBroker, MockMetaManipulationBroker};
     use super::super::core::ParFailureHandler;
     use super::*;
-    use crate::common::cluster::Proxy;
+    use crate::common::cluster::{ClusterName, Node, Proxy, ReplMeta, ReplPeer};
     use crate::coordinator::core::FailureHandler;
+    use crate::protocol::{BinSafeStr, DummyRedisClientFactory, MockRedisClient};
     use futures::{stream, StreamExt};
+    use std::convert::TryFrom;
     use tokio;
 
     fn gen_testing_dummy_proxy() -> Proxy {
@@ -101,9 +306,16 @@ mod tests {
         let failure2 = failure;
         mock_broker
             .expect_replace_proxy()
-            .withf(move |f| f == failure2)
+            .withf(move |f, hint| f == failure2 && hint.is_empty())
             .times(1)
-            .returning(move |_| Box::pin(async { Ok(Some(gen_testing_dummy_proxy())) }));
+            .returning(move |_, _| {
+                Box::pin(async {
+                    Ok(ReplaceProxyResponse {
+                        proxy: Some(gen_testing_dummy_proxy()),
+                        hint_satisfied: true,
+                    })
+                })
+            });
         let mock_broker = Arc::new(mock_broker);
 
         let handler = ReplaceNodeHandler::new(mock_broker);
@@ -125,9 +337,16 @@ mod tests {
         let failure2 = failure;
         mock_mani_broker
             .expect_replace_proxy()
-            .withf(move |f| f == failure2)
+            .withf(move |f, hint| f == failure2 && hint.is_empty())
             .times(1)
-            .returning(move |_| Box::pin(async { Ok(Some(gen_testing_dummy_proxy())) }));
+            .returning(move |_, _| {
+                Box::pin(async {
+                    Ok(ReplaceProxyResponse {
+                        proxy: Some(gen_testing_dummy_proxy()),
+                        hint_satisfied: true,
+                    })
+                })
+            });
         let mock_mani_broker = Arc::new(mock_mani_broker);
 
         let retriever = BrokerProxyFailureRetriever::new(mock_data_broker);
@@ -137,4 +356,133 @@ mod tests {
         assert_eq!(res.len(), 1);
         assert!(res[0].is_ok());
     }
+
+    fn gen_testing_master_proxy() -> Proxy {
+        let replica = ReplPeer {
+            node_address: "127.0.0.1:7001".to_string(),
+            proxy_address: "127.0.0.1:6001".to_string(),
+        };
+        let master = Node::new(
+            "127.0.0.1:7000".to_string(),
+            "127.0.0.1:6000".to_string(),
+            vec![],
+            ReplMeta::new(Role::Master, vec![replica]),
+        );
+        Proxy::new(
+            Some(ClusterName::try_from("mycluster").unwrap()),
+            "127.0.0.1:6000".to_string(),
+            7799,
+            vec![master],
+            vec![],
+            None,
+        )
+    }
+
+    fn gen_replication_info(replica_offset: u64) -> String {
+        format!(
+            "# Replication\r\nrole:master\r\nconnected_slaves:1\r\n\
+             slave0:ip=127.0.0.1,port=7001,state=online,offset={},lag=0\r\n\
+             master_repl_offset:1000\r\n",
+            replica_offset
+        )
+    }
+
+    fn gen_failover_handler(
+        replica_offset: u64,
+        failures: Vec<String>,
+        replace_times: usize,
+    ) -> ManualFailoverHandler<
+        MockMetaDataBroker,
+        MockMetaManipulationBroker,
+        impl RedisClientFactory,
+    > {
+        let mut mock_data_broker = MockMetaDataBroker::new();
+        mock_data_broker
+            .expect_get_proxy()
+            .returning(|_| Box::pin(async { Ok(Some(gen_testing_master_proxy())) }));
+        mock_data_broker.expect_get_failures().returning(move || {
+            Box::pin(stream::iter(
+                failures.clone().into_iter().map(Ok).collect::<Vec<_>>(),
+            ))
+        });
+
+        let mut mock_mani_broker = MockMetaManipulationBroker::new();
+        mock_mani_broker
+            .expect_replace_proxy()
+            .withf(|address, _| address == "127.0.0.1:6000")
+            .times(replace_times)
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(ReplaceProxyResponse {
+                        proxy: Some(gen_testing_dummy_proxy()),
+                        hint_satisfied: true,
+                    })
+                })
+            });
+
+        let create_client_func = move |_| {
+            let mut mock_client = MockRedisClient::new();
+            let info_cmd = vec![b"INFO".to_vec(), b"REPLICATION".to_vec()];
+            mock_client
+                .expect_execute_single()
+                .withf(move |command: &Vec<BinSafeStr>| command.eq(&info_cmd))
+                .returning(move |_| {
+                    let info = gen_replication_info(replica_offset).into_bytes();
+                    Box::pin(async { Ok(Resp::Bulk(BulkStr::Str(info))) })
+                });
+            mock_client
+        };
+        ManualFailoverHandler::new(
+            Arc::new(mock_data_broker),
+            Arc::new(mock_mani_broker),
+            Arc::new(DummyRedisClientFactory::new(create_client_func, false)),
+        )
+    }
+
+    #[test]
+    fn test_parse_replication_lag() {
+        assert_eq!(
+            parse_replication_lag(&gen_replication_info(900)),
+            Some((1, 100))
+        );
+        let info = "role:master\r\nconnected_slaves:1\r\n\
+             slave0:ip=127.0.0.1,port=7001,state=wait_bgsave,offset=0,lag=0\r\n\
+             master_repl_offset:1000\r\n";
+        assert_eq!(parse_replication_lag(info), Some((0, 0)));
+        assert_eq!(parse_replication_lag("role:master\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_manual_failover() {
+        let handler = gen_failover_handler(1000, vec![], 1);
+        let res = handler
+            .failover("127.0.0.1:6000".to_string(), 0)
+            .await
+            .unwrap();
+        assert_eq!(res.proxy, Some(gen_testing_dummy_proxy()));
+    }
+
+    #[tokio::test]
+    async fn test_manual_failover_rejected() {
+        let handler = gen_failover_handler(900, vec![], 0);
+        let err = handler
+            .failover("127.0.0.1:6000".to_string(), 10)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ManualFailoverError::ReplicaLagging { lag: 100, .. }
+        ));
+        assert!(handler
+            .failover("127.0.0.1:6000".to_string(), 99)
+            .await
+            .is_err());
+
+        let handler = gen_failover_handler(1000, vec!["127.0.0.1:6001".to_string()], 0);
+        let err = handler
+            .failover("127.0.0.1:6000".to_string(), 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ManualFailoverError::ReplicaFailed(_)));
+    }
 }
@@ -5,13 +5,16 @@ use super::core::{
     ParFailureDetector, ParFailureHandler, ParMigrationStateSynchronizer,
     ProxyMetaRespSynchronizer, ProxyMetaSynchronizer,
 };
+pub use super::core::{DetectorEvent, DetectorEventKind, FailureCallback, FailureHooks};
 use super::detector::{
     BrokerFailureReporter, BrokerOrderedProxiesRetriever, BrokerProxiesRetriever,
-    PingFailureDetector,
+    PingFailureDetector, PingRetryHistory,
 };
+use super::heartbeat::HeartbeatReporter;
 use super::migration::{BrokerMigrationCommitter, MigrationStateRespChecker};
-use super::recover::{BrokerProxyFailureRetriever, ReplaceNodeHandler};
+use super::recover::{BrokerProxyFailureRetriever, ManualFailoverHandler, ReplaceNodeHandler};
 use super::sync::{BrokerMetaRetriever, ProxyMetaRespSender};
+use crate::common::clock::{Clock, SystemClock};
 use crate::common::utils::ThreadSafe;
 use crate::protocol::RedisClientFactory;
 use arc_swap::ArcSwap;
@@ -19,7 +22,7 @@ use futures::future::select_all;
 use futures::{Future, StreamExt};
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub type BrokerAddresses = Arc<ArcSwap<Vec<String>>>;
 
@@ -34,6 +37,22 @@ pub struct CoordinatorConfig {
     // In kubernetes we may need to disable failover
     // to test normal case without failover.
     pub disable_failover: bool,
+    // Bounds of the adaptive ping retries in the failure detector.
+    pub min_ping_retry: usize,
+    pub max_ping_retry: usize,
+    // In seconds. 0 disables the heartbeats of the proxies.
+    pub heartbeat_interval: u64,
+    // Let the failure detector use its own connections
+    // so that the PINGs won't wait behind the other requests to the proxies.
+    pub isolate_detector_conn: bool,
+    // In milliseconds. The delay between the detecting rounds
+    // and between the checks of the proxies in the same round.
+    pub detector_interval: u64,
+    pub detector_stagger: u64,
+    // Spread the checks of a round across `detector_interval` with random offsets.
+    pub detector_jitter: bool,
+    // The `admin_password` of the server proxies, sent by AUTH on connecting to them.
+    pub proxy_admin_password: Option<String>,
 }
 
 impl CoordinatorConfig {
@@ -55,7 +74,10 @@ pub struct CoordinatorService<
     data_broker: Arc<DB>,
     mani_broker: Arc<MB>,
     client_factory: Arc<F>,
-    api_service: Arc<ApiService>,
+    detector_client_factory: Arc<F>,
+    api_service: Arc<ApiService<DB, MB, F>>,
+    failure_hooks: Option<Arc<FailureHooks>>,
+    ping_retry_history: Arc<PingRetryHistory>,
 }
 
 type CoordResult = Result<(), CoordinateError>;
@@ -68,17 +90,47 @@ impl<DB: MetaDataBroker + ThreadSafe, MB: MetaManipulationBroker, F: RedisClient
         data_broker: Arc<DB>,
         mani_broker: Arc<MB>,
         client_factory: F,
+        // The failure detector shares `client_factory` when it's None.
+        detector_client_factory: Option<F>,
+        // Connects to the backend nodes, so it should not send the proxy admin password.
+        backend_client_factory: F,
     ) -> Self {
-        let api_service = Arc::new(ApiService::new(Arc::new(config.clone())));
+        let client_factory = Arc::new(client_factory);
+        let detector_client_factory = match detector_client_factory {
+            Some(factory) => Arc::new(factory),
+            None => client_factory.clone(),
+        };
+        let failover_handler = Arc::new(ManualFailoverHandler::new(
+            data_broker.clone(),
+            mani_broker.clone(),
+            Arc::new(backend_client_factory),
+        ));
+        let api_service = Arc::new(ApiService::new(
+            Arc::new(config.clone()),
+            data_broker.clone(),
+            failover_handler,
+        ));
+        let ping_retry_history = Arc::new(PingRetryHistory::new(
+            config.min_ping_retry,
+            config.max_ping_retry,
+        ));
         Self {
             config,
             data_broker,
             mani_broker,
-            client_factory: Arc::new(client_factory),
+            client_factory,
+            detector_client_factory,
             api_service,
+            failure_hooks: None,
+            ping_retry_history,
         }
     }
 
+    pub fn with_failure_hooks(mut self, failure_hooks: FailureHooks) -> Self {
+        self.failure_hooks = Some(Arc::new(failure_hooks));
+        self
+    }
+
     pub async fn run(&self) -> Result<(), CoordinateError> {
         info!("coordinator config: {:?}", self.config);
 
@@ -88,6 +140,9 @@ impl<DB: MetaDataBroker + ThreadSafe, MB: MetaManipulationBroker, F: RedisClient
             Box::pin(self.loop_migration_sync()),
             Box::pin(self.api_service.run()),
         ];
+        if self.config.heartbeat_interval != 0 {
+            futs.push(Box::pin(self.loop_heartbeat()));
+        }
         if self.config.disable_failover {
             warn!("disable failover for server proxy");
         } else {
@@ -103,11 +158,23 @@ impl<DB: MetaDataBroker + ThreadSafe, MB: MetaManipulationBroker, F: RedisClient
         reporter_id: String,
         data_broker: Arc<DB>,
         client_factory: Arc<F>,
+        failure_hooks: Option<Arc<FailureHooks>>,
+        ping_retry_history: Arc<PingRetryHistory>,
+        stagger: Duration,
+        jitter_window: Duration,
     ) -> impl FailureDetector {
         let retriever = BrokerProxiesRetriever::new(data_broker.clone());
-        let checker = PingFailureDetector::new(client_factory);
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let checker = PingFailureDetector::new(client_factory, ping_retry_history, clock.clone());
         let reporter = BrokerFailureReporter::new(reporter_id, data_broker);
-        ParFailureDetector::new(retriever, checker, reporter)
+        let detector = ParFailureDetector::new(retriever, checker, reporter)
+            .with_stagger(stagger)
+            .with_jitter_window(jitter_window)
+            .with_clock(clock);
+        match failure_hooks {
+            Some(hooks) => detector.with_hooks(hooks),
+            None => detector,
+        }
     }
 
     fn gen_proxy_meta_synchronizer(
@@ -135,7 +202,7 @@ impl<DB: MetaDataBroker + ThreadSafe, MB: MetaManipulationBroker, F: RedisClient
     ) -> impl MigrationStateSynchronizer {
         let proxy_retriever = BrokerProxiesRetriever::new(data_broker.clone());
         let checker = MigrationStateRespChecker::new(client_factory.clone());
-        let committer = BrokerMigrationCommitter::new(mani_broker);
+        let committer = BrokerMigrationCommitter::new(mani_broker, data_broker.clone());
         let meta_retriever = BrokerMetaRetriever::new(data_broker);
         let sender = ProxyMetaRespSender::new(client_factory, enable_compression);
         ParMigrationStateSynchronizer::new(
@@ -149,22 +216,40 @@ impl<DB: MetaDataBroker + ThreadSafe, MB: MetaManipulationBroker, F: RedisClient
 
     async fn loop_detect(&self) -> Result<(), CoordinateError> {
         let data_broker = self.data_broker.clone();
-        let client_factory = self.client_factory.clone();
+        let client_factory = self.detector_client_factory.clone();
         let reporter_id = self.config.reporter_id.clone();
+        let interval = Duration::from_millis(self.config.detector_interval);
+        let stagger = Duration::from_millis(self.config.detector_stagger);
+        let jitter_window = if self.config.detector_jitter {
+            interval
+        } else {
+            Duration::from_secs(0)
+        };
         loop {
             trace!("start detecting failures");
             defer!(trace!("detecting finished a round"));
+            let round_start = Instant::now();
             if let Err(e) = Self::gen_detector(
                 reporter_id.clone(),
                 data_broker.clone(),
                 client_factory.clone(),
+                self.failure_hooks.clone(),
+                self.ping_retry_history.clone(),
+                stagger,
+                jitter_window,
             )
             .run()
             .await
             {
                 error!("detector stream err {:?}", e);
             }
-            tokio::time::sleep(Duration::from_secs(1)).await;
+            // With the jitter, the round itself already spreads across the interval.
+            let sleep_time = if self.config.detector_jitter {
+                interval.saturating_sub(round_start.elapsed())
+            } else {
+                interval
+            };
+            tokio::time::sleep(sleep_time).await;
         }
     }
 
@@ -206,6 +291,24 @@ impl<DB: MetaDataBroker + ThreadSafe, MB: MetaManipulationBroker, F: RedisClient
         }
     }
 
+    async fn loop_heartbeat(&self) -> Result<(), CoordinateError> {
+        let interval = Duration::from_secs(self.config.heartbeat_interval);
+        loop {
+            trace!("start sending heartbeats");
+            defer!(trace!("sending heartbeats finished a round"));
+            let reporter = HeartbeatReporter::new(
+                self.config.reporter_id.clone(),
+                BrokerProxiesRetriever::new(self.data_broker.clone()),
+                self.mani_broker.clone(),
+                self.client_factory.clone(),
+            );
+            if let Err(e) = reporter.run().await {
+                error!("heartbeat err {:?}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
     async fn loop_migration_sync(&self) -> Result<(), CoordinateError> {
         let data_broker = self.data_broker.clone();
         let mani_broker = self.mani_broker.clone();
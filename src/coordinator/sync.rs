@@ -3,7 +3,7 @@ use super::core::{CoordinateError, ProxyMetaRetriever, ProxyMetaSender};
 use crate::common::cluster::{Proxy, Role, SlotRange, EMPTY_CLUSTER_NAME};
 use crate::common::proto::{ClusterMapFlags, MetaCompressError, ProxyClusterMeta};
 use crate::common::response::{ERR_NOT_MY_META, OK_REPLY, OLD_EPOCH_REPLY};
-use crate::protocol::{RedisClient, RedisClientFactory, Resp};
+use crate::protocol::{CmdBuilder, RedisClient, RedisClientFactory, Resp};
 use crate::replication::replicator::{encode_repl_meta, MasterMeta, ReplicaMeta, ReplicatorMeta};
 use futures::{Future, TryFutureExt};
 use std::collections::HashMap;
@@ -149,15 +149,14 @@ async fn send_meta<C: RedisClient>(
     args: Vec<String>,
 ) -> Result<(), CoordinateError> {
     trace!("sending meta {} {:?}", sub_command, args);
-    let mut cmd = vec!["UMCTL".to_string(), sub_command.clone()];
-    cmd.extend(args);
-    let resp = client
-        .execute_single(cmd.into_iter().map(String::into_bytes).collect())
-        .await
-        .map_err(|e| {
-            error!("failed to send meta data of proxy {:?}", e);
-            CoordinateError::Redis(e)
-        })?;
+    let cmd = CmdBuilder::new("UMCTL")
+        .arg(sub_command.clone())
+        .args(args)
+        .build();
+    let resp = client.execute_single(cmd).await.map_err(|e| {
+        error!("failed to send meta data of proxy {:?}", e);
+        CoordinateError::Redis(e)
+    })?;
     match resp {
         Resp::Error(err_str) => {
             if err_str == OLD_EPOCH_REPLY.as_bytes() {
@@ -0,0 +1,295 @@
+use super::broker::{MetaDataBroker, MetaDataBrokerError};
+use super::http_meta_broker::FailureReport;
+use crate::common::cluster::{Cluster, ClusterName, Proxy};
+use futures::{future, stream, Future, Stream};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::pin::Pin;
+
+// An in-memory `MetaDataBroker` for testing the coordinator.
+// Tests can preload the clusters and proxies,
+// and assert the failures reported by the coordinator.
+#[derive(Default)]
+pub struct InMemoryMetaBroker {
+    state: parking_lot::Mutex<BrokerState>,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    clusters: HashMap<ClusterName, Cluster>,
+    proxies: BTreeMap<String, Proxy>,
+    // Reported by `add_failure` and `add_failures` in the reporting order.
+    reported_failures: Vec<FailureReport>,
+    failed_proxies: BTreeSet<String>,
+    unavailable: bool,
+}
+
+impl InMemoryMetaBroker {
+    pub fn add_cluster(&self, cluster: Cluster) {
+        let mut state = self.state.lock();
+        state.clusters.insert(cluster.get_name().clone(), cluster);
+    }
+
+    pub fn remove_cluster(&self, cluster_name: &ClusterName) -> Option<Cluster> {
+        self.state.lock().clusters.remove(cluster_name)
+    }
+
+    pub fn add_proxy(&self, proxy: Proxy) {
+        let mut state = self.state.lock();
+        state.proxies.insert(proxy.get_address().to_string(), proxy);
+    }
+
+    pub fn remove_proxy(&self, address: &str) -> Option<Proxy> {
+        self.state.lock().proxies.remove(address)
+    }
+
+    // Mark the proxy as failed, which is normally decided by the broker
+    // after enough coordinators reported the failure.
+    pub fn add_failed_proxy(&self, address: String) {
+        self.state.lock().failed_proxies.insert(address);
+    }
+
+    pub fn remove_failed_proxy(&self, address: &str) -> bool {
+        self.state.lock().failed_proxies.remove(address)
+    }
+
+    // When unavailable, all the APIs will return `MetaDataBrokerError::NoBroker`.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.state.lock().unavailable = unavailable;
+    }
+
+    // Returns (address, reporter_id) in the reporting order.
+    pub fn get_reported_failures(&self) -> Vec<(String, String)> {
+        self.state
+            .lock()
+            .reported_failures
+            .iter()
+            .map(|report| (report.proxy_address.clone(), report.reporter_id.clone()))
+            .collect()
+    }
+
+    pub fn clear_reported_failures(&self) {
+        self.state.lock().reported_failures.clear();
+    }
+
+    fn check_available(&self) -> Result<(), MetaDataBrokerError> {
+        if self.state.lock().unavailable {
+            return Err(MetaDataBrokerError::NoBroker);
+        }
+        Ok(())
+    }
+
+    fn gen_stream<'s, T: Send + 's>(
+        &self,
+        items: Vec<T>,
+    ) -> Pin<Box<dyn Stream<Item = Result<T, MetaDataBrokerError>> + Send + 's>> {
+        match self.check_available() {
+            Ok(()) => Box::pin(stream::iter(items.into_iter().map(Ok))),
+            Err(err) => Box::pin(stream::once(future::err(err))),
+        }
+    }
+}
+
+fn gen_report(proxy_address: String, reporter_id: String) -> FailureReport {
+    FailureReport {
+        proxy_address,
+        reporter_id,
+        report_time: chrono::Utc::now().timestamp(),
+    }
+}
+
+impl MetaDataBroker for InMemoryMetaBroker {
+    fn get_cluster_names<'s>(
+        &'s self,
+    ) -> Pin<Box<dyn Stream<Item = Result<ClusterName, MetaDataBrokerError>> + Send + 's>> {
+        let mut names: Vec<ClusterName> = self.state.lock().clusters.keys().cloned().collect();
+        names.sort_by_key(|name| name.to_string());
+        self.gen_stream(names)
+    }
+
+    fn get_cluster<'s>(
+        &'s self,
+        name: ClusterName,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Cluster>, MetaDataBrokerError>> + Send + 's>>
+    {
+        let res = self
+            .check_available()
+            .map(|()| self.state.lock().clusters.get(&name).cloned());
+        Box::pin(future::ready(res))
+    }
+
+    fn get_proxy_addresses<'s>(
+        &'s self,
+    ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>> {
+        let addresses = self.state.lock().proxies.keys().cloned().collect();
+        self.gen_stream(addresses)
+    }
+
+    fn get_proxy<'s>(
+        &'s self,
+        address: String,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Proxy>, MetaDataBrokerError>> + Send + 's>> {
+        let res = self
+            .check_available()
+            .map(|()| self.state.lock().proxies.get(&address).cloned());
+        Box::pin(future::ready(res))
+    }
+
+    fn add_failure<'s>(
+        &'s self,
+        address: String,
+        reporter_id: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), MetaDataBrokerError>> + Send + 's>> {
+        let res = self.check_available().map(|()| {
+            self.state
+                .lock()
+                .reported_failures
+                .push(gen_report(address, reporter_id))
+        });
+        Box::pin(future::ready(res))
+    }
+
+    fn add_failures<'s>(
+        &'s self,
+        addresses: Vec<String>,
+        reporter_id: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), MetaDataBrokerError>> + Send + 's>> {
+        let res = self.check_available().map(|()| {
+            let mut state = self.state.lock();
+            for address in addresses.into_iter() {
+                state
+                    .reported_failures
+                    .push(gen_report(address, reporter_id.clone()));
+            }
+        });
+        Box::pin(future::ready(res))
+    }
+
+    fn get_failures<'s>(
+        &'s self,
+    ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>> {
+        let addresses: BTreeSet<String> = self
+            .state
+            .lock()
+            .reported_failures
+            .iter()
+            .map(|report| report.proxy_address.clone())
+            .collect();
+        self.gen_stream(addresses.into_iter().collect())
+    }
+
+    fn get_failure_reports<'s>(
+        &'s self,
+    ) -> Pin<Box<dyn Stream<Item = Result<FailureReport, MetaDataBrokerError>> + Send + 's>> {
+        let reports = self.state.lock().reported_failures.clone();
+        self.gen_stream(reports)
+    }
+
+    fn get_failed_proxies<'s>(
+        &'s self,
+    ) -> Pin<Box<dyn Stream<Item = Result<String, MetaDataBrokerError>> + Send + 's>> {
+        let addresses = self.state.lock().failed_proxies.iter().cloned().collect();
+        self.gen_stream(addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::cluster::{Node, RangeList, ReplMeta, Role, SlotRange, SlotRangeTag};
+    use crate::common::config::ClusterConfig;
+    use futures::StreamExt;
+    use std::convert::TryFrom;
+
+    fn gen_cluster(name: &str) -> Cluster {
+        let nodes = vec![Node::new(
+            "127.0.0.1:7000".to_string(),
+            "127.0.0.1:6000".to_string(),
+            vec![SlotRange {
+                range_list: RangeList::try_from("1 0-16383").unwrap(),
+                tag: SlotRangeTag::None,
+            }],
+            ReplMeta::new(Role::Master, Vec::new()),
+        )];
+        Cluster::new(
+            ClusterName::try_from(name).unwrap(),
+            1,
+            nodes,
+            ClusterConfig::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_broker_clusters() {
+        let broker = InMemoryMetaBroker::default();
+        broker.add_cluster(gen_cluster("cluster_b"));
+        broker.add_cluster(gen_cluster("cluster_a"));
+
+        let names: Vec<String> = broker
+            .get_cluster_names()
+            .map(|name| name.unwrap().to_string())
+            .collect()
+            .await;
+        assert_eq!(names, vec!["cluster_a", "cluster_b"]);
+
+        let name = ClusterName::try_from("cluster_a").unwrap();
+        let cluster = broker.get_cluster(name.clone()).await.unwrap().unwrap();
+        assert_eq!(cluster.get_name(), &name);
+
+        assert!(broker.remove_cluster(&name).is_some());
+        assert!(broker.get_cluster(name).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_broker_failures() {
+        let broker = InMemoryMetaBroker::default();
+        broker
+            .add_failure("127.0.0.1:6000".to_string(), "coordinator1".to_string())
+            .await
+            .unwrap();
+        broker
+            .add_failure("127.0.0.1:6000".to_string(), "coordinator2".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            broker.get_reported_failures(),
+            vec![
+                ("127.0.0.1:6000".to_string(), "coordinator1".to_string()),
+                ("127.0.0.1:6000".to_string(), "coordinator2".to_string()),
+            ]
+        );
+        let failures: Vec<String> = broker
+            .get_failures()
+            .map(|address| address.unwrap())
+            .collect()
+            .await;
+        assert_eq!(failures, vec!["127.0.0.1:6000"]);
+
+        broker.add_failed_proxy("127.0.0.1:6000".to_string());
+        let failed: Vec<String> = broker
+            .get_failed_proxies()
+            .map(|address| address.unwrap())
+            .collect()
+            .await;
+        assert_eq!(failed, vec!["127.0.0.1:6000"]);
+
+        broker.clear_reported_failures();
+        assert!(broker.get_reported_failures().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_broker_unavailable() {
+        let broker = InMemoryMetaBroker::default();
+        broker.add_cluster(gen_cluster("cluster_a"));
+        broker.set_unavailable(true);
+
+        let names: Vec<Result<ClusterName, MetaDataBrokerError>> =
+            broker.get_cluster_names().collect().await;
+        assert_eq!(names.len(), 1);
+        assert!(names[0].is_err());
+        let name = ClusterName::try_from("cluster_a").unwrap();
+        assert!(broker.get_cluster(name.clone()).await.is_err());
+
+        broker.set_unavailable(false);
+        assert!(broker.get_cluster(name).await.unwrap().is_some());
+    }
+}
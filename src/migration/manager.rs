@@ -1,10 +1,15 @@
 use super::scan_task::{RedisScanImportingTask, RedisScanMigratingTask};
 use super::stats::MigrationStats;
-use super::task::{ImportingTask, MigratingTask, MigrationError, MigrationState, SwitchArg};
+use super::task::{
+    ImportingTask, MigratingTask, MigrationError, MigrationState, MigrationTaskStatus, SwitchArg,
+};
 use crate::common::cluster::{
     ClusterName, MigrationTaskMeta, RangeList, SlotRange, SlotRangeTag, EMPTY_CLUSTER_NAME,
 };
-use crate::common::config::{AtomicMigrationConfig, ClusterConfig};
+use crate::common::config::{
+    AtomicMigrationConfig, ClusterConfig, ConfigError, MigrationConfig, MigrationConfigOverrides,
+};
+use crate::common::future_group::{new_auto_drop_future, FutureAutoStopHandle};
 use crate::common::track::TrackedFutureRegistry;
 use crate::common::utils::{generate_slot, ThreadSafe};
 use crate::migration::task::MgrSubCmd;
@@ -59,6 +64,8 @@ where
     cmd_task_factory: Arc<CTF>,
     future_registry: Arc<TrackedFutureRegistry>,
     stats: Arc<MigrationStats>,
+    stop_handles: parking_lot::Mutex<Vec<FutureAutoStopHandle>>,
+    config_overrides: parking_lot::Mutex<MigrationConfigOverrides>,
 }
 
 impl<RCF, TSF, DTSF, PTSF, CTF> MigrationManager<RCF, TSF, DTSF, PTSF, CTF>
@@ -94,6 +101,8 @@ where
             cmd_task_factory,
             future_registry,
             stats,
+            stop_handles: parking_lot::Mutex::new(vec![]),
+            config_overrides: parking_lot::Mutex::new(MigrationConfigOverrides::default()),
         }
     }
 
@@ -106,6 +115,13 @@ where
         cluster_config: &ClusterConfig,
         blocking_ctrl_factory: Arc<BCF>,
     ) -> NewMigrationTuple<CTF::Task> {
+        let mut cluster_config = cluster_config.clone();
+        cluster_config.migration_config = {
+            let mut config_overrides = self.config_overrides.lock();
+            config_overrides.set_cluster_config(cluster_config.migration_config.clone());
+            config_overrides.get_config()
+        };
+
         // TODO: Remove AtomicMigrationConfig and use MigrationConfig directly.
         let mgr_config = Arc::new(AtomicMigrationConfig::from_config(
             cluster_config.migration_config.clone(),
@@ -113,7 +129,7 @@ where
         old_migration_map.update_from_old_task_map(
             cluster_name,
             local_cluster_map,
-            cluster_config,
+            &cluster_config,
             self.config.clone(),
             mgr_config,
             self.client_factory.clone(),
@@ -126,11 +142,24 @@ where
         )
     }
 
+    // Only applies to the migration tasks created afterward.
+    pub fn set_config_field(&self, field: &str, value: &str) -> Result<(), ConfigError> {
+        self.config_overrides.lock().set_field(field, value)
+    }
+
+    pub fn get_config(&self) -> MigrationConfig {
+        self.config_overrides.lock().get_config()
+    }
+
     pub fn run_tasks(&self, new_tasks: Vec<NewTask<CTF::Task>>) {
         if new_tasks.is_empty() {
             return;
         }
 
+        let mut stop_handles = self.stop_handles.lock();
+        // Remove the handles of the finished tasks.
+        stop_handles.retain(|handle| !handle.is_stopped());
+
         for NewTask {
             cluster_name,
             epoch,
@@ -164,6 +193,8 @@ where
                             );
                         }
                     };
+                    let (fut, handle) = new_auto_drop_future(fut);
+                    stop_handles.push(handle);
 
                     let fut = TrackedFutureRegistry::wrap(self.future_registry.clone(), fut, desc);
                     tokio::spawn(fut);
@@ -193,6 +224,8 @@ where
                             );
                         }
                     };
+                    let (fut, handle) = new_auto_drop_future(fut);
+                    stop_handles.push(handle);
 
                     let fut = TrackedFutureRegistry::wrap(self.future_registry.clone(), fut, desc);
                     tokio::spawn(fut);
@@ -205,6 +238,32 @@ where
     pub fn get_stats(&self) -> Vec<(String, usize)> {
         self.stats.to_lines_str()
     }
+
+    // Dropping the handles stops all the spawned migration futures.
+    pub fn stop(&self) {
+        let handles: Vec<FutureAutoStopHandle> = self.stop_handles.lock().drain(..).collect();
+        if !handles.is_empty() {
+            info!("stop {} migration tasks", handles.len());
+        }
+    }
+}
+
+impl<RCF, TSF, DTSF, PTSF, CTF> Drop for MigrationManager<RCF, TSF, DTSF, PTSF, CTF>
+where
+    RCF: RedisClientFactory,
+    <TSF as CmdTaskSenderFactory>::Sender: ThreadSafe + CmdTaskSender<Task = ReqTask<CTF::Task>>,
+    TSF: CmdTaskSenderFactory + ThreadSafe,
+    <DTSF as CmdTaskSenderFactory>::Sender:
+        ThreadSafe + CmdTaskSender<Task = ReqTask<WaitableTask<CTF::Task>>>,
+    DTSF: CmdTaskSenderFactory + ThreadSafe,
+    <PTSF as CmdTaskSenderFactory>::Sender: ThreadSafe + CmdTaskSender<Task = ReqTask<CTF::Task>>,
+    PTSF: CmdTaskSenderFactory + ThreadSafe,
+    CTF: CmdTaskFactory + ThreadSafe,
+    CTF::Task: CmdTask<TaskType = CmdTypeTuple>,
+{
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
 
 pub struct MigrationMap<T>
@@ -451,6 +510,7 @@ where
                             client_factory.clone(),
                             ctrl,
                             stats.clone(),
+                            cluster_config.key_prefix.as_bytes().to_vec(),
                         ));
                         new_tasks.push(NewTask {
                             cluster_name: cluster_name.clone(),
@@ -554,6 +614,8 @@ where
         Err(SwitchError::TaskNotFound)
     }
 
+    // The tasks are only kept while the slot ranges are still migrating in the metadata
+    // so there's no need to evict the finished ones.
     pub fn get_finished_tasks(&self) -> Vec<MigrationTaskMeta> {
         let mut metadata = vec![];
         {
@@ -570,6 +632,37 @@ where
         metadata
     }
 
+    // The task could be specified by either the migrating or the importing slot range.
+    pub fn get_task_status(&self, meta: &MigrationTaskMeta) -> MigrationTaskStatus {
+        let migration_meta = match meta.slot_range.tag.get_migration_meta() {
+            Some(migration_meta) => migration_meta,
+            None => return MigrationTaskStatus::Failed,
+        };
+        let tags = [
+            SlotRangeTag::Migrating(migration_meta.clone()),
+            SlotRangeTag::Importing(migration_meta.clone()),
+        ];
+        for tag in tags.iter() {
+            let mut task_meta = meta.clone();
+            task_meta.slot_range.tag = tag.clone();
+            let mgr_task = match self.task_map.get(&task_meta) {
+                Some(mgr_task) => mgr_task,
+                None => continue,
+            };
+            return match &mgr_task.task {
+                Either::Left(migrating_task) => match migrating_task.get_state() {
+                    MigrationState::SwitchCommitted => MigrationTaskStatus::Committed,
+                    _ => MigrationTaskStatus::Source,
+                },
+                Either::Right(importing_task) => match importing_task.get_state() {
+                    MigrationState::SwitchCommitted => MigrationTaskStatus::Committed,
+                    _ => MigrationTaskStatus::Switching,
+                },
+            };
+        }
+        MigrationTaskStatus::Failed
+    }
+
     pub fn get_states(&self) -> HashMap<RangeList, MigrationState> {
         let mut m = HashMap::new();
         for (meta, mgr_task) in self.task_map.iter() {
@@ -1,5 +1,6 @@
 use super::stats::MigrationStats;
 use super::task::{ScanResponse, SlotRangeArray};
+use crate::common::clock::SystemClock;
 use crate::common::cluster::SlotRange;
 use crate::common::config::AtomicMigrationConfig;
 use crate::common::future_group::{new_auto_drop_future, FutureAutoStopHandle};
@@ -11,8 +12,8 @@ use crate::common::utils::{generate_lock_slot, pretty_print_bytes};
 use crate::common::yield_now::YieldNow;
 use crate::migration::task::MigrationError;
 use crate::protocol::{
-    BinSafeStr, BulkStr, OptionalMulti, Pool, RedisClient, RedisClientError, RedisClientFactory,
-    Resp, RespVec,
+    BinSafeStr, BulkStr, CmdBuilder, OptionalMulti, Pool, RedisClient, RedisClientError,
+    RedisClientFactory, Resp, RespVec,
 };
 use crate::proxy::backend::CmdTask;
 use atomic_option::AtomicOption;
@@ -86,9 +87,10 @@ impl<T: CmdTask, F: RedisClientFactory> ScanMigrationTask<T, F> {
         client_factory: Arc<F>,
         config: Arc<AtomicMigrationConfig>,
         stats: Arc<MigrationStats>,
+        key_prefix: Vec<u8>,
     ) -> Self {
         let ranges = slot_range.to_range_list();
-        let slot_ranges = SlotRangeArray::new(ranges);
+        let slot_ranges = SlotRangeArray::new(ranges, key_prefix);
         let (sender, receiver) = unbounded();
         let slot_mutex = Arc::new(SlotMutex::default());
         let (fut, fut_handle) = Self::gen_future(
@@ -585,15 +587,13 @@ impl<T: CmdTask, F: RedisClientFactory> ScanMigrationTask<T, F> {
         index: u64,
         scan_count: u64,
     ) -> Result<ScanResponse, RedisClientError> {
-        let scan_cmd = vec![
-            "SCAN".to_string(),
-            index.to_string(),
-            "COUNT".to_string(),
-            scan_count.to_string(),
-        ];
-        let byte_cmd = scan_cmd.into_iter().map(|s| s.into_bytes()).collect();
-
-        let resp = src_client.execute_single(byte_cmd).await?;
+        let scan_cmd = CmdBuilder::new("SCAN")
+            .arg(index.to_string())
+            .arg("COUNT")
+            .arg(scan_count.to_string())
+            .build();
+
+        let resp = src_client.execute_single(scan_cmd).await?;
         ScanResponse::parse_scan(&resp).ok_or_else(|| {
             error!("Invalid scan reply: {:?}", resp);
             RedisClientError::InvalidReply
@@ -608,8 +608,8 @@ impl<T: CmdTask, F: RedisClientFactory> ScanMigrationTask<T, F> {
 
         let mut commands = vec![];
         for key in &keys {
-            let pttl_cmd = vec!["PTTL".to_string().into_bytes(), key.clone()];
-            let dump_cmd = vec!["DUMP".to_string().into_bytes(), key.clone()];
+            let pttl_cmd = CmdBuilder::new("PTTL").arg(key.clone()).build();
+            let dump_cmd = CmdBuilder::new("DUMP").arg(key.clone()).build();
 
             commands.push(pttl_cmd);
             commands.push(dump_cmd);
@@ -695,12 +695,14 @@ impl<T: CmdTask, F: RedisClientFactory> ScanMigrationTask<T, F> {
         }
 
         let retry_interval = Duration::from_millis(1);
+        // It's safe to retry RESTORE since `BUSYKEY` is ignored in `handle_forward`.
         keep_connecting_and_sending_cmd_with_cached_client(
             cached_dst_client,
             client_factory,
             dst_address,
             OptionalMulti::Multi(commands),
             retry_interval,
+            &SystemClock,
             Self::handle_forward,
         )
         .await
@@ -710,8 +712,7 @@ impl<T: CmdTask, F: RedisClientFactory> ScanMigrationTask<T, F> {
         client: &mut C,
         keys: Vec<BinSafeStr>,
     ) -> Result<(), RedisClientError> {
-        let mut del_cmd = vec!["DEL".to_string().into_bytes()];
-        del_cmd.extend_from_slice(keys.as_slice());
+        let del_cmd = CmdBuilder::new("DEL").args(keys).build();
         let resp = client.execute_single(del_cmd).await?;
 
         match resp {
@@ -4,16 +4,18 @@ use super::task::{
     AtomicMigrationState, ImportingTask, MgrSubCmd, MigratingTask, MigrationError, MigrationState,
     SwitchArg,
 };
+use crate::common::clock::SystemClock;
 use crate::common::cluster::{
     ClusterName, MigrationMeta, MigrationTaskMeta, RangeMap, SlotRange, SlotRangeTag,
 };
 use crate::common::config::AtomicMigrationConfig;
 use crate::common::resp_execution::keep_connecting_and_sending_cmd;
 use crate::common::response;
-use crate::common::utils::{gen_moved, pretty_print_bytes, ThreadSafe};
+use crate::common::utils::{gen_ask, gen_moved, pretty_print_bytes, ThreadSafe};
 use crate::common::version::UNDERMOON_MIGRATION_VERSION;
 use crate::protocol::{
-    PreCheckRedisClientFactory, RedisClientError, RedisClientFactory, Resp, RespVec,
+    BinSafeStr, CmdBuilder, PreCheckRedisClientFactory, RedisClientError, RedisClientFactory, Resp,
+    RespVec,
 };
 use crate::proxy::backend::{CmdTask, CmdTaskFactory, ReqTask};
 use crate::proxy::blocking::{
@@ -30,7 +32,7 @@ use futures::future::BoxFuture;
 use futures::{future, select, Future, FutureExt, TryFutureExt};
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -53,6 +55,9 @@ where
     range_map: RangeMap,
     meta: MigrationMeta,
     state: Arc<AtomicMigrationState>,
+    // Set when the migration is forced to go ahead without the destination
+    // acknowledging PRESWITCH. The destination then only accepts the commands after ASKING.
+    unconfirmed_switch: AtomicBool,
     client_factory: Arc<RCF>,
     stop_signal_sender: AtomicOption<oneshot::Sender<()>>,
     stop_signal_receiver: AtomicOption<oneshot::Receiver<()>>,
@@ -78,6 +83,7 @@ where
         client_factory: Arc<RCF>,
         blocking_ctrl: Arc<BC>,
         stats: Arc<MigrationStats>,
+        key_prefix: Vec<u8>,
     ) -> Self {
         let (stop_signal_sender, stop_signal_receiver) = oneshot::channel();
         let task = ScanMigrationTask::new(
@@ -87,6 +93,7 @@ where
             client_factory.clone(),
             mgr_config.clone(),
             stats,
+            key_prefix,
         );
         let range_map = RangeMap::from(slot_range.get_range_list());
         let active_redirection = config.active_redirection;
@@ -97,6 +104,7 @@ where
             range_map,
             meta,
             state: Arc::new(AtomicMigrationState::initial_state()),
+            unconfirmed_switch: AtomicBool::new(false),
             client_factory,
             stop_signal_sender: AtomicOption::new(Box::new(stop_signal_sender)),
             stop_signal_receiver: AtomicOption::new(Box::new(stop_signal_receiver)),
@@ -107,8 +115,7 @@ where
         }
     }
 
-    fn gen_switch_arg(&self, sub_cmd: &str) -> Vec<String> {
-        let mut cmd = vec!["UMCTL".to_string(), sub_cmd.to_string()];
+    fn gen_switch_cmd(&self, sub_cmd: &str) -> Vec<BinSafeStr> {
         let arg = SwitchArg {
             version: UNDERMOON_MIGRATION_VERSION.to_string(),
             meta: MigrationTaskMeta {
@@ -120,8 +127,7 @@ where
             },
         }
         .into_strings();
-        cmd.extend(arg.into_iter());
-        cmd
+        CmdBuilder::new("UMCTL").arg(sub_cmd).args(arg).build()
     }
 
     async fn pre_check(&self) {
@@ -156,11 +162,7 @@ where
             2,
         ));
         let dst_proxy_address = self.meta.dst_proxy_address.clone();
-        let cmd = self
-            .gen_switch_arg("PRECHECK")
-            .into_iter()
-            .map(|e| e.into_bytes())
-            .collect();
+        let cmd = self.gen_switch_cmd("PRECHECK");
         let interval = Duration::from_millis(10);
 
         keep_connecting_and_sending_cmd(
@@ -168,6 +170,7 @@ where
             dst_proxy_address,
             cmd,
             interval,
+            &SystemClock,
             handle_pre_check,
         )
         .await;
@@ -218,11 +221,7 @@ where
             2,
         ));
         let dst_proxy_address = self.meta.dst_proxy_address.clone();
-        let cmd = self
-            .gen_switch_arg("PRESWITCH")
-            .into_iter()
-            .map(|e| e.into_bytes())
-            .collect();
+        let cmd = self.gen_switch_cmd("PRESWITCH");
         let interval = Duration::from_millis(1);
 
         keep_connecting_and_sending_cmd(
@@ -230,6 +229,7 @@ where
             dst_proxy_address,
             cmd,
             interval,
+            &SystemClock,
             handle_pre_switch,
         )
         .await;
@@ -287,11 +287,7 @@ where
             2,
         ));
         let dst_proxy_address = self.meta.dst_proxy_address.clone();
-        let cmd = self
-            .gen_switch_arg("FINALSWITCH")
-            .into_iter()
-            .map(|e| e.into_bytes())
-            .collect();
+        let cmd = self.gen_switch_cmd("FINALSWITCH");
         let interval = Duration::from_millis(1);
 
         keep_connecting_and_sending_cmd(
@@ -299,6 +295,7 @@ where
             dst_proxy_address,
             cmd,
             interval,
+            &SystemClock,
             handle_final_switch,
         )
         .await;
@@ -352,6 +349,13 @@ where
             error!("Migration failed {:?}. Force to go ahead.", err);
         }
 
+        // The keys are deleted from the source once scanned,
+        // so the commands must not be processed here from now on.
+        if self.state.get_state() != MigrationState::Scanning {
+            self.unconfirmed_switch.store(true, Ordering::SeqCst);
+            self.state.set_state(MigrationState::Scanning);
+        }
+
         scan_migrate.await
     }
 }
@@ -421,6 +425,11 @@ where
                     blocking_hint,
                 )));
             }
+            MigrationState::Scanning | MigrationState::FinalSwitch
+                if self.unconfirmed_switch.load(Ordering::SeqCst) =>
+            {
+                return handle_ask_redirection(cmd_task, self.meta.dst_proxy_address.clone());
+            }
             _ => (),
         }
 
@@ -620,7 +629,8 @@ where
         &self,
         cmd_task: Self::Task,
     ) -> Result<(), ClusterSendError<BlockingHintTask<Self::Task>>> {
-        if self.state.get_state() == MigrationState::PreCheck {
+        // ASKING allows the command to access the importing slot before switching.
+        if self.state.get_state() == MigrationState::PreCheck && !cmd_task.is_asking() {
             return handle_redirection(
                 cmd_task,
                 self.meta.src_proxy_address.clone(),
@@ -722,3 +732,17 @@ fn handle_redirection<T: CmdTask>(
         Ok(())
     }
 }
+
+// Even with active redirection, the clients need to retry with ASKING by themselves
+// since the forwarded commands don't carry ASKING and would be redirected back.
+fn handle_ask_redirection<T: CmdTask>(
+    cmd_task: T,
+    redirection_address: String,
+) -> Result<(), ClusterSendError<BlockingHintTask<T>>> {
+    let resp = match cmd_task.get_slot() {
+        Some(slot) => Resp::Error(gen_ask(slot, redirection_address).into_bytes()),
+        None => Resp::Error("missing key".to_string().into_bytes()),
+    };
+    cmd_task.set_resp_result(Ok(resp));
+    Ok(())
+}
@@ -1,30 +1,5 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
-
-macro_rules! atomic_usize_stats {
-    (pub struct $struct_name:ident {
-        $(pub $field_name:ident: AtomicUsize,)*
-    }) => {
-        pub struct $struct_name {
-            $(pub $field_name: AtomicUsize,)*
-        }
-
-        impl Default for $struct_name {
-            fn default() -> Self {
-                Self {
-                    $($field_name: AtomicUsize::new(0),)*
-                }
-            }
-        }
-
-        impl $struct_name {
-            pub fn to_lines_str(&self) -> Vec<(String, usize)> {
-                vec![
-                    $((stringify!($field_name).to_string(), self.$field_name.load(Ordering::Relaxed))),*
-                ]
-            }
-        }
-    }
-}
+use crate::common::stats::atomic_usize_stats;
+use std::sync::atomic::AtomicUsize;
 
 atomic_usize_stats! {
     pub struct MigrationStats {
@@ -57,6 +57,31 @@ impl fmt::Display for MigrationState {
     }
 }
 
+// The progress of a migration task reported by `UMCTL MGRSTATUS`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MigrationTaskStatus {
+    // The destination proxy has taken over the slots.
+    Committed,
+    // The destination proxy is importing the slots.
+    Switching,
+    // The slots are still owned by this source proxy.
+    Source,
+    // The task is not found, e.g. it was replaced by a newer metadata.
+    Failed,
+}
+
+impl fmt::Display for MigrationTaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Committed => "committed",
+            Self::Switching => "switching",
+            Self::Source => "source",
+            Self::Failed => "failed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 #[derive(Debug)]
 pub struct AtomicMigrationState {
     inner: AtomicU16,
@@ -161,6 +186,15 @@ pub fn parse_switch_command(resp: &RespSlice) -> Option<SwitchArg> {
     SwitchArg::from_strings(&mut it)
 }
 
+pub fn parse_mgr_status_command(resp: &RespSlice) -> Option<MigrationTaskMeta> {
+    let command = get_resp_strings(resp)?;
+    let mut it = command.into_iter().peekable();
+    // Skip UMCTL MGRSTATUS
+    it.next()?;
+    it.next()?;
+    MigrationTaskMeta::from_strings(&mut it)
+}
+
 #[derive(Debug)]
 pub enum MigrationError {
     IncompatibleVersion,
@@ -200,6 +234,8 @@ impl Error for MigrationError {
 pub struct SlotRangeArray {
     ranges: RangeList,
     range_map: RangeMap,
+    // The `key_prefix` of the cluster. The slots are computed without it.
+    key_prefix: Vec<u8>,
 }
 
 impl fmt::Display for SlotRangeArray {
@@ -215,12 +251,21 @@ impl fmt::Display for SlotRangeArray {
 }
 
 impl SlotRangeArray {
-    pub fn new(ranges: RangeList) -> Self {
+    pub fn new(ranges: RangeList, key_prefix: Vec<u8>) -> Self {
         let range_map = RangeMap::from(&ranges);
-        Self { ranges, range_map }
+        Self {
+            ranges,
+            range_map,
+            key_prefix,
+        }
     }
 
+    // The keys without the prefix do not belong to the cluster.
     pub fn is_key_inside(&self, key: &[u8]) -> bool {
+        let key = match key.strip_prefix(self.key_prefix.as_slice()) {
+            Some(key) => key,
+            None => return false,
+        };
         let slot = generate_slot(key);
         self.range_map.contains_slot(slot)
     }
@@ -256,3 +301,23 @@ impl ScanResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_inside_with_prefix() {
+        let slot = generate_slot(b"a");
+        let ranges = RangeList::from_single_range(Range(slot, slot));
+
+        let range_array = SlotRangeArray::new(ranges.clone(), vec![]);
+        assert!(range_array.is_key_inside(b"a"));
+        assert!(!range_array.is_key_inside(b"t:a"));
+
+        let range_array = SlotRangeArray::new(ranges, b"t:".to_vec());
+        assert!(range_array.is_key_inside(b"t:a"));
+        assert!(!range_array.is_key_inside(b"a"));
+        assert!(!range_array.is_key_inside(b"s:a"));
+    }
+}
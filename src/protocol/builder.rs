@@ -0,0 +1,101 @@
+use super::resp::{Array, BinSafeStr, BulkStr, Resp, RespVec};
+
+// Builds the commands sent to Redis or server proxies.
+// All the arguments are binary safe.
+// The command name is always a literal so building the command never fails.
+#[derive(Debug, Clone)]
+pub struct CmdBuilder {
+    cmd: Vec<BinSafeStr>,
+}
+
+impl CmdBuilder {
+    pub fn new(cmd_name: &'static str) -> Self {
+        Self {
+            cmd: vec![cmd_name.as_bytes().to_vec()],
+        }
+    }
+
+    pub fn arg<T: Into<BinSafeStr>>(mut self, arg: T) -> Self {
+        self.cmd.push(arg.into());
+        self
+    }
+
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<BinSafeStr>,
+    {
+        self.cmd.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn build(self) -> Vec<BinSafeStr> {
+        self.cmd
+    }
+
+    pub fn build_resp(self) -> RespVec {
+        let elements = self
+            .cmd
+            .into_iter()
+            .map(|arg| Resp::Bulk(BulkStr::Str(arg)))
+            .collect();
+        Resp::Arr(Array::Arr(elements))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{encode_resp, DecodedPacket, EncodedPacket, RespPacket};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_build_cmd() {
+        let cmd = CmdBuilder::new("SET")
+            .arg("key")
+            .arg(b"binary\r\n\x00".to_vec())
+            .arg(233.to_string())
+            .build();
+        assert_eq!(
+            cmd,
+            vec![
+                b"SET".to_vec(),
+                b"key".to_vec(),
+                b"binary\r\n\x00".to_vec(),
+                b"233".to_vec()
+            ]
+        );
+
+        let cmd = CmdBuilder::new("DEL")
+            .args(vec![b"a".to_vec(), b"b".to_vec()])
+            .build();
+        assert_eq!(cmd, vec![b"DEL".to_vec(), b"a".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn test_cmd_round_trip() {
+        let builder = CmdBuilder::new("UMCTL")
+            .arg("SETDB")
+            .arg(b"\xff\r\n".to_vec());
+        let cmd = builder.clone().build();
+        let resp = builder.build_resp();
+
+        let mut encoded = vec![];
+        encode_resp(&mut encoded, &resp).unwrap();
+        let mut cmd_encoded = vec![];
+        let (size, _) = cmd
+            .clone()
+            .encode(|data| cmd_encoded.extend_from_slice(data))
+            .unwrap();
+        assert_eq!(size, cmd_encoded.len());
+        assert_eq!(encoded, cmd_encoded);
+
+        let mut buf = BytesMut::from(encoded.as_slice());
+        let packet = RespPacket::decode(&mut buf, ()).unwrap().unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(packet.get_array_len(), Some(cmd.len()));
+        for (i, arg) in cmd.iter().enumerate() {
+            assert_eq!(packet.get_array_element(i), Some(arg.as_slice()));
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::resp::{BinSafeStr, RespVec};
+use super::resp::{BinSafeStr, Resp, RespVec};
 use crate::common::utils::{resolve_first_address, ThreadSafe};
 use crate::protocol::{
     new_optional_multi_packet_codec, EncodeError, OptionalMulti, OptionalMultiPacketDecoder,
@@ -393,11 +393,16 @@ pub struct PooledRedisClientFactory {
 
 impl PooledRedisClientFactory {
     pub fn new(capacity: usize, timeout: Duration) -> Self {
+        Self::new_with_password(capacity, timeout, None)
+    }
+
+    // The pooled connections stay authenticated so AUTH is only sent on connecting.
+    pub fn new_with_password(capacity: usize, timeout: Duration, password: Option<String>) -> Self {
         Self {
             capacity,
             pool_map: DashMap::new(),
             timeout,
-            simple_factory: SimpleRedisClientFactory::new(timeout),
+            simple_factory: SimpleRedisClientFactory::new_with_password(timeout, password),
         }
     }
 
@@ -523,11 +528,17 @@ impl RedisClient for SimpleRedisClient {
 
 pub struct SimpleRedisClientFactory {
     timeout: Duration,
+    // Sent by AUTH right after connecting.
+    password: Option<String>,
 }
 
 impl SimpleRedisClientFactory {
     pub fn new(timeout: Duration) -> Self {
-        Self { timeout }
+        Self::new_with_password(timeout, None)
+    }
+
+    pub fn new_with_password(timeout: Duration, password: Option<String>) -> Self {
+        Self { timeout, password }
     }
 
     async fn create_conn(&self, address: String) -> Result<TcpStream, RedisClientError> {
@@ -560,8 +571,28 @@ impl SimpleRedisClientFactory {
             Ok(Ok(conn)) => {
                 let (encoder, decoder) = new_optional_multi_packet_codec();
                 let frame = ClientCodec::new(encoder, decoder).framed(conn);
-                Ok(SimpleRedisClient::new(frame, timeout))
+                let mut client = SimpleRedisClient::new(frame, timeout);
+                self.auth(&mut client).await?;
+                Ok(client)
+            }
+        }
+    }
+
+    async fn auth(&self, client: &mut SimpleRedisClient) -> Result<(), RedisClientError> {
+        let password = match self.password.as_ref() {
+            Some(password) => password,
+            None => return Ok(()),
+        };
+        let cmd = vec![b"AUTH".to_vec(), password.as_bytes().to_vec()];
+        match client.execute_single(cmd).await? {
+            Resp::Error(err) => {
+                error!(
+                    "failed to authenticate the connection: {}",
+                    String::from_utf8_lossy(&err)
+                );
+                Err(RedisClientError::InitError)
             }
+            _ => Ok(()),
         }
     }
 }
@@ -1,3 +1,4 @@
+mod builder;
 mod client;
 mod codec;
 mod decoder;
@@ -7,6 +8,7 @@ mod packet;
 mod resp;
 mod stateless;
 
+pub use self::builder::CmdBuilder;
 pub use self::client::{
     DummyRedisClientFactory, MockRedisClient, MockRedisClientFactory, Pool, PooledRedisClient,
     PooledRedisClientFactory, PreCheckRedisClientFactory, RedisClient, RedisClientError,
@@ -17,13 +19,14 @@ pub use self::decoder::DecodeError;
 pub use self::encoder::{encode_resp, resp_to_buf, EncodeError};
 pub use self::fp::{RFunctor, VFunctor};
 pub use self::packet::{
-    new_optional_multi_packet_codec, new_simple_packet_codec, DecodedPacket, EncodedPacket,
-    FromResp, MonoPacket, OptionalMulti, OptionalMultiPacketDecoder, OptionalMultiPacketEncoder,
-    Packet, PacketDecoder, PacketEncoder, PacketSizeHint, RespPacket, SimplePacketDecoder,
-    SimplePacketEncoder,
+    new_limited_packet_codec, new_optional_multi_packet_codec, new_simple_packet_codec,
+    DecodedPacket, EncodedPacket, FromResp, LimitedRespPacketDecoder, MonoPacket, OptionalMulti,
+    OptionalMultiPacketDecoder, OptionalMultiPacketEncoder, Packet, PacketDecoder, PacketEncoder,
+    PacketSizeHint, RespPacket, SimplePacketDecoder, SimplePacketEncoder,
 };
 pub use self::resp::{
     Array, ArrayBytes, ArrayIndex, ArraySlice, ArrayVec, BinSafeStr, BulkStr, BulkStrBytes,
     BulkStrIndex, BulkStrSlice, BulkStrVec, IndexedResp, Resp, RespBytes, RespIndex, RespSlice,
     RespVec,
 };
+pub use self::stateless::ProtocolLimits;
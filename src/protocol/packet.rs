@@ -1,8 +1,11 @@
 use super::decoder::DecodeError;
 use super::encoder::{command_to_buf, encode_resp};
 use super::fp::{RFunctor, VFunctor};
-use super::resp::{BinSafeStr, IndexedResp, Resp, RespSlice, RespVec};
-use super::stateless::{parse_indexed_resp, ParseError};
+use super::resp::{Array, BinSafeStr, BulkStr, IndexedResp, Resp, RespSlice, RespVec};
+use super::stateless::{
+    parse_indexed_resp, parse_indexed_resp_with_limits, parse_inline_cmd_with_limits, ParseError,
+    ProtocolLimits,
+};
 use crate::common::utils::{
     array_append_front, change_bulk_array_element, change_bulk_str, get_command_element,
     get_command_len, left_trim_array, ThreadSafe,
@@ -145,6 +148,15 @@ impl RespPacket {
         str::from_utf8(element).ok()
     }
 
+    // Cheaper than `to_resp_slice` when only the error reply matters.
+    pub fn get_error(&self) -> Option<&[u8]> {
+        match self {
+            Self::Indexed(indexed_resp) => indexed_resp.get_error(),
+            Self::Data(Resp::Error(err)) => Some(err.as_slice()),
+            Self::Data(_) => None,
+        }
+    }
+
     pub fn to_resp_slice(&self) -> RespSlice {
         match self {
             Self::Indexed(indexed_resp) => indexed_resp.to_resp_slice(),
@@ -300,17 +312,25 @@ impl DecodedPacket for IndexedResp {
     where
         Self: Sized,
     {
-        match parse_indexed_resp(buf) {
-            Ok(r) => Ok(Some(r)),
-            Err(e) => match e {
-                ParseError::NotEnoughData => Ok(None),
-                ParseError::InvalidProtocol => Err(DecodeError::InvalidProtocol),
-                ParseError::UnexpectedErr => {
-                    error!("Unexpected error");
-                    Err(DecodeError::InvalidProtocol)
-                }
-            },
-        }
+        to_decode_result(parse_indexed_resp(buf))
+    }
+}
+
+fn to_decode_result<T>(res: Result<T, ParseError>) -> Result<Option<T>, DecodeError> {
+    match res {
+        Ok(r) => Ok(Some(r)),
+        Err(e) => match e {
+            ParseError::NotEnoughData => Ok(None),
+            ParseError::InvalidProtocol => Err(DecodeError::InvalidProtocol),
+            ParseError::UnexpectedErr => {
+                error!("Unexpected error");
+                Err(DecodeError::InvalidProtocol)
+            }
+            ParseError::ExceedLimit => {
+                warn!("packet exceeds the protocol limits");
+                Err(DecodeError::InvalidProtocol)
+            }
+        },
     }
 }
 
@@ -419,6 +439,66 @@ impl<T: DecodedPacket<Hint = ()>> PacketDecoder for SimplePacketDecoder<T> {
     }
 }
 
+// Used for the untrusted input from the clients.
+pub struct LimitedRespPacketDecoder {
+    limits: ProtocolLimits,
+}
+
+impl LimitedRespPacketDecoder {
+    pub fn new(limits: ProtocolLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl PacketDecoder for LimitedRespPacketDecoder {
+    type Pkt = Box<RespPacket>;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Pkt>, DecodeError>
+    where
+        Self: Sized,
+    {
+        loop {
+            match buf.first() {
+                None => return Ok(None),
+                Some(b'*') => {
+                    let res = parse_indexed_resp_with_limits(buf, &self.limits);
+                    return Ok(to_decode_result(res)?.map(|r| Box::new(RespPacket::Indexed(r))));
+                }
+                // Inline commands are mostly used for debugging with tools like telnet.
+                Some(_) => {
+                    let res = parse_inline_cmd_with_limits(buf, &self.limits);
+                    let args = match to_decode_result(res)? {
+                        Some(args) => args,
+                        None => return Ok(None),
+                    };
+                    // Just skip the empty lines like Redis.
+                    if args.is_empty() {
+                        continue;
+                    }
+                    let elements = args
+                        .into_iter()
+                        .map(|arg| Resp::Bulk(BulkStr::Str(arg)))
+                        .collect();
+                    let resp = Resp::Arr(Array::Arr(elements));
+                    return Ok(Some(Box::new(RespPacket::Data(resp))));
+                }
+            }
+        }
+    }
+}
+
+pub fn new_limited_packet_codec(
+    limits: ProtocolLimits,
+) -> (
+    SimplePacketEncoder<Box<RespPacket>>,
+    LimitedRespPacketDecoder,
+) {
+    (
+        SimplePacketEncoder::default(),
+        LimitedRespPacketDecoder::new(limits),
+    )
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum OptionalMulti<T> {
     Single(T),
@@ -748,4 +828,47 @@ mod tests {
         };
         assert_eq!(response.len(), 0);
     }
+
+    #[test]
+    fn test_limited_packet_decoder() {
+        let limits = ProtocolLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 4,
+            max_inline_len: 64,
+        };
+        let (_, mut decoder) = new_limited_packet_codec(limits);
+
+        let mut buf = BytesMut::from(&b"*1\r\n$4\r\nPING\r\n"[..]);
+        let pkt = decoder.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(pkt.get_command_name(), Some("PING"));
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from(&b"*1\r\n$1073741824\r\n"[..]);
+        let res = decoder.decode(&mut buf);
+        assert!(matches!(res, Err(DecodeError::InvalidProtocol)));
+    }
+
+    #[test]
+    fn test_limited_packet_decoder_inline_cmd() {
+        let (_, mut decoder) = new_limited_packet_codec(ProtocolLimits::default());
+
+        let mut buf = BytesMut::from(&b"\r\n\r\nset  'a b'  \"c\"\r\n*1\r\n$4\r\nPING\r\n"[..]);
+        let pkt = decoder.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(pkt.get_array_len(), Some(3));
+        assert_eq!(pkt.get_command_name(), Some("set"));
+        assert_eq!(pkt.get_array_element(1), Some(&b"a b"[..]));
+        assert_eq!(pkt.get_array_element(2), Some(&b"c"[..]));
+
+        let pkt = decoder.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(pkt.get_command_name(), Some("PING"));
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from(&b"\r\nPIN"[..]);
+        assert!(decoder.decode(&mut buf).unwrap().is_none());
+        assert_eq!(buf.as_ref(), b"PIN");
+
+        let mut buf = BytesMut::from(&b"get \"a\r\n"[..]);
+        let res = decoder.decode(&mut buf);
+        assert!(matches!(res, Err(DecodeError::InvalidProtocol)));
+    }
 }
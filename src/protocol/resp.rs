@@ -60,6 +60,13 @@ impl IndexedResp {
         }
     }
 
+    pub fn get_error(&self) -> Option<&[u8]> {
+        match self.resp {
+            RespIndex::Error(ref s) => self.data.get(s.to_range()),
+            _ => None,
+        }
+    }
+
     pub fn to_resp_slice(&self) -> RespSlice {
         self.resp.map_to_slice(&self.data)
     }
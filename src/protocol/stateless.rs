@@ -1,8 +1,11 @@
 use super::decoder::LF;
-use super::resp::{AdvanceIndex, ArrayIndex, BulkStrIndex, DataIndex, IndexedResp, RespIndex};
+use super::resp::{
+    AdvanceIndex, ArrayIndex, BinSafeStr, BulkStrIndex, DataIndex, IndexedResp, RespIndex,
+};
 use btoi::btoi;
 use bytes::BytesMut;
 use memchr::memchr;
+use std::cmp::min;
 use std::error::Error;
 use std::fmt;
 
@@ -11,8 +14,42 @@ pub enum ParseError {
     InvalidProtocol,
     NotEnoughData,
     UnexpectedErr,
+    ExceedLimit,
 }
 
+// Limits used to reject malformed or malicious input
+// before it makes us allocate or buffer too much memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolLimits {
+    pub max_bulk_len: usize,
+    pub max_multibulk_len: usize,
+    pub max_inline_len: usize,
+}
+
+impl Default for ProtocolLimits {
+    fn default() -> Self {
+        // Same as the defaults of Redis.
+        Self {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_inline_len: 64 * 1024,
+        }
+    }
+}
+
+impl ProtocolLimits {
+    // Used for the trusted data like the replies from Redis.
+    pub const fn unlimited() -> Self {
+        Self {
+            max_bulk_len: usize::MAX,
+            max_multibulk_len: usize::MAX,
+            max_inline_len: usize::MAX,
+        }
+    }
+}
+
+const UNLIMITED: ProtocolLimits = ProtocolLimits::unlimited();
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -30,12 +67,19 @@ impl Error for ParseError {
 }
 
 pub fn parse_indexed_resp(buf: &mut BytesMut) -> Result<IndexedResp, ParseError> {
-    let (resp, consumed) = parse_resp(buf)?;
+    parse_indexed_resp_with_limits(buf, &UNLIMITED)
+}
+
+pub fn parse_indexed_resp_with_limits(
+    buf: &mut BytesMut,
+    limits: &ProtocolLimits,
+) -> Result<IndexedResp, ParseError> {
+    let (resp, consumed) = parse_resp(buf, limits)?;
     let data = buf.split_to(consumed).freeze();
     Ok(IndexedResp::new(resp, data))
 }
 
-pub fn parse_resp(buf: &[u8]) -> Result<(RespIndex, usize), ParseError> {
+pub fn parse_resp(buf: &[u8], limits: &ProtocolLimits) -> Result<(RespIndex, usize), ParseError> {
     if buf.is_empty() {
         return Err(ParseError::NotEnoughData);
     }
@@ -45,27 +89,27 @@ pub fn parse_resp(buf: &[u8]) -> Result<(RespIndex, usize), ParseError> {
 
     match prefix {
         b'$' => {
-            let (mut v, consumed) = parse_bulk_str(next_buf)?;
+            let (mut v, consumed) = parse_bulk_str(next_buf, limits)?;
             v.advance(1);
             Ok((RespIndex::Bulk(v), 1 + consumed))
         }
         b'+' => {
-            let (mut v, consumed) = parse_line(next_buf)?;
+            let (mut v, consumed) = parse_line(next_buf, limits)?;
             v.advance(1);
             Ok((RespIndex::Simple(v), 1 + consumed))
         }
         b':' => {
-            let (mut v, consumed) = parse_line(next_buf)?;
+            let (mut v, consumed) = parse_line(next_buf, limits)?;
             v.advance(1);
             Ok((RespIndex::Integer(v), 1 + consumed))
         }
         b'-' => {
-            let (mut v, consumed) = parse_line(next_buf)?;
+            let (mut v, consumed) = parse_line(next_buf, limits)?;
             v.advance(1);
             Ok((RespIndex::Error(v), 1 + consumed))
         }
         b'*' => {
-            let (mut v, consumed) = parse_array(next_buf)?;
+            let (mut v, consumed) = parse_array(next_buf, limits)?;
             v.advance(1);
             Ok((RespIndex::Arr(v), 1 + consumed))
         }
@@ -76,18 +120,22 @@ pub fn parse_resp(buf: &[u8]) -> Result<(RespIndex, usize), ParseError> {
     }
 }
 
-fn parse_array(buf: &[u8]) -> Result<(ArrayIndex, usize), ParseError> {
-    let (len, mut consumed) = parse_len(buf)?;
+fn parse_array(buf: &[u8], limits: &ProtocolLimits) -> Result<(ArrayIndex, usize), ParseError> {
+    let (len, mut consumed) = parse_len(buf, limits)?;
     if len < 0 {
         return Ok((ArrayIndex::Nil, consumed));
     }
 
     let array_size = len as usize;
-    let mut array = Vec::with_capacity(array_size);
+    if array_size > limits.max_multibulk_len {
+        return Err(ParseError::ExceedLimit);
+    }
+    // Don't trust the length header before we get the data.
+    let mut array = Vec::with_capacity(min(array_size, buf.len()));
 
     for _ in 0..array_size {
         let next_buf = buf.get(consumed..).ok_or(ParseError::InvalidProtocol)?;
-        let (mut v, element_consumed) = parse_resp(next_buf)?;
+        let (mut v, element_consumed) = parse_resp(next_buf, limits)?;
         v.advance(consumed);
         consumed += element_consumed;
         array.push(v);
@@ -96,13 +144,19 @@ fn parse_array(buf: &[u8]) -> Result<(ArrayIndex, usize), ParseError> {
     Ok((ArrayIndex::Arr(array), consumed))
 }
 
-fn parse_bulk_str(buf: &[u8]) -> Result<(BulkStrIndex, usize), ParseError> {
-    let (len, consumed) = parse_len(buf)?;
+fn parse_bulk_str(
+    buf: &[u8],
+    limits: &ProtocolLimits,
+) -> Result<(BulkStrIndex, usize), ParseError> {
+    let (len, consumed) = parse_len(buf, limits)?;
     if len < 0 {
         return Ok((BulkStrIndex::Nil, consumed));
     }
 
     let content_size = len as usize;
+    if content_size > limits.max_bulk_len {
+        return Err(ParseError::ExceedLimit);
+    }
     if buf.len() < consumed + content_size + 2 {
         return Err(ParseError::NotEnoughData);
     }
@@ -111,8 +165,8 @@ fn parse_bulk_str(buf: &[u8]) -> Result<(BulkStrIndex, usize), ParseError> {
     Ok((BulkStrIndex::Str(s), consumed + content_size + 2))
 }
 
-fn parse_len(buf: &[u8]) -> Result<(i64, usize), ParseError> {
-    let (data_index, consumed) = parse_line(buf)?;
+fn parse_len(buf: &[u8], limits: &ProtocolLimits) -> Result<(i64, usize), ParseError> {
+    let (data_index, consumed) = parse_line(buf, limits)?;
     let next_buf = buf
         .get(data_index.to_range())
         .ok_or(ParseError::UnexpectedErr)?;
@@ -121,8 +175,15 @@ fn parse_len(buf: &[u8]) -> Result<(i64, usize), ParseError> {
     Ok((len, consumed))
 }
 
-fn parse_line(buf: &[u8]) -> Result<(DataIndex, usize), ParseError> {
-    let lf_index = memchr(LF, buf).ok_or(ParseError::NotEnoughData)?;
+fn parse_line(buf: &[u8], limits: &ProtocolLimits) -> Result<(DataIndex, usize), ParseError> {
+    let lf_index = match memchr(LF, buf) {
+        Some(lf_index) => lf_index,
+        None if buf.len() > limits.max_inline_len => return Err(ParseError::ExceedLimit),
+        None => return Err(ParseError::NotEnoughData),
+    };
+    if lf_index > limits.max_inline_len {
+        return Err(ParseError::ExceedLimit);
+    }
     if lf_index == 0 {
         return Err(ParseError::InvalidProtocol);
     }
@@ -133,6 +194,133 @@ fn parse_line(buf: &[u8]) -> Result<(DataIndex, usize), ParseError> {
     Ok((line, lf_index + 1))
 }
 
+// Inline commands are the commands without the RESP multibulk format, e.g. `SET a "b c"\r\n`.
+// An empty line results in empty arguments.
+pub fn parse_inline_cmd_with_limits(
+    buf: &mut BytesMut,
+    limits: &ProtocolLimits,
+) -> Result<Vec<BinSafeStr>, ParseError> {
+    let lf_index = match memchr(LF, buf) {
+        Some(lf_index) => lf_index,
+        None if buf.len() > limits.max_inline_len => return Err(ParseError::ExceedLimit),
+        None => return Err(ParseError::NotEnoughData),
+    };
+    if lf_index > limits.max_inline_len {
+        return Err(ParseError::ExceedLimit);
+    }
+
+    let line = buf.split_to(lf_index + 1);
+    // The CR is optional.
+    let line = match line.get(..lf_index) {
+        Some([line @ .., b'\r']) => line,
+        Some(line) => line,
+        None => return Err(ParseError::UnexpectedErr),
+    };
+    let args = split_inline_args(line)?;
+    if args.len() > limits.max_multibulk_len {
+        return Err(ParseError::ExceedLimit);
+    }
+    Ok(args)
+}
+
+enum InlineArgState {
+    Unquoted,
+    DoubleQuoted,
+    SingleQuoted,
+}
+
+// Follow the behavior of `sdssplitargs` in Redis.
+fn split_inline_args(line: &[u8]) -> Result<Vec<BinSafeStr>, ParseError> {
+    let mut args = vec![];
+    let mut i = 0;
+    loop {
+        while matches!(line.get(i), Some(c) if c.is_ascii_whitespace()) {
+            i += 1;
+        }
+        if i >= line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = vec![];
+        let mut state = InlineArgState::Unquoted;
+        loop {
+            let c = line.get(i).cloned();
+            match state {
+                InlineArgState::Unquoted => match c {
+                    None => break,
+                    Some(c) if c.is_ascii_whitespace() => break,
+                    Some(b'"') => state = InlineArgState::DoubleQuoted,
+                    Some(b'\'') => state = InlineArgState::SingleQuoted,
+                    Some(c) => arg.push(c),
+                },
+                InlineArgState::DoubleQuoted => match c {
+                    // Unbalanced quotes
+                    None => return Err(ParseError::InvalidProtocol),
+                    Some(b'\\') => {
+                        if let Some(b) = parse_hex_escape(line.get(i + 1..i + 4)) {
+                            arg.push(b);
+                            i += 3;
+                        } else if let Some(&next) = line.get(i + 1) {
+                            let b = match next {
+                                b'n' => b'\n',
+                                b'r' => b'\r',
+                                b't' => b'\t',
+                                b'b' => 0x08,
+                                b'a' => 0x07,
+                                others => others,
+                            };
+                            arg.push(b);
+                            i += 1;
+                        } else {
+                            arg.push(b'\\');
+                        }
+                    }
+                    Some(b'"') => {
+                        i += 1;
+                        check_closing_quote(line.get(i))?;
+                        break;
+                    }
+                    Some(c) => arg.push(c),
+                },
+                InlineArgState::SingleQuoted => match c {
+                    None => return Err(ParseError::InvalidProtocol),
+                    Some(b'\\') if line.get(i + 1) == Some(&b'\'') => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    Some(b'\'') => {
+                        i += 1;
+                        check_closing_quote(line.get(i))?;
+                        break;
+                    }
+                    Some(c) => arg.push(c),
+                },
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+// The closing quote must be followed by a space or nothing at all.
+fn check_closing_quote(next: Option<&u8>) -> Result<(), ParseError> {
+    match next {
+        Some(c) if !c.is_ascii_whitespace() => Err(ParseError::InvalidProtocol),
+        _ => Ok(()),
+    }
+}
+
+fn parse_hex_escape(s: Option<&[u8]>) -> Option<u8> {
+    match s? {
+        [b'x', h, l] => {
+            let h = (*h as char).to_digit(16)?;
+            let l = (*l as char).to_digit(16)?;
+            Some((h * 16 + l) as u8)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,33 +328,33 @@ mod tests {
 
     #[test]
     fn test_parse_len_bytes() {
-        let r = parse_len(b"233\r\n");
+        let r = parse_len(b"233\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (len, s) = r.unwrap();
         assert_eq!(s, 5);
         assert_eq!(len, 233);
 
-        let r = parse_len(b"-233\r\n");
+        let r = parse_len(b"-233\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (len, s) = r.unwrap();
         assert_eq!(s, 6);
         assert_eq!(len, -233);
 
-        let r = parse_len(b"2a3\r\n");
+        let r = parse_len(b"2a3\r\n", &UNLIMITED);
         assert!(r.is_err());
     }
 
     #[test]
     fn test_parse_line_bytes() {
         let data = b"233\r\n";
-        let r = parse_line(data);
+        let r = parse_line(data, &UNLIMITED);
         assert!(r.is_ok());
         let (b, l) = r.unwrap();
         assert_eq!(l, 5);
         assert_eq!(&data[b.to_range()], b"233");
 
         let data = b"\r\n";
-        let r = parse_line(data);
+        let r = parse_line(data, &UNLIMITED);
         assert!(r.is_ok());
         let (b, l) = r.unwrap();
         assert_eq!(l, 2);
@@ -176,7 +364,7 @@ mod tests {
     #[test]
     fn test_parse_bulk_str_bytes() {
         let data = b"2\r\nab\r\n";
-        let r = parse_bulk_str(data);
+        let r = parse_bulk_str(data, &UNLIMITED);
         assert!(r.is_ok());
         let (content, s) = r.unwrap();
         assert_eq!(s, 7);
@@ -185,22 +373,22 @@ mod tests {
             content.try_to_range().map(|r| &data[r])
         );
 
-        let r = parse_bulk_str(b"-1\r\n");
+        let r = parse_bulk_str(b"-1\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (content, s) = r.unwrap();
         assert_eq!(s, 4);
         assert_eq!(BulkStrIndex::Nil, content);
 
-        let r = parse_bulk_str(b"2a3\r\nab\r\n");
+        let r = parse_bulk_str(b"2a3\r\nab\r\n", &UNLIMITED);
         assert!(r.is_err());
 
-        let r = parse_bulk_str(b"0\r\n\r\n");
+        let r = parse_bulk_str(b"0\r\n\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (content, s) = r.unwrap();
         assert_eq!(s, 5);
         assert_eq!(Some(b"".as_ref()), content.try_to_range().map(|r| &data[r]));
 
-        let r = parse_bulk_str(b"1\r\na\r\n");
+        let r = parse_bulk_str(b"1\r\na\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (content, s) = r.unwrap();
         assert_eq!(s, 6);
@@ -209,7 +397,7 @@ mod tests {
             content.try_to_range().map(|r| &data[r])
         );
 
-        let r = parse_bulk_str(b"2\r\na\r\n");
+        let r = parse_bulk_str(b"2\r\na\r\n", &UNLIMITED);
         assert!(r.is_err());
 
         // TODO: Support this check
@@ -220,7 +408,7 @@ mod tests {
     #[test]
     fn test_parse_array_bytes() {
         let data = b"2\r\n$1\r\na\r\n$2\r\nbc\r\n";
-        let r = parse_array(data);
+        let r = parse_array(data, &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 18);
@@ -233,19 +421,19 @@ mod tests {
             arr
         );
 
-        let r = parse_array(b"-1\r\n");
+        let r = parse_array(b"-1\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 4);
         assert_eq!(ArrayIndex::Nil, a);
 
-        let r = parse_array(b"0\r\n");
+        let r = parse_array(b"0\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 3);
         assert_eq!(ArrayIndex::Arr(vec![]), a);
 
-        let r = parse_array(b"1\r\n$2\r\na\r\n");
+        let r = parse_array(b"1\r\n$2\r\na\r\n", &UNLIMITED);
         assert!(r.is_err());
 
         // TODO: Support this check
@@ -255,41 +443,41 @@ mod tests {
 
     #[test]
     fn test_parse_resp_bytes() {
-        let r = parse_resp(b"*-1\r\n");
+        let r = parse_resp(b"*-1\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 5);
         assert_eq!(RespIndex::Arr(ArrayIndex::Nil), a);
 
-        let r = parse_resp(b"*0\r\n");
+        let r = parse_resp(b"*0\r\n", &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 4);
         assert_eq!(RespIndex::Arr(ArrayIndex::Arr(vec![])), a);
 
         let data = b"-abc\r\n";
-        let r = parse_resp(data);
+        let r = parse_resp(data, &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 6);
         assert_eq!(RespSlice::Error(b"abc"), a.map_to_slice(data));
 
         let data = b":233\r\n";
-        let r = parse_resp(data);
+        let r = parse_resp(data, &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 6);
         assert_eq!(RespSlice::Integer(b"233"), a.map_to_slice(data));
 
         let data = b"+233\r\n";
-        let r = parse_resp(data);
+        let r = parse_resp(data, &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 6);
         assert_eq!(RespSlice::Simple(b"233"), a.map_to_slice(data));
 
         let data = b"$3\r\nfoo\r\n";
-        let r = parse_resp(data);
+        let r = parse_resp(data, &UNLIMITED);
         assert!(r.is_ok());
         let (a, s) = r.unwrap();
         assert_eq!(s, 9);
@@ -298,4 +486,131 @@ mod tests {
             a.map_to_slice(data),
         );
     }
+
+    #[test]
+    fn test_exceed_limits() {
+        let limits = ProtocolLimits {
+            max_bulk_len: 16,
+            max_multibulk_len: 4,
+            max_inline_len: 8,
+        };
+
+        let r = parse_resp(b"$17\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"$9999999999999\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"$16\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::NotEnoughData)));
+
+        let r = parse_resp(b"*5\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"*4\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::NotEnoughData)));
+        // Nested arrays should also be checked.
+        let r = parse_resp(b"*1\r\n*5\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"*1\r\n$17\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+
+        // Line without LF
+        let r = parse_resp(b"+123456789", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"+1234567", &limits);
+        assert!(matches!(r, Err(ParseError::NotEnoughData)));
+        let r = parse_resp(b"+123456789\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"+12345\r\n", &limits);
+        assert!(r.is_ok());
+    }
+
+    fn parse_inline(data: &[u8]) -> Result<Vec<BinSafeStr>, ParseError> {
+        let mut buf = BytesMut::from(data);
+        let args = parse_inline_cmd_with_limits(&mut buf, &UNLIMITED)?;
+        assert!(buf.is_empty());
+        Ok(args)
+    }
+
+    #[test]
+    fn test_parse_inline_cmd() {
+        let args = parse_inline(b"PING\r\n").unwrap();
+        assert_eq!(args, vec![b"PING".to_vec()]);
+        let args = parse_inline(b"get a\n").unwrap();
+        assert_eq!(args, vec![b"get".to_vec(), b"a".to_vec()]);
+        let args = parse_inline(b"\r\n").unwrap();
+        assert!(args.is_empty());
+
+        let mut buf = BytesMut::from(&b"get a"[..]);
+        let r = parse_inline_cmd_with_limits(&mut buf, &UNLIMITED);
+        assert!(matches!(r, Err(ParseError::NotEnoughData)));
+        assert_eq!(buf.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_inline_cmd_with_extra_whitespace() {
+        let args = parse_inline(b"  set \t a   b  \r\n").unwrap();
+        assert_eq!(args, vec![b"set".to_vec(), b"a".to_vec(), b"b".to_vec()]);
+        let args = parse_inline(b"   \r\n").unwrap();
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_parse_inline_cmd_with_quotes() {
+        let args = parse_inline(b"set \"a b\" 'c d'\r\n").unwrap();
+        assert_eq!(
+            args,
+            vec![b"set".to_vec(), b"a b".to_vec(), b"c d".to_vec()]
+        );
+        let args = parse_inline(b"set k \"\\x41\\n\\\"\"\r\n").unwrap();
+        assert_eq!(
+            args,
+            vec![b"set".to_vec(), b"k".to_vec(), b"A\n\"".to_vec()]
+        );
+        let args = parse_inline(b"set k 'it\\'s'\r\n").unwrap();
+        assert_eq!(args, vec![b"set".to_vec(), b"k".to_vec(), b"it's".to_vec()]);
+        let args = parse_inline(b"set k \"\"\r\n").unwrap();
+        assert_eq!(args, vec![b"set".to_vec(), b"k".to_vec(), b"".to_vec()]);
+        let args = parse_inline(b"set k a\"b\"\r\n").unwrap();
+        assert_eq!(args, vec![b"set".to_vec(), b"k".to_vec(), b"ab".to_vec()]);
+
+        assert!(matches!(
+            parse_inline(b"set \"a b\r\n"),
+            Err(ParseError::InvalidProtocol)
+        ));
+        assert!(matches!(
+            parse_inline(b"set 'a b\r\n"),
+            Err(ParseError::InvalidProtocol)
+        ));
+        assert!(matches!(
+            parse_inline(b"set \"a\"b\r\n"),
+            Err(ParseError::InvalidProtocol)
+        ));
+    }
+
+    #[test]
+    fn test_parse_inline_cmd_exceed_limits() {
+        let limits = ProtocolLimits {
+            max_bulk_len: 16,
+            max_multibulk_len: 2,
+            max_inline_len: 8,
+        };
+        let mut buf = BytesMut::from(&b"get 123456789"[..]);
+        let r = parse_inline_cmd_with_limits(&mut buf, &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let mut buf = BytesMut::from(&b"a b c\r\n"[..]);
+        let r = parse_inline_cmd_with_limits(&mut buf, &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let limits = ProtocolLimits::default();
+        let r = parse_resp(b"$536870913\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+        let r = parse_resp(b"*1048577\r\n", &limits);
+        assert!(matches!(r, Err(ParseError::ExceedLimit)));
+
+        // Should not be limited by default.
+        let r = parse_resp(b"$536870913\r\n", &UNLIMITED);
+        assert!(matches!(r, Err(ParseError::NotEnoughData)));
+    }
 }
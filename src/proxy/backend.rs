@@ -2,7 +2,8 @@ use super::command::{CommandError, CommandResult};
 use super::service::ServerProxyConfig;
 use super::slowlog::TaskEvent;
 use crate::common::batch::{BatchState, BatchStats, BatchStrategy};
-use crate::common::utils::{resolve_first_address, RetryError, ThreadSafe};
+use crate::common::response;
+use crate::common::utils::{resolve_first_address, RetryError, SocketBufferSizes, ThreadSafe};
 use crate::protocol::{
     new_simple_packet_codec, DecodeError, EncodeError, EncodedPacket, FromResp, MonoPacket,
     OptionalMulti, Packet, PacketSizeHint, Resp, RespCodec, RespVec,
@@ -12,7 +13,7 @@ use futures::channel::mpsc;
 use futures::task::{Context, Poll};
 use futures::{future, select, Future, FutureExt, Sink, SinkExt, Stream, StreamExt, TryStreamExt};
 use std::boxed::Box;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::error::Error;
 use std::fmt;
 use std::io;
@@ -21,10 +22,9 @@ use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::pin::Pin;
 use std::result::Result;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
-use tokio::net::TcpStream;
 use tokio_util::codec::Decoder;
 
 pub type BackendResult<T> = Result<T, BackendError>;
@@ -63,6 +63,11 @@ pub trait CmdTask: ThreadSafe {
         Self: Sized;
 
     fn log_event(&mut self, event: TaskEvent);
+
+    // Whether the command follows an ASKING command in the same session.
+    fn is_asking(&self) -> bool {
+        false
+    }
 }
 
 pub trait IntoTask<T: CmdTask>: CmdTask {
@@ -220,6 +225,12 @@ impl<T: CmdTask> CmdTask for ReqTask<T> {
             }
         }
     }
+    fn is_asking(&self) -> bool {
+        match self {
+            Self::Simple(t) => t.is_asking(),
+            Self::Multi(v) => v.iter().any(|t| t.is_asking()),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -243,6 +254,7 @@ impl<H: CmdTaskResultHandler> BackendNode<H> {
         config: Arc<ServerProxyConfig>,
         conn_factory: Arc<CF>,
         batch_stats: Arc<BatchStats>,
+        conn_states: Arc<BackendConnStates>,
     ) -> (
         BackendNode<H>,
         impl Future<Output = Result<(), BackendError>> + Send,
@@ -254,6 +266,7 @@ impl<H: CmdTaskResultHandler> BackendNode<H> {
     {
         let (tx, rx) = mpsc::unbounded();
         let conn_failed = Arc::new(AtomicBool::new(false));
+        let conn_state = conn_states.register(address.clone());
         let handle_backend_fut = handle_backend(
             handler,
             rx,
@@ -261,6 +274,7 @@ impl<H: CmdTaskResultHandler> BackendNode<H> {
             address,
             conn_factory,
             batch_stats,
+            conn_state,
             config,
         );
         (Self { tx, conn_failed }, handle_backend_fut)
@@ -280,6 +294,138 @@ impl<H: CmdTaskResultHandler> BackendNode<H> {
     pub fn is_closed(&self) -> bool {
         self.tx.is_closed()
     }
+
+    pub fn is_failing(&self) -> bool {
+        self.conn_failed.load(Ordering::SeqCst) || self.is_closed()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackendConnStatus {
+    Connecting,
+    Connected,
+    Failed,
+}
+
+impl fmt::Display for BackendConnStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Connecting => write!(f, "connecting"),
+            Self::Connected => write!(f, "connected"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+// The live state of a single backend connection.
+pub struct BackendConnState {
+    address: String,
+    status: parking_lot::Mutex<(BackendConnStatus, Option<String>)>,
+    in_flight: AtomicUsize,
+}
+
+impl BackendConnState {
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            status: parking_lot::Mutex::new((BackendConnStatus::Connecting, None)),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn get_address(&self) -> &str {
+        self.address.as_str()
+    }
+
+    pub fn get_status(&self) -> BackendConnStatus {
+        self.status.lock().0
+    }
+
+    pub fn get_last_error(&self) -> Option<String> {
+        self.status.lock().1.clone()
+    }
+
+    pub fn get_in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    fn set_status(&self, status: BackendConnStatus) {
+        self.status.lock().0 = status;
+    }
+
+    fn set_error(&self, status: BackendConnStatus, err: &BackendError) {
+        *self.status.lock() = (status, Some(err.to_string()));
+    }
+
+    fn set_in_flight(&self, in_flight: usize) {
+        self.in_flight.store(in_flight, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendSummary {
+    pub address: String,
+    pub status: BackendConnStatus,
+    pub conn_num: usize,
+    pub in_flight: usize,
+    pub last_error: Option<String>,
+}
+
+// Keeps track of all the living backend connections.
+// The states will be removed after the connections are dropped.
+#[derive(Default)]
+pub struct BackendConnStates {
+    states: parking_lot::Mutex<Vec<Weak<BackendConnState>>>,
+}
+
+impl BackendConnStates {
+    pub fn register(&self, address: String) -> Arc<BackendConnState> {
+        let state = Arc::new(BackendConnState::new(address));
+        let mut states = self.states.lock();
+        states.retain(|s| s.strong_count() > 0);
+        states.push(Arc::downgrade(&state));
+        state
+    }
+
+    // Merge the states of the connections to the same address.
+    pub fn get_summaries(&self) -> Vec<BackendSummary> {
+        let states: Vec<Arc<BackendConnState>> = self
+            .states
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+
+        let mut summaries: BTreeMap<String, BackendSummary> = BTreeMap::new();
+        for state in states.iter() {
+            let status = state.get_status();
+            let last_error = state.get_last_error();
+            let summary = summaries
+                .entry(state.get_address().to_string())
+                .or_insert_with(|| BackendSummary {
+                    address: state.get_address().to_string(),
+                    status,
+                    conn_num: 0,
+                    in_flight: 0,
+                    last_error: None,
+                });
+            summary.conn_num += 1;
+            summary.in_flight += state.get_in_flight();
+            summary.status = match (summary.status, status) {
+                (BackendConnStatus::Failed, _) | (_, BackendConnStatus::Failed) => {
+                    BackendConnStatus::Failed
+                }
+                (BackendConnStatus::Connecting, _) | (_, BackendConnStatus::Connecting) => {
+                    BackendConnStatus::Connecting
+                }
+                _ => BackendConnStatus::Connected,
+            };
+            if last_error.is_some() {
+                summary.last_error = last_error;
+            }
+        }
+        summaries.into_values().collect()
+    }
 }
 
 pub type ConnSink<T> = Pin<Box<dyn Sink<T, Error = BackendError> + Send>>;
@@ -295,11 +441,23 @@ pub trait ConnFactory: ThreadSafe {
     ) -> Pin<Box<dyn Future<Output = CreateConnResult<Self::Pkt>> + Send>>;
 }
 
-pub struct DefaultConnFactory<P>(PhantomData<P>);
+pub struct DefaultConnFactory<P> {
+    socket_buffer: SocketBufferSizes,
+    phantom: PhantomData<P>,
+}
 
 impl<P> Default for DefaultConnFactory<P> {
     fn default() -> Self {
-        Self(PhantomData)
+        Self::new(SocketBufferSizes::default())
+    }
+}
+
+impl<P> DefaultConnFactory<P> {
+    pub fn new(socket_buffer: SocketBufferSizes) -> Self {
+        Self {
+            socket_buffer,
+            phantom: PhantomData,
+        }
     }
 }
 
@@ -310,15 +468,25 @@ impl<P: MonoPacket> ConnFactory for DefaultConnFactory<P> {
         &self,
         addr: SocketAddr,
     ) -> Pin<Box<dyn Future<Output = CreateConnResult<Self::Pkt>> + Send>> {
-        Box::pin(create_conn(addr))
+        Box::pin(create_conn(addr, self.socket_buffer))
     }
 }
 
-async fn create_conn<T>(address: SocketAddr) -> CreateConnResult<T>
+async fn create_conn<T>(
+    address: SocketAddr,
+    socket_buffer: SocketBufferSizes,
+) -> CreateConnResult<T>
 where
     T: MonoPacket,
 {
-    let socket = match TcpStream::connect(address).await {
+    let socket = match socket_buffer.new_socket(address) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("failed to create socket: {} {:?}", address, err);
+            return Err(BackendError::Io(err));
+        }
+    };
+    let socket = match socket.connect(address).await {
         Ok(socket) => socket,
         Err(err) => {
             error!("failed to connect: {} {:?}", address, err);
@@ -355,11 +523,27 @@ where
 
 const MAX_BACKEND_RETRY: usize = 3;
 
+// The backend address is only included when `expose_address` is enabled
+// since it discloses the topology of the cluster.
+pub fn backend_unreachable_reply(address: &str, expose_address: bool) -> RespVec {
+    let err_msg = if expose_address {
+        format!(
+            "{} backend {} unreachable",
+            response::ERR_BACKEND_CONNECTION,
+            address
+        )
+    } else {
+        format!("{} backend unreachable", response::ERR_BACKEND_CONNECTION)
+    };
+    Resp::Error(err_msg.into_bytes())
+}
+
 struct RetryState<T: CmdTask> {
     retry_times: usize,
     tasks: Vec<T>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_backend<H, F>(
     handler: Arc<H>,
     mut task_receiver: mpsc::UnboundedReceiver<H::Task>,
@@ -367,6 +551,7 @@ pub async fn handle_backend<H, F>(
     address: String,
     conn_factory: Arc<F>,
     batch_stats: Arc<BatchStats>,
+    conn_state: Arc<BackendConnState>,
     config: Arc<ServerProxyConfig>,
 ) -> Result<(), BackendError>
 where
@@ -378,7 +563,9 @@ where
         Some(addr) => addr,
         None => {
             error!("invalid address: {:?}", address);
-            return Err(BackendError::InvalidAddress);
+            let err = BackendError::InvalidAddress;
+            conn_state.set_error(BackendConnStatus::Failed, &err);
+            return Err(err);
         }
     };
 
@@ -389,6 +576,7 @@ where
             Ok(conn) => conn,
             Err(err) => {
                 conn_failed.store(true, Ordering::SeqCst);
+                conn_state.set_error(BackendConnStatus::Failed, &err);
                 error!("failed to connect: {} {:?}", address, err);
                 if let Some(RetryState { tasks, .. }) = retry_state.take() {
                     for task in tasks.into_iter() {
@@ -413,14 +601,16 @@ where
                             return Err(BackendError::Canceled);
                         }
                     };
-                    task.set_resp_result(Ok(Resp::Error(
-                        format!("failed to connect to {}", address).into_bytes(),
+                    task.set_resp_result(Ok(backend_unreachable_reply(
+                        &address,
+                        config.get_expose_backend_address(),
                     )))
                 }
                 continue;
             }
         };
         conn_failed.store(false, Ordering::SeqCst);
+        conn_state.set_status(BackendConnStatus::Connected);
 
         let res = handle_conn(
             writer,
@@ -429,6 +619,7 @@ where
             handler.clone(),
             retry_state.take(),
             batch_stats.clone(),
+            &conn_state,
             config.backend_batch_strategy,
             config.backend_flush_size,
             config.backend_low_flush_interval,
@@ -443,6 +634,8 @@ where
             }
             Err((err, state)) => {
                 error!("connection is closed: {:?}", err);
+                conn_state.set_error(BackendConnStatus::Connecting, &err);
+                conn_state.set_in_flight(0);
                 retry_state = state;
                 continue;
             }
@@ -460,6 +653,7 @@ async fn handle_conn<H, S>(
     handler: Arc<H>,
     mut retry_state_opt: Option<RetryState<H::Task>>,
     batch_stats: Arc<BatchStats>,
+    conn_state: &BackendConnState,
     backend_batch_strategy: BatchStrategy,
     backend_flush_size: NonZeroUsize,
     backend_low_flush_interval: Duration,
@@ -487,6 +681,7 @@ where
     let mut timeout_interval = tokio::time::interval(backend_timeout);
     let mut response_received = false;
     let mut task_empty = true;
+    let mut receiver_closed = false;
 
     future::poll_fn(
         |cx: &mut Context<'_>| -> Poll<Result<(), HandleConnErr<H::Task>>> {
@@ -505,16 +700,19 @@ where
                 None => None,
             };
 
-            while let Poll::Ready(task_opt) = Pin::new(&mut task_receiver).poll_next(cx) {
-                match task_opt {
-                    Some(mut task) => {
+            while !receiver_closed {
+                match Pin::new(&mut task_receiver).poll_next(cx) {
+                    Poll::Pending => break,
+                    Poll::Ready(Some(mut task)) => {
                         task.log_event(TaskEvent::WritingQueueReceived);
                         packets.push_back(task.get_packet());
                         tasks.push_back(task);
                     }
-                    None => {
+                    Poll::Ready(None) => {
+                        // The sender is dropped along with the old metadata after SETCLUSTER.
+                        // Still reply the received tasks before exiting.
                         info!("backend task_receiver is closed");
-                        return Poll::Ready(Ok(()));
+                        receiver_closed = true;
                     }
                 }
             }
@@ -553,7 +751,7 @@ where
                         }
                     }
                     None => {
-                        if !batch_state.need_flush(cx) {
+                        if !receiver_closed && !batch_state.need_flush(cx) {
                             break Ok(());
                         }
 
@@ -606,6 +804,10 @@ where
                 return Poll::Ready(Err((err, retry_state)));
             }
 
+            if receiver_closed && tasks.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+
             if Pin::new(&mut timeout_interval).poll_tick(cx).is_ready() {
                 if !task_empty && !response_received {
                     let err = BackendError::Timeout;
@@ -622,6 +824,7 @@ where
                 response_received = false;
             }
 
+            conn_state.set_in_flight(tasks.len());
             Poll::Pending
         },
     )
@@ -859,6 +1062,49 @@ mod tests {
         fn log_event(&mut self, _event: TaskEvent) {}
     }
 
+    #[test]
+    fn test_backend_unreachable_reply() {
+        let reply = backend_unreachable_reply("127.0.0.1:6379", true);
+        assert_eq!(
+            reply,
+            Resp::Error(b"ERR_BACKEND_CONNECTION backend 127.0.0.1:6379 unreachable".to_vec())
+        );
+        let reply = backend_unreachable_reply("127.0.0.1:6379", false);
+        assert_eq!(
+            reply,
+            Resp::Error(b"ERR_BACKEND_CONNECTION backend unreachable".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_backend_conn_states() {
+        let states = BackendConnStates::default();
+        let state1 = states.register("127.0.0.1:6379".to_string());
+        let state2 = states.register("127.0.0.1:6379".to_string());
+        let state3 = states.register("127.0.0.1:6380".to_string());
+
+        state1.set_status(BackendConnStatus::Connected);
+        state1.set_in_flight(2);
+        state2.set_status(BackendConnStatus::Connected);
+        state2.set_in_flight(3);
+        state3.set_error(BackendConnStatus::Failed, &BackendError::Timeout);
+
+        let summaries = states.get_summaries();
+        assert_eq!(summaries.len(), 2);
+        let s = &summaries[0];
+        assert_eq!(s.address, "127.0.0.1:6379");
+        assert_eq!(s.status, BackendConnStatus::Connected);
+        assert_eq!(s.conn_num, 2);
+        assert_eq!(s.in_flight, 5);
+        assert!(s.last_error.is_none());
+        let s = &summaries[1];
+        assert_eq!(s.status, BackendConnStatus::Failed);
+        assert!(s.last_error.is_some());
+
+        drop(state3);
+        assert_eq!(states.get_summaries().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_handle_conn() {
         test_handle_conn_helper(BatchStrategy::Disabled).await;
@@ -900,6 +1146,7 @@ mod tests {
             handler.clone(),
             None,
             batch_stats,
+            &BackendConnState::new("127.0.0.1:6379".to_string()),
             strategy,
             NonZeroUsize::new(1024).unwrap(),
             Duration::from_nanos(200_000),
@@ -970,6 +1217,7 @@ mod tests {
             handler.clone(),
             Some(retry),
             batch_stats,
+            &BackendConnState::new("127.0.0.1:6379".to_string()),
             strategy,
             NonZeroUsize::new(1024).unwrap(),
             Duration::from_nanos(200_000),
@@ -1023,6 +1271,7 @@ mod tests {
             handler.clone(),
             None,
             batch_stats,
+            &BackendConnState::new("127.0.0.1:6379".to_string()),
             strategy,
             NonZeroUsize::new(1024).unwrap(),
             Duration::from_nanos(200_000),
@@ -1,6 +1,6 @@
 use super::backend::{
-    CmdTask, CmdTaskResultHandler, CmdTaskResultHandlerFactory, ConnFactory, IntoTask,
-    SenderBackendError,
+    BackendConnStates, CmdTask, CmdTaskResultHandler, CmdTaskResultHandlerFactory, ConnFactory,
+    IntoTask, SenderBackendError,
 };
 use super::command::{CommandError, CommandResult};
 use super::sender::{
@@ -62,6 +62,7 @@ pub fn gen_basic_blocking_sender_factory<F: CmdTaskResultHandlerFactory, CF: Con
     conn_factory: Arc<CF>,
     future_registry: Arc<TrackedFutureRegistry>,
     batch_stats: Arc<BatchStats>,
+    conn_states: Arc<BackendConnStates>,
 ) -> BasicBlockingSenderFactory<F, CF>
 where
     <F::Handler as CmdTaskResultHandler>::Task: CmdTask<Pkt = CF::Pkt>,
@@ -75,6 +76,7 @@ where
             conn_factory,
             future_registry,
             batch_stats,
+            conn_states,
         ),
     )
 }
@@ -375,6 +377,10 @@ where
     fn send(&self, cmd_task: Self::Task) -> Result<(), SenderBackendError<Self::Task>> {
         self.queue.send(cmd_task)
     }
+
+    fn is_failing(&self) -> bool {
+        self.queue.inner_sender.is_failing()
+    }
 }
 
 pub struct TaskBlockingQueueSenderFactory<F, BS>
@@ -507,6 +513,10 @@ impl<T: CmdTask> CmdTask for CounterTask<T> {
     fn log_event(&mut self, event: TaskEvent) {
         self.inner.log_event(event)
     }
+
+    fn is_asking(&self) -> bool {
+        self.inner.is_asking()
+    }
 }
 
 pub struct BlockingHintTask<T: CmdTask> {
@@ -570,6 +580,10 @@ impl<T: CmdTask> CmdTask for BlockingHintTask<T> {
     fn log_event(&mut self, event: TaskEvent) {
         self.inner.log_event(event)
     }
+
+    fn is_asking(&self) -> bool {
+        self.inner.is_asking()
+    }
 }
 
 impl<T: CmdTask> IntoTask<T> for BlockingHintTask<T> {
@@ -1,10 +1,13 @@
-use super::backend::{BackendError, CmdTask, IntoTask, SenderBackendError};
+use super::backend::{
+    BackendConnStatus, BackendError, BackendSummary, CmdTask, IntoTask, SenderBackendError,
+};
 use super::sender::{CmdTaskSender, CmdTaskSenderFactory};
 use super::service::ClusterNodesVersion;
 use super::slot::SlotMap;
 use crate::common::cluster::{ClusterName, RangeList, SlotRange, SlotRangeTag, EMPTY_CLUSTER_NAME};
 use crate::common::config::ClusterConfig;
 use crate::common::proto::ProxyClusterMeta;
+use crate::common::response;
 use crate::common::utils::gen_moved;
 use crate::migration::task::MigrationState;
 use crate::protocol::{Array, BulkStr, Resp, RespVec};
@@ -172,6 +175,55 @@ where
         Ok(Resp::Arr(Array::Arr(local)))
     }
 
+    pub fn gen_cluster_shards(
+        &self,
+        service_address: String,
+        migration_states: &HashMap<RangeList, MigrationState>,
+    ) -> Result<RespVec, String> {
+        let mut local = self
+            .local_cluster
+            .gen_local_cluster_shards(service_address, migration_states)?;
+        let mut remote = self
+            .remote_cluster
+            .gen_remote_cluster_shards(migration_states)?;
+        local.append(&mut remote);
+        Ok(Resp::Arr(Array::Arr(local)))
+    }
+
+    pub fn gen_cluster_info(
+        &self,
+        migration_states: &HashMap<RangeList, MigrationState>,
+        backends: &[BackendSummary],
+    ) -> String {
+        // The proxy itself and the peer proxies.
+        let known_nodes = 1 + self.remote_cluster.slot_ranges.len();
+        let slots_info = count_slots(
+            &self.local_cluster.slot_ranges,
+            &self.remote_cluster.slot_ranges,
+            migration_states,
+            backends,
+        );
+        gen_cluster_info_helper(self.get_epoch(), self.is_ready(), known_nodes, &slots_info)
+    }
+
+    // The slots being taken over by other nodes are skipped just like `CLUSTER NODES`
+    // so that each slot is only shown in the node serving it right now.
+    pub fn gen_routes(&self, migration_states: &HashMap<RangeList, MigrationState>) -> Vec<String> {
+        let mut routes =
+            gen_routes_helper(&self.local_cluster.slot_ranges, migration_states, "local");
+        routes.extend(gen_routes_helper(
+            &self.remote_cluster.slot_ranges,
+            migration_states,
+            "peer",
+        ));
+        routes.sort_by_key(|(start, _)| *start);
+        routes.into_iter().map(|(_, route)| route).collect()
+    }
+
+    pub fn get_epoch(&self) -> u64 {
+        self.local_cluster.epoch
+    }
+
     pub fn is_ready(&self) -> bool {
         self.local_cluster.is_ready()
     }
@@ -183,6 +235,31 @@ where
     pub fn get_cluster_any_node(&self) -> Option<String> {
         self.local_cluster.get_any_node()
     }
+
+    pub fn get_local_nodes(&self) -> Vec<String> {
+        self.local_cluster.get_nodes()
+    }
+
+    // Like `is_ready`, the migrating slots are regarded as owned by the importing proxy.
+    pub fn get_local_owned_slots(&self) -> RangeList {
+        let range_lists = self
+            .local_cluster
+            .slot_ranges
+            .values()
+            .flatten()
+            .filter(|slot_range| !matches!(slot_range.tag, SlotRangeTag::Migrating(_)))
+            .map(|slot_range| slot_range.get_range_list().clone())
+            .collect();
+        RangeList::merge(range_lists)
+    }
+
+    pub fn send_to_local_node(
+        &self,
+        cmd_task: <S as CmdTaskSender>::Task,
+        address: &str,
+    ) -> Result<(), ClusterSendError<<S as CmdTaskSender>::Task>> {
+        self.local_cluster.send_to_node(cmd_task, address)
+    }
 }
 
 struct SenderMap<S: CmdTaskSender> {
@@ -286,6 +363,19 @@ impl<S: CmdTaskSender> LocalCluster<S> {
 
         match self.local_backend.slot_map.get(slot) {
             Some(addr) => match self.local_backend.nodes.get(addr) {
+                // The slot has an owner but none of its connections is available.
+                Some(sender) if sender.is_failing() => {
+                    let resp = Resp::Error(
+                        format!(
+                            "{} slot {} has no reachable node",
+                            response::ERR_CLUSTER_DOWN,
+                            slot
+                        )
+                        .into_bytes(),
+                    );
+                    cmd_task.set_resp_result(Ok(resp));
+                    Err(ClusterSendError::NoReachableNode(slot))
+                }
                 Some(sender) => sender
                     .send(cmd_task)
                     .map_err(ClusterSendError::from_sender_backend_error),
@@ -307,8 +397,8 @@ impl<S: CmdTaskSender> LocalCluster<S> {
         let slots: Vec<SlotRange> = self
             .slot_ranges
             .values()
-            .cloned()
             .flatten()
+            .cloned()
             .collect::<Vec<SlotRange>>();
         let mut slot_ranges = HashMap::new();
         slot_ranges.insert(service_address, slots);
@@ -330,14 +420,30 @@ impl<S: CmdTaskSender> LocalCluster<S> {
         let slots: Vec<SlotRange> = self
             .slot_ranges
             .values()
-            .cloned()
             .flatten()
+            .cloned()
             .collect::<Vec<SlotRange>>();
         let mut slot_ranges = HashMap::new();
         slot_ranges.insert(service_address, slots);
         gen_cluster_slots_helper(&self.cluster_name, &slot_ranges, migration_states)
     }
 
+    pub fn gen_local_cluster_shards(
+        &self,
+        service_address: String,
+        migration_states: &HashMap<RangeList, MigrationState>,
+    ) -> Result<Vec<RespVec>, String> {
+        let slots: Vec<SlotRange> = self
+            .slot_ranges
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<SlotRange>>();
+        let mut slot_ranges = HashMap::new();
+        slot_ranges.insert(service_address, slots);
+        gen_cluster_shards_helper(&self.cluster_name, &slot_ranges, migration_states)
+    }
+
     pub fn is_ready(&self) -> bool {
         is_ready(&self.slot_ranges)
     }
@@ -345,6 +451,27 @@ impl<S: CmdTaskSender> LocalCluster<S> {
     pub fn get_any_node(&self) -> Option<String> {
         self.local_backend.nodes.keys().next().cloned()
     }
+
+    // Sorted so that the index of a node keeps the same with the same metadata.
+    pub fn get_nodes(&self) -> Vec<String> {
+        let mut nodes: Vec<String> = self.local_backend.nodes.keys().cloned().collect();
+        nodes.sort();
+        nodes
+    }
+
+    // Send the command to the specified node regardless of the slot.
+    pub fn send_to_node(
+        &self,
+        cmd_task: <S as CmdTaskSender>::Task,
+        address: &str,
+    ) -> Result<(), ClusterSendError<<S as CmdTaskSender>::Task>> {
+        match self.local_backend.nodes.get(address) {
+            Some(sender) => sender
+                .send(cmd_task)
+                .map_err(ClusterSendError::from_sender_backend_error),
+            None => Err(ClusterSendError::SlotNotFound(cmd_task)),
+        }
+    }
 }
 
 fn is_ready(slot_ranges: &HashMap<String, Vec<SlotRange>>) -> bool {
@@ -491,6 +618,13 @@ impl<P: CmdTaskSender> RemoteCluster<P> {
     ) -> Result<Vec<RespVec>, String> {
         gen_cluster_slots_helper(&self.cluster_name, &self.slot_ranges, migration_states)
     }
+
+    pub fn gen_remote_cluster_shards(
+        &self,
+        migration_states: &HashMap<RangeList, MigrationState>,
+    ) -> Result<Vec<RespVec>, String> {
+        gen_cluster_shards_helper(&self.cluster_name, &self.slot_ranges, migration_states)
+    }
 }
 
 fn format_slot_ranges(slot_ranges: &HashMap<String, Vec<SlotRange>>) -> Vec<RespVec> {
@@ -536,6 +670,8 @@ pub enum ClusterSendError<T: CmdTask> {
     },
     SlotNotFound(T),
     SlotNotCovered,
+    // The reply has already been set.
+    NoReachableNode(usize),
     Backend(BackendError),
     MigrationError,
     ActiveRedirection {
@@ -568,6 +704,7 @@ impl<T: CmdTask> fmt::Debug for ClusterSendError<T> {
             Self::ClusterNotFound { task: _ } => "ClusterSendError::ClusterNotFound".to_string(),
             Self::SlotNotFound(_) => "ClusterSendError::SlotNotFound".to_string(),
             Self::SlotNotCovered => "ClusterSendError::SlotNotCovered".to_string(),
+            Self::NoReachableNode(slot) => format!("ClusterSendError::NoReachableNode({})", slot),
             Self::Backend(err) => format!("ClusterSendError::Backend({})", err),
             Self::MigrationError => "ClusterSendError::MigrationError".to_string(),
             Self::ActiveRedirection { slot, address, .. } => {
@@ -587,6 +724,7 @@ impl<T: CmdTask> Error for ClusterSendError<T> {
             Self::SlotNotFound(_) => None,
             Self::Backend(err) => Some(err),
             Self::SlotNotCovered => None,
+            Self::NoReachableNode(_) => None,
             Self::MigrationError => None,
             Self::ActiveRedirection { .. } => None,
             Self::Retry(_) => None,
@@ -606,6 +744,7 @@ impl<T: CmdTask> ClusterSendError<T> {
             Self::SlotNotFound(task) => ClusterSendError::SlotNotFound(f(task)),
             Self::Backend(err) => ClusterSendError::Backend(err),
             Self::SlotNotCovered => ClusterSendError::SlotNotCovered,
+            Self::NoReachableNode(slot) => ClusterSendError::NoReachableNode(slot),
             Self::MigrationError => ClusterSendError::MigrationError,
             Self::ActiveRedirection {
                 task,
@@ -715,6 +854,81 @@ fn should_ignore_slots(
     }
 }
 
+// Clients could compare the epoch with the previous one
+// to decide whether they need to refresh the slot cache.
+#[derive(Debug, Default, PartialEq)]
+struct SlotsInfo {
+    assigned: usize,
+    ok: usize,
+    pfail: usize,
+    fail: usize,
+}
+
+// The slots of the local backends are checked against the live connection states:
+// - `fail` if the proxy fails to connect to the backend.
+// - `pfail` if the connection is broken and the proxy is reconnecting.
+// The slots of the peer proxies are always `ok`
+// since their backends are not visible to this proxy.
+fn count_slots(
+    local_slot_ranges: &HashMap<String, Vec<SlotRange>>,
+    remote_slot_ranges: &HashMap<String, Vec<SlotRange>>,
+    migration_states: &HashMap<RangeList, MigrationState>,
+    backends: &[BackendSummary],
+) -> SlotsInfo {
+    let slots_num = |ranges: &Vec<SlotRange>| -> usize {
+        ranges
+            .iter()
+            .filter(|slot_range| !should_ignore_slots(slot_range, migration_states))
+            .map(|slot_range| slot_range.get_range_list().get_slots_num())
+            .sum()
+    };
+
+    let mut info = SlotsInfo::default();
+    for (addr, ranges) in local_slot_ranges.iter() {
+        let num = slots_num(ranges);
+        info.assigned += num;
+        let summary = backends.iter().find(|summary| summary.address.eq(addr));
+        match summary {
+            Some(summary) if summary.status == BackendConnStatus::Failed => info.fail += num,
+            Some(summary)
+                if summary.status == BackendConnStatus::Connecting
+                    && summary.last_error.is_some() =>
+            {
+                info.pfail += num
+            }
+            _ => info.ok += num,
+        }
+    }
+    for ranges in remote_slot_ranges.values() {
+        let num = slots_num(ranges);
+        info.assigned += num;
+        info.ok += num;
+    }
+    info
+}
+
+fn gen_cluster_info_helper(
+    epoch: u64,
+    ready: bool,
+    known_nodes: usize,
+    slots_info: &SlotsInfo,
+) -> String {
+    let state = if ready { "ok" } else { "fail" };
+    let lines = [
+        format!("cluster_state:{}", state),
+        format!("cluster_slots_assigned:{}", slots_info.assigned),
+        format!("cluster_slots_ok:{}", slots_info.ok),
+        format!("cluster_slots_pfail:{}", slots_info.pfail),
+        format!("cluster_slots_fail:{}", slots_info.fail),
+        format!("cluster_known_nodes:{}", known_nodes),
+        format!("cluster_current_epoch:{}", epoch),
+        format!("cluster_my_epoch:{}", epoch),
+    ];
+    let mut info = lines.join("\r\n");
+    info.push_str("\r\n");
+    info
+}
+
 fn gen_cluster_slots_helper(
     name: &ClusterName,
     slot_ranges: &HashMap<String, Vec<SlotRange>>,
@@ -722,15 +936,10 @@ fn gen_cluster_slots_helper(
 ) -> Result<Vec<RespVec>, String> {
     let name_seg = gen_cluster_name_seg(name);
 
+    // (start slot, entry)
     let mut slot_range_element = Vec::new();
     for (addr, ranges) in slot_ranges {
-        let mut segs = addr.split(':');
-        let host = segs
-            .next()
-            .ok_or_else(|| format!("invalid address {}", addr))?;
-        let port = segs
-            .next()
-            .ok_or_else(|| format!("invalid address {}", addr))?;
+        let (host, port) = split_host_port(addr)?;
 
         for slot_range in ranges {
             if should_ignore_slots(slot_range, migration_states) {
@@ -744,26 +953,186 @@ fn gen_cluster_slots_helper(
                 Resp::Bulk(BulkStr::Str(node_id.into_bytes())),
             ]));
 
+            // Each range, including a single slot, is a separate entry
+            // so that the gaps between them are never covered.
             for range in slot_range.get_range_list().get_ranges().iter() {
                 let mut arr = vec![
                     Resp::Integer(range.start().to_string().into_bytes()),
                     Resp::Integer(range.end().to_string().into_bytes()),
                 ];
                 arr.push(ip_port_array.clone());
-                slot_range_element.push(Resp::Arr(Array::Arr(arr)))
+                slot_range_element.push((range.start(), Resp::Arr(Array::Arr(arr))))
             }
         }
     }
-    Ok(slot_range_element)
+    // Keep the output stable regardless of the order of the HashMap.
+    slot_range_element.sort_by_key(|(start, _)| *start);
+    Ok(slot_range_element
+        .into_iter()
+        .map(|(_, element)| element)
+        .collect())
+}
+
+// Returns (start slot, route) with the format
+// `<start>-<end> -> <address> <local|peer> [migrating:<dst proxy>|importing:<src proxy>] [state:<state>]`
+fn gen_routes_helper(
+    slot_ranges: &HashMap<String, Vec<SlotRange>>,
+    migration_states: &HashMap<RangeList, MigrationState>,
+    kind: &str,
+) -> Vec<(usize, String)> {
+    let mut routes = vec![];
+    for (addr, ranges) in slot_ranges {
+        for slot_range in ranges {
+            if should_ignore_slots(slot_range, migration_states) {
+                continue;
+            }
+            let migration = match &slot_range.tag {
+                SlotRangeTag::Migrating(meta) => format!(" migrating:{}", meta.dst_proxy_address),
+                SlotRangeTag::Importing(meta) => format!(" importing:{}", meta.src_proxy_address),
+                SlotRangeTag::None => String::new(),
+            };
+            let state = match migration_states.get(slot_range.get_range_list()) {
+                Some(state) if slot_range.tag != SlotRangeTag::None => format!(" state:{}", state),
+                _ => String::new(),
+            };
+            for range in slot_range.get_range_list().get_ranges().iter() {
+                let route = format!(
+                    "{}-{} -> {} {}{}{}",
+                    range.start(),
+                    range.end(),
+                    addr,
+                    kind,
+                    migration,
+                    state
+                );
+                routes.push((range.start(), route));
+            }
+        }
+    }
+    routes
+}
+
+// Every proxy is a shard with only one master node.
+fn gen_cluster_shards_helper(
+    name: &ClusterName,
+    slot_ranges: &HashMap<String, Vec<SlotRange>>,
+    migration_states: &HashMap<RangeList, MigrationState>,
+) -> Result<Vec<RespVec>, String> {
+    let name_seg = gen_cluster_name_seg(name);
+
+    // (first slot, address, shard)
+    let mut shards = Vec::new();
+    for (addr, ranges) in slot_ranges {
+        let (host, port) = split_host_port(addr)?;
+
+        let mut ranges: Vec<(usize, usize)> = ranges
+            .iter()
+            .filter(|slot_range| !should_ignore_slots(slot_range, migration_states))
+            .flat_map(|slot_range| {
+                slot_range
+                    .get_range_list()
+                    .get_ranges()
+                    .iter()
+                    .map(|range| (range.start(), range.end()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        ranges.sort_unstable();
+        // The shards without any slot are put at the end.
+        let first_slot = ranges
+            .first()
+            .map(|(start, _)| *start)
+            .unwrap_or(usize::MAX);
+        let slots = ranges
+            .into_iter()
+            .flat_map(|(start, end)| {
+                vec![
+                    Resp::Integer(start.to_string().into_bytes()),
+                    Resp::Integer(end.to_string().into_bytes()),
+                ]
+            })
+            .collect();
+
+        let node_id = gen_node_id(name_seg.as_str(), addr.as_str());
+        let node = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"id".to_vec())),
+            Resp::Bulk(BulkStr::Str(node_id.into_bytes())),
+            Resp::Bulk(BulkStr::Str(b"port".to_vec())),
+            Resp::Integer(port.as_bytes().to_vec()),
+            Resp::Bulk(BulkStr::Str(b"ip".to_vec())),
+            Resp::Bulk(BulkStr::Str(host.as_bytes().to_vec())),
+            Resp::Bulk(BulkStr::Str(b"endpoint".to_vec())),
+            Resp::Bulk(BulkStr::Str(host.as_bytes().to_vec())),
+            Resp::Bulk(BulkStr::Str(b"role".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"master".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"replication-offset".to_vec())),
+            Resp::Integer(b"0".to_vec()),
+            Resp::Bulk(BulkStr::Str(b"health".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"online".to_vec())),
+        ]));
+
+        let shard = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"slots".to_vec())),
+            Resp::Arr(Array::Arr(slots)),
+            Resp::Bulk(BulkStr::Str(b"nodes".to_vec())),
+            Resp::Arr(Array::Arr(vec![node])),
+        ]));
+        shards.push((first_slot, addr, shard));
+    }
+    // Keep the output stable regardless of the order of the HashMap.
+    shards.sort_by(|(slot1, addr1, _), (slot2, addr2, _)| (slot1, addr1).cmp(&(slot2, addr2)));
+    Ok(shards.into_iter().map(|(_, _, shard)| shard).collect())
+}
+
+// Emulates `CLUSTER LINKS` with the backend connections of this proxy.
+// The proxy only has outbound links so the direction is always `to`,
+// and the node is the backend address since backends have no node id here.
+// The send buffers are not tracked so the in-flight commands are reported instead.
+pub fn gen_cluster_links(summaries: Vec<BackendSummary>) -> RespVec {
+    let links = summaries
+        .into_iter()
+        .map(|summary| {
+            Resp::Arr(Array::Arr(vec![
+                Resp::Bulk(BulkStr::Str(b"direction".to_vec())),
+                Resp::Bulk(BulkStr::Str(b"to".to_vec())),
+                Resp::Bulk(BulkStr::Str(b"node".to_vec())),
+                Resp::Bulk(BulkStr::Str(summary.address.into_bytes())),
+                Resp::Bulk(BulkStr::Str(b"state".to_vec())),
+                Resp::Bulk(BulkStr::Str(summary.status.to_string().into_bytes())),
+                Resp::Bulk(BulkStr::Str(b"conns".to_vec())),
+                Resp::Integer(summary.conn_num.to_string().into_bytes()),
+                Resp::Bulk(BulkStr::Str(b"in-flight".to_vec())),
+                Resp::Integer(summary.in_flight.to_string().into_bytes()),
+            ]))
+        })
+        .collect();
+    Resp::Arr(Array::Arr(links))
+}
+
+fn split_host_port(addr: &str) -> Result<(&str, &str), String> {
+    let mut segs = addr.split(':');
+    let host = segs
+        .next()
+        .ok_or_else(|| format!("invalid address {}", addr))?;
+    let port = segs
+        .next()
+        .ok_or_else(|| format!("invalid address {}", addr))?;
+    Ok((host, port))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::common::cluster::{MigrationMeta, RangeList};
-    use crate::protocol::{Array, BulkStr};
+    use crate::protocol::{Array, BulkStr, RespPacket};
+    use crate::proxy::command::{new_command_pair, CmdReplyReceiver, Command};
+    use crate::proxy::sender::{RoundRobinSenderGroup, RoundRobinSenderGroupFactory};
+    use crate::proxy::session::CmdCtx;
     use std::convert::TryFrom;
     use std::iter::repeat;
+    use std::num::NonZeroUsize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     fn gen_testing_slot_ranges(address: &str) -> HashMap<String, Vec<SlotRange>> {
         let mut slot_ranges = HashMap::new();
@@ -970,6 +1339,151 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_gen_cluster_info() {
+        let slots_info = SlotsInfo {
+            assigned: 16384,
+            ok: 16000,
+            pfail: 300,
+            fail: 84,
+        };
+        let output = gen_cluster_info_helper(233, true, 3, &slots_info);
+        assert_eq!(
+            output,
+            "cluster_state:ok\r\ncluster_slots_assigned:16384\r\ncluster_slots_ok:16000\r\ncluster_slots_pfail:300\r\ncluster_slots_fail:84\r\ncluster_known_nodes:3\r\ncluster_current_epoch:233\r\ncluster_my_epoch:233\r\n"
+        );
+        let output = gen_cluster_info_helper(0, false, 1, &SlotsInfo::default());
+        assert!(output.starts_with("cluster_state:fail\r\n"));
+    }
+
+    #[test]
+    fn test_gen_routes() {
+        let m = HashMap::new();
+        let routes: Vec<String> =
+            gen_routes_helper(&gen_testing_slot_ranges("127.0.0.1:6379"), &m, "local")
+                .into_iter()
+                .map(|(_, route)| route)
+                .collect();
+        assert_eq!(
+            routes,
+            vec![
+                "0-100 -> 127.0.0.1:6379 local",
+                "300-300 -> 127.0.0.1:6379 local"
+            ]
+        );
+
+        let mut m = HashMap::new();
+        m.insert(
+            RangeList::try_from("1 0-1000").unwrap(),
+            MigrationState::Scanning,
+        );
+        let routes = gen_routes_helper(&gen_testing_migration_slot_ranges(false), &m, "local");
+        assert_eq!(
+            routes,
+            vec![(
+                0,
+                "0-1000 -> 127.0.0.1:5299 local importing:127.0.0.1:7000 state:SCANNING"
+                    .to_string()
+            )]
+        );
+        // The migrating slots have been switched to the importing node.
+        let routes = gen_routes_helper(&gen_testing_migration_slot_ranges(true), &m, "peer");
+        assert!(routes.is_empty());
+
+        m.insert(
+            RangeList::try_from("1 0-1000").unwrap(),
+            MigrationState::PreCheck,
+        );
+        let routes = gen_routes_helper(&gen_testing_migration_slot_ranges(true), &m, "peer");
+        assert_eq!(
+            routes,
+            vec![(
+                0,
+                "0-1000 -> 127.0.0.1:5299 peer migrating:127.0.0.1:7001 state:PRE_CHECK"
+                    .to_string()
+            )]
+        );
+    }
+
+    fn gen_backend_summary(
+        address: &str,
+        status: BackendConnStatus,
+        last_error: Option<&str>,
+    ) -> BackendSummary {
+        BackendSummary {
+            address: address.to_string(),
+            status,
+            conn_num: 1,
+            in_flight: 0,
+            last_error: last_error.map(|err| err.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_count_slots() {
+        let mut local_slot_ranges = HashMap::new();
+        for (address, range) in &[
+            ("127.0.0.1:6001", "1 0-99"),
+            ("127.0.0.1:6002", "1 100-299"),
+            ("127.0.0.1:6003", "2 300-300 400-499"),
+            ("127.0.0.1:6004", "1 500-999"),
+            ("127.0.0.1:6005", "1 1000-1999"),
+        ] {
+            local_slot_ranges.insert(
+                address.to_string(),
+                vec![SlotRange {
+                    range_list: RangeList::try_from(*range).unwrap(),
+                    tag: SlotRangeTag::None,
+                }],
+            );
+        }
+        let remote_slot_ranges = gen_testing_slot_ranges("127.0.0.1:5300");
+        let backends = vec![
+            gen_backend_summary("127.0.0.1:6001", BackendConnStatus::Connected, None),
+            gen_backend_summary("127.0.0.1:6002", BackendConnStatus::Failed, Some("err")),
+            gen_backend_summary("127.0.0.1:6003", BackendConnStatus::Connecting, Some("err")),
+            // Connecting for the first time.
+            gen_backend_summary("127.0.0.1:6004", BackendConnStatus::Connecting, None),
+            // 127.0.0.1:6005 is not connected yet.
+        ];
+        let info = count_slots(
+            &local_slot_ranges,
+            &remote_slot_ranges,
+            &HashMap::new(),
+            &backends,
+        );
+        assert_eq!(
+            info,
+            SlotsInfo {
+                assigned: 1901 + 102,
+                ok: 100 + 500 + 1000 + 102,
+                pfail: 101,
+                fail: 200,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cluster_nodes_epoch_bump() {
+        let cluster_name = ClusterName::try_from("testcluster").unwrap();
+        let m = HashMap::new();
+        let slot_ranges = gen_testing_slot_ranges("127.0.0.1:5299");
+        let gen = |epoch| {
+            gen_cluster_nodes_helper(
+                &cluster_name,
+                epoch,
+                &slot_ranges,
+                &m,
+                true,
+                ClusterNodesVersion::V1,
+            )
+        };
+        let old_nodes = gen(1);
+        let new_nodes = gen(2);
+        assert!(old_nodes.contains(" - 0 0 1 connected"));
+        assert!(new_nodes.contains(" - 0 0 2 connected"));
+    }
+
     #[test]
     fn test_gen_cluster_slots() {
         let cluster_name = ClusterName::try_from("test_cluster_name").unwrap();
@@ -998,9 +1512,40 @@ mod tests {
                 )),
             ])),
         ]));
-        if output != vec![slot_range2.clone(), slot_range1.clone()] {
-            assert_eq!(output, vec![slot_range1, slot_range2]);
-        }
+        assert_eq!(output, vec![slot_range1, slot_range2]);
+    }
+
+    #[test]
+    fn test_gen_cluster_slots_with_gaps() {
+        let cluster_name = ClusterName::try_from("test_cluster_name").unwrap();
+        let m = HashMap::new();
+        let mut slot_ranges = HashMap::new();
+        slot_ranges.insert(
+            "127.0.0.1:5299".to_string(),
+            vec![SlotRange {
+                range_list: RangeList::try_from("3 200-300 0-100 102-102").unwrap(),
+                tag: SlotRangeTag::None,
+            }],
+        );
+        let output = gen_cluster_slots_helper(&cluster_name, &slot_ranges, &m).unwrap();
+        let node = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str("127.0.0.1".to_string().into_bytes())),
+            Resp::Integer(5299.to_string().into_bytes()),
+            Resp::Bulk(BulkStr::Str(
+                b"test_cluster_name___9f8fca2805923328____".to_vec(),
+            )),
+        ]));
+        let expected: Vec<RespVec> = vec![(0, 100), (102, 102), (200, 300)]
+            .into_iter()
+            .map(|(start, end): (usize, usize)| {
+                Resp::Arr(Array::Arr(vec![
+                    Resp::Integer(start.to_string().into_bytes()),
+                    Resp::Integer(end.to_string().into_bytes()),
+                    node.clone(),
+                ]))
+            })
+            .collect();
+        assert_eq!(output, expected);
     }
 
     #[test]
@@ -1021,6 +1566,140 @@ mod tests {
         assert_eq!(output.len(), 0);
     }
 
+    #[test]
+    fn test_gen_cluster_shards() {
+        let cluster_name = ClusterName::try_from("test_cluster_name").unwrap();
+        let m = HashMap::new();
+        let slot_ranges = gen_testing_slot_ranges("127.0.0.1:5299");
+        let output = gen_cluster_shards_helper(&cluster_name, &slot_ranges, &m).unwrap();
+        assert_eq!(output.len(), 1);
+        let shard = match &output[0] {
+            Resp::Arr(Array::Arr(shard)) => shard.clone(),
+            _ => panic!(),
+        };
+        assert_eq!(shard.len(), 4);
+        assert_eq!(shard[0], Resp::Bulk(BulkStr::Str(b"slots".to_vec())));
+        let slots = match &shard[1] {
+            Resp::Arr(Array::Arr(slots)) => slots
+                .iter()
+                .map(|slot| match slot {
+                    Resp::Integer(n) => atoi::atoi::<usize>(n).unwrap(),
+                    _ => panic!(),
+                })
+                .collect::<Vec<usize>>(),
+            _ => panic!(),
+        };
+        assert_eq!(slots, vec![0, 100, 300, 300]);
+        assert_eq!(shard[2], Resp::Bulk(BulkStr::Str(b"nodes".to_vec())));
+        let node = match &shard[3] {
+            Resp::Arr(Array::Arr(nodes)) => {
+                assert_eq!(nodes.len(), 1);
+                nodes[0].clone()
+            }
+            _ => panic!(),
+        };
+        let node = match node {
+            Resp::Arr(Array::Arr(node)) => node,
+            _ => panic!(),
+        };
+        assert_eq!(
+            node[1],
+            Resp::Bulk(BulkStr::Str(
+                b"test_cluster_name___9f8fca2805923328____".to_vec()
+            ))
+        );
+        assert_eq!(node[3], Resp::Integer(b"5299".to_vec()));
+        assert_eq!(node[5], Resp::Bulk(BulkStr::Str(b"127.0.0.1".to_vec())));
+        assert_eq!(node[9], Resp::Bulk(BulkStr::Str(b"master".to_vec())));
+    }
+
+    #[test]
+    fn test_gen_cluster_shards_order() {
+        let cluster_name = ClusterName::try_from("test_cluster_name").unwrap();
+        let m = HashMap::new();
+        let mut slot_ranges = HashMap::new();
+        for (address, ranges) in &[
+            ("127.0.0.1:5299", "1 5000-5999"),
+            ("127.0.0.1:5300", "2 1000-1999 0-99"),
+            ("127.0.0.1:5301", "1 2000-2999"),
+        ] {
+            slot_ranges.insert(
+                address.to_string(),
+                vec![SlotRange {
+                    range_list: RangeList::try_from(*ranges).unwrap(),
+                    tag: SlotRangeTag::None,
+                }],
+            );
+        }
+        let output = gen_cluster_shards_helper(&cluster_name, &slot_ranges, &m).unwrap();
+        let first_slots: Vec<Resp<Vec<u8>>> = output
+            .iter()
+            .map(|shard| match shard {
+                Resp::Arr(Array::Arr(shard)) => match &shard[1] {
+                    Resp::Arr(Array::Arr(slots)) => slots[0].clone(),
+                    _ => panic!(),
+                },
+                _ => panic!(),
+            })
+            .collect();
+        assert_eq!(
+            first_slots,
+            vec![
+                Resp::Integer(b"0".to_vec()),
+                Resp::Integer(b"2000".to_vec()),
+                Resp::Integer(b"5000".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gen_cluster_links() {
+        assert_eq!(gen_cluster_links(vec![]), Resp::Arr(Array::Arr(vec![])));
+
+        let summaries = vec![BackendSummary {
+            address: "127.0.0.1:6379".to_string(),
+            status: BackendConnStatus::Connected,
+            conn_num: 2,
+            in_flight: 3,
+            last_error: None,
+        }];
+        let link = match gen_cluster_links(summaries) {
+            Resp::Arr(Array::Arr(links)) => {
+                assert_eq!(links.len(), 1);
+                links[0].clone()
+            }
+            _ => panic!(),
+        };
+        let expected: Vec<RespVec> = vec![
+            Resp::Bulk(BulkStr::Str(b"direction".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"to".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"node".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"127.0.0.1:6379".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"state".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"connected".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"conns".to_vec())),
+            Resp::Integer(b"2".to_vec()),
+            Resp::Bulk(BulkStr::Str(b"in-flight".to_vec())),
+            Resp::Integer(b"3".to_vec()),
+        ];
+        assert_eq!(link, Resp::Arr(Array::Arr(expected)));
+    }
+
+    #[test]
+    fn test_gen_migrating_cluster_shards() {
+        let cluster_name = ClusterName::try_from("test_cluster_name").unwrap();
+        let m = HashMap::new();
+        let slot_ranges = gen_testing_migration_slot_ranges(true);
+        let output = gen_cluster_shards_helper(&cluster_name, &slot_ranges, &m).unwrap();
+        assert_eq!(output.len(), 1);
+        match &output[0] {
+            Resp::Arr(Array::Arr(shard)) => {
+                assert_eq!(shard[1], Resp::Arr(Array::Arr(vec![])));
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn test_ready_check() {
         assert!(is_ready(&gen_testing_slot_ranges("127.0.0.1:5299")));
@@ -1032,4 +1711,119 @@ mod tests {
     fn test_default_cluster_length() {
         ClusterName::try_from(DEFAULT_CLUSTER).unwrap();
     }
+
+    struct DummyNodeSender {
+        failing: bool,
+        sent: Arc<AtomicUsize>,
+    }
+
+    impl CmdTaskSender for DummyNodeSender {
+        type Task = CmdCtx;
+
+        fn send(&self, cmd_task: Self::Task) -> Result<(), SenderBackendError<Self::Task>> {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            if self.failing {
+                cmd_task.set_resp_result(Ok(Resp::Error(b"unreachable".to_vec())));
+                return Err(SenderBackendError::Canceled);
+            }
+            cmd_task.set_resp_result(Ok(Resp::Simple(b"OK".to_vec())));
+            Ok(())
+        }
+
+        fn is_failing(&self) -> bool {
+            self.failing
+        }
+    }
+
+    // The first `failing_num` created senders are failing.
+    struct DummyNodeSenderFactory {
+        failing_num: AtomicUsize,
+        sent: Arc<AtomicUsize>,
+    }
+
+    impl CmdTaskSenderFactory for DummyNodeSenderFactory {
+        type Sender = DummyNodeSender;
+
+        fn create(&self, _address: String) -> Self::Sender {
+            let failing = self
+                .failing_num
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            DummyNodeSender {
+                failing,
+                sent: self.sent.clone(),
+            }
+        }
+    }
+
+    fn gen_local_cluster(
+        group_size: usize,
+        failing_num: usize,
+    ) -> (
+        LocalCluster<RoundRobinSenderGroup<DummyNodeSender>>,
+        Arc<AtomicUsize>,
+    ) {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let factory = RoundRobinSenderGroupFactory::new(
+            NonZeroUsize::new(group_size).unwrap(),
+            DummyNodeSenderFactory {
+                failing_num: AtomicUsize::new(failing_num),
+                sent: sent.clone(),
+            },
+        );
+        let mut slot_ranges = HashMap::new();
+        slot_ranges.insert(
+            "127.0.0.1:6379".to_string(),
+            vec![SlotRange {
+                range_list: RangeList::try_from("1 0-16383").unwrap(),
+                tag: SlotRangeTag::None,
+            }],
+        );
+        let local_cluster = LocalCluster::from_slot_map(
+            &factory,
+            ClusterName::try_from("test_cluster").unwrap(),
+            1,
+            slot_ranges,
+            ClusterConfig::default(),
+        );
+        (local_cluster, sent)
+    }
+
+    fn gen_get_cmd_ctx() -> (CmdCtx, CmdReplyReceiver) {
+        let resp = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"GET".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"key".to_vec())),
+        ]));
+        let cmd = Command::new(Box::new(RespPacket::from_resp_vec(resp)));
+        let (reply_sender, reply_receiver) = new_command_pair(&cmd);
+        (CmdCtx::new(cmd, reply_sender, 0, false), reply_receiver)
+    }
+
+    #[tokio::test]
+    async fn test_slot_with_no_reachable_node() {
+        let (local_cluster, sent) = gen_local_cluster(3, 3);
+        let (cmd_ctx, reply_receiver) = gen_get_cmd_ctx();
+        let slot = cmd_ctx.get_slot().unwrap();
+        match local_cluster.send(cmd_ctx) {
+            Err(ClusterSendError::NoReachableNode(s)) => assert_eq!(s, slot),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+        let (_, packet, _) = reply_receiver.await.unwrap().into_inner();
+        let expected = format!("CLUSTERDOWN slot {} has no reachable node", slot);
+        assert_eq!(packet.to_resp_vec(), Resp::Error(expected.into_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_skip_failing_sender_in_group() {
+        let (local_cluster, sent) = gen_local_cluster(3, 2);
+        for _ in 0..3 {
+            let (cmd_ctx, reply_receiver) = gen_get_cmd_ctx();
+            local_cluster.send(cmd_ctx).unwrap();
+            let (_, packet, _) = reply_receiver.await.unwrap().into_inner();
+            assert_eq!(packet.to_resp_vec(), Resp::Simple(b"OK".to_vec()));
+        }
+        assert_eq!(sent.load(Ordering::SeqCst), 3);
+    }
 }
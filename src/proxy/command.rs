@@ -35,6 +35,14 @@ pub enum CmdType {
     Command,
     Asking,
     Hello,
+    Lolwut,
+    Time,
+    LastSave,
+    Role,
+    Client,
+    Debug,
+    Watch,
+    Unwatch,
 }
 
 impl CmdType {
@@ -64,14 +72,22 @@ impl CmdType {
             b"COMMAND" => CmdType::Command,
             b"ASKING" => CmdType::Asking,
             b"HELLO" => CmdType::Hello,
+            b"LOLWUT" => CmdType::Lolwut,
+            b"TIME" => CmdType::Time,
+            b"LASTSAVE" => CmdType::LastSave,
+            b"ROLE" => CmdType::Role,
+            b"CLIENT" => CmdType::Client,
+            b"DEBUG" => CmdType::Debug,
+            b"WATCH" => CmdType::Watch,
+            b"UNWATCH" => CmdType::Unwatch,
             _ => CmdType::Others,
         }
     }
 
     pub fn from_packet(packet: &RespPacket) -> Self {
         let cmd_name = match packet.get_array_element(0) {
-            Some(cmd_name) => cmd_name,
-            None => return CmdType::Invalid,
+            Some(cmd_name) if !cmd_name.is_empty() => cmd_name,
+            _ => return CmdType::Invalid,
         };
 
         CmdType::from_cmd_name(cmd_name)
@@ -92,6 +108,8 @@ pub enum DataCmdType {
     Getbit,
     Getrange,
     Getset,
+    Getdel,
+    Getex,
     Incr,
     Incrby,
     Incrbyfloat,
@@ -118,12 +136,14 @@ pub enum DataCmdType {
     Rpoplpush,
     Lrem,
     Ltrim,
+    Lmpop,
     // Hash commands
     Hdel,
     // Set commands
     Smove,
     Spop,
     Srem,
+    Sintercard,
     // Sorted Set commands
     Zpopmax,
     Zpopmin,
@@ -133,6 +153,8 @@ pub enum DataCmdType {
     Zremrangebyscore,
     Bzpopmin,
     Bzpopmax,
+    Zmpop,
+    Zintercard,
     // Key commands
     Expire,
     Expireat,
@@ -142,11 +164,15 @@ pub enum DataCmdType {
     Rename,
     Renamenx,
     Unlink,
+    Touch,
+    Object,
+    Copy,
+    Memory,
     Others,
 }
 
 impl DataCmdType {
-    fn is_blocking_cmd(self) -> bool {
+    pub fn is_blocking_cmd(self) -> bool {
         matches!(
             self,
             Self::Bzpopmin | Self::Bzpopmax | Self::Blpop | Self::Brpop | Self::Brpoplpush
@@ -178,6 +204,8 @@ impl DataCmdType {
             b"GETBIT" => DataCmdType::Getbit,
             b"GETRANGE" => DataCmdType::Getrange,
             b"GETSET" => DataCmdType::Getset,
+            b"GETDEL" => DataCmdType::Getdel,
+            b"GETEX" => DataCmdType::Getex,
             b"INCR" => DataCmdType::Incr,
             b"INCRBY" => DataCmdType::Incrby,
             b"INCRBYFLOAT" => DataCmdType::Incrbyfloat,
@@ -208,13 +236,19 @@ impl DataCmdType {
             b"RPOPLPUSH" => DataCmdType::Rpoplpush,
             b"LREM" => DataCmdType::Lrem,
             b"LTRIM" => DataCmdType::Ltrim,
+            b"LMPOP" => DataCmdType::Lmpop,
             b"MOVE" => DataCmdType::Move,
             b"RENAME" => DataCmdType::Rename,
             b"RENAMENX" => DataCmdType::Renamenx,
             b"SMOVE" => DataCmdType::Smove,
             b"SPOP" => DataCmdType::Spop,
             b"SREM" => DataCmdType::Srem,
+            b"SINTERCARD" => DataCmdType::Sintercard,
             b"UNLINK" => DataCmdType::Unlink,
+            b"TOUCH" => DataCmdType::Touch,
+            b"OBJECT" => DataCmdType::Object,
+            b"COPY" => DataCmdType::Copy,
+            b"MEMORY" => DataCmdType::Memory,
             b"ZPOPMAX" => DataCmdType::Zpopmax,
             b"ZPOPMIN" => DataCmdType::Zpopmin,
             b"BZPOPMAX" => DataCmdType::Bzpopmax,
@@ -223,6 +257,8 @@ impl DataCmdType {
             b"ZREMRANGEBYLEX" => DataCmdType::Zremrangebylex,
             b"ZREMRANGEBYRANK" => DataCmdType::Zremrangebyrank,
             b"ZREMRANGEBYSCORE" => DataCmdType::Zremrangebyscore,
+            b"ZMPOP" => DataCmdType::Zmpop,
+            b"ZINTERCARD" => DataCmdType::Zintercard,
             _ => DataCmdType::Others,
         }
     }
@@ -248,12 +284,15 @@ pub fn requires_blocking_migration(data_cmd_type: DataCmdType) -> bool {
             | DataCmdType::Evalsha
             | DataCmdType::Expire
             | DataCmdType::Expireat
+            | DataCmdType::Getdel
+            | DataCmdType::Getex
             | DataCmdType::Hdel
             | DataCmdType::Lpop
             | DataCmdType::Rpop
             | DataCmdType::Rpoplpush
             | DataCmdType::Lrem
             | DataCmdType::Ltrim
+            | DataCmdType::Lmpop
             | DataCmdType::Move
             | DataCmdType::Pexpire
             | DataCmdType::Pexpireat
@@ -269,6 +308,7 @@ pub fn requires_blocking_migration(data_cmd_type: DataCmdType) -> bool {
             | DataCmdType::Zremrangebylex
             | DataCmdType::Zremrangebyrank
             | DataCmdType::Zremrangebyscore
+            | DataCmdType::Zmpop
     )
 }
 
@@ -294,6 +334,14 @@ impl CommandInfo {
     fn get_key(data_cmd_type: DataCmdType, packet: &RespPacket) -> Option<&[u8]> {
         match data_cmd_type {
             DataCmdType::Eval | DataCmdType::Evalsha => packet.get_array_element(3),
+            // OBJECT <subcommand> <key>
+            // MEMORY USAGE <key>
+            DataCmdType::Object | DataCmdType::Memory => packet.get_array_element(2),
+            // <command> <numkeys> <key> [key ...] [options]
+            DataCmdType::Sintercard
+            | DataCmdType::Zintercard
+            | DataCmdType::Lmpop
+            | DataCmdType::Zmpop => packet.get_array_element(2),
             _ => packet.get_array_element(1),
         }
     }
@@ -348,10 +396,16 @@ impl Command {
         self.request.get_command_name()
     }
 
+    // The slot is not recomputed so that the keys could be changed
+    // without affecting the routing. See `refresh_info`.
     pub fn change_element(&mut self, index: usize, data: Vec<u8>) -> bool {
         self.request.change_bulk_array_element(index, data)
     }
 
+    pub fn refresh_info(&mut self) {
+        self.info = CommandInfo::new(&self.request);
+    }
+
     pub fn extract_inner_cmd(&mut self, removed_num: usize) -> Option<usize> {
         let remaining = self.request.left_trim_cmd(removed_num)?;
         self.info = CommandInfo::new(&self.request);
@@ -499,6 +553,7 @@ pub enum CommandError {
     Dropped,
     Canceled,
     InnerError,
+    Timeout,
 }
 
 impl Clone for CommandError {
@@ -512,6 +567,7 @@ impl Clone for CommandError {
             Self::Dropped => Self::Dropped,
             Self::Canceled => Self::Canceled,
             Self::InnerError => Self::InnerError,
+            Self::Timeout => Self::Timeout,
         }
     }
 }
@@ -544,6 +600,30 @@ mod tests {
     fn test_parse_cmd_type() {
         assert_eq!(CmdType::from_cmd_name(b"pInG"), CmdType::Ping);
         assert_eq!(CmdType::from_cmd_name(b"get"), CmdType::Others);
+        assert_eq!(CmdType::from_cmd_name(b"lolwut"), CmdType::Lolwut);
+        assert_eq!(CmdType::from_cmd_name(b"TIME"), CmdType::Time);
+        assert_eq!(CmdType::from_cmd_name(b"LastSave"), CmdType::LastSave);
+        assert_eq!(CmdType::from_cmd_name(b"role"), CmdType::Role);
+        assert_eq!(CmdType::from_cmd_name(b"client"), CmdType::Client);
+        assert_eq!(CmdType::from_cmd_name(b"debug"), CmdType::Debug);
+        assert_eq!(CmdType::from_cmd_name(b"watch"), CmdType::Watch);
+        assert_eq!(CmdType::from_cmd_name(b"UnWatch"), CmdType::Unwatch);
+    }
+
+    #[test]
+    fn test_empty_cmd_type() {
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![])));
+        let cmd = Command::new(Box::new(request));
+        assert_eq!(cmd.get_type(), CmdType::Invalid);
+        assert_eq!(cmd.get_key(), None);
+        assert_eq!(cmd.get_slot(), None);
+
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+            vec![],
+        ))])));
+        let cmd = Command::new(Box::new(request));
+        assert_eq!(cmd.get_type(), CmdType::Invalid);
+        assert_eq!(cmd.get_key(), None);
     }
 
     #[test]
@@ -554,6 +634,148 @@ mod tests {
         assert_eq!(DataCmdType::from_cmd_name(b"HMGET"), DataCmdType::Others);
     }
 
+    #[test]
+    fn test_object_key() {
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"OBJECT".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"ENCODING".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"somekey".to_vec())),
+        ])));
+        let cmd = Command::new(Box::new(request));
+        assert_eq!(cmd.get_type(), CmdType::Others);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Object);
+        assert_eq!(cmd.get_key(), Some(b"somekey".as_ref()));
+        assert_eq!(cmd.get_slot(), Some(generate_slot(b"somekey")));
+    }
+
+    #[test]
+    fn test_memory_usage_key() {
+        let cmd = new_cmd(&[b"MEMORY", b"USAGE", b"somekey", b"SAMPLES", b"5"]);
+        assert_eq!(cmd.get_type(), CmdType::Others);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Memory);
+        assert_eq!(cmd.get_key(), Some(b"somekey".as_ref()));
+        assert_eq!(cmd.get_slot(), Some(generate_slot(b"somekey")));
+
+        let cmd = new_cmd(&[b"memory", b"doctor"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Memory);
+        assert_eq!(cmd.get_key(), None);
+    }
+
+    fn new_cmd(args: &[&[u8]]) -> Command {
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(
+            args.iter()
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.to_vec())))
+                .collect(),
+        )));
+        Command::new(Box::new(request))
+    }
+
+    #[test]
+    fn test_single_key_commands() {
+        let cmd = new_cmd(&[b"GETDEL", b"somekey"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Getdel);
+        assert_eq!(cmd.get_key(), Some(b"somekey".as_ref()));
+        assert!(requires_blocking_migration(cmd.get_data_cmd_type()));
+
+        let cmd = new_cmd(&[b"getex", b"somekey", b"PERSIST"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Getex);
+        assert_eq!(cmd.get_key(), Some(b"somekey".as_ref()));
+        assert!(requires_blocking_migration(cmd.get_data_cmd_type()));
+
+        let cmd = new_cmd(&[b"COPY", b"{tag}src", b"{tag}dst", b"REPLACE"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Copy);
+        assert_eq!(cmd.get_key(), Some(b"{tag}src".as_ref()));
+        assert_eq!(cmd.get_slot(), Some(generate_slot(b"{tag}src")));
+    }
+
+    #[test]
+    fn test_expire_commands() {
+        for (args, data_cmd_type) in &[
+            (
+                &[b"EXPIRE".as_ref(), b"somekey", b"10"][..],
+                DataCmdType::Expire,
+            ),
+            (
+                &[b"pexpire", b"somekey", b"10000", b"NX"],
+                DataCmdType::Pexpire,
+            ),
+            (
+                &[b"EXPIREAT", b"somekey", b"1700000000"],
+                DataCmdType::Expireat,
+            ),
+            (
+                &[b"PEXPIREAT", b"somekey", b"1700000000000"],
+                DataCmdType::Pexpireat,
+            ),
+        ] {
+            let cmd = new_cmd(args);
+            assert_eq!(cmd.get_data_cmd_type(), *data_cmd_type);
+            assert_eq!(cmd.get_key(), Some(b"somekey".as_ref()));
+            assert_eq!(cmd.get_slot(), Some(generate_slot(b"somekey")));
+            // The key could be deleted when the time is in the past.
+            assert!(requires_blocking_migration(cmd.get_data_cmd_type()));
+        }
+
+        for args in &[
+            [b"PERSIST".as_ref(), b"somekey"],
+            [b"TTL", b"somekey"],
+            [b"pttl", b"somekey"],
+        ] {
+            let cmd = new_cmd(args);
+            assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Others);
+            assert_eq!(cmd.get_key(), Some(b"somekey".as_ref()));
+            assert_eq!(cmd.get_slot(), Some(generate_slot(b"somekey")));
+            assert!(!requires_blocking_migration(cmd.get_data_cmd_type()));
+        }
+
+        let cmd = new_cmd(&[b"TTL"]);
+        assert_eq!(cmd.get_key(), None);
+        assert_eq!(cmd.get_slot(), None);
+    }
+
+    #[test]
+    fn test_numkeys_commands() {
+        let cmd = new_cmd(&[b"SINTERCARD", b"2", b"key1", b"key2", b"LIMIT", b"1"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Sintercard);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+        assert_eq!(cmd.get_slot(), Some(generate_slot(b"key1")));
+
+        let cmd = new_cmd(&[b"LMPOP", b"1", b"key1", b"LEFT", b"COUNT", b"2"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Lmpop);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+        assert!(requires_blocking_migration(cmd.get_data_cmd_type()));
+
+        let cmd = new_cmd(&[b"zmpop", b"2", b"key1", b"key2", b"MIN"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Zmpop);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+        assert!(requires_blocking_migration(cmd.get_data_cmd_type()));
+
+        let cmd = new_cmd(&[b"ZINTERCARD", b"2", b"key1", b"key2", b"LIMIT", b"1"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Zintercard);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+        assert!(!requires_blocking_migration(cmd.get_data_cmd_type()));
+
+        // Missing keys
+        let cmd = new_cmd(&[b"ZMPOP", b"1"]);
+        assert_eq!(cmd.get_key(), None);
+        assert_eq!(cmd.get_slot(), None);
+    }
+
+    #[test]
+    fn test_option_tail_commands() {
+        let cmd = new_cmd(&[b"LPOS", b"key1", b"elem", b"RANK", b"-1", b"COUNT", b"2"]);
+        assert_eq!(cmd.get_data_cmd_type(), DataCmdType::Others);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+        assert_eq!(cmd.get_slot(), Some(generate_slot(b"key1")));
+
+        let cmd = new_cmd(&[b"OBJECT", b"FREQ", b"key1"]);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+
+        let cmd = new_cmd(&[b"sintercard", b"1", b"key1", b"LIMIT", b"5"]);
+        assert_eq!(cmd.get_key(), Some(b"key1".as_ref()));
+        assert_eq!(cmd.get_slot(), Some(generate_slot(b"key1")));
+    }
+
     #[test]
     fn test_umforward() {
         let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![
@@ -135,7 +135,7 @@ impl<C: CompressionStrategyConfig> CmdReplyDecompressor<C> {
 
         let data_cmd_type = cmd_ctx.get_data_cmd_type();
         match data_cmd_type {
-            DataCmdType::Get | DataCmdType::Getset => {
+            DataCmdType::Get | DataCmdType::Getset | DataCmdType::Getdel | DataCmdType::Getex => {
                 let compressed = if let Resp::Bulk(BulkStr::Str(s)) = packet.to_resp_slice() {
                     let compressed = match zstd::decode_all(s) {
                         Ok(c) => c,
@@ -1,13 +1,20 @@
-use super::backend::{CmdTask, CmdTaskFactory, ConnFactory};
+use super::backend::{CmdTask, CmdTaskFactory, CmdTaskResult, ConnFactory};
 use super::cluster::ClusterMetaError;
-use super::command::{CmdReplyReceiver, CmdType, DataCmdType, TaskResult};
+use super::command::{CmdReplyReceiver, CmdType, Command, DataCmdType, TaskResult};
 use super::compress::{CmdCompressor, CompressionError, CompressionStrategyMetaMapConfig};
-use super::manager::{MetaManager, SharedMetaMap};
-use super::service::ServerProxyConfig;
-use super::session::{CmdCtx, CmdCtxFactory, CmdCtxHandler, CmdReplyFuture};
+use super::fanout::{gen_scan_reply, merge_replies, split_scan_cursor};
+use super::manager::{MetaManager, SharedMetaMap, SlotOwnershipEvent};
+use super::meta_queue::{MetaApplyQueue, MetaApplyStatus};
+use super::pause::{ClientPause, ClientPauseMode};
+use super::prefix::KeyPrefixError;
+use super::rename::CommandLookup;
+use super::service::{SelectPolicy, ServerProxyConfig, UnknownCommandPolicy};
+use super::session::{AuthState, ClientInfo, CmdCtx, CmdCtxFactory, CmdCtxHandler, CmdReplyFuture};
 use super::slowlog::{slowlogs_to_resp, SlowRequestLogger};
-use super::table::CommandTable;
-use crate::common::proto::ProxyClusterMeta;
+use super::stats::{DbConnCounter, ProxyStats};
+use super::table::{CommandTable, NoKeyRouting};
+use crate::common::cluster::ClusterName;
+use crate::common::proto::{ParseExtendedMetaError, ProxyClusterMeta};
 use crate::common::response;
 use crate::common::track::TrackedFutureRegistry;
 use crate::common::utils::{
@@ -16,22 +23,27 @@ use crate::common::utils::{
 };
 use crate::common::version::UNDERMOON_VERSION;
 use crate::migration::manager::SwitchError;
-use crate::migration::task::parse_switch_command;
 use crate::migration::task::MgrSubCmd;
+use crate::migration::task::{parse_mgr_status_command, parse_switch_command};
 use crate::protocol::{
     Array, BulkStr, RFunctor, RedisClientFactory, Resp, RespPacket, RespVec, VFunctor,
 };
 use crate::replication::replicator::ReplicatorMeta;
 use atoi::atoi;
-use btoi::btou;
+use btoi::{btoi, btou};
 use futures::channel::mpsc;
-use futures::future;
-use std::collections::HashMap;
+use futures::{future, Future, Stream};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::hash::BuildHasher;
 use std::str;
-use std::sync::{self, atomic, Arc};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{self, Arc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 type NonBlockingCommandsWithKey = Vec<(Vec<u8>, RespVec)>;
+type ExtendedMetaResult = Result<(), ParseExtendedMetaError>;
 
 pub struct SharedForwardHandler<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> {
     handler: sync::Arc<ForwardHandler<F, C>>,
@@ -63,6 +75,8 @@ where
         conn_factory: Arc<C>,
         future_registry: Arc<TrackedFutureRegistry>,
         stopped: mpsc::UnboundedSender<()>,
+        stats: Arc<ProxyStats>,
+        db_conn_counter: Arc<DbConnCounter>,
     ) -> Self {
         Self {
             handler: sync::Arc::new(ForwardHandler::new(
@@ -73,9 +87,21 @@ where
                 conn_factory,
                 future_registry,
                 stopped,
+                stats,
+                db_conn_counter,
             )),
         }
     }
+
+    pub fn from_handler(handler: ForwardHandler<F, C>) -> Self {
+        Self {
+            handler: sync::Arc::new(handler),
+        }
+    }
+
+    pub fn subscribe_slot_changes(&self) -> impl Stream<Item = SlotOwnershipEvent> {
+        self.handler.subscribe_slot_changes()
+    }
 }
 
 impl<F, C> CmdCtxHandler for SharedForwardHandler<F, C>
@@ -87,21 +113,46 @@ where
         &self,
         cmd_ctx: CmdCtx,
         reply_receiver: CmdReplyReceiver,
-        authenticated: &atomic::AtomicBool,
-    ) -> CmdReplyFuture {
+        auth_state: &AuthState,
+        client_info: &ClientInfo,
+    ) -> CmdReplyFuture<'_> {
         self.handler
-            .handle_cmd_ctx(cmd_ctx, reply_receiver, authenticated)
+            .handle_cmd_ctx(cmd_ctx, reply_receiver, auth_state, client_info)
+    }
+
+    fn get_db_name(&self) -> String {
+        self.handler.get_db_name()
+    }
+
+    fn get_pause_remaining(&self) -> Duration {
+        self.handler.get_pause_remaining()
     }
 }
 
 pub struct ForwardHandler<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> {
     config: Arc<ServerProxyConfig>,
-    manager: MetaManager<F, C>,
+    manager: Arc<MetaManager<F, C>>,
     slow_request_logger: Arc<SlowRequestLogger>,
     compressor: CmdCompressor<CompressionStrategyMetaMapConfig<C>>,
     future_registry: Arc<TrackedFutureRegistry>,
     stopped: mpsc::UnboundedSender<()>,
     command_table: Arc<CommandTable>,
+    stats: Arc<ProxyStats>,
+    db_conn_counter: Arc<DbConnCounter>,
+    // Set by `UMCTL DBMODE`. Not persisted.
+    readonly_dbs: parking_lot::RwLock<HashSet<String>>,
+    // Set by `UMCTL MAINTENANCE`. Not persisted.
+    maintenance: AtomicBool,
+    // The epoch and the time of the last successful `UMCTL SETCLUSTER` and `UMCTL SETREPL`.
+    last_setcluster: Arc<parking_lot::Mutex<Option<(u64, Instant)>>>,
+    set_cluster_queue: Arc<MetaApplyQueue<(ProxyClusterMeta, ExtendedMetaResult)>>,
+    last_setrepl: parking_lot::Mutex<Option<(u64, Instant)>>,
+    client_pause: ClientPause,
+    unknown_command_policy: UnknownCommandPolicy,
+    // In seconds. Used as the synthesized LASTSAVE.
+    start_time: u64,
+    // In microseconds. Used to keep TIME from going backward.
+    last_time: AtomicU64,
 }
 
 impl<F, C> ForwardHandler<F, C>
@@ -118,23 +169,67 @@ where
         conn_factory: Arc<C>,
         future_registry: Arc<TrackedFutureRegistry>,
         stopped: mpsc::UnboundedSender<()>,
+        stats: Arc<ProxyStats>,
+        db_conn_counter: Arc<DbConnCounter>,
+    ) -> Self {
+        let manager = MetaManager::new(
+            config.clone(),
+            client_factory,
+            conn_factory,
+            meta_map,
+            future_registry.clone(),
+        );
+        Self::with_manager(
+            config,
+            manager,
+            slow_request_logger,
+            future_registry,
+            stopped,
+            stats,
+            db_conn_counter,
+        )
+    }
+
+    // Takes a pre-built `MetaManager` along with its replicator and migration managers
+    // so that the tests could set up the metadata and the backends beforehand.
+    pub fn with_manager(
+        config: Arc<ServerProxyConfig>,
+        manager: MetaManager<F, C>,
+        slow_request_logger: Arc<SlowRequestLogger>,
+        future_registry: Arc<TrackedFutureRegistry>,
+        stopped: mpsc::UnboundedSender<()>,
+        stats: Arc<ProxyStats>,
+        db_conn_counter: Arc<DbConnCounter>,
     ) -> Self {
+        let unknown_command_policy = config.unknown_command_policy;
+        let meta_map = manager.get_meta_map().clone();
         Self {
-            config: config.clone(),
-            manager: MetaManager::new(
-                config,
-                client_factory,
-                conn_factory,
-                meta_map.clone(),
-                future_registry.clone(),
-            ),
+            config,
+            manager: Arc::new(manager),
             slow_request_logger,
             compressor: CmdCompressor::new(CompressionStrategyMetaMapConfig::new(meta_map)),
             future_registry,
             stopped,
             command_table: Arc::new(CommandTable::default()),
+            stats,
+            db_conn_counter,
+            readonly_dbs: parking_lot::RwLock::new(HashSet::new()),
+            maintenance: AtomicBool::new(false),
+            last_setcluster: Arc::new(parking_lot::Mutex::new(None)),
+            set_cluster_queue: Arc::new(MetaApplyQueue::default()),
+            last_setrepl: parking_lot::Mutex::new(None),
+            client_pause: ClientPause::default(),
+            unknown_command_policy,
+            start_time: unix_time_now().as_secs(),
+            last_time: AtomicU64::new(0),
         }
     }
+
+    // For the components such as caches and metrics to follow the changes of the local slots
+    // set by `UMCTL SETCLUSTER` without recomputing them.
+    pub fn subscribe_slot_changes(&self) -> impl Stream<Item = SlotOwnershipEvent> {
+        self.manager.subscribe_slot_changes()
+    }
 }
 
 impl<F, C> ForwardHandler<F, C>
@@ -142,17 +237,325 @@ where
     F: RedisClientFactory,
     C: ConnFactory<Pkt = RespPacket>,
 {
-    fn handle_info(&self, cmd_ctx: CmdCtx) {
+    async fn handle_info(&self, cmd_ctx: CmdCtx, reply_receiver: CmdReplyReceiver) -> TaskResult {
         let flush_size = self.manager.get_batch_stats().get_flush_size();
         let flush_interval = self.manager.get_batch_stats().get_flush_interval();
+        let command_stats = self.manager.get_command_stats();
         let content = format!(
-            "version:{}\r\n\r\n# Stats\r\nflush_size:{}\r\nflush_interval:{}\r\n",
-            UNDERMOON_VERSION, flush_size, flush_interval,
+            concat!(
+                "# Server\r\nredis_version:{}\r\nversion:{}\r\nmaintenance:{}\r\n\r\n",
+                "# Clients\r\nconnected_clients:{}\r\nmaxclients:{}\r\n\r\n",
+                "# Stats\r\nflush_size:{}\r\nflush_interval:{}\r\n",
+                "total_commands_processed:{}\r\ninstantaneous_ops_per_sec:{}\r\n",
+                "keyspace_hits:{}\r\nkeyspace_misses:{}\r\n",
+                "backend_moved_replies:{}\r\nbackend_moved_storms:{}\r\n",
+            ),
+            self.config.redis_version,
+            UNDERMOON_VERSION,
+            self.maintenance.load(Ordering::Relaxed) as u8,
+            self.db_conn_counter.get_total(),
+            self.config.get_maxclients(),
+            flush_size,
+            flush_interval,
+            command_stats.get_total_commands(),
+            command_stats.get_ops_per_sec(),
+            command_stats.get_keyspace_hits(),
+            command_stats.get_keyspace_misses(),
+            command_stats.get_moved_replies(),
+            command_stats.get_moved_storms(),
         );
+
+        let mut content = content + "\r\n# Config\r\n";
+        for (field, value) in self.get_last_meta_fields().into_iter() {
+            content += &format!("{}:{}\r\n", field, value);
+        }
+
+        content += "\r\n# Replication\r\n";
+        content += &self.manager.get_info_replication();
+
+        // Like Redis, the databases without any key are not listed.
+        // The backends also have the keys of the other key prefixes,
+        // so the key number is not available with `key_prefix`.
+        content += "\r\n# Keyspace\r\n";
+        if !self.manager.has_key_prefix() {
+            match self.manager.get_dbsize().await {
+                Ok(0) => (),
+                Ok(dbsize) => content += &format!("db0:keys={}\r\n", dbsize),
+                Err(err) => warn!(
+                    "failed to get keyspace for INFO db={} error={}",
+                    self.get_db_name(),
+                    err
+                ),
+            }
+        }
+        cmd_ctx.set_resp_result(Ok(Resp::Bulk(BulkStr::Str(content.into_bytes()))));
+        reply_receiver.await
+    }
+
+    fn handle_lolwut(&self, cmd_ctx: CmdCtx) {
+        let content = format!("undermoon ver. {}\n", UNDERMOON_VERSION);
         cmd_ctx.set_resp_result(Ok(Resp::Bulk(BulkStr::Str(content.into_bytes()))));
     }
 
-    fn handle_auth(&self, cmd_ctx: CmdCtx, authenticated: &atomic::AtomicBool) {
+    fn handle_time(&self, cmd_ctx: CmdCtx) {
+        // The wall clock could go backward after being adjusted.
+        let now = unix_time_now().as_micros() as u64;
+        let last = self.last_time.fetch_max(now, Ordering::Relaxed);
+        let time = std::cmp::max(now, last);
+        let secs = (time / 1_000_000).to_string();
+        let micros = (time % 1_000_000).to_string();
+        let resp = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(secs.into_bytes())),
+            Resp::Bulk(BulkStr::Str(micros.into_bytes())),
+        ]));
+        cmd_ctx.set_resp_result(Ok(resp));
+    }
+
+    fn handle_lastsave(&self, cmd_ctx: CmdCtx) {
+        // Server proxy never saves anything. Just use the start time.
+        cmd_ctx.set_resp_result(Ok(Resp::Integer(self.start_time.to_string().into_bytes())));
+    }
+
+    // WATCH is accepted without checking whether the keys are modified.
+    // The keys still need to be in the same slot like the other multi-key commands.
+    // MULTI and EXEC are not supported yet, so there is no transaction
+    // to pin to the slot of the watched keys.
+    fn handle_watch(&self, cmd_ctx: CmdCtx) {
+        let arg_len = cmd_ctx.get_cmd().get_command_len().unwrap_or(0);
+        if arg_len < 2 {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                b"ERR wrong number of arguments for 'watch' command".to_vec(),
+            )));
+            return;
+        }
+
+        let in_same_slot =
+            same_slot((1..arg_len).filter_map(|i| cmd_ctx.get_cmd().get_command_element(i)));
+        let resp = if in_same_slot {
+            Resp::Simple(response::OK_REPLY.to_string().into_bytes())
+        } else {
+            Resp::Error(response::ERR_NOT_THE_SAME_SLOT.to_string().into_bytes())
+        };
+        cmd_ctx.set_resp_result(Ok(resp));
+    }
+
+    fn handle_debug(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+    ) -> CmdReplyFuture<'_> {
+        if !self.config.enable_debug {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                String::from("ERR DEBUG is disabled").into_bytes(),
+            )));
+            return CmdReplyFuture::Left(reply_receiver);
+        }
+
+        let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 1) {
+            Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd),
+            None => return CmdReplyFuture::Left(reply_receiver),
+        };
+
+        if !sub_cmd.eq_ignore_ascii_case("SLEEP") {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                String::from("ERR only DEBUG SLEEP is supported").into_bytes(),
+            )));
+            return CmdReplyFuture::Left(reply_receiver);
+        }
+
+        // Same as Redis, the seconds could be a float.
+        let duration = cmd_ctx
+            .get_cmd()
+            .get_command_element(2)
+            .and_then(|secs| str::from_utf8(secs).ok())
+            .and_then(|secs| secs.parse::<f64>().ok())
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+        let duration = match duration {
+            Some(duration) => duration,
+            None => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    String::from("ERR invalid sleep seconds").into_bytes(),
+                )));
+                return CmdReplyFuture::Left(reply_receiver);
+            }
+        };
+
+        // Only this session waits for the reply.
+        CmdReplyFuture::Right(Box::pin(async move {
+            tokio::time::sleep(duration).await;
+            cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                response::OK_REPLY.to_string().into_bytes(),
+            )));
+            reply_receiver.await
+        }))
+    }
+
+    fn handle_client(&self, cmd_ctx: CmdCtx, auth_state: &AuthState, client_info: &ClientInfo) {
+        let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 1) {
+            Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd),
+            None => return,
+        };
+
+        let sub_cmd = sub_cmd.to_uppercase();
+
+        if sub_cmd.eq("INFO") {
+            // Only db 0 is supported.
+            let line = client_info.to_info_line(0);
+            cmd_ctx.set_resp_result(Ok(Resp::Bulk(BulkStr::Str(line.into_bytes()))));
+        } else if sub_cmd.eq("ID") {
+            let id = client_info.get_id().to_string().into_bytes();
+            cmd_ctx.set_resp_result(Ok(Resp::Integer(id)));
+        } else if sub_cmd.eq("GETNAME") {
+            let name = client_info.get_name();
+            let reply = if name.is_empty() {
+                BulkStr::Nil
+            } else {
+                BulkStr::Str(name.into_bytes())
+            };
+            cmd_ctx.set_resp_result(Ok(Resp::Bulk(reply)));
+        } else if sub_cmd.eq("SETNAME") {
+            let name = match cmd_ctx.get_cmd().get_command_element(2) {
+                Some(name) => name,
+                None => {
+                    cmd_ctx.set_resp_result(Ok(Resp::Error(b"Missing client name".to_vec())));
+                    return;
+                }
+            };
+            // Same as Redis, the name is shown in CLIENT INFO without escaping.
+            if name.iter().any(|b| *b < b'!' || *b > b'~') {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    response::ERR_INVALID_CLIENT_NAME.to_string().into_bytes(),
+                )));
+                return;
+            }
+            client_info.set_name(String::from_utf8_lossy(name).to_string());
+            cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                response::OK_REPLY.to_string().into_bytes(),
+            )));
+        } else if sub_cmd.eq("NO-EVICT") || sub_cmd.eq("NO-TOUCH") {
+            // The proxy neither evicts the clients nor tracks the access time of the keys,
+            // so they are accepted only for the clients sending them on connecting.
+            let valid = cmd_ctx
+                .get_cmd()
+                .get_command_element(2)
+                .map(|arg| arg.eq_ignore_ascii_case(b"ON") || arg.eq_ignore_ascii_case(b"OFF"))
+                .unwrap_or(false);
+            if !valid {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(b"ERR syntax error".to_vec())));
+                return;
+            }
+            cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                response::OK_REPLY.to_string().into_bytes(),
+            )));
+        } else if sub_cmd.eq("PAUSE") {
+            self.handle_client_pause(cmd_ctx, auth_state);
+        } else if sub_cmd.eq("UNPAUSE") {
+            if let Some(err) = self.check_client_pause_permission(auth_state) {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(err.as_bytes().to_vec())));
+                return;
+            }
+            info!("unpause clients");
+            self.client_pause.unpause();
+            cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                response::OK_REPLY.to_string().into_bytes(),
+            )));
+        } else {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                String::from("Invalid sub command").into_bytes(),
+            )));
+        }
+    }
+
+    // Pausing affects all the sessions so it always requires the authentication.
+    fn check_client_pause_permission(&self, auth_state: &AuthState) -> Option<&'static str> {
+        if self.data_auth_required(auth_state) || self.admin_auth_required(auth_state) {
+            Some(response::ERR_NOAUTH)
+        } else {
+            None
+        }
+    }
+
+    // CLIENT PAUSE <ms> [WRITE|ALL]
+    fn handle_client_pause(&self, cmd_ctx: CmdCtx, auth_state: &AuthState) {
+        if let Some(err) = self.check_client_pause_permission(auth_state) {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err.as_bytes().to_vec())));
+            return;
+        }
+
+        let timeout = match cmd_ctx
+            .get_cmd()
+            .get_command_element(2)
+            .and_then(|arg| btou::<u64>(arg).ok())
+        {
+            Some(timeout) => timeout,
+            None => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    b"ERR timeout is not an integer or out of range".to_vec(),
+                )));
+                return;
+            }
+        };
+        let mode = match cmd_ctx.get_cmd().get_command_element(3) {
+            None => ClientPauseMode::All,
+            Some(mode) if mode.eq_ignore_ascii_case(b"ALL") => ClientPauseMode::All,
+            Some(mode) if mode.eq_ignore_ascii_case(b"WRITE") => ClientPauseMode::Write,
+            Some(_) => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(b"ERR syntax error".to_vec())));
+                return;
+            }
+        };
+        if cmd_ctx.get_cmd().get_command_element(4).is_some() {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(b"ERR syntax error".to_vec())));
+            return;
+        }
+
+        if !self
+            .client_pause
+            .pause(Duration::from_millis(timeout), mode)
+        {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(b"ERR timeout is out of range".to_vec())));
+            return;
+        }
+        info!("pause clients for {}ms in {:?} mode", timeout, mode);
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(
+            response::OK_REPLY.to_string().into_bytes(),
+        )));
+    }
+
+    fn handle_select(&self, cmd_ctx: CmdCtx) {
+        let db = match cmd_ctx.get_cmd().get_command_element(1) {
+            Some(db) => db,
+            None => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    b"ERR wrong number of arguments for 'select' command".to_vec(),
+                )));
+                return;
+            }
+        };
+
+        let err: &[u8] = if let Ok(index) = btoi::<i64>(db) {
+            if index == 0 {
+                cmd_ctx.set_resp_result(Ok(Resp::Simple(response::OK_REPLY.as_bytes().to_vec())));
+                return;
+            }
+            b"ERR DB index is out of range"
+        } else if self.config.select_policy == SelectPolicy::IndexOnly {
+            b"ERR invalid DB index"
+        } else {
+            match str::from_utf8(db).ok().map(ClusterName::try_from) {
+                Some(Ok(name)) if name.as_str() == self.get_db_name() => {
+                    cmd_ctx
+                        .set_resp_result(Ok(Resp::Simple(response::OK_REPLY.as_bytes().to_vec())));
+                    return;
+                }
+                Some(Ok(_)) => response::ERR_CLUSTER_NOT_FOUND.as_bytes(),
+                _ => b"ERR invalid cluster name",
+            }
+        };
+        cmd_ctx.set_resp_result(Ok(Resp::Error(err.to_vec())));
+    }
+
+    fn handle_auth(&self, cmd_ctx: CmdCtx, auth_state: &AuthState) {
         let password_opt = cmd_ctx.get_key();
         let pwd = match password_opt {
             None => {
@@ -170,25 +573,65 @@ where
             },
         };
 
-        match self.config.password.as_ref() {
-            None => {
-                cmd_ctx.set_resp_result(Ok(Resp::Error(b"no password configured".to_vec())));
-            }
-            Some(conf_pwd) => {
-                if conf_pwd == &pwd {
-                    // Command handler does not run in parallel.
-                    authenticated.store(true, atomic::Ordering::Relaxed);
-                    cmd_ctx.set_resp_result(Ok(Resp::Simple(
-                        response::OK_REPLY.to_string().into_bytes(),
-                    )));
-                } else {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(b"invalid password".to_vec())));
-                }
-            }
+        if self.config.password.is_none() && self.config.admin_password.is_none() {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(b"no password configured".to_vec())));
+            return;
+        }
+
+        if self.config.admin_password.as_ref() == Some(&pwd) {
+            auth_state.set_admin();
+        } else if self.config.password.as_ref() == Some(&pwd) {
+            auth_state.set_authenticated();
+        } else {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(b"invalid password".to_vec())));
+            return;
+        }
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(
+            response::OK_REPLY.to_string().into_bytes(),
+        )));
+    }
+
+    fn data_auth_required(&self, auth_state: &AuthState) -> bool {
+        self.config.password.is_some() && !auth_state.is_authenticated()
+    }
+
+    fn admin_auth_required(&self, auth_state: &AuthState) -> bool {
+        self.config.auth_admin_commands
+            && (self.config.password.is_some() || self.config.admin_password.is_some())
+            && !auth_state.is_authenticated()
+    }
+
+    fn check_umctl_permission(&self, auth_state: &AuthState) -> Result<(), &'static [u8]> {
+        if self.config.admin_password.is_none() || auth_state.is_admin() {
+            return Ok(());
+        }
+        if auth_state.is_authenticated() {
+            Err(b"NOPERM this session has no permissions to run UMCTL commands")
+        } else {
+            Err(response::ERR_NOAUTH.as_bytes())
+        }
+    }
+
+    fn handle_ping(&self, cmd_ctx: CmdCtx) {
+        if self.config.ping_requires_meta && self.manager.get_cluster().is_empty() {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                response::ERR_LOADING_META.to_string().into_bytes(),
+            )));
+            return;
         }
+        // Same as Redis, PING with a message replies the message as a bulk string.
+        let resp = match cmd_ctx.get_cmd().get_command_len() {
+            Some(1) => Resp::Simple(response::PONG_REPLY.as_bytes().to_vec()),
+            Some(2) => match cmd_ctx.get_cmd().get_command_element(1) {
+                Some(msg) => Resp::Bulk(BulkStr::Str(msg.to_vec())),
+                None => Resp::Error(b"ERR invalid message".to_vec()),
+            },
+            _ => Resp::Error(b"ERR wrong number of arguments for 'ping' command".to_vec()),
+        };
+        cmd_ctx.set_resp_result(Ok(resp))
     }
 
-    fn handle_cluster(&self, cmd_ctx: CmdCtx) {
+    fn handle_cluster(&self, cmd_ctx: CmdCtx, auth_state: &AuthState) {
         let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 1) {
             Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd),
             None => return,
@@ -203,6 +646,18 @@ where
                 Ok(resp) => cmd_ctx.set_resp_result(Ok(resp)),
                 Err(s) => cmd_ctx.set_resp_result(Ok(Resp::Error(s.into_bytes()))),
             }
+        } else if str_ascii_case_insensitive_eq(&sub_cmd, "shards") {
+            let cluster_shards = self.manager.gen_cluster_shards();
+            match cluster_shards {
+                Ok(resp) => cmd_ctx.set_resp_result(Ok(resp)),
+                Err(s) => cmd_ctx.set_resp_result(Ok(Resp::Error(s.into_bytes()))),
+            }
+        } else if str_ascii_case_insensitive_eq(&sub_cmd, "links") {
+            let cluster_links = self.manager.gen_cluster_links();
+            cmd_ctx.set_resp_result(Ok(cluster_links))
+        } else if str_ascii_case_insensitive_eq(&sub_cmd, "info") {
+            let cluster_info = self.manager.gen_cluster_info();
+            cmd_ctx.set_resp_result(Ok(Resp::Bulk(BulkStr::Str(cluster_info.into_bytes()))))
         } else if str_ascii_case_insensitive_eq(&sub_cmd, "keyslot") {
             match cmd_ctx.get_cmd().get_command_element(2) {
                 Some(key) => {
@@ -214,6 +669,12 @@ where
                         .set_resp_result(Ok(Resp::Error(String::from("Missing key").into_bytes())));
                 }
             }
+        } else if str_ascii_case_insensitive_eq(&sub_cmd, "reset") {
+            if let Err(err) = self.check_umctl_permission(auth_state) {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(err.to_vec())));
+                return;
+            }
+            self.handle_cluster_reset(cmd_ctx);
         } else {
             cmd_ctx.set_resp_result(Ok(Resp::Error(
                 String::from("Unsupported sub command").into_bytes(),
@@ -221,6 +682,31 @@ where
         }
     }
 
+    fn handle_cluster_reset(&self, cmd_ctx: CmdCtx) {
+        let hard = match cmd_ctx.get_cmd().get_command_element(2) {
+            None => false,
+            Some(mode) if mode.eq_ignore_ascii_case(b"soft") => false,
+            Some(mode) if mode.eq_ignore_ascii_case(b"hard") => true,
+            Some(_) => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    String::from("Invalid reset mode").into_bytes(),
+                )));
+                return;
+            }
+        };
+        if hard && !self.config.cluster_reset_hard {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                String::from("CLUSTER RESET HARD is disabled").into_bytes(),
+            )));
+            return;
+        }
+
+        self.manager.reset(hard);
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(
+            response::OK_REPLY.to_string().into_bytes(),
+        )));
+    }
+
     fn get_sub_command(cmd_ctx: CmdCtx, index: usize) -> Option<(CmdCtx, String)> {
         let sub_cmd = match cmd_ctx.get_cmd().get_command_element(index) {
             None => {
@@ -265,18 +751,39 @@ where
             self.handle_umctl_info_repl(cmd_ctx);
         } else if sub_cmd.eq("INFOMGR") {
             self.handle_umctl_info_migration(cmd_ctx);
+        } else if sub_cmd.eq("BACKENDS") {
+            self.handle_umctl_backends(cmd_ctx);
+        } else if sub_cmd.eq("DBSTATS") {
+            self.handle_umctl_dbstats(cmd_ctx);
+        } else if sub_cmd.eq("DBMODE") {
+            self.handle_umctl_dbmode(cmd_ctx);
+        } else if sub_cmd.eq("MAINTENANCE") {
+            self.handle_umctl_maintenance(cmd_ctx);
+        } else if sub_cmd.eq("HEALTH") {
+            self.handle_umctl_health(cmd_ctx);
+        } else if sub_cmd.eq("ROUTES") {
+            self.handle_umctl_routes(cmd_ctx);
         } else if sub_cmd.eq(MgrSubCmd::PreCheck.as_str()) {
             self.handle_umctl_mgr_cmd(cmd_ctx, MgrSubCmd::PreCheck);
         } else if sub_cmd.eq(MgrSubCmd::PreSwitch.as_str()) {
             self.handle_umctl_mgr_cmd(cmd_ctx, MgrSubCmd::PreSwitch);
         } else if sub_cmd.eq(MgrSubCmd::FinalSwitch.as_str()) {
             self.handle_umctl_mgr_cmd(cmd_ctx, MgrSubCmd::FinalSwitch);
+        } else if sub_cmd.eq("MGRSTATUS") {
+            self.handle_umctl_mgr_status(cmd_ctx);
+        } else if sub_cmd.eq("MGRCONFIG") {
+            self.handle_umctl_mgrconfig(cmd_ctx);
         } else if sub_cmd.eq("SLOWLOG") {
             self.handle_umctl_slowlog(cmd_ctx);
         } else if sub_cmd.eq("DEBUG") {
             self.handle_umctl_debug(cmd_ctx);
         } else if sub_cmd.eq("STATS") {
             self.handle_umctl_stats(cmd_ctx);
+        } else if sub_cmd.eq("RESETSTATS") {
+            self.manager.get_command_stats().reset();
+            cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                response::OK_REPLY.to_string().into_bytes(),
+            )));
         } else if sub_cmd.eq("GETEPOCH") {
             self.handle_umctl_get_epoch(cmd_ctx);
         } else if sub_cmd.eq("READY") {
@@ -290,62 +797,177 @@ where
         }
     }
 
+    // Reject the enormous metadata before parsing it
+    // since the parsed metadata could take much more memory.
+    fn check_meta_size(&self, cmd: &Command) -> Result<(), String> {
+        let len = cmd.get_command_len().unwrap_or(0);
+        if len > self.config.umctl_max_meta_args {
+            return Err(format!(
+                "{}: {} elements exceeds {}",
+                response::ERR_META_TOO_LARGE,
+                len,
+                self.config.umctl_max_meta_args
+            ));
+        }
+        let bytes: usize = (0..len)
+            .filter_map(|i| cmd.get_command_element(i))
+            .map(|element| element.len())
+            .sum();
+        if bytes > self.config.umctl_max_meta_bytes {
+            return Err(format!(
+                "{}: {} bytes exceeds {}",
+                response::ERR_META_TOO_LARGE,
+                bytes,
+                self.config.umctl_max_meta_bytes
+            ));
+        }
+        Ok(())
+    }
+
     fn handle_umctl_set_cluster(&self, cmd_ctx: CmdCtx) {
-        let (cluster_meta, extended_res) =
-            match ProxyClusterMeta::from_resp(&cmd_ctx.get_cmd().get_resp_slice()) {
-                Ok(r) => r,
-                Err(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(
-                        format!("Failed to parse args: {:?}", err).into_bytes(),
-                    )));
-                    return;
-                }
-            };
+        let sub_cmd = cmd_ctx
+            .get_cmd()
+            .get_command_element(2)
+            .map(|arg| arg.to_ascii_uppercase());
+        if sub_cmd.as_deref() == Some(b"STATUS") {
+            self.handle_umctl_set_cluster_status(cmd_ctx);
+            return;
+        }
+        let is_async = sub_cmd.as_deref() == Some(b"ASYNC");
+
+        if let Err(err) = self.check_meta_size(cmd_ctx.get_cmd()) {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err.into_bytes())));
+            return;
+        }
+        let skipped = if is_async { 3 } else { 2 };
+        let (cluster_meta, extended_res) = match ProxyClusterMeta::from_resp_skipping(
+            &cmd_ctx.get_cmd().get_resp_slice(),
+            skipped,
+        ) {
+            Ok(r) => r,
+            Err(err) => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    format!("Failed to parse args: {}", err).into_bytes(),
+                )));
+                return;
+            }
+        };
+
+        if !is_async {
+            let reply = Self::apply_cluster_meta(
+                &self.manager,
+                &self.last_setcluster,
+                cluster_meta,
+                extended_res,
+            );
+            cmd_ctx.set_resp_result(Ok(reply));
+            return;
+        }
+
+        // The coordinator could send the metadata to all the proxies without waiting
+        // for each of them to apply it, and then query the results by the token.
+        let token = self.set_cluster_queue.push((cluster_meta, extended_res));
+        let manager = self.manager.clone();
+        let last_setcluster = self.last_setcluster.clone();
+        let queue = self.set_cluster_queue.clone();
+        let fut = async move {
+            queue.apply_next(|(cluster_meta, extended_res)| {
+                Self::apply_cluster_meta(&manager, &last_setcluster, cluster_meta, extended_res)
+            });
+        };
+        let desc = format!("set cluster async: token={}", token);
+        let fut = TrackedFutureRegistry::wrap(self.future_registry.clone(), fut, desc);
+        tokio::spawn(fut);
+        cmd_ctx.set_resp_result(Ok(Resp::Integer(token.to_string().into_bytes())));
+    }
+
+    fn handle_umctl_set_cluster_status(&self, cmd_ctx: CmdCtx) {
+        let token = cmd_ctx
+            .get_cmd()
+            .get_command_element(3)
+            .and_then(|token| btou::<u64>(token).ok());
+        let token = match token {
+            Some(token) => token,
+            None => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(b"invalid SETCLUSTER token".to_vec())));
+                return;
+            }
+        };
+        let reply = match self.set_cluster_queue.get_status(token) {
+            Some(MetaApplyStatus::Done(reply)) => reply,
+            Some(MetaApplyStatus::Pending) => Resp::Simple(b"PENDING".to_vec()),
+            None => Resp::Error(b"ERR unknown SETCLUSTER token".to_vec()),
+        };
+        cmd_ctx.set_resp_result(Ok(reply));
+    }
 
-        match self.manager.set_meta(cluster_meta) {
+    fn apply_cluster_meta(
+        manager: &MetaManager<F, C>,
+        last_setcluster: &parking_lot::Mutex<Option<(u64, Instant)>>,
+        cluster_meta: ProxyClusterMeta,
+        extended_res: ExtendedMetaResult,
+    ) -> RespVec {
+        let epoch = cluster_meta.get_epoch();
+        let res = manager.set_meta(cluster_meta);
+        if res.is_ok() {
+            *last_setcluster.lock() = Some((epoch, Instant::now()));
+        }
+        match res {
             Ok(()) => match extended_res {
                 Ok(()) => {
-                    debug!("Successfully update local meta data");
-                    cmd_ctx.set_resp_result(Ok(Resp::Simple("OK".to_string().into_bytes())));
-                }
-                Err(_) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Simple(
-                        "WARNING: ignored invalid config".to_string().into_bytes(),
-                    )));
+                    debug!(
+                        "successfully updated local meta data db={}",
+                        manager.get_cluster()
+                    );
+                    Resp::Simple("OK".to_string().into_bytes())
                 }
+                Err(_) => Resp::Simple("WARNING: ignored invalid config".to_string().into_bytes()),
             },
             Err(err) => match err {
-                ClusterMetaError::OldEpoch => cmd_ctx.set_resp_result(Ok(Resp::Error(
-                    response::OLD_EPOCH_REPLY.to_string().into_bytes(),
-                ))),
-                ClusterMetaError::TryAgain => cmd_ctx.set_resp_result(Ok(Resp::Error(
-                    response::TRY_AGAIN_REPLY.to_string().into_bytes(),
-                ))),
-                ClusterMetaError::NotMyMeta => cmd_ctx.set_resp_result(Ok(Resp::Error(
-                    response::ERR_NOT_MY_META.to_string().into_bytes(),
-                ))),
+                ClusterMetaError::OldEpoch => {
+                    Resp::Error(response::OLD_EPOCH_REPLY.to_string().into_bytes())
+                }
+                ClusterMetaError::TryAgain => {
+                    Resp::Error(response::TRY_AGAIN_REPLY.to_string().into_bytes())
+                }
+                ClusterMetaError::NotMyMeta => {
+                    Resp::Error(response::ERR_NOT_MY_META.to_string().into_bytes())
+                }
             },
         }
     }
 
     fn handle_umctl_setrepl(&self, cmd_ctx: CmdCtx) {
+        if let Err(err) = self.check_meta_size(cmd_ctx.get_cmd()) {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err.into_bytes())));
+            return;
+        }
         let meta = match ReplicatorMeta::from_resp(&cmd_ctx.get_cmd().get_resp_slice()) {
             Ok(m) => m,
-            Err(_) => {
+            Err(err) => {
                 cmd_ctx.set_resp_result(Ok(Resp::Error(
-                    String::from("Invalid arguments").into_bytes(),
+                    format!("Invalid arguments: {}", err).into_bytes(),
                 )));
                 return;
             }
         };
 
+        let epoch = meta.epoch;
         match self.manager.update_replicators(meta) {
             Ok(()) => {
-                debug!("Successfully update replicator meta data");
+                *self.last_setrepl.lock() = Some((epoch, Instant::now()));
+                debug!(
+                    "successfully updated replicator meta data db={}",
+                    self.get_db_name()
+                );
                 cmd_ctx.set_resp_result(Ok(Resp::Simple(String::from("OK").into_bytes())))
             }
             Err(e) => {
-                //                debug!("Failed to update replicator meta data {:?}", e);
+                debug!(
+                    "failed to update replicator meta data db={} error={:?}",
+                    self.get_db_name(),
+                    e
+                );
                 match e {
                     ClusterMetaError::OldEpoch => cmd_ctx.set_resp_result(Ok(Resp::Error(
                         response::OLD_EPOCH_REPLY.to_string().into_bytes(),
@@ -383,6 +1005,11 @@ where
                 cmd_ctx.set_resp_result(Ok(Resp::Simple("OK".to_string().into_bytes())));
             }
             Err(err) => {
+                error!(
+                    "failed to switch migration db={} error={:?}",
+                    self.get_db_name(),
+                    err
+                );
                 let err_str = match err {
                     SwitchError::InvalidArg => "Invalid Arg".to_string(),
                     SwitchError::TaskNotFound => response::TASK_NOT_FOUND.to_string(),
@@ -395,6 +1022,178 @@ where
         }
     }
 
+    fn handle_umctl_mgr_status(&self, cmd_ctx: CmdCtx) {
+        let task_meta = match parse_mgr_status_command(&cmd_ctx.get_cmd().get_resp_slice()) {
+            Some(task_meta) => task_meta,
+            None => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    "failed to parse migration task arguments"
+                        .to_string()
+                        .into_bytes(),
+                )));
+                return;
+            }
+        };
+        let resp = match self.manager.get_migration_task_status(&task_meta) {
+            Ok(status) => {
+                let range_list = task_meta.slot_range.to_range_list().to_strings().join(" ");
+                Resp::Arr(Array::Arr(vec![
+                    Resp::Bulk(BulkStr::Str(status.to_string().into_bytes())),
+                    Resp::Bulk(BulkStr::Str(range_list.into_bytes())),
+                ]))
+            }
+            Err(SwitchError::NotReady) => Resp::Error(
+                response::NOT_READY_FOR_SWITCHING_REPLY
+                    .to_string()
+                    .into_bytes(),
+            ),
+            Err(_) => Resp::Error("Invalid Arg".to_string().into_bytes()),
+        };
+        cmd_ctx.set_resp_result(Ok(resp));
+    }
+
+    fn handle_umctl_backends(&self, cmd_ctx: CmdCtx) {
+        let summaries = self.manager.get_backend_summaries();
+        let warmup_stats = self.manager.get_backend_warmup_stats();
+        let packet: Vec<RespVec> = summaries
+            .into_iter()
+            .map(|summary| {
+                let (warmup_success, warmup_failure) = warmup_stats.get(&summary.address);
+                format!(
+                    "{} {} conns:{} in_flight:{} last_error:{} warmup_success:{} warmup_failure:{}",
+                    summary.address,
+                    summary.status,
+                    summary.conn_num,
+                    summary.in_flight,
+                    summary.last_error.unwrap_or_else(|| "-".to_string()),
+                    warmup_success,
+                    warmup_failure,
+                )
+            })
+            .map(|s| Resp::Bulk(BulkStr::Str(s.into_bytes())))
+            .collect();
+        cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(packet))))
+    }
+
+    fn handle_umctl_dbstats(&self, cmd_ctx: CmdCtx) {
+        let max_conn = self.config.get_max_connections_per_db();
+        // The read-only databases are also listed even without any connection.
+        let mut counts: BTreeMap<String, usize> =
+            self.db_conn_counter.get_counts().into_iter().collect();
+        let readonly_dbs = self.readonly_dbs.read().clone();
+        for db in readonly_dbs.iter() {
+            counts.entry(db.clone()).or_insert(0);
+        }
+        let packet: Vec<RespVec> = counts
+            .into_iter()
+            .map(|(db, conn_num)| {
+                let mode = if readonly_dbs.contains(&db) {
+                    "readonly"
+                } else {
+                    "readwrite"
+                };
+                format!(
+                    "{} connections:{} max_connections:{} mode:{}",
+                    db, conn_num, max_conn, mode
+                )
+            })
+            .map(|s| Resp::Bulk(BulkStr::Str(s.into_bytes())))
+            .collect();
+        cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(packet))))
+    }
+
+    fn handle_umctl_dbmode(&self, cmd_ctx: CmdCtx) {
+        let (cmd_ctx, db) = match Self::get_sub_command(cmd_ctx, 2) {
+            Some((cmd_ctx, db)) => (cmd_ctx, db),
+            None => return,
+        };
+        let (cmd_ctx, mode) = match Self::get_sub_command(cmd_ctx, 3) {
+            Some((cmd_ctx, mode)) => (cmd_ctx, mode.to_uppercase()),
+            None => return,
+        };
+
+        if mode.eq("READONLY") {
+            self.readonly_dbs.write().insert(db);
+        } else if mode.eq("READWRITE") {
+            self.readonly_dbs.write().remove(&db);
+        } else {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                "invalid DBMODE mode".to_string().into_bytes(),
+            )));
+            return;
+        }
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(
+            response::OK_REPLY.to_string().into_bytes(),
+        )))
+    }
+
+    fn handle_umctl_maintenance(&self, cmd_ctx: CmdCtx) {
+        let (cmd_ctx, mode) = match Self::get_sub_command(cmd_ctx, 2) {
+            Some((cmd_ctx, mode)) => (cmd_ctx, mode.to_uppercase()),
+            None => return,
+        };
+
+        if mode.eq("ON") {
+            self.maintenance.store(true, Ordering::SeqCst);
+        } else if mode.eq("OFF") {
+            self.maintenance.store(false, Ordering::SeqCst);
+        } else {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                "invalid MAINTENANCE mode".to_string().into_bytes(),
+            )));
+            return;
+        }
+        info!("maintenance mode: {}", mode);
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(
+            response::OK_REPLY.to_string().into_bytes(),
+        )))
+    }
+
+    fn is_write_cmd(&self, cmd_ctx: &CmdCtx) -> bool {
+        match cmd_ctx.get_cmd().get_command_name() {
+            Some(cmd_name) => self
+                .command_table
+                .is_write(cmd_name.to_lowercase().as_bytes()),
+            None => false,
+        }
+    }
+
+    fn is_readonly_write(&self, cmd_ctx: &CmdCtx) -> bool {
+        let readonly_dbs = self.readonly_dbs.read();
+        if readonly_dbs.is_empty() {
+            return false;
+        }
+        self.is_write_cmd(cmd_ctx) && readonly_dbs.contains(&self.get_db_name())
+    }
+
+    fn handle_umctl_health(&self, cmd_ctx: CmdCtx) {
+        let health = self.manager.get_health(self.db_conn_counter.get_total());
+        let resp = match serde_json::to_vec(&health) {
+            Ok(json) => Resp::Bulk(BulkStr::Str(json)),
+            Err(err) => Resp::Error(format!("failed to encode health: {}", err).into_bytes()),
+        };
+        cmd_ctx.set_resp_result(Ok(resp))
+    }
+
+    fn handle_umctl_routes(&self, cmd_ctx: CmdCtx) {
+        // The optional db needs to be the cluster of this proxy.
+        if let Some(db) = cmd_ctx.get_cmd().get_command_element(2) {
+            if db != self.manager.get_cluster().to_string().as_bytes() {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    response::ERR_CLUSTER_NOT_FOUND.to_string().into_bytes(),
+                )));
+                return;
+            }
+        }
+        let packet: Vec<RespVec> = self
+            .manager
+            .gen_routes()
+            .into_iter()
+            .map(|s| Resp::Bulk(BulkStr::Str(s.into_bytes())))
+            .collect();
+        cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(packet))))
+    }
+
     fn handle_umctl_info_migration(&self, cmd_ctx: CmdCtx) {
         let finished_tasks = self.manager.get_finished_migration_tasks();
         let packet: Vec<RespVec> = finished_tasks
@@ -405,6 +1204,51 @@ where
         cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(packet))))
     }
 
+    fn handle_umctl_mgrconfig(&self, cmd_ctx: CmdCtx) {
+        let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 2) {
+            Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd.to_uppercase()),
+            None => return,
+        };
+
+        if sub_cmd.eq("GET") {
+            let packet: Vec<RespVec> = self
+                .manager
+                .get_migration_config()
+                .to_fields()
+                .into_iter()
+                .flat_map(|(field, value)| vec![field.to_string(), value.to_string()])
+                .map(|s| Resp::Bulk(BulkStr::Str(s.into_bytes())))
+                .collect();
+            cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(packet))));
+        } else if sub_cmd.eq("SET") {
+            let (cmd_ctx, field) = match Self::get_sub_command(cmd_ctx, 3) {
+                Some((cmd_ctx, field)) => (cmd_ctx, field),
+                None => return,
+            };
+            let (cmd_ctx, value) = match Self::get_sub_command(cmd_ctx, 4) {
+                Some((cmd_ctx, value)) => (cmd_ctx, value),
+                None => return,
+            };
+            match self.manager.set_migration_config_field(&field, &value) {
+                Ok(()) => cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                    response::OK_REPLY.to_string().into_bytes(),
+                ))),
+                Err(err) => cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    format!(
+                        "ERR invalid migration config {}: {}",
+                        field,
+                        err.to_string()
+                    )
+                    .into_bytes(),
+                ))),
+            }
+        } else {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                "invalid MGRCONFIG sub-command".to_string().into_bytes(),
+            )))
+        }
+    }
+
     fn handle_umctl_slowlog(&self, cmd_ctx: CmdCtx) {
         let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 2) {
             Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd),
@@ -457,7 +1301,11 @@ where
     }
 
     fn handle_umctl_stats(&self, cmd_ctx: CmdCtx) {
-        let stats = self.manager.get_stats();
+        let mut stats = vec!["# Proxy".to_string()];
+        for (k, v) in self.stats.to_lines_str().into_iter() {
+            stats.push(format!("{}: {}", k, v));
+        }
+        stats.extend(self.manager.get_stats());
         cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(
             stats
                 .into_iter()
@@ -466,9 +1314,53 @@ where
         ))));
     }
 
-    fn handle_umctl_get_epoch(&self, cmd_ctx: CmdCtx) {
-        let epoch = self.manager.get_epoch();
-        cmd_ctx.set_resp_result(Ok(Resp::Integer(epoch.to_string().into_bytes())))
+    fn handle_umctl_get_epoch(&self, cmd_ctx: CmdCtx) {
+        let detail = match cmd_ctx.get_cmd().get_command_element(2) {
+            None => false,
+            Some(arg) if arg.eq_ignore_ascii_case(b"DETAIL") => true,
+            Some(_) => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(
+                    "invalid GETEPOCH argument".to_string().into_bytes(),
+                )));
+                return;
+            }
+        };
+        let epoch = self.manager.get_epoch();
+        if !detail {
+            cmd_ctx.set_resp_result(Ok(Resp::Integer(epoch.to_string().into_bytes())));
+            return;
+        }
+
+        let fields = vec![("epoch".to_string(), epoch.to_string())]
+            .into_iter()
+            .chain(self.get_last_meta_fields());
+        let packet = fields
+            .flat_map(|(field, value)| vec![field, value])
+            .map(|s| Resp::Bulk(BulkStr::Str(s.into_bytes())))
+            .collect();
+        cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(packet))));
+    }
+
+    // For detecting the proxies falling behind on the metadata.
+    // The age is -1 if the metadata has never been applied since the proxy started.
+    fn get_last_meta_fields(&self) -> Vec<(String, String)> {
+        let records = [
+            ("setcluster", *self.last_setcluster.lock()),
+            ("setrepl", *self.last_setrepl.lock()),
+        ];
+        records
+            .iter()
+            .flat_map(|(name, last)| {
+                let (epoch, age) = match last {
+                    Some((epoch, applied_at)) => (*epoch, applied_at.elapsed().as_secs() as i64),
+                    None => (0, -1),
+                };
+                vec![
+                    (format!("last_{}_epoch", name), epoch.to_string()),
+                    (format!("last_{}_age_seconds", name), age.to_string()),
+                ]
+            })
+            .collect()
     }
 
     fn handle_umctl_ready(&self, cmd_ctx: CmdCtx) {
@@ -536,7 +1428,7 @@ where
         &self,
         cmd_ctx: CmdCtx,
         reply_receiver: CmdReplyReceiver,
-    ) -> CmdReplyFuture {
+    ) -> CmdReplyFuture<'_> {
         let table = self.command_table.clone();
         CmdReplyFuture::Right(Box::pin(async move {
             let res = self.manager.send_to_any_local_node(&cmd_ctx).await;
@@ -564,6 +1456,10 @@ where
                 };
                 match cmd_name {
                     Some(Resp::Bulk(BulkStr::Str(s))) => {
+                        // Replaced by the entries generated by the proxy below.
+                        if table.is_proxy_command(s.as_slice()) {
+                            continue;
+                        }
                         if table.is_supported(s.as_slice()) {
                             filtered.push(cmd);
                         }
@@ -575,13 +1471,97 @@ where
                     }
                 };
             }
+            filtered.extend(table.gen_proxy_command_infos());
 
             cmd_ctx.set_resp_result(Ok(Resp::Arr(Array::Arr(filtered))));
             reply_receiver.await
         }))
     }
 
-    fn handle_data_cmd(&self, cmd_ctx: CmdCtx, reply_receiver: CmdReplyReceiver) -> CmdReplyFuture {
+    fn is_known_command(&self, cmd_ctx: &CmdCtx) -> bool {
+        match cmd_ctx.get_cmd().get_command_name() {
+            Some(cmd_name) => self
+                .command_table
+                .is_supported(cmd_name.to_lowercase().as_bytes()),
+            None => false,
+        }
+    }
+
+    // Replaces the alias with the original command name.
+    // Returns false for the disabled commands and the original names of the renamed ones.
+    fn rename_command(&self, cmd_ctx: &mut CmdCtx) -> bool {
+        let renames = &self.config.rename_command;
+        if renames.is_empty() {
+            return true;
+        }
+        let cmd_name = match cmd_ctx.get_cmd().get_command_name() {
+            Some(cmd_name) => cmd_name.to_lowercase(),
+            None => return true,
+        };
+        match renames.lookup(&cmd_name) {
+            CommandLookup::Unchanged => true,
+            CommandLookup::Unknown => false,
+            CommandLookup::Alias(original) => {
+                cmd_ctx.change_cmd_element(0, original.as_bytes().to_vec());
+                cmd_ctx.refresh_cmd_info();
+                true
+            }
+        }
+    }
+
+    // The commands forwarded by other proxies by UMFORWARD are not paused.
+    fn handle_client_data_cmd(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+        client_info: &ClientInfo,
+    ) -> CmdReplyFuture<'_> {
+        let is_write = self.is_write_cmd(&cmd_ctx);
+        let deferred = client_info.get_deferred_counter();
+        if self.client_pause.get_deadline(is_write).is_none() && !deferred.is_deferring() {
+            return self.handle_data_cmd(cmd_ctx, reply_receiver);
+        }
+
+        // The session only polls the reply future of its oldest command,
+        // so the deferred commands are dispatched in order after the pause.
+        let guard = deferred.defer();
+        CmdReplyFuture::Right(Box::pin(async move {
+            self.client_pause.wait(is_write).await;
+            let res = self.handle_data_cmd(cmd_ctx, reply_receiver).await;
+            drop(guard);
+            res
+        }))
+    }
+
+    fn handle_data_cmd(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+    ) -> CmdReplyFuture<'_> {
+        // The connections are kept so that the clients could retry after the maintenance.
+        if self.maintenance.load(Ordering::Relaxed) {
+            let err = self.config.maintenance_error.as_bytes().to_vec();
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err)));
+            return CmdReplyFuture::Left(reply_receiver);
+        }
+
+        if self.is_readonly_write(&cmd_ctx) {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(response::ERR_READONLY.as_bytes().to_vec())));
+            return CmdReplyFuture::Left(reply_receiver);
+        }
+
+        let no_key_routing = cmd_ctx
+            .get_cmd()
+            .get_command_name()
+            .and_then(|cmd_name| self.command_table.get_no_key_routing(cmd_name.as_bytes()));
+        if let Some(routing) = no_key_routing {
+            return CmdReplyFuture::Right(Box::pin(self.handle_no_key_cmd(
+                cmd_ctx,
+                reply_receiver,
+                routing,
+            )));
+        }
+
         match cmd_ctx.get_data_cmd_type() {
             DataCmdType::Mget => {
                 CmdReplyFuture::Right(Box::pin(self.handle_mget(cmd_ctx, reply_receiver)))
@@ -592,18 +1572,19 @@ where
             DataCmdType::Msetnx => {
                 CmdReplyFuture::Right(Box::pin(self.handle_msetnx(cmd_ctx, reply_receiver)))
             }
-            DataCmdType::Del if cmd_ctx.get_cmd().get_command_element(2).is_some() => {
-                CmdReplyFuture::Right(Box::pin(self.handle_multi_int_cmd(
-                    cmd_ctx,
-                    reply_receiver,
-                    "DEL",
-                )))
-            }
-            DataCmdType::Exists if cmd_ctx.get_cmd().get_command_element(2).is_some() => {
+            DataCmdType::Del | DataCmdType::Exists | DataCmdType::Touch | DataCmdType::Unlink
+                if cmd_ctx.get_cmd().get_command_element(2).is_some() =>
+            {
+                let cmd_name = match cmd_ctx.get_data_cmd_type() {
+                    DataCmdType::Del => "DEL",
+                    DataCmdType::Exists => "EXISTS",
+                    DataCmdType::Touch => "TOUCH",
+                    _ => "UNLINK",
+                };
                 CmdReplyFuture::Right(Box::pin(self.handle_multi_int_cmd(
                     cmd_ctx,
                     reply_receiver,
-                    "EXISTS",
+                    cmd_name,
                 )))
             }
             DataCmdType::Blpop
@@ -613,12 +1594,113 @@ where
             | DataCmdType::Bzpopmax => CmdReplyFuture::Right(Box::pin(
                 self.handle_blocking_commands(cmd_ctx, reply_receiver),
             )),
-            DataCmdType::Eval => self.handle_eval_cmd(cmd_ctx, reply_receiver),
-            _ => {
-                self.handle_single_key_data_cmd(cmd_ctx);
+            DataCmdType::Eval | DataCmdType::Evalsha => {
+                self.handle_numkeys_cmd(cmd_ctx, reply_receiver, 2)
+            }
+            DataCmdType::Sintercard
+            | DataCmdType::Zintercard
+            | DataCmdType::Lmpop
+            | DataCmdType::Zmpop => self.handle_numkeys_cmd(cmd_ctx, reply_receiver, 1),
+            DataCmdType::Copy => self.handle_copy_cmd(cmd_ctx, reply_receiver),
+            DataCmdType::Object => {
+                self.handle_object_cmd(cmd_ctx);
                 CmdReplyFuture::Left(reply_receiver)
             }
+            DataCmdType::Memory => self.handle_memory_cmd(cmd_ctx, reply_receiver),
+            _ => {
+                let keys: Option<Vec<_>> = self
+                    .command_table
+                    .get_keys(cmd_ctx.get_cmd())
+                    .map(|keys| keys.into_iter().map(|key| key.to_vec()).collect());
+                match keys {
+                    Some(keys) if keys.len() > 1 => CmdReplyFuture::Right(Box::pin(
+                        self.handle_multi_key_same_slot_cmd(cmd_ctx, reply_receiver, keys),
+                    )),
+                    _ => {
+                        self.handle_single_key_data_cmd(cmd_ctx);
+                        CmdReplyFuture::Left(reply_receiver)
+                    }
+                }
+            }
+        }
+    }
+
+    // The commands without keys are sent to all the local backends of this proxy.
+    // The backends of the other proxies are not included.
+    async fn handle_no_key_cmd(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+        routing: NoKeyRouting,
+    ) -> TaskResult {
+        if self.manager.has_key_prefix() {
+            let err = KeyPrefixError::UnknownKeys.to_err_msg();
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err.as_bytes().to_vec())));
+            return reply_receiver.await;
+        }
+
+        let nodes = self.manager.get_local_nodes();
+        if nodes.is_empty() {
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                response::ERR_LOADING_META.as_bytes().to_vec(),
+            )));
+            return reply_receiver.await;
+        }
+
+        let mut args: Vec<RespVec> = (0..)
+            .map(|i| cmd_ctx.get_cmd().get_command_element(i))
+            .take_while(|arg| arg.is_some())
+            .flatten()
+            .map(|arg| Resp::Bulk(BulkStr::Str(arg.to_vec())))
+            .collect();
+        let factory = CmdCtxFactory;
+
+        if routing == NoKeyRouting::Scan {
+            let cursor = match args.get(1) {
+                Some(Resp::Bulk(BulkStr::Str(cursor))) => btou::<u64>(cursor).ok(),
+                _ => None,
+            };
+            let (index, node_cursor) = match cursor.map(split_scan_cursor) {
+                Some((index, node_cursor)) if index < nodes.len() => (index, node_cursor),
+                _ => {
+                    cmd_ctx.set_resp_result(Ok(Resp::Error(b"ERR invalid cursor".to_vec())));
+                    return reply_receiver.await;
+                }
+            };
+            if let Some(arg) = args.get_mut(1) {
+                *arg = Resp::Bulk(BulkStr::Str(node_cursor.to_string().into_bytes()));
+            }
+            let (sub_cmd_ctx, fut) =
+                factory.create_with_ctx(cmd_ctx.get_context(), Resp::Arr(Array::Arr(args)));
+            if let Some(address) = nodes.get(index) {
+                self.manager.send_to_local_node(sub_cmd_ctx, address);
+            }
+            let reply = match fut.await {
+                Ok(reply) => gen_scan_reply(index, nodes.len(), reply),
+                Err(err) => Resp::Error(format!("ERR: {}", err).into_bytes()),
+            };
+            cmd_ctx.set_resp_result(Ok(reply));
+            return reply_receiver.await;
         }
+
+        let futs: Vec<_> = nodes
+            .iter()
+            .map(|address| {
+                let resp = Resp::Arr(Array::Arr(args.clone()));
+                let (sub_cmd_ctx, fut) = factory.create_with_ctx(cmd_ctx.get_context(), resp);
+                self.manager.send_to_local_node(sub_cmd_ctx, address);
+                fut
+            })
+            .collect();
+        let reply = match join_sub_replies(futs).await {
+            Ok(replies) => {
+                let random = RandomState::new().hash_one(Instant::now());
+                merge_replies(routing, replies, random)
+            }
+            Err(err_resp) => err_resp,
+        };
+        cmd_ctx.set_resp_result(Ok(reply));
+        reply_receiver.await
     }
 
     async fn handle_mget(&self, cmd_ctx: CmdCtx, reply_receiver: CmdReplyReceiver) -> TaskResult {
@@ -635,7 +1717,7 @@ where
             }
         }
 
-        let factory = CmdCtxFactory::default();
+        let factory = CmdCtxFactory;
         let mut futs = vec![];
         for i in 1.. {
             let key = match cmd_ctx.get_cmd().get_command_element(i) {
@@ -658,24 +1740,10 @@ where
             return reply_receiver.await;
         }
 
-        let mut values = vec![];
-        let res = future::join_all(futs).await;
-        for sub_result in res.into_iter() {
-            let reply = match sub_result {
-                Ok(reply) => reply,
-                Err(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(format!("ERR: {}", err).into_bytes())));
-                    return Err(err);
-                }
-            };
-            if let Resp::Error(err) = &reply {
-                cmd_ctx.set_resp_result(Ok(Resp::Error(err.clone())));
-                return reply_receiver.await;
-            }
-            values.push(reply);
-        }
-
-        let resp = Resp::Arr(Array::Arr(values));
+        let resp = match join_sub_replies(futs).await {
+            Ok(values) => Resp::Arr(Array::Arr(values)),
+            Err(err_resp) => err_resp,
+        };
         cmd_ctx.set_resp_result(Ok(resp));
         reply_receiver.await
     }
@@ -695,7 +1763,7 @@ where
             }
         }
 
-        let factory = CmdCtxFactory::default();
+        let factory = CmdCtxFactory;
         let mut futs = vec![];
         for i in 0.. {
             let key = match cmd_ctx.get_cmd().get_command_element(2 * i + 1) {
@@ -729,22 +1797,10 @@ where
             return reply_receiver.await;
         }
 
-        let res = future::join_all(futs).await;
-        for sub_result in res.into_iter() {
-            let reply = match sub_result {
-                Ok(reply) => reply,
-                Err(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(format!("ERR: {}", err).into_bytes())));
-                    return Err(err);
-                }
-            };
-            if let Resp::Error(err) = reply {
-                cmd_ctx.set_resp_result(Ok(Resp::Error(err)));
-                return reply_receiver.await;
-            }
-        }
-
-        let resp = Resp::Simple(response::OK_REPLY.to_string().into_bytes());
+        let resp = match join_sub_replies(futs).await {
+            Ok(_) => Resp::Simple(response::OK_REPLY.to_string().into_bytes()),
+            Err(err_resp) => err_resp,
+        };
         cmd_ctx.set_resp_result(Ok(resp));
         reply_receiver.await
     }
@@ -799,7 +1855,7 @@ where
             return reply_receiver.await;
         }
 
-        let factory = CmdCtxFactory::default();
+        let factory = CmdCtxFactory;
         let mut futs = vec![];
         for (_, cmd) in slotted_kvs {
             let resp = Resp::Arr(Array::Arr(cmd));
@@ -815,21 +1871,16 @@ where
             return reply_receiver.await;
         }
 
-        let res = future::join_all(futs).await;
+        let replies = match join_sub_replies(futs).await {
+            Ok(replies) => replies,
+            Err(err_resp) => {
+                cmd_ctx.set_resp_result(Ok(err_resp));
+                return reply_receiver.await;
+            }
+        };
         let mut count = 0usize;
-        for sub_result in res.into_iter() {
-            let reply = match sub_result {
-                Ok(reply) => reply,
-                Err(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(format!("ERR: {}", err).into_bytes())));
-                    return Err(err);
-                }
-            };
+        for reply in replies.into_iter() {
             match reply {
-                Resp::Error(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(err.clone())));
-                    return reply_receiver.await;
-                }
                 Resp::Integer(data) => {
                     let n = match btou::<usize>(&data) {
                         Ok(n) => n,
@@ -855,7 +1906,10 @@ where
         reply_receiver.await
     }
 
-    // DEL and EXISTS
+    // DEL, EXISTS, TOUCH, and UNLINK with multiple keys.
+    // The keys in the same slot are forwarded as a whole like Redis Cluster.
+    // The keys across slots are only supported in active redirection mode
+    // by splitting them into single-key commands and summing up the replies.
     async fn handle_multi_int_cmd(
         &self,
         cmd_ctx: CmdCtx,
@@ -863,19 +1917,24 @@ where
         cmd_name: &'static str,
     ) -> TaskResult {
         let arg_len = cmd_ctx.get_cmd().get_command_len().unwrap_or(0);
+        let keys: Vec<Vec<u8>> = (1..arg_len)
+            .filter_map(|i| cmd_ctx.get_cmd().get_command_element(i))
+            .map(|key| key.to_vec())
+            .collect();
 
+        if same_slot(keys.iter().map(|key| key.as_slice())) {
+            return self
+                .handle_multi_key_same_slot_cmd(cmd_ctx, reply_receiver, keys)
+                .await;
+        }
         if !self.config.active_redirection {
-            let in_same_slot =
-                same_slot((1..arg_len).filter_map(|i| cmd_ctx.get_cmd().get_command_element(i)));
-            if !in_same_slot {
-                cmd_ctx.set_resp_result(Ok(Resp::Error(
-                    response::ERR_NOT_THE_SAME_SLOT.to_string().into_bytes(),
-                )));
-                return reply_receiver.await;
-            }
+            cmd_ctx.set_resp_result(Ok(Resp::Error(
+                response::ERR_NOT_THE_SAME_SLOT.to_string().into_bytes(),
+            )));
+            return reply_receiver.await;
         }
 
-        let factory = CmdCtxFactory::default();
+        let factory = CmdCtxFactory;
         let mut futs = vec![];
         for i in 1.. {
             let key = match cmd_ctx.get_cmd().get_command_element(i) {
@@ -898,21 +1957,16 @@ where
             return reply_receiver.await;
         }
 
+        let replies = match join_sub_replies(futs).await {
+            Ok(replies) => replies,
+            Err(err_resp) => {
+                cmd_ctx.set_resp_result(Ok(err_resp));
+                return reply_receiver.await;
+            }
+        };
         let mut count: usize = 0;
-        let res = future::join_all(futs).await;
-        for sub_result in res.into_iter() {
-            let reply = match sub_result {
-                Ok(reply) => reply,
-                Err(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(format!("ERR: {}", err).into_bytes())));
-                    return Err(err);
-                }
-            };
+        for reply in replies.into_iter() {
             match reply {
-                Resp::Error(err) => {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(err.clone())));
-                    return reply_receiver.await;
-                }
                 Resp::Integer(data) => {
                     let n = match btou::<usize>(&data) {
                         Ok(n) => n,
@@ -984,7 +2038,7 @@ where
             }
         }
 
-        let factory = CmdCtxFactory::default();
+        let factory = CmdCtxFactory;
         let mut retry_num = 0;
         loop {
             let cmds = match Self::transfer_cmd_from_blocking_to_non_blocking(
@@ -1037,8 +2091,20 @@ where
         }
     }
 
-    fn handle_eval_cmd(&self, cmd_ctx: CmdCtx, reply_receiver: CmdReplyReceiver) -> CmdReplyFuture {
-        let key_num = match cmd_ctx.get_cmd().get_command_element(2) {
+    // Handles the commands with the keys prefixed by `numkeys`,
+    // e.g. EVAL <script> <numkeys> <key> [key ...] and LMPOP <numkeys> <key> [key ...]
+    fn handle_numkeys_cmd(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+        numkeys_index: usize,
+    ) -> CmdReplyFuture<'_> {
+        let cmd_name = cmd_ctx
+            .get_cmd()
+            .get_command_name()
+            .unwrap_or("")
+            .to_uppercase();
+        let key_num = match cmd_ctx.get_cmd().get_command_element(numkeys_index) {
             Some(key_num_str) => match btoi::btoi::<usize>(key_num_str) {
                 Ok(key_num) => key_num,
                 Err(err) => {
@@ -1048,37 +2114,97 @@ where
                 }
             },
             None => {
-                let err_msg = b"ERR: Missing `numkeys` for EVAL";
-                cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.to_vec())));
+                let err_msg = format!("ERR: Missing `numkeys` for {}", cmd_name);
+                cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
                 return CmdReplyFuture::Left(reply_receiver);
             }
         };
 
+        let first_key_index = numkeys_index + 1;
+        let arg_len = cmd_ctx.get_cmd().get_command_len().unwrap_or(0);
+        if first_key_index.saturating_add(key_num) > arg_len {
+            let err_msg = format!("ERR: Missing keys for {}", cmd_name);
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
+            return CmdReplyFuture::Left(reply_receiver);
+        }
+
         if key_num == 1 {
             self.handle_single_key_data_cmd(cmd_ctx);
             return CmdReplyFuture::Left(reply_receiver);
         }
 
-        CmdReplyFuture::Right(Box::pin(self.handle_multi_key_eval_cmd(
+        let keys: Vec<_> = (first_key_index..first_key_index + key_num)
+            .filter_map(|i| cmd_ctx.get_cmd().get_command_element(i))
+            .map(|b| b.to_vec())
+            .collect();
+        CmdReplyFuture::Right(Box::pin(self.handle_multi_key_same_slot_cmd(
             cmd_ctx,
             reply_receiver,
-            key_num,
+            keys,
         )))
     }
 
-    async fn handle_multi_key_eval_cmd(
+    // COPY <source> <destination> [DB <destination-db>] [REPLACE]
+    fn handle_copy_cmd(
         &self,
         cmd_ctx: CmdCtx,
         reply_receiver: CmdReplyReceiver,
-        key_num: usize,
-    ) -> TaskResult {
-        let keys: Vec<_> = (3..3 + key_num)
+    ) -> CmdReplyFuture<'_> {
+        let keys: Vec<_> = (1..3)
             .filter_map(|i| cmd_ctx.get_cmd().get_command_element(i))
             .map(|b| b.to_vec())
             .collect();
+        if keys.len() < 2 {
+            // Let the backend return the error of wrong number of arguments.
+            self.handle_single_key_data_cmd(cmd_ctx);
+            return CmdReplyFuture::Left(reply_receiver);
+        }
+
+        // COPY source destination [DB destination-db] [REPLACE]
+        // Same as Redis Cluster, only the current db is allowed.
+        let arg_len = cmd_ctx.get_cmd().get_command_len().unwrap_or(0);
+        let mut i = 3;
+        while i < arg_len {
+            let is_db_option = cmd_ctx
+                .get_cmd()
+                .get_command_element(i)
+                .map(|arg| arg.eq_ignore_ascii_case(b"DB"))
+                .unwrap_or(false);
+            if is_db_option {
+                let db = cmd_ctx
+                    .get_cmd()
+                    .get_command_element(i + 1)
+                    .and_then(|db| btoi::<i64>(db).ok());
+                if let Some(db) = db {
+                    if db != 0 {
+                        cmd_ctx.set_resp_result(Ok(Resp::Error(
+                            b"ERR Copying to another database is not allowed in cluster mode"
+                                .to_vec(),
+                        )));
+                        return CmdReplyFuture::Left(reply_receiver);
+                    }
+                }
+                i += 1;
+            }
+            i += 1;
+        }
 
+        CmdReplyFuture::Right(Box::pin(self.handle_multi_key_same_slot_cmd(
+            cmd_ctx,
+            reply_receiver,
+            keys,
+        )))
+    }
+
+    async fn handle_multi_key_same_slot_cmd(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+        keys: Vec<Vec<u8>>,
+    ) -> TaskResult {
         // Even for `self.config.active_redirection == true`,
-        // EVAL should only contain keys in the same slot.
+        // these commands are sent to a single node
+        // so they should only contain keys in the same slot.
         if !same_slot(keys.iter().map(|b| b.as_slice())) {
             cmd_ctx.set_resp_result(Ok(Resp::Error(
                 response::ERR_NOT_THE_SAME_SLOT.to_string().into_bytes(),
@@ -1099,6 +2225,71 @@ where
         reply_receiver.await
     }
 
+    fn handle_object_cmd(&self, cmd_ctx: CmdCtx) {
+        let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 1) {
+            Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd),
+            None => return,
+        };
+
+        // Only the sub commands with a key can be routed to the backend.
+        let sub_cmd = sub_cmd.to_uppercase();
+        match sub_cmd.as_str() {
+            "ENCODING" | "FREQ" | "IDLETIME" | "REFCOUNT" => {
+                if cmd_ctx.get_cmd().get_command_element(2).is_none() {
+                    let err_msg = format!("ERR: Missing key for OBJECT {}", sub_cmd);
+                    cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
+                    return;
+                }
+                self.handle_single_key_data_cmd(cmd_ctx);
+            }
+            _ => {
+                let err_msg = format!("ERR: Unsupported OBJECT sub command {}", sub_cmd);
+                cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
+            }
+        }
+    }
+
+    fn handle_memory_cmd(
+        &self,
+        cmd_ctx: CmdCtx,
+        reply_receiver: CmdReplyReceiver,
+    ) -> CmdReplyFuture<'_> {
+        let (cmd_ctx, sub_cmd) = match Self::get_sub_command(cmd_ctx, 1) {
+            Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd),
+            None => return CmdReplyFuture::Left(reply_receiver),
+        };
+
+        let sub_cmd = sub_cmd.to_uppercase();
+        match sub_cmd.as_str() {
+            "USAGE" => {
+                if cmd_ctx.get_cmd().get_command_element(2).is_none() {
+                    let err_msg = "ERR: Missing key for MEMORY USAGE";
+                    cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.as_bytes().to_vec())));
+                } else {
+                    self.handle_single_key_data_cmd(cmd_ctx);
+                }
+            }
+            // The memory issues of the backends should be checked on the backends directly.
+            "DOCTOR" => {
+                let msg = b"Hi Sam, I can't find any memory issue in your instance. I can only account for what occurs on this base.";
+                cmd_ctx.set_resp_result(Ok(Resp::Bulk(BulkStr::Str(msg.to_vec()))));
+            }
+            // Only the stats of an arbitrary backend.
+            "STATS" => {
+                return CmdReplyFuture::Right(Box::pin(async move {
+                    let res = self.manager.send_to_any_local_node(&cmd_ctx).await;
+                    cmd_ctx.set_resp_result(Ok(res));
+                    reply_receiver.await
+                }))
+            }
+            _ => {
+                let err_msg = format!("ERR: Unsupported MEMORY sub command {}", sub_cmd);
+                cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
+            }
+        }
+        CmdReplyFuture::Left(reply_receiver)
+    }
+
     fn handle_single_key_data_cmd(&self, cmd_ctx: CmdCtx) {
         let mut cmd_ctx = cmd_ctx;
         match self.compressor.try_compressing_cmd_ctx(&mut cmd_ctx) {
@@ -1114,6 +2305,12 @@ where
                 return cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.to_string().into_bytes())));
             }
             Err(CompressionError::Io(err)) => {
+                error!(
+                    "failed to compress data db={} command={:?} error={:?}",
+                    self.get_db_name(),
+                    cmd_ctx.get_cmd().get_data_cmd_type(),
+                    err
+                );
                 return cmd_ctx.set_resp_result(Ok(Resp::Error(
                     format!("failed to compress data: {:?}", err).into_bytes(),
                 )));
@@ -1126,7 +2323,7 @@ where
         &self,
         cmd_ctx: CmdCtx,
         reply_receiver: CmdReplyReceiver,
-    ) -> CmdReplyFuture {
+    ) -> CmdReplyFuture<'_> {
         let (mut cmd_ctx, redirection_times) = match Self::get_sub_command(cmd_ctx, 1) {
             Some((cmd_ctx, sub_cmd)) => (cmd_ctx, sub_cmd.to_uppercase()),
             None => return CmdReplyFuture::Left(reply_receiver),
@@ -1149,6 +2346,7 @@ where
             }
         }
 
+        self.manager.strip_key_prefix(&mut cmd_ctx);
         cmd_ctx.set_redirection_times(times);
         self.handle_data_cmd(cmd_ctx, reply_receiver)
     }
@@ -1308,17 +2506,26 @@ where
         &self,
         cmd_ctx: CmdCtx,
         reply_receiver: CmdReplyReceiver,
-        authenticated: &atomic::AtomicBool,
-    ) -> CmdReplyFuture {
+        auth_state: &AuthState,
+        client_info: &ClientInfo,
+    ) -> CmdReplyFuture<'_> {
+        self.manager.get_command_stats().incr_commands();
+        let mut cmd_ctx = cmd_ctx;
+        cmd_ctx.set_asking(auth_state.take_asking());
+        if !self.rename_command(&mut cmd_ctx) {
+            let err_msg = gen_unknown_command_error(cmd_ctx.get_cmd());
+            cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
+            return CmdReplyFuture::Left(reply_receiver);
+        }
         let cmd_type = cmd_ctx.get_cmd().get_type();
         match cmd_type {
-            CmdType::Ping => {
-                cmd_ctx.set_resp_result(Ok(Resp::Simple(String::from("OK").into_bytes())))
+            CmdType::Ping => self.handle_ping(cmd_ctx),
+            CmdType::Info => {
+                return CmdReplyFuture::Right(Box::pin(self.handle_info(cmd_ctx, reply_receiver)))
             }
-            CmdType::Info => self.handle_info(cmd_ctx),
-            CmdType::Auth => self.handle_auth(cmd_ctx, authenticated),
+            CmdType::Auth => self.handle_auth(cmd_ctx, auth_state),
             CmdType::Quit => {
-                cmd_ctx.set_resp_result(Ok(Resp::Simple(String::from("OK").into_bytes())))
+                cmd_ctx.set_resp_result(Ok(Resp::Simple(response::OK_REPLY.as_bytes().to_vec())))
             }
             CmdType::Echo => {
                 match cmd_ctx
@@ -1330,41 +2537,179 @@ where
                     None => cmd_ctx.set_resp_result(Ok(Resp::Error(b"Missing message".to_vec()))),
                 }
             }
-            CmdType::Select => cmd_ctx.set_resp_result(Ok(Resp::Simple(
-                response::OK_REPLY.to_string().into_bytes(),
-            ))),
+            CmdType::Select => self.handle_select(cmd_ctx),
             CmdType::Invalid => cmd_ctx.set_resp_result(Ok(Resp::Error(
-                String::from("Invalid command").into_bytes(),
+                response::ERR_INVALID_COMMAND.to_string().into_bytes(),
             ))),
-            CmdType::UmCtl => self.handle_umctl(cmd_ctx),
+            CmdType::UmCtl | CmdType::Cluster if self.admin_auth_required(auth_state) => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(response::ERR_NOAUTH.as_bytes().to_vec())))
+            }
+            CmdType::UmCtl => match self.check_umctl_permission(auth_state) {
+                Ok(()) => self.handle_umctl(cmd_ctx),
+                Err(err) => cmd_ctx.set_resp_result(Ok(Resp::Error(err.to_vec()))),
+            },
             CmdType::UmForward => return self.handle_umforward(cmd_ctx, reply_receiver),
             CmdType::UmSync => {
                 return CmdReplyFuture::Right(Box::pin(self.handle_umsync(cmd_ctx, reply_receiver)))
             }
-            CmdType::Cluster => self.handle_cluster(cmd_ctx),
+            CmdType::Cluster => self.handle_cluster(cmd_ctx, auth_state),
             CmdType::Config => self.handle_config(cmd_ctx),
             CmdType::Command => return self.handle_command_cmd(cmd_ctx, reply_receiver),
-            CmdType::Asking => cmd_ctx.set_resp_result(Ok(Resp::Simple(
-                response::OK_REPLY.to_string().into_bytes(),
-            ))),
+            CmdType::Asking => {
+                auth_state.set_asking();
+                cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                    response::OK_REPLY.to_string().into_bytes(),
+                )))
+            }
             CmdType::Hello => {
                 // Redis 6 clients would use this command to change protocol.
                 // Make server proxy act as low version Redis.
                 let err_msg = b"ERR unknown command `hello`";
                 cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.to_vec())))
             }
+            CmdType::Lolwut => self.handle_lolwut(cmd_ctx),
+            CmdType::Time => self.handle_time(cmd_ctx),
+            CmdType::LastSave => self.handle_lastsave(cmd_ctx),
+            CmdType::Role => cmd_ctx.set_resp_result(Ok(self.manager.get_role())),
+            CmdType::Client => self.handle_client(cmd_ctx, auth_state, client_info),
+            CmdType::Debug => return self.handle_debug(cmd_ctx, reply_receiver),
+            CmdType::Watch if self.data_auth_required(auth_state) => {
+                cmd_ctx.set_resp_result(Ok(Resp::Error(response::ERR_NOAUTH.as_bytes().to_vec())))
+            }
+            CmdType::Watch => self.handle_watch(cmd_ctx),
+            CmdType::Unwatch => cmd_ctx.set_resp_result(Ok(Resp::Simple(
+                response::OK_REPLY.to_string().into_bytes(),
+            ))),
             CmdType::Others => {
-                if self.config.password.is_some() && !authenticated.load(atomic::Ordering::Relaxed)
+                if self.data_auth_required(auth_state) {
+                    cmd_ctx
+                        .set_resp_result(Ok(Resp::Error(response::ERR_NOAUTH.as_bytes().to_vec())));
+                    return CmdReplyFuture::Left(reply_receiver);
+                }
+
+                if self.unknown_command_policy == UnknownCommandPolicy::Reject
+                    && !self.is_known_command(&cmd_ctx)
                 {
-                    cmd_ctx.set_resp_result(Ok(Resp::Error(
-                        b"Password not given by AUTH command".to_vec(),
-                    )));
+                    let err_msg = gen_unknown_command_error(cmd_ctx.get_cmd());
+                    cmd_ctx.set_resp_result(Ok(Resp::Error(err_msg.into_bytes())));
                     return CmdReplyFuture::Left(reply_receiver);
                 }
 
-                return self.handle_data_cmd(cmd_ctx, reply_receiver);
+                return self.handle_client_data_cmd(cmd_ctx, reply_receiver, client_info);
             }
         };
         CmdReplyFuture::Left(reply_receiver)
     }
+
+    fn get_db_name(&self) -> String {
+        self.manager.get_cluster().to_string()
+    }
+
+    fn get_pause_remaining(&self) -> Duration {
+        self.client_pause.get_remaining()
+    }
+}
+
+fn unix_time_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|err| {
+            error!("system time is before unix epoch: {:?}", err);
+            Duration::default()
+        })
+}
+
+// Multi-key commands like MGET, MSET, MSETNX, and cross-slot DEL and EXISTS are split into sub commands.
+// Like Redis, they fail as a whole without partial results.
+// If any sub command fails, the first error in the key order is returned.
+// Note that the succeeded sub commands of MSET and DEL are not rolled back.
+async fn join_sub_replies<I>(futs: I) -> Result<Vec<RespVec>, RespVec>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = CmdTaskResult>,
+{
+    let mut replies = vec![];
+    for sub_result in future::join_all(futs).await.into_iter() {
+        match sub_result {
+            Ok(Resp::Error(err)) => return Err(Resp::Error(err)),
+            Ok(reply) => replies.push(reply),
+            Err(err) => return Err(Resp::Error(format!("ERR: {}", err).into_bytes())),
+        }
+    }
+    Ok(replies)
+}
+
+// Follow the format of Redis:
+// ERR unknown command `xxx`, with args beginning with: `a`, `b`,
+fn gen_unknown_command_error(cmd: &Command) -> String {
+    const MAX_ARGS_LEN: usize = 128;
+
+    let cmd_name = cmd.get_command_name().unwrap_or("");
+    let mut args = String::new();
+    for i in 1.. {
+        // Same as Redis, the last argument is cut to fit the limit.
+        let remaining = match MAX_ARGS_LEN.checked_sub(args.len()) {
+            Some(remaining) if remaining > 0 => remaining,
+            _ => break,
+        };
+        let arg = match cmd.get_command_element(i) {
+            Some(arg) => arg,
+            None => break,
+        };
+        let arg: String = String::from_utf8_lossy(arg)
+            .chars()
+            .take(remaining)
+            .collect();
+        args.push_str(&format!("`{}`, ", arg));
+    }
+    format!(
+        "ERR unknown command `{}`, with args beginning with: {}",
+        cmd_name, args
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::command::CommandError;
+
+    #[tokio::test]
+    async fn test_join_sub_replies() {
+        let futs = vec![
+            future::ok(Resp::Bulk(BulkStr::Str(b"v1".to_vec()))),
+            future::ok(Resp::Bulk(BulkStr::Nil)),
+        ];
+        let replies = join_sub_replies(futs).await.unwrap();
+        assert_eq!(
+            replies,
+            vec![
+                Resp::Bulk(BulkStr::Str(b"v1".to_vec())),
+                Resp::Bulk(BulkStr::Nil)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_join_sub_replies_with_error_reply() {
+        let futs = vec![
+            future::ok(Resp::Bulk(BulkStr::Str(b"v1".to_vec()))),
+            future::ok(Resp::Error(b"ERR_BACKEND_CONNECTION".to_vec())),
+            future::ok(Resp::Error(b"MOVED 233 127.0.0.1:6000".to_vec())),
+        ];
+        let err = join_sub_replies(futs).await.unwrap_err();
+        assert_eq!(err, Resp::Error(b"ERR_BACKEND_CONNECTION".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_join_sub_replies_with_task_error() {
+        let futs = vec![
+            future::ok(Resp::Bulk(BulkStr::Str(b"v1".to_vec()))),
+            future::err(CommandError::Canceled),
+        ];
+        let err = join_sub_replies(futs).await.unwrap_err();
+        match err {
+            Resp::Error(err) => assert!(err.starts_with(b"ERR: ")),
+            other => panic!("unexpected reply {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,199 @@
+use super::table::NoKeyRouting;
+use crate::protocol::{Array, BulkStr, Resp, RespVec};
+use btoi::{btoi, btou};
+
+// The cursor of SCAN returned to the clients keeps the index of the backend
+// in the high bits and the cursor of that backend in the low bits.
+const SCAN_INDEX_SHIFT: u32 = 54;
+const SCAN_CURSOR_MASK: u64 = (1 << SCAN_INDEX_SHIFT) - 1;
+
+const ERR_INVALID_BACKEND_REPLY: &str = "ERR invalid reply from backend";
+
+// The error replies should already be filtered out.
+pub fn merge_replies(routing: NoKeyRouting, replies: Vec<RespVec>, random: u64) -> RespVec {
+    match routing {
+        NoKeyRouting::Random => {
+            let mut keys: Vec<RespVec> = replies
+                .into_iter()
+                .filter(|reply| matches!(reply, Resp::Bulk(BulkStr::Str(_))))
+                .collect();
+            if keys.is_empty() {
+                return Resp::Bulk(BulkStr::Nil);
+            }
+            let index = (random % keys.len() as u64) as usize;
+            keys.swap_remove(index)
+        }
+        NoKeyRouting::AllOk => Resp::Simple(b"OK".to_vec()),
+        NoKeyRouting::SumIntegers => {
+            let mut sum: i64 = 0;
+            for reply in replies.into_iter() {
+                let n = match reply {
+                    Resp::Integer(n) => btoi::<i64>(&n).ok(),
+                    _ => None,
+                };
+                match n {
+                    Some(n) => sum = sum.saturating_add(n),
+                    None => return Resp::Error(ERR_INVALID_BACKEND_REPLY.as_bytes().to_vec()),
+                }
+            }
+            Resp::Integer(sum.to_string().into_bytes())
+        }
+        NoKeyRouting::ConcatArrays => {
+            let mut elements = vec![];
+            for reply in replies.into_iter() {
+                match reply {
+                    Resp::Arr(Array::Arr(arr)) => elements.extend(arr),
+                    Resp::Arr(Array::Nil) => (),
+                    _ => return Resp::Error(ERR_INVALID_BACKEND_REPLY.as_bytes().to_vec()),
+                }
+            }
+            Resp::Arr(Array::Arr(elements))
+        }
+        NoKeyRouting::Scan => Resp::Error(b"ERR SCAN replies can't be merged".to_vec()),
+    }
+}
+
+// Returns the index of the backend and the cursor of that backend.
+pub fn split_scan_cursor(cursor: u64) -> (usize, u64) {
+    (
+        (cursor >> SCAN_INDEX_SHIFT) as usize,
+        cursor & SCAN_CURSOR_MASK,
+    )
+}
+
+pub fn join_scan_cursor(index: usize, cursor: u64) -> Option<u64> {
+    if cursor > SCAN_CURSOR_MASK || (index as u64) > (u64::MAX >> SCAN_INDEX_SHIFT) {
+        return None;
+    }
+    Some(((index as u64) << SCAN_INDEX_SHIFT) | cursor)
+}
+
+// Converts the SCAN reply of the backend at `index` to the reply for the clients.
+// When the backend finishes, the next cursor starts from the next backend.
+pub fn gen_scan_reply(index: usize, node_num: usize, reply: RespVec) -> RespVec {
+    let (cursor, keys) = match reply {
+        Resp::Arr(Array::Arr(mut arr)) if arr.len() == 2 => {
+            let keys = arr.pop();
+            let cursor = match arr.pop() {
+                Some(Resp::Bulk(BulkStr::Str(cursor))) => btou::<u64>(&cursor).ok(),
+                _ => None,
+            };
+            match (cursor, keys) {
+                (Some(cursor), Some(keys)) => (cursor, keys),
+                _ => return Resp::Error(ERR_INVALID_BACKEND_REPLY.as_bytes().to_vec()),
+            }
+        }
+        Resp::Error(err) => return Resp::Error(err),
+        _ => return Resp::Error(ERR_INVALID_BACKEND_REPLY.as_bytes().to_vec()),
+    };
+
+    let next_cursor = if cursor != 0 {
+        join_scan_cursor(index, cursor)
+    } else if index + 1 < node_num {
+        join_scan_cursor(index + 1, 0)
+    } else {
+        Some(0)
+    };
+    let next_cursor = match next_cursor {
+        Some(next_cursor) => next_cursor,
+        None => return Resp::Error(b"ERR backend cursor is too large".to_vec()),
+    };
+    Resp::Arr(Array::Arr(vec![
+        Resp::Bulk(BulkStr::Str(next_cursor.to_string().into_bytes())),
+        keys,
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gen_bulk(s: &str) -> RespVec {
+        Resp::Bulk(BulkStr::Str(s.as_bytes().to_vec()))
+    }
+
+    fn gen_scan(cursor: &str, keys: &[&str]) -> RespVec {
+        Resp::Arr(Array::Arr(vec![
+            gen_bulk(cursor),
+            Resp::Arr(Array::Arr(keys.iter().map(|key| gen_bulk(key)).collect())),
+        ]))
+    }
+
+    #[test]
+    fn test_merge_replies() {
+        let replies = vec![Resp::Integer(b"3".to_vec()), Resp::Integer(b"4".to_vec())];
+        assert_eq!(
+            merge_replies(NoKeyRouting::SumIntegers, replies, 0),
+            Resp::Integer(b"7".to_vec())
+        );
+
+        let replies = vec![
+            Resp::Arr(Array::Arr(vec![gen_bulk("a")])),
+            Resp::Arr(Array::Arr(vec![])),
+            Resp::Arr(Array::Arr(vec![gen_bulk("b"), gen_bulk("c")])),
+        ];
+        assert_eq!(
+            merge_replies(NoKeyRouting::ConcatArrays, replies, 0),
+            Resp::Arr(Array::Arr(vec![
+                gen_bulk("a"),
+                gen_bulk("b"),
+                gen_bulk("c")
+            ]))
+        );
+
+        let replies = vec![Resp::Simple(b"OK".to_vec()), Resp::Simple(b"OK".to_vec())];
+        assert_eq!(
+            merge_replies(NoKeyRouting::AllOk, replies, 0),
+            Resp::Simple(b"OK".to_vec())
+        );
+
+        let replies = vec![Resp::Bulk(BulkStr::Nil), gen_bulk("a"), gen_bulk("b")];
+        assert_eq!(
+            merge_replies(NoKeyRouting::Random, replies.clone(), 0),
+            gen_bulk("a")
+        );
+        assert_eq!(
+            merge_replies(NoKeyRouting::Random, replies, 3),
+            gen_bulk("b")
+        );
+        assert_eq!(
+            merge_replies(NoKeyRouting::Random, vec![Resp::Bulk(BulkStr::Nil)], 1),
+            Resp::Bulk(BulkStr::Nil)
+        );
+
+        let replies = vec![Resp::Integer(b"1".to_vec()), gen_bulk("a")];
+        assert!(matches!(
+            merge_replies(NoKeyRouting::SumIntegers, replies, 0),
+            Resp::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_scan_cursor() {
+        assert_eq!(split_scan_cursor(0), (0, 0));
+        assert_eq!(join_scan_cursor(0, 233), Some(233));
+        let cursor = join_scan_cursor(2, 233).unwrap();
+        assert_eq!(split_scan_cursor(cursor), (2, 233));
+        assert_eq!(join_scan_cursor(1, 1 << SCAN_INDEX_SHIFT), None);
+    }
+
+    #[test]
+    fn test_gen_scan_reply() {
+        let reply = gen_scan_reply(0, 2, gen_scan("233", &["a"]));
+        assert_eq!(reply, gen_scan("233", &["a"]));
+
+        let next = join_scan_cursor(1, 0).unwrap().to_string();
+        let reply = gen_scan_reply(0, 2, gen_scan("0", &["b"]));
+        assert_eq!(reply, gen_scan(&next, &["b"]));
+
+        let cursor = join_scan_cursor(1, 7).unwrap().to_string();
+        let reply = gen_scan_reply(1, 2, gen_scan("7", &[]));
+        assert_eq!(reply, gen_scan(&cursor, &[]));
+
+        let reply = gen_scan_reply(1, 2, gen_scan("0", &["c"]));
+        assert_eq!(reply, gen_scan("0", &["c"]));
+
+        let reply = gen_scan_reply(0, 1, Resp::Integer(b"1".to_vec()));
+        assert!(matches!(reply, Resp::Error(_)));
+    }
+}
@@ -0,0 +1,226 @@
+use super::backend::{BackendConnStatus, BackendSummary};
+use crate::common::cluster::RangeList;
+use crate::migration::task::MigrationState;
+use crate::replication::replicator::{MasterMeta, ReplicaMeta};
+use std::collections::HashMap;
+
+// Bump it on any incompatible change of `ProxyHealth`.
+// Adding new fields is compatible.
+pub const PROXY_HEALTH_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    // Some backends are unreachable.
+    Degraded,
+    // Same as `UMCTL READY` returning 0.
+    NotReady,
+}
+
+// The health of the whole proxy in one document for the dashboards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyHealth {
+    pub version: u32,
+    pub status: HealthStatus,
+    pub cluster: String,
+    pub epoch: u64,
+    #[serde(default)]
+    pub connected_clients: usize,
+    pub backends: Vec<BackendHealth>,
+    pub migrations: Vec<MigrationHealth>,
+    pub replication: Vec<ReplicationHealth>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendHealth {
+    pub address: String,
+    pub reachable: bool,
+    pub status: String,
+    pub conns: usize,
+    pub in_flight: usize,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationHealth {
+    pub slot_range: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationHealth {
+    pub role: String,
+    pub node_address: String,
+    // <node address>@<proxy address> of the replicas or masters.
+    pub peers: Vec<String>,
+}
+
+pub struct ProxyHealthSource {
+    pub cluster: String,
+    pub epoch: u64,
+    pub ready: bool,
+    pub connected_clients: usize,
+    pub backends: Vec<BackendSummary>,
+    pub migration_states: HashMap<RangeList, MigrationState>,
+    pub master_metadata: Vec<MasterMeta>,
+    pub replica_metadata: Vec<ReplicaMeta>,
+}
+
+impl ProxyHealth {
+    pub fn new(source: ProxyHealthSource) -> Self {
+        let ProxyHealthSource {
+            cluster,
+            epoch,
+            ready,
+            connected_clients,
+            backends,
+            migration_states,
+            master_metadata,
+            replica_metadata,
+        } = source;
+
+        let backends: Vec<BackendHealth> = backends
+            .into_iter()
+            .map(|summary| BackendHealth {
+                reachable: summary.status != BackendConnStatus::Failed,
+                status: summary.status.to_string(),
+                address: summary.address,
+                conns: summary.conn_num,
+                in_flight: summary.in_flight,
+                last_error: summary.last_error,
+            })
+            .collect();
+
+        let mut migrations: Vec<MigrationHealth> = migration_states
+            .into_iter()
+            .map(|(range_list, state)| MigrationHealth {
+                slot_range: range_list.to_string(),
+                state: state.to_string(),
+            })
+            .collect();
+        migrations.sort_by(|a, b| a.slot_range.cmp(&b.slot_range));
+
+        let masters = master_metadata.into_iter().map(|meta| ReplicationHealth {
+            role: "master".to_string(),
+            node_address: meta.master_node_address,
+            peers: meta
+                .replicas
+                .into_iter()
+                .map(|peer| format!("{}@{}", peer.node_address, peer.proxy_address))
+                .collect(),
+        });
+        let replicas = replica_metadata.into_iter().map(|meta| ReplicationHealth {
+            role: "replica".to_string(),
+            node_address: meta.replica_node_address,
+            peers: meta
+                .masters
+                .into_iter()
+                .map(|peer| format!("{}@{}", peer.node_address, peer.proxy_address))
+                .collect(),
+        });
+        let replication = masters.chain(replicas).collect();
+
+        let status = if !ready {
+            HealthStatus::NotReady
+        } else if backends.iter().any(|backend| !backend.reachable) {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Ok
+        };
+
+        Self {
+            version: PROXY_HEALTH_VERSION,
+            status,
+            cluster,
+            epoch,
+            connected_clients,
+            backends,
+            migrations,
+            replication,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::cluster::{ClusterName, ReplPeer};
+    use std::convert::TryFrom;
+
+    fn gen_summary(address: &str, status: BackendConnStatus) -> BackendSummary {
+        BackendSummary {
+            address: address.to_string(),
+            status,
+            conn_num: 2,
+            in_flight: 0,
+            last_error: None,
+        }
+    }
+
+    fn gen_source() -> ProxyHealthSource {
+        let mut migration_states = HashMap::new();
+        migration_states.insert(
+            RangeList::try_from("1 0-100").unwrap(),
+            MigrationState::Scanning,
+        );
+        ProxyHealthSource {
+            cluster: "mycluster".to_string(),
+            epoch: 7,
+            ready: true,
+            connected_clients: 3,
+            backends: vec![gen_summary("127.0.0.1:6379", BackendConnStatus::Connected)],
+            migration_states,
+            master_metadata: vec![MasterMeta {
+                cluster_name: ClusterName::try_from("mycluster").unwrap(),
+                master_node_address: "127.0.0.1:6379".to_string(),
+                replicas: vec![ReplPeer {
+                    node_address: "127.0.0.1:6380".to_string(),
+                    proxy_address: "127.0.0.1:5300".to_string(),
+                }],
+            }],
+            replica_metadata: vec![],
+        }
+    }
+
+    #[test]
+    fn test_proxy_health() {
+        let health = ProxyHealth::new(gen_source());
+        assert_eq!(health.version, PROXY_HEALTH_VERSION);
+        assert_eq!(health.status, HealthStatus::Ok);
+        assert_eq!(health.migrations[0].slot_range, "[0-100]");
+        assert_eq!(
+            health.migrations[0].state,
+            MigrationState::Scanning.to_string()
+        );
+        assert_eq!(health.replication[0].role, "master");
+        assert_eq!(
+            health.replication[0].peers,
+            vec!["127.0.0.1:6380@127.0.0.1:5300".to_string()]
+        );
+
+        let json = serde_json::to_value(&health).unwrap();
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["connected_clients"], 3);
+        assert_eq!(json["backends"][0]["reachable"], true);
+        assert_eq!(json["backends"][0]["status"], "connected");
+    }
+
+    #[test]
+    fn test_proxy_health_status() {
+        let mut source = gen_source();
+        source
+            .backends
+            .push(gen_summary("127.0.0.1:6380", BackendConnStatus::Failed));
+        let health = ProxyHealth::new(source);
+        assert_eq!(health.status, HealthStatus::Degraded);
+        assert!(!health.backends[1].reachable);
+
+        let mut source = gen_source();
+        source.ready = false;
+        let health = ProxyHealth::new(source);
+        assert_eq!(health.status, HealthStatus::NotReady);
+        let json = serde_json::to_value(&health).unwrap();
+        assert_eq!(json["status"], "not_ready");
+    }
+}
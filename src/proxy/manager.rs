@@ -1,9 +1,15 @@
-use super::backend::{CmdTask, ConnFactory, IntoTask, SenderBackendError};
+use super::backend::{
+    BackendConnStates, BackendSummary, CmdTask, ConnFactory, IntoTask, SenderBackendError,
+};
 use super::blocking::{
     gen_basic_blocking_sender_factory, gen_blocking_sender_factory, BasicBlockingSenderFactory,
-    BlockingBackendSenderFactory, BlockingCmdTaskSender, BlockingMap, CounterTask,
+    BlockingBackendSenderFactory, BlockingCmdTaskSender, BlockingHint, BlockingHintTask,
+    BlockingMap, CounterTask,
 };
-use super::cluster::{ClusterBackendMap, ClusterMetaError, ClusterSendError};
+use super::cluster::{gen_cluster_links, ClusterBackendMap, ClusterMetaError, ClusterSendError};
+use super::command::{new_command_pair, Command};
+use super::health::{ProxyHealth, ProxyHealthSource};
+use super::prefix::{prefix_keys, strip_cmd_keys};
 use super::reply::{DecompressCommitHandlerFactory, ReplyCommitHandlerFactory};
 use super::sender::{
     gen_migration_sender_factory, gen_sender_factory, BackendSenderFactory, CmdTaskSender,
@@ -12,15 +18,18 @@ use super::sender::{
 use super::service::ServerProxyConfig;
 use super::session::{CmdCtx, CmdCtxFactory};
 use super::slowlog::TaskEvent;
+use super::stats::{BackendWarmupStats, CommandStats};
+use super::table::CommandTable;
 use crate::common::batch::BatchStats;
-use crate::common::cluster::{ClusterName, MigrationTaskMeta, SlotRangeTag};
+use crate::common::cluster::{ClusterName, MigrationTaskMeta, RangeList, SlotRangeTag};
+use crate::common::config::{ConfigError, MigrationConfig};
 use crate::common::proto::{NodeMap, ProxyClusterMeta};
 use crate::common::response;
 use crate::common::track::TrackedFutureRegistry;
-use crate::common::utils::{gen_moved, RetryError};
+use crate::common::utils::{gen_moved, RedirectionTarget, RetryError};
 use crate::migration::manager::{MigrationManager, MigrationMap, SwitchError};
 use crate::migration::task::MgrSubCmd;
-use crate::migration::task::SwitchArg;
+use crate::migration::task::{MigrationTaskStatus, SwitchArg};
 use crate::protocol::{
     Array, BinSafeStr, BulkStr, RedisClient, RedisClientFactory, Resp, RespPacket, RespVec,
 };
@@ -29,8 +38,9 @@ use crate::proxy::migration_backend::WaitableTask;
 use crate::replication::manager::ReplicatorManager;
 use crate::replication::replicator::ReplicatorMeta;
 use arc_swap::{ArcSwap, Lease};
+use futures::channel::mpsc;
+use futures::{future, Stream};
 use std::num::NonZeroUsize;
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 pub struct MetaMap<S: CmdTaskSender, P: CmdTaskSender, T>
@@ -38,6 +48,9 @@ where
     <S as CmdTaskSender>::Task: IntoTask<<P as CmdTaskSender>::Task>,
     T: CmdTask,
 {
+    // The epoch is kept in the same snapshot as the topology
+    // so that the readers never see a new epoch with the old topology or the reverse.
+    epoch: u64,
     cluster_map: ClusterBackendMap<S, P>,
     migration_map: MigrationMap<T>,
 }
@@ -51,16 +64,29 @@ where
         let cluster_map = ClusterBackendMap::default();
         let migration_map = MigrationMap::empty();
         Self {
+            epoch: 0,
             cluster_map,
             migration_map,
         }
     }
 
+    pub fn get_epoch(&self) -> u64 {
+        self.epoch
+    }
+
     pub fn get_cluster_map(&self) -> &ClusterBackendMap<S, P> {
         &self.cluster_map
     }
 }
 
+// The changes of the local slots compared with the previous metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotOwnershipEvent {
+    pub epoch: u64,
+    pub added: RangeList,
+    pub removed: RangeList,
+}
+
 type BasicSenderFactory<C> =
     BasicBlockingSenderFactory<DecompressCommitHandlerFactory<CounterTask<CmdCtx>, C>, C>;
 type SenderFactory<C> = BlockingBackendSenderFactory<
@@ -70,6 +96,7 @@ type SenderFactory<C> = BlockingBackendSenderFactory<
 >;
 
 type PeerSenderFactory<C> = BackendSenderFactory<ReplyCommitHandlerFactory, C>;
+pub type PeerSender<C> = <PeerSenderFactory<C> as CmdTaskSenderFactory>::Sender;
 
 type MigrationSenderFactory<C> =
     MigrationBackendSenderFactory<DecompressCommitHandlerFactory<CmdCtx, C>, C>;
@@ -77,11 +104,8 @@ type MigrationDstSenderFactory<C> =
     MigrationBackendSenderFactory<DecompressCommitHandlerFactory<WaitableTask<CmdCtx>, C>, C>;
 type MigrationProxySenderFactory<C> = MigrationBackendSenderFactory<ReplyCommitHandlerFactory, C>;
 
-type ProxyMetaMap<C> = MetaMap<
-    <SenderFactory<C> as CmdTaskSenderFactory>::Sender,
-    <PeerSenderFactory<C> as CmdTaskSenderFactory>::Sender,
-    CmdCtx,
->;
+type ProxyMetaMap<C> =
+    MetaMap<<SenderFactory<C> as CmdTaskSenderFactory>::Sender, PeerSender<C>, CmdCtx>;
 pub type SharedMetaMap<C> = Arc<ArcSwap<ProxyMetaMap<C>>>;
 
 pub struct MetaManager<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> {
@@ -90,8 +114,7 @@ pub struct MetaManager<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>>
     // between replication metadata and other metadata, we should put that
     // inside meta_map.
     meta_map: SharedMetaMap<C>,
-    epoch: AtomicU64,
-    lock: parking_lot::Mutex<()>, // This is the write lock for `epoch`, `cluster`, and `task`.
+    lock: parking_lot::Mutex<()>, // This is the write lock for `meta_map`.
     replicator_manager: ReplicatorManager<F>,
     migration_manager: MigrationManager<
         F,
@@ -105,6 +128,13 @@ pub struct MetaManager<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>>
     blocking_map: Arc<BlockingMap<BasicSenderFactory<C>, BlockingTaskRetrySender<C>>>,
     client_factory: Arc<F>,
     batch_stats: Arc<BatchStats>,
+    conn_states: Arc<BackendConnStates>,
+    warmup_stats: Arc<BackendWarmupStats>,
+    command_stats: Arc<CommandStats>,
+    future_registry: Arc<TrackedFutureRegistry>,
+    // For finding the keys to be prefixed.
+    command_table: CommandTable,
+    slot_subscribers: parking_lot::Mutex<Vec<mpsc::UnboundedSender<SlotOwnershipEvent>>>,
 }
 
 impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C> {
@@ -116,7 +146,12 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         future_registry: Arc<TrackedFutureRegistry>,
     ) -> Self {
         let batch_stats = Arc::new(BatchStats::default());
-        let reply_handler_factory = Arc::new(DecompressCommitHandlerFactory::new(meta_map.clone()));
+        let conn_states = Arc::new(BackendConnStates::default());
+        let command_stats = Arc::new(CommandStats::default());
+        let reply_handler_factory = Arc::new(DecompressCommitHandlerFactory::new(
+            meta_map.clone(),
+            command_stats.clone(),
+        ));
         let blocking_task_sender = Arc::new(BlockingTaskRetrySender::new(
             meta_map.clone(),
             config.max_redirections,
@@ -128,6 +163,7 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
             conn_factory.clone(),
             future_registry.clone(),
             batch_stats.clone(),
+            conn_states.clone(),
         );
         let blocking_map = Arc::new(BlockingMap::new(basic_sender_factory, blocking_task_sender));
         let sender_factory = gen_blocking_sender_factory(blocking_map.clone());
@@ -138,20 +174,29 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
             conn_factory.clone(),
             future_registry.clone(),
             batch_stats.clone(),
+            conn_states.clone(),
         );
         let migration_sender_factory = Arc::new(gen_migration_sender_factory(
             config.clone(),
-            Arc::new(DecompressCommitHandlerFactory::new(meta_map.clone())),
+            Arc::new(DecompressCommitHandlerFactory::new(
+                meta_map.clone(),
+                command_stats.clone(),
+            )),
             conn_factory.clone(),
             future_registry.clone(),
             batch_stats.clone(),
+            conn_states.clone(),
         ));
         let migration_dst_sender_factory = Arc::new(gen_migration_sender_factory(
             config.clone(),
-            Arc::new(DecompressCommitHandlerFactory::new(meta_map.clone())),
+            Arc::new(DecompressCommitHandlerFactory::new(
+                meta_map.clone(),
+                command_stats.clone(),
+            )),
             conn_factory.clone(),
             future_registry.clone(),
             batch_stats.clone(),
+            conn_states.clone(),
         ));
         let migration_proxy_sender_factory = Arc::new(gen_migration_sender_factory(
             config.clone(),
@@ -159,13 +204,13 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
             conn_factory,
             future_registry.clone(),
             batch_stats.clone(),
+            conn_states.clone(),
         ));
         let cmd_ctx_factory = Arc::new(CmdCtxFactory::default());
         let config_clone = config.clone();
         Self {
             config,
             meta_map,
-            epoch: AtomicU64::new(0),
             lock: parking_lot::Mutex::new(()),
             replicator_manager: ReplicatorManager::new(
                 client_factory.clone(),
@@ -178,13 +223,19 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
                 migration_dst_sender_factory,
                 migration_proxy_sender_factory,
                 cmd_ctx_factory,
-                future_registry,
+                future_registry.clone(),
             ),
             sender_factory,
             peer_sender_factory,
             blocking_map,
             client_factory,
             batch_stats,
+            conn_states,
+            warmup_stats: Arc::new(BackendWarmupStats::default()),
+            command_stats,
+            future_registry,
+            command_table: CommandTable::default(),
+            slot_subscribers: parking_lot::Mutex::new(vec![]),
         }
     }
 
@@ -207,6 +258,32 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
             .gen_cluster_slots(self.config.announce_address.clone(), &migration_states)
     }
 
+    pub fn gen_cluster_shards(&self) -> Result<RespVec, String> {
+        let meta_map = self.meta_map.load();
+        let migration_states = meta_map.migration_map.get_states();
+        meta_map
+            .cluster_map
+            .gen_cluster_shards(self.config.announce_address.clone(), &migration_states)
+    }
+
+    pub fn gen_routes(&self) -> Vec<String> {
+        let meta_map = self.meta_map.load();
+        let migration_states = meta_map.migration_map.get_states();
+        meta_map.cluster_map.gen_routes(&migration_states)
+    }
+
+    pub fn gen_cluster_links(&self) -> RespVec {
+        gen_cluster_links(self.get_backend_summaries())
+    }
+
+    pub fn gen_cluster_info(&self) -> String {
+        let meta_map = self.meta_map.load();
+        let migration_states = meta_map.migration_map.get_states();
+        meta_map
+            .cluster_map
+            .gen_cluster_info(&migration_states, &self.get_backend_summaries())
+    }
+
     pub fn get_cluster(&self) -> ClusterName {
         self.meta_map.load().cluster_map.get_cluster()
     }
@@ -229,13 +306,11 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         {
             let _guard = self.lock.lock();
 
-            if cluster_meta.get_epoch() <= self.epoch.load(Ordering::SeqCst)
-                && !cluster_meta.get_flags().force
-            {
+            let old_meta_map = self.meta_map.load();
+            if cluster_meta.get_epoch() <= old_meta_map.epoch && !cluster_meta.get_flags().force {
                 return Err(ClusterMetaError::OldEpoch);
             }
 
-            let old_meta_map = self.meta_map.load();
             let cluster_map = ClusterBackendMap::from_cluster_map(
                 &cluster_meta,
                 sender_factory,
@@ -250,19 +325,132 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
                 self.blocking_map.clone(),
             );
 
-            self.meta_map.store(Arc::new(MetaMap {
+            // The data commands will see either the old or the new one as a whole.
+            let new_meta_map = Arc::new(MetaMap {
+                epoch: cluster_meta.get_epoch(),
                 cluster_map,
                 migration_map,
-            }));
-            // Should go after the meta_map.store above
-            self.epoch.store(cluster_meta.get_epoch(), Ordering::SeqCst);
+            });
+            self.meta_map.store(new_meta_map.clone());
+            self.publish_slot_changes(&old_meta_map, &new_meta_map);
 
             self.migration_manager.run_tasks(new_tasks);
         };
 
+        if self.config.backend_warmup {
+            self.warmup_backends();
+        }
+
         Ok(())
     }
 
+    // Clears all the cluster, migration and replication metadata.
+    // The running migration tasks will be aborted.
+    pub fn reset(&self, hard: bool) {
+        let _guard = self.lock.lock();
+
+        self.migration_manager.stop();
+        let old_meta_map = self.meta_map.load();
+        let epoch = if hard { 0 } else { old_meta_map.epoch };
+        let new_meta_map = Arc::new(MetaMap {
+            epoch,
+            ..MetaMap::empty()
+        });
+        self.meta_map.store(new_meta_map.clone());
+        self.publish_slot_changes(&old_meta_map, &new_meta_map);
+        self.replicator_manager.reset(hard);
+        self.warmup_stats.retain(&[]);
+        info!("metadata reset hard: {}", hard);
+    }
+
+    // Receives the changes of the local slots whenever the metadata is updated.
+    // Dropping the stream unsubscribes it.
+    pub fn subscribe_slot_changes(&self) -> impl Stream<Item = SlotOwnershipEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.slot_subscribers.lock().push(sender);
+        receiver
+    }
+
+    // Should be called with `lock` held so that the events are in the order of the updates.
+    fn publish_slot_changes(&self, old_meta_map: &ProxyMetaMap<C>, new_meta_map: &ProxyMetaMap<C>) {
+        let mut subscribers = self.slot_subscribers.lock();
+        // Don't compute the changes when nobody cares.
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let old_slots = old_meta_map.cluster_map.get_local_owned_slots();
+        let new_slots = new_meta_map.cluster_map.get_local_owned_slots();
+        let added = new_slots.difference(&old_slots);
+        let removed = old_slots.difference(&new_slots);
+        if added.get_ranges().is_empty() && removed.get_ranges().is_empty() {
+            return;
+        }
+
+        let event = SlotOwnershipEvent {
+            epoch: new_meta_map.epoch,
+            added,
+            removed,
+        };
+        subscribers.retain(|sender| sender.unbounded_send(event.clone()).is_ok());
+    }
+
+    // Best-effort. Send PINGs through all the connections of the local backends
+    // so that they are established before the client commands come.
+    fn warmup_backends(&self) {
+        let meta_map = self.meta_map.load();
+        let addresses = meta_map.cluster_map.get_local_nodes();
+        self.warmup_stats.retain(&addresses);
+        if addresses.is_empty() {
+            return;
+        }
+
+        // The connections of a backend are used in a round robin way.
+        let conn_num = self.config.backend_conn_num.get();
+        let warmup_stats = self.warmup_stats.clone();
+        let fut = async move {
+            let warmup_futs = addresses.into_iter().map(|address| {
+                let reply_futs: Vec<_> = (0..conn_num)
+                    .map(|_| {
+                        let packet =
+                            Box::new(RespPacket::from_resp_vec(Resp::Arr(Array::Arr(vec![
+                                Resp::Bulk(BulkStr::Str(b"PING".to_vec())),
+                            ]))));
+                        let cmd = Command::new(packet);
+                        let (reply_sender, reply_receiver) = new_command_pair(&cmd);
+                        let cmd_ctx = CmdCtx::new(cmd, reply_sender, 0, false);
+                        let task = BlockingHintTask::new(cmd_ctx, BlockingHint::NotBlocking);
+                        if let Err(err) = meta_map.cluster_map.send_to_local_node(task, &address) {
+                            warn!("failed to send warmup PING to {}: {:?}", address, err);
+                        }
+                        reply_receiver
+                    })
+                    .collect();
+                async move {
+                    let success =
+                        future::join_all(reply_futs)
+                            .await
+                            .into_iter()
+                            .all(|res| match res {
+                                Ok(reply) => !matches!(reply.into_resp_vec(), Resp::Error(_)),
+                                Err(_) => false,
+                            });
+                    if !success {
+                        warn!("failed to warm up backend {}", address);
+                    }
+                    (address, success)
+                }
+            });
+            for (address, success) in future::join_all(warmup_futs).await.into_iter() {
+                warmup_stats.record(address, success);
+            }
+        };
+
+        let desc = "backend warmup".to_string();
+        let fut = TrackedFutureRegistry::wrap(self.future_registry.clone(), fut, desc);
+        tokio::spawn(fut);
+    }
+
     pub fn update_replicators(&self, meta: ReplicatorMeta) -> Result<(), ClusterMetaError> {
         self.replicator_manager
             .update_replicators(meta, self.config.announce_host.clone())
@@ -272,6 +460,14 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         self.replicator_manager.get_metadata_report()
     }
 
+    pub fn get_role(&self) -> RespVec {
+        self.replicator_manager.get_role_report()
+    }
+
+    pub fn get_info_replication(&self) -> String {
+        self.replicator_manager.get_info_replication()
+    }
+
     pub fn info(&self) -> RespVec {
         let meta_map = self.meta_map.load();
         let cluster_info = meta_map.cluster_map.info();
@@ -314,11 +510,12 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
             SlotRangeTag::Importing(ref meta) => meta.epoch,
         };
 
-        if self.epoch.load(Ordering::SeqCst) < arg_epoch {
+        let meta_map = self.meta_map.load();
+        if meta_map.epoch < arg_epoch {
             return Err(SwitchError::NotReady);
         }
 
-        self.meta_map.load().migration_map.handle_switch(
+        meta_map.migration_map.handle_switch(
             SwitchArg {
                 version: switch_arg.version,
                 meta: task_meta,
@@ -327,6 +524,30 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         )
     }
 
+    pub fn set_migration_config_field(&self, field: &str, value: &str) -> Result<(), ConfigError> {
+        self.migration_manager.set_config_field(field, value)
+    }
+
+    pub fn get_migration_config(&self) -> MigrationConfig {
+        self.migration_manager.get_config()
+    }
+
+    pub fn get_migration_task_status(
+        &self,
+        meta: &MigrationTaskMeta,
+    ) -> Result<MigrationTaskStatus, SwitchError> {
+        let arg_epoch = match meta.slot_range.tag.get_migration_meta() {
+            Some(migration_meta) => migration_meta.epoch,
+            None => return Err(SwitchError::InvalidArg),
+        };
+        let meta_map = self.meta_map.load();
+        // The task is not created until the metadata of its epoch is set.
+        if meta_map.epoch < arg_epoch {
+            return Err(SwitchError::NotReady);
+        }
+        Ok(meta_map.migration_map.get_task_status(meta))
+    }
+
     pub fn get_finished_migration_tasks(&self) -> Vec<MigrationTaskMeta> {
         self.meta_map.load().migration_map.get_finished_tasks()
     }
@@ -358,7 +579,19 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         }
     }
 
-    pub fn send(&self, cmd_ctx: CmdCtx) {
+    pub fn send(&self, mut cmd_ctx: CmdCtx) {
+        {
+            let meta_map = self.meta_map.lease();
+            let key_prefix = &meta_map.get_cluster_map().get_config().key_prefix;
+            if !key_prefix.is_empty() {
+                let res = prefix_keys(&mut cmd_ctx, &self.command_table, key_prefix.as_bytes());
+                if let Err(err) = res {
+                    cmd_ctx.set_resp_result(Ok(Resp::Error(err.to_err_msg().as_bytes().to_vec())));
+                    return;
+                }
+            }
+        }
+
         let max_redirections = self.config.max_redirections;
         let default_redirection_address = self.config.default_redirection_address.as_ref();
         loop_send_cmd_ctx(
@@ -369,6 +602,24 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         );
     }
 
+    pub fn has_key_prefix(&self) -> bool {
+        let meta_map = self.meta_map.lease();
+        !meta_map
+            .get_cluster_map()
+            .get_config()
+            .key_prefix
+            .is_empty()
+    }
+
+    // The commands forwarded by UMFORWARD were already prefixed by the peer proxies.
+    pub fn strip_key_prefix(&self, cmd_ctx: &mut CmdCtx) {
+        let meta_map = self.meta_map.lease();
+        let key_prefix = &meta_map.get_cluster_map().get_config().key_prefix;
+        if !key_prefix.is_empty() {
+            strip_cmd_keys(cmd_ctx, &self.command_table, key_prefix.as_bytes());
+        }
+    }
+
     pub async fn send_sync_task(&self, cmd_ctx: CmdCtx) {
         let meta_map = self.meta_map.load();
         if let Err(err) = meta_map.migration_map.send_sync_task(cmd_ctx).await {
@@ -442,7 +693,7 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
     }
 
     pub fn get_epoch(&self) -> u64 {
-        self.epoch.load(Ordering::SeqCst)
+        self.meta_map.load().epoch
     }
 
     pub fn is_ready(&self) -> bool {
@@ -458,19 +709,114 @@ impl<F: RedisClientFactory, C: ConnFactory<Pkt = RespPacket>> MetaManager<F, C>
         // once migration started, which could leave the time for
         // the service to remove the proxy.
 
+        self.is_ready_in(&self.meta_map.load())
+    }
+
+    fn is_ready_in(&self, meta_map: &ProxyMetaMap<C>) -> bool {
         // If there's only replicas, this proxy don't own any slot
         // but should be in ready state.
         let (master_num, replica_num) = self.replicator_manager.get_role_num();
         if master_num == 0 && replica_num > 0 {
             return true;
         }
-        let meta_map = self.meta_map.load();
         meta_map.cluster_map.is_ready()
     }
 
+    pub fn get_meta_map(&self) -> &SharedMetaMap<C> {
+        &self.meta_map
+    }
+
     pub fn get_batch_stats(&self) -> &Arc<BatchStats> {
         &self.batch_stats
     }
+
+    pub fn get_backend_summaries(&self) -> Vec<BackendSummary> {
+        self.conn_states.get_summaries()
+    }
+
+    // Reads the same states as `UMCTL READY`, `BACKENDS`, `INFOMGR`, and `INFOREPL`.
+    pub fn get_health(&self, connected_clients: usize) -> ProxyHealth {
+        let (master_metadata, replica_metadata) = self.replicator_manager.get_metadata();
+        // Use the same snapshot for all the cluster states.
+        let meta_map = self.meta_map.load();
+        let migration_states = meta_map.migration_map.get_states();
+        ProxyHealth::new(ProxyHealthSource {
+            cluster: meta_map.cluster_map.get_cluster().to_string(),
+            epoch: meta_map.epoch,
+            ready: self.is_ready_in(&meta_map),
+            connected_clients,
+            backends: self.get_backend_summaries(),
+            migration_states,
+            master_metadata,
+            replica_metadata,
+        })
+    }
+
+    // Sums up the DBSIZE of all the local backends.
+    // The keys being migrated could be counted twice or missed.
+    pub async fn get_dbsize(&self) -> Result<usize, String> {
+        let reply_futs: Vec<_> = self
+            .get_local_nodes()
+            .into_iter()
+            .map(|address| {
+                let packet = Box::new(RespPacket::from_resp_vec(Resp::Arr(Array::Arr(vec![
+                    Resp::Bulk(BulkStr::Str(b"DBSIZE".to_vec())),
+                ]))));
+                let cmd = Command::new(packet);
+                let (reply_sender, reply_receiver) = new_command_pair(&cmd);
+                let cmd_ctx = CmdCtx::new(cmd, reply_sender, 0, false);
+                self.send_to_local_node(cmd_ctx, &address);
+                reply_receiver
+            })
+            .collect();
+
+        let mut dbsize = 0;
+        for res in future::join_all(reply_futs).await.into_iter() {
+            let reply = res
+                .map_err(|err| format!("failed to get DBSIZE: {:?}", err))?
+                .into_resp_vec();
+            match reply {
+                Resp::Integer(n) => {
+                    dbsize += btoi::btou::<usize>(&n)
+                        .map_err(|err| format!("invalid DBSIZE reply: {:?}", err))?;
+                }
+                others => return Err(format!("unexpected DBSIZE reply: {:?}", others)),
+            }
+        }
+        Ok(dbsize)
+    }
+
+    pub fn get_local_nodes(&self) -> Vec<String> {
+        self.meta_map.load().cluster_map.get_local_nodes()
+    }
+
+    // Send the command to the local backend regardless of the slot and the key prefix.
+    // The reply would be a dropped error if it fails to send.
+    pub fn send_to_local_node(&self, cmd_ctx: CmdCtx, address: &str) {
+        let meta_map = self.meta_map.load();
+        let task = BlockingHintTask::new(cmd_ctx, BlockingHint::NotBlocking);
+        if let Err(err) = meta_map.cluster_map.send_to_local_node(task, address) {
+            warn!(
+                "failed to send command to local node {}: {:?}",
+                address, err
+            );
+        }
+    }
+
+    pub fn get_backend_warmup_stats(&self) -> &Arc<BackendWarmupStats> {
+        &self.warmup_stats
+    }
+
+    pub fn get_command_stats(&self) -> &Arc<CommandStats> {
+        &self.command_stats
+    }
+
+    // Unlike the active redirection for migration which only sends to the peers
+    // in the metadata, the target here is parsed from a `-MOVED` or `-ASK` reply
+    // and could be any address.
+    pub fn create_redirection_sender(&self, target: &RedirectionTarget) -> PeerSender<C> {
+        self.peer_sender_factory.create(target.address.clone())
+    }
 }
 
 pub fn loop_send_cmd_ctx<C: ConnFactory<Pkt = RespPacket>>(
@@ -540,6 +886,7 @@ pub fn send_cmd_ctx<C: ConnFactory<Pkt = RespPacket>>(
     if let Err(e) = res {
         match e {
             ClusterSendError::MissingKey => (),
+            ClusterSendError::NoReachableNode(_) => (),
             ClusterSendError::ClusterNotFound { task } => match default_redirection_address {
                 Some(redirection_address) => match task.get_slot() {
                     None => {
@@ -0,0 +1,123 @@
+use crate::protocol::RespVec;
+use std::collections::{BTreeMap, VecDeque};
+
+// Only the results of the recent tokens are kept for the coordinator to query.
+const MAX_KEPT_RESULTS: usize = 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum MetaApplyStatus {
+    Pending,
+    Done(RespVec),
+}
+
+struct PendingQueue<T> {
+    next_token: u64,
+    metas: VecDeque<(u64, T)>,
+}
+
+// The metadata of `UMCTL SETCLUSTER ASYNC` is applied in the background in the order of the tokens,
+// and the replies of applying them are kept for `UMCTL SETCLUSTER STATUS`.
+pub struct MetaApplyQueue<T> {
+    pending: parking_lot::Mutex<PendingQueue<T>>,
+    results: parking_lot::Mutex<BTreeMap<u64, RespVec>>,
+}
+
+impl<T> Default for MetaApplyQueue<T> {
+    fn default() -> Self {
+        Self {
+            pending: parking_lot::Mutex::new(PendingQueue {
+                next_token: 1,
+                metas: VecDeque::new(),
+            }),
+            results: parking_lot::Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl<T> MetaApplyQueue<T> {
+    // Returns the token for querying the result.
+    pub fn push(&self, meta: T) -> u64 {
+        let mut pending = self.pending.lock();
+        let token = pending.next_token;
+        pending.next_token += 1;
+        pending.metas.push_back((token, meta));
+        token
+    }
+
+    // Applies the oldest pending metadata.
+    // The lock is held during applying so that the metadata is applied one by one in order.
+    pub fn apply_next<Func: FnOnce(T) -> RespVec>(&self, apply: Func) {
+        let mut pending = self.pending.lock();
+        let (token, meta) = match pending.metas.pop_front() {
+            Some(item) => item,
+            None => return,
+        };
+        let reply = apply(meta);
+
+        let mut results = self.results.lock();
+        results.insert(token, reply);
+        while results.len() > MAX_KEPT_RESULTS {
+            let oldest = match results.keys().next() {
+                Some(token) => *token,
+                None => break,
+            };
+            results.remove(&oldest);
+        }
+    }
+
+    // Returns None for the unknown or expired tokens.
+    pub fn get_status(&self, token: u64) -> Option<MetaApplyStatus> {
+        // Lock `pending` first to wait for the metadata being applied.
+        let pending = self.pending.lock();
+        if let Some(reply) = self.results.lock().get(&token) {
+            return Some(MetaApplyStatus::Done(reply.clone()));
+        }
+        if pending.metas.iter().any(|(t, _)| *t == token) {
+            return Some(MetaApplyStatus::Pending);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Resp;
+
+    #[test]
+    fn test_apply_in_order() {
+        let queue = MetaApplyQueue::default();
+        assert_eq!(queue.get_status(1), None);
+
+        let token1 = queue.push(1);
+        let token2 = queue.push(2);
+        assert!(token1 < token2);
+        assert_eq!(queue.get_status(token1), Some(MetaApplyStatus::Pending));
+
+        let mut applied = vec![];
+        for _ in 0..3 {
+            queue.apply_next(|meta| {
+                applied.push(meta);
+                Resp::Integer(meta.to_string().into_bytes())
+            });
+        }
+        assert_eq!(applied, vec![1, 2]);
+        assert_eq!(
+            queue.get_status(token2),
+            Some(MetaApplyStatus::Done(Resp::Integer(b"2".to_vec())))
+        );
+    }
+
+    #[test]
+    fn test_expired_results() {
+        let queue = MetaApplyQueue::default();
+        let first = queue.push(());
+        queue.apply_next(|()| Resp::Simple(b"OK".to_vec()));
+        for _ in 0..MAX_KEPT_RESULTS {
+            queue.push(());
+            queue.apply_next(|()| Resp::Simple(b"OK".to_vec()));
+        }
+        assert_eq!(queue.get_status(first), None);
+        assert!(queue.get_status(first + 1).is_some());
+    }
+}
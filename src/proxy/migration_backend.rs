@@ -163,6 +163,10 @@ impl<T: CmdTask> CmdTask for WaitableTask<T> {
     fn log_event(&mut self, event: TaskEvent) {
         self.inner.log_event(event)
     }
+
+    fn is_asking(&self) -> bool {
+        self.inner.is_asking()
+    }
 }
 
 type ReplyFuture = Pin<Box<dyn Future<Output = Result<RespVec, CommandError>> + Send>>;
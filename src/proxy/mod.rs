@@ -4,12 +4,19 @@ pub mod cluster;
 pub mod command;
 mod compress;
 pub mod executor;
+mod fanout;
+pub mod health;
 pub mod manager;
+mod meta_queue;
 pub mod migration_backend;
+mod pause;
+mod prefix;
+pub mod rename;
 pub mod reply;
 pub mod sender;
 pub mod service;
 pub mod session;
 mod slot;
 pub mod slowlog;
+pub mod stats;
 mod table;
@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// A longer pause is more likely to be a mistake,
+// and it also keeps the deadline far from overflowing `Instant`.
+pub const MAX_PAUSE_DURATION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientPauseMode {
+    Write,
+    All,
+}
+
+// Set by `CLIENT PAUSE`. The affected commands are dispatched after the deadline.
+#[derive(Default)]
+pub struct ClientPause {
+    state: parking_lot::RwLock<Option<(Instant, ClientPauseMode)>>,
+    unpaused: Notify,
+}
+
+impl ClientPause {
+    // Same as Redis, a new pause can only extend the current one.
+    // Returns false if the duration is too large.
+    pub fn pause(&self, duration: Duration, mode: ClientPauseMode) -> bool {
+        if duration > MAX_PAUSE_DURATION {
+            return false;
+        }
+        let now = Instant::now();
+        let deadline = match now.checked_add(duration) {
+            Some(deadline) => deadline,
+            None => return false,
+        };
+        let mut state = self.state.write();
+        let (deadline, mode) = match *state {
+            Some((curr_deadline, curr_mode)) if now < curr_deadline => {
+                let mode = if curr_mode == ClientPauseMode::All {
+                    ClientPauseMode::All
+                } else {
+                    mode
+                };
+                (deadline.max(curr_deadline), mode)
+            }
+            _ => (deadline, mode),
+        };
+        *state = Some((deadline, mode));
+        true
+    }
+
+    pub fn unpause(&self) {
+        self.state.write().take();
+        self.unpaused.notify_waiters();
+    }
+
+    pub fn get_deadline(&self, is_write: bool) -> Option<Instant> {
+        match *self.state.read() {
+            Some((deadline, mode))
+                if Instant::now() < deadline && (is_write || mode == ClientPauseMode::All) =>
+            {
+                Some(deadline)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_remaining(&self) -> Duration {
+        match *self.state.read() {
+            Some((deadline, _)) => deadline.saturating_duration_since(Instant::now()),
+            None => Duration::from_secs(0),
+        }
+    }
+
+    pub async fn wait(&self, is_write: bool) {
+        // The pause could be extended or lifted during the sleep.
+        loop {
+            // Registered before checking the state so that `unpause` can't be missed.
+            let unpaused = self.unpaused.notified();
+            let deadline = match self.get_deadline(is_write) {
+                Some(deadline) => deadline,
+                None => break,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline.into()) => (),
+                _ = unpaused => (),
+            }
+        }
+    }
+}
+
+// Once a command of a session is deferred, the following commands
+// of the same session are also deferred so that they run in order.
+#[derive(Debug, Default)]
+pub struct DeferredCounter {
+    count: Arc<AtomicUsize>,
+}
+
+impl DeferredCounter {
+    pub fn is_deferring(&self) -> bool {
+        self.count.load(Ordering::SeqCst) > 0
+    }
+
+    pub fn defer(&self) -> DeferredGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        DeferredGuard {
+            count: self.count.clone(),
+        }
+    }
+}
+
+pub struct DeferredGuard {
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for DeferredGuard {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_mode() {
+        let pause = ClientPause::default();
+        assert!(pause.get_deadline(true).is_none());
+        assert_eq!(pause.get_remaining(), Duration::from_secs(0));
+
+        assert!(pause.pause(Duration::from_secs(60), ClientPauseMode::Write));
+        assert!(pause.get_deadline(true).is_some());
+        assert!(pause.get_deadline(false).is_none());
+        assert!(pause.get_remaining() > Duration::from_secs(30));
+
+        // A shorter pause does not shorten the current one but ALL still takes effect.
+        pause.pause(Duration::from_secs(1), ClientPauseMode::All);
+        assert!(pause.get_deadline(false).is_some());
+        assert!(pause.get_remaining() > Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_expired_pause() {
+        let pause = ClientPause::default();
+        pause.pause(Duration::from_secs(0), ClientPauseMode::All);
+        assert!(pause.get_deadline(true).is_none());
+
+        // The expired ALL mode is not inherited.
+        pause.pause(Duration::from_secs(60), ClientPauseMode::Write);
+        assert!(pause.get_deadline(false).is_none());
+    }
+
+    #[test]
+    fn test_huge_pause() {
+        let pause = ClientPause::default();
+        assert!(!pause.pause(Duration::from_millis(u64::MAX), ClientPauseMode::All));
+        assert!(!pause.pause(Duration::MAX, ClientPauseMode::All));
+        assert!(pause.get_deadline(true).is_none());
+        assert!(pause.pause(MAX_PAUSE_DURATION, ClientPauseMode::All));
+        assert!(pause.get_deadline(false).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unpause() {
+        let pause = Arc::new(ClientPause::default());
+        assert!(pause.pause(Duration::from_secs(60), ClientPauseMode::All));
+        let waiter = {
+            let pause = pause.clone();
+            tokio::spawn(async move { pause.wait(false).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        pause.unpause();
+        assert!(pause.get_deadline(true).is_none());
+        assert_eq!(pause.get_remaining(), Duration::from_secs(0));
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_deferred_counter() {
+        let counter = DeferredCounter::default();
+        assert!(!counter.is_deferring());
+        let guard1 = counter.defer();
+        let guard2 = counter.defer();
+        assert!(counter.is_deferring());
+        drop(guard1);
+        assert!(counter.is_deferring());
+        drop(guard2);
+        assert!(!counter.is_deferring());
+    }
+}
@@ -0,0 +1,246 @@
+use super::command::{Command, DataCmdType};
+use super::session::CmdCtx;
+use super::table::CommandTable;
+use crate::protocol::{Array, BulkStr, Resp, RespPacket};
+
+// With the `key_prefix` of the cluster config, the keys are prefixed right before
+// being sent to the backends, and the prefix is stripped from the keys in the replies.
+// The slots are still computed from the keys without the prefix,
+// so the clients and the slot migration see the same slots as before.
+
+#[derive(Debug, PartialEq)]
+pub enum KeyPrefixError {
+    // The keys of the commands not in the command table are unknown.
+    UnknownKeys,
+    // The patterns of SORT BY and GET could access the keys without the prefix.
+    SortPattern,
+}
+
+impl KeyPrefixError {
+    pub fn to_err_msg(&self) -> &'static str {
+        match self {
+            Self::UnknownKeys => "ERR command is not supported with key prefix",
+            Self::SortPattern => "ERR SORT BY and GET are not supported with key prefix",
+        }
+    }
+}
+
+pub fn get_key_indices(cmd: &Command, table: &CommandTable) -> Result<Vec<usize>, KeyPrefixError> {
+    let cmd_name = match cmd.get_command_name() {
+        Some(cmd_name) => cmd_name.to_lowercase(),
+        None => return Err(KeyPrefixError::UnknownKeys),
+    };
+    if !table.is_supported(cmd_name.as_bytes()) {
+        return Err(KeyPrefixError::UnknownKeys);
+    }
+    // They would access the keys of the other clusters sharing the backends.
+    if table.get_no_key_routing(cmd_name.as_bytes()).is_some() {
+        return Err(KeyPrefixError::UnknownKeys);
+    }
+
+    let arg_len = cmd.get_command_len().unwrap_or(0);
+    let indices = match cmd.get_data_cmd_type() {
+        DataCmdType::Mget
+        | DataCmdType::Del
+        | DataCmdType::Exists
+        | DataCmdType::Touch
+        | DataCmdType::Unlink => (1..arg_len).collect(),
+        DataCmdType::Mset | DataCmdType::Msetnx => (1..arg_len).step_by(2).collect(),
+        // The last argument is the timeout.
+        DataCmdType::Blpop | DataCmdType::Brpop | DataCmdType::Bzpopmin | DataCmdType::Bzpopmax => {
+            (1..arg_len.saturating_sub(1)).collect()
+        }
+        DataCmdType::Brpoplpush | DataCmdType::Copy => (1..arg_len.min(3)).collect(),
+        // OBJECT <subcommand> <key>
+        DataCmdType::Object => (2..arg_len.min(3)).collect(),
+        // MEMORY USAGE <key>
+        DataCmdType::Memory => match cmd.get_command_element(1) {
+            Some(sub_cmd) if sub_cmd.eq_ignore_ascii_case(b"USAGE") => {
+                (2..arg_len.min(3)).collect()
+            }
+            _ => vec![],
+        },
+        _ if cmd_name == "sort" && has_sort_pattern(cmd, arg_len) => {
+            return Err(KeyPrefixError::SortPattern);
+        }
+        // The invalid arguments are left to the backends.
+        _ if table.has_key_specs(cmd_name.as_bytes()) => {
+            table.get_key_indices(cmd).unwrap_or_default()
+        }
+        _ => (1..arg_len.min(2)).collect(),
+    };
+    Ok(indices)
+}
+
+fn has_sort_pattern(cmd: &Command, arg_len: usize) -> bool {
+    (2..arg_len)
+        .filter_map(|i| cmd.get_command_element(i))
+        .any(|arg| arg.eq_ignore_ascii_case(b"BY") || arg.eq_ignore_ascii_case(b"GET"))
+}
+
+pub fn prefix_keys(
+    cmd_ctx: &mut CmdCtx,
+    table: &CommandTable,
+    prefix: &[u8],
+) -> Result<(), KeyPrefixError> {
+    let indices = get_key_indices(cmd_ctx.get_cmd(), table)?;
+    for index in indices.into_iter() {
+        let key = match cmd_ctx.get_cmd().get_command_element(index) {
+            Some(key) => key,
+            None => continue,
+        };
+        let mut prefixed = Vec::with_capacity(prefix.len() + key.len());
+        prefixed.extend_from_slice(prefix);
+        prefixed.extend_from_slice(key);
+        cmd_ctx.change_cmd_element(index, prefixed);
+    }
+    Ok(())
+}
+
+// For the commands forwarded by the other proxies which have already prefixed the keys.
+// The slot is recomputed from the keys without the prefix.
+pub fn strip_cmd_keys(cmd_ctx: &mut CmdCtx, table: &CommandTable, prefix: &[u8]) {
+    let indices = get_key_indices(cmd_ctx.get_cmd(), table).unwrap_or_default();
+    for index in indices.into_iter() {
+        let key = match cmd_ctx
+            .get_cmd()
+            .get_command_element(index)
+            .and_then(|key| key.strip_prefix(prefix))
+        {
+            Some(key) => key.to_vec(),
+            None => continue,
+        };
+        cmd_ctx.change_cmd_element(index, key);
+    }
+    cmd_ctx.refresh_cmd_info();
+}
+
+// Only LMPOP and ZMPOP reply the keys from the backends. The replies of the blocking
+// commands such as BLPOP are built by the executor with the keys without the prefix,
+// and the commands replying keys such as SCAN and KEYS are not in the command table.
+pub fn strip_reply_keys(data_cmd_type: DataCmdType, packet: &mut RespPacket, prefix: &[u8]) {
+    match data_cmd_type {
+        DataCmdType::Lmpop | DataCmdType::Zmpop => (),
+        _ => return,
+    }
+
+    let key = match packet.to_resp_slice() {
+        Resp::Arr(Array::Arr(arr)) => match arr.first() {
+            Some(Resp::Bulk(BulkStr::Str(key))) => key.strip_prefix(prefix).map(|k| k.to_vec()),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(key) = key {
+        packet.change_bulk_array_element(0, key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::command::new_command_pair;
+
+    fn gen_cmd_ctx(args: &str) -> CmdCtx {
+        let resp = RespPacket::Data(Resp::Arr(Array::Arr(
+            args.split(' ')
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+                .collect(),
+        )));
+        let cmd = Command::new(Box::new(resp));
+        let (sender, _) = new_command_pair(&cmd);
+        CmdCtx::new(cmd, sender, 0, false)
+    }
+
+    fn get_args(cmd_ctx: &CmdCtx) -> String {
+        cmd_ctx
+            .get_cmd()
+            .to_safe_str_vec()
+            .unwrap()
+            .into_iter()
+            .map(|arg| String::from_utf8(arg).unwrap())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_prefix_keys() {
+        let table = CommandTable::default();
+        for (args, expected) in &[
+            ("GET a", "GET t:a"),
+            ("SET a b EX 10", "SET t:a b EX 10"),
+            ("MGET a b", "MGET t:a t:b"),
+            ("MSET a 1 b 2", "MSET t:a 1 t:b 2"),
+            ("BLPOP a b 0", "BLPOP t:a t:b 0"),
+            ("LMPOP 2 a b LEFT COUNT 2", "LMPOP 2 t:a t:b LEFT COUNT 2"),
+            ("BRPOPLPUSH a b 0", "BRPOPLPUSH t:a t:b 0"),
+            ("EVAL script 2 a b arg", "EVAL script 2 t:a t:b arg"),
+            ("ZUNIONSTORE d 2 a b", "ZUNIONSTORE t:d 2 t:a t:b"),
+            ("SORT a LIMIT 0 1 STORE d", "SORT t:a LIMIT 0 1 STORE t:d"),
+            ("OBJECT ENCODING a", "OBJECT ENCODING t:a"),
+            ("MEMORY USAGE a", "MEMORY USAGE t:a"),
+            ("MEMORY STATS", "MEMORY STATS"),
+            ("COPY a b REPLACE", "COPY t:a t:b REPLACE"),
+        ] {
+            let mut cmd_ctx = gen_cmd_ctx(args);
+            let slot = cmd_ctx.get_cmd().get_slot();
+            prefix_keys(&mut cmd_ctx, &table, b"t:").unwrap();
+            assert_eq!(&get_args(&cmd_ctx), expected);
+            // The routing is not affected.
+            assert_eq!(cmd_ctx.get_cmd().get_slot(), slot);
+        }
+    }
+
+    #[test]
+    fn test_prefix_unsupported_cmd() {
+        let table = CommandTable::default();
+        for (args, err) in &[
+            ("KEYS *", KeyPrefixError::UnknownKeys),
+            ("SCAN 0", KeyPrefixError::UnknownKeys),
+            ("FLUSHALL", KeyPrefixError::UnknownKeys),
+            ("SORT a BY w_*", KeyPrefixError::SortPattern),
+            ("SORT a GET #", KeyPrefixError::SortPattern),
+        ] {
+            let mut cmd_ctx = gen_cmd_ctx(args);
+            let res = prefix_keys(&mut cmd_ctx, &table, b"t:");
+            assert_eq!(res.as_ref(), Err(err), "{}", args);
+        }
+    }
+
+    #[test]
+    fn test_strip_cmd_keys() {
+        let table = CommandTable::default();
+        let mut cmd_ctx = gen_cmd_ctx("MSET t:a 1 t:b 2");
+        strip_cmd_keys(&mut cmd_ctx, &table, b"t:");
+        assert_eq!(get_args(&cmd_ctx), "MSET a 1 b 2");
+        assert_eq!(
+            cmd_ctx.get_cmd().get_slot(),
+            gen_cmd_ctx("GET a").get_cmd().get_slot()
+        );
+    }
+
+    #[test]
+    fn test_strip_reply_keys() {
+        let reply = || {
+            RespPacket::Data(Resp::Arr(Array::Arr(vec![
+                Resp::Bulk(BulkStr::Str(b"t:a".to_vec())),
+                Resp::Bulk(BulkStr::Str(b"t:value".to_vec())),
+            ])))
+        };
+        let mut packet = reply();
+        strip_reply_keys(DataCmdType::Lmpop, &mut packet, b"t:");
+        let expected = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"a".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"t:value".to_vec())),
+        ]));
+        assert_eq!(packet.into_resp_vec(), expected);
+
+        let mut packet = reply();
+        strip_reply_keys(DataCmdType::Others, &mut packet, b"t:");
+        assert_eq!(packet.into_resp_vec(), reply().into_resp_vec());
+
+        let mut packet = RespPacket::Data(Resp::Bulk(BulkStr::Nil));
+        strip_reply_keys(DataCmdType::Zmpop, &mut packet, b"t:");
+        assert_eq!(packet.into_resp_vec(), Resp::Bulk(BulkStr::Nil));
+    }
+}
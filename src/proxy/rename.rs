@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+// Used by the coordinators and the other proxies.
+const UNRENAMABLE_COMMANDS: [&str; 3] = ["umctl", "umforward", "umsync"];
+
+#[derive(Debug, PartialEq)]
+pub enum RenameCommandError {
+    Unrenamable(String),
+    InvalidName(String),
+    DuplicateAlias(String),
+}
+
+impl fmt::Display for RenameCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unrenamable(name) => write!(f, "command {} can't be renamed", name),
+            Self::InvalidName(name) => write!(f, "invalid command name: {:?}", name),
+            Self::DuplicateAlias(alias) => write!(f, "duplicate command alias: {}", alias),
+        }
+    }
+}
+
+impl Error for RenameCommandError {}
+
+#[derive(Debug, PartialEq)]
+pub enum CommandLookup<'a> {
+    Unchanged,
+    // The alias of the original command.
+    Alias(&'a str),
+    // Disabled or only available by its alias.
+    Unknown,
+}
+
+// Like `rename-command` of Redis. The commands renamed to an empty name are disabled.
+// All the names are case-insensitive.
+#[derive(Debug, Default, Clone)]
+pub struct CommandRenames {
+    // alias => original name
+    aliases: HashMap<String, String>,
+    originals: HashSet<String>,
+}
+
+impl CommandRenames {
+    pub fn new(renames: HashMap<String, String>) -> Result<Self, RenameCommandError> {
+        let mut aliases = HashMap::new();
+        let mut originals = HashSet::new();
+        for (original, alias) in renames.into_iter() {
+            let original = original.to_lowercase();
+            let alias = alias.to_lowercase();
+            if UNRENAMABLE_COMMANDS.contains(&original.as_str()) {
+                return Err(RenameCommandError::Unrenamable(original));
+            }
+            if !is_valid_name(&original) {
+                return Err(RenameCommandError::InvalidName(original));
+            }
+            originals.insert(original.clone());
+            if alias.is_empty() {
+                continue;
+            }
+            if !is_valid_name(&alias) || UNRENAMABLE_COMMANDS.contains(&alias.as_str()) {
+                return Err(RenameCommandError::InvalidName(alias));
+            }
+            if aliases.contains_key(&alias) {
+                return Err(RenameCommandError::DuplicateAlias(alias));
+            }
+            aliases.insert(alias, original);
+        }
+        Ok(Self { aliases, originals })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.originals.is_empty()
+    }
+
+    // `cmd_name` should be in lowercase.
+    // The aliases are checked first so that two commands could swap their names.
+    pub fn lookup(&self, cmd_name: &str) -> CommandLookup<'_> {
+        if let Some(original) = self.aliases.get(cmd_name) {
+            return CommandLookup::Alias(original.as_str());
+        }
+        if self.originals.contains(cmd_name) {
+            return CommandLookup::Unknown;
+        }
+        CommandLookup::Unchanged
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gen_renames(renames: &[(&str, &str)]) -> Result<CommandRenames, RenameCommandError> {
+        let renames = renames
+            .iter()
+            .map(|(original, alias)| (original.to_string(), alias.to_string()))
+            .collect();
+        CommandRenames::new(renames)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let renames = gen_renames(&[]).unwrap();
+        assert!(renames.is_empty());
+        assert_eq!(renames.lookup("keys"), CommandLookup::Unchanged);
+
+        let renames = gen_renames(&[
+            ("FLUSHALL", ""),
+            ("keys", "Secret-Keys"),
+            ("get", "set"),
+            ("set", "get"),
+        ])
+        .unwrap();
+        assert!(!renames.is_empty());
+        assert_eq!(renames.lookup("flushall"), CommandLookup::Unknown);
+        assert_eq!(renames.lookup("keys"), CommandLookup::Unknown);
+        assert_eq!(renames.lookup("secret-keys"), CommandLookup::Alias("keys"));
+        assert_eq!(renames.lookup("get"), CommandLookup::Alias("set"));
+        assert_eq!(renames.lookup("set"), CommandLookup::Alias("get"));
+        assert_eq!(renames.lookup("flushdb"), CommandLookup::Unchanged);
+    }
+
+    #[test]
+    fn test_invalid_renames() {
+        assert_eq!(
+            gen_renames(&[("UMCTL", "")]).unwrap_err(),
+            RenameCommandError::Unrenamable("umctl".to_string())
+        );
+        assert_eq!(
+            gen_renames(&[("keys", "umsync")]).unwrap_err(),
+            RenameCommandError::InvalidName("umsync".to_string())
+        );
+        assert_eq!(
+            gen_renames(&[("keys", "my keys")]).unwrap_err(),
+            RenameCommandError::InvalidName("my keys".to_string())
+        );
+        assert_eq!(
+            gen_renames(&[("", "keys")]).unwrap_err(),
+            RenameCommandError::InvalidName("".to_string())
+        );
+        assert_eq!(
+            gen_renames(&[("keys", "k"), ("scan", "K")]).unwrap_err(),
+            RenameCommandError::DuplicateAlias("k".to_string())
+        );
+    }
+}
@@ -1,18 +1,23 @@
 use super::backend::{
     BackendResult, CmdTask, CmdTaskResultHandler, CmdTaskResultHandlerFactory, ConnFactory,
 };
+use super::command::DataCmdType;
 use super::compress::{CmdReplyDecompressor, CompressionError, CompressionStrategyMetaMapConfig};
 use super::manager::SharedMetaMap;
+use super::prefix::strip_reply_keys;
 use super::session::CmdCtx;
+use super::stats::CommandStats;
 use crate::common::utils::Wrapper;
 use crate::protocol::{BulkStr, Resp, RespPacket};
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 pub struct DecompressCommitHandlerFactory<
     T: CmdTask<Pkt = RespPacket> + Into<Wrapper<CmdCtx>>,
     C: ConnFactory<Pkt = RespPacket>,
 > {
     meta_map: SharedMetaMap<C>,
+    command_stats: Arc<CommandStats>,
     phanthom: PhantomData<T>,
 }
 
@@ -21,9 +26,10 @@ where
     T: CmdTask<Pkt = RespPacket> + Into<Wrapper<CmdCtx>>,
     C: ConnFactory<Pkt = RespPacket>,
 {
-    pub fn new(meta_map: SharedMetaMap<C>) -> Self {
+    pub fn new(meta_map: SharedMetaMap<C>, command_stats: Arc<CommandStats>) -> Self {
         Self {
             meta_map,
+            command_stats,
             phanthom: PhantomData,
         }
     }
@@ -41,6 +47,8 @@ where
             decompressor: CmdReplyDecompressor::new(CompressionStrategyMetaMapConfig::new(
                 self.meta_map.clone(),
             )),
+            meta_map: self.meta_map.clone(),
+            command_stats: self.command_stats.clone(),
             phanthom: PhantomData,
         }
     }
@@ -51,6 +59,8 @@ pub struct DecompressCommitHandler<
     C: ConnFactory<Pkt = RespPacket>,
 > {
     decompressor: CmdReplyDecompressor<CompressionStrategyMetaMapConfig<C>>,
+    meta_map: SharedMetaMap<C>,
+    command_stats: Arc<CommandStats>,
     phanthom: PhantomData<T>,
 }
 
@@ -90,6 +100,21 @@ where
             }
         }
 
+        let cmd_type = cmd_ctx.get_data_cmd_type();
+        {
+            let meta_map = self.meta_map.lease();
+            let key_prefix = &meta_map.get_cluster_map().get_config().key_prefix;
+            if !key_prefix.is_empty() {
+                strip_reply_keys(cmd_type, &mut packet, key_prefix.as_bytes());
+            }
+        }
+
+        // The same-slot multi-key EXISTS is forwarded as a whole
+        // and could not be classified per key.
+        if cmd_type != DataCmdType::Exists || cmd_ctx.get_cmd().get_command_len() == Some(2) {
+            self.command_stats.record_keyspace(cmd_type, &packet);
+        }
+        self.command_stats.record_redirection(&packet);
         cmd_ctx.set_result(Ok(Box::new(packet)))
     }
 }
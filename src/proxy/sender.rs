@@ -1,6 +1,6 @@
 use super::backend::{
-    BackendError, BackendNode, CmdTask, CmdTaskResultHandler, CmdTaskResultHandlerFactory,
-    ConnFactory, ReqTask, SenderBackendError,
+    backend_unreachable_reply, BackendConnStates, BackendError, BackendNode, CmdTask,
+    CmdTaskResultHandler, CmdTaskResultHandlerFactory, ConnFactory, ReqTask, SenderBackendError,
 };
 use super::service::ServerProxyConfig;
 use crate::common::batch::BatchStats;
@@ -17,6 +17,11 @@ pub trait CmdTaskSender {
     type Task: CmdTask;
 
     fn send(&self, cmd_task: Self::Task) -> Result<(), SenderBackendError<Self::Task>>;
+
+    // Whether the backend is known to be unreachable for now.
+    fn is_failing(&self) -> bool {
+        false
+    }
 }
 
 pub trait CmdTaskSenderFactory {
@@ -28,6 +33,7 @@ pub trait CmdTaskSenderFactory {
 pub struct RecoverableBackendNode<F: CmdTaskResultHandlerFactory> {
     address: String,
     node: BackendNode<<F as CmdTaskResultHandlerFactory>::Handler>,
+    config: Arc<ServerProxyConfig>,
 }
 
 impl<F: CmdTaskResultHandlerFactory> CmdTaskSender for RecoverableBackendNode<F> {
@@ -36,13 +42,18 @@ impl<F: CmdTaskResultHandlerFactory> CmdTaskSender for RecoverableBackendNode<F>
     fn send(&self, cmd_task: Self::Task) -> Result<(), SenderBackendError<Self::Task>> {
         self.node.send(cmd_task).map_err(|e| {
             let cmd_task = e.into_inner();
-            cmd_task.set_resp_result(Ok(Resp::Error(
-                format!("{}: {}", response::ERR_BACKEND_CONNECTION, self.address).into_bytes(),
+            cmd_task.set_resp_result(Ok(backend_unreachable_reply(
+                &self.address,
+                self.config.get_expose_backend_address(),
             )));
             error!("backend node is closed");
             SenderBackendError::Canceled
         })
     }
+
+    fn is_failing(&self) -> bool {
+        self.node.is_failing()
+    }
 }
 
 pub struct RecoverableBackendNodeFactory<F: CmdTaskResultHandlerFactory, CF: ConnFactory>
@@ -55,6 +66,7 @@ where
     conn_factory: Arc<CF>,
     future_registry: Arc<TrackedFutureRegistry>,
     batch_stats: Arc<BatchStats>,
+    conn_states: Arc<BackendConnStates>,
 }
 
 impl<F: CmdTaskResultHandlerFactory, CF: ConnFactory> RecoverableBackendNodeFactory<F, CF>
@@ -68,6 +80,7 @@ where
         conn_factory: Arc<CF>,
         future_registry: Arc<TrackedFutureRegistry>,
         batch_stats: Arc<BatchStats>,
+        conn_states: Arc<BackendConnStates>,
     ) -> Self {
         Self {
             config,
@@ -75,6 +88,7 @@ where
             conn_factory,
             future_registry,
             batch_stats,
+            conn_states,
         }
     }
 }
@@ -94,11 +108,16 @@ where
             self.config.clone(),
             self.conn_factory.clone(),
             self.batch_stats.clone(),
+            self.conn_states.clone(),
         );
         let desc = format!("backend::RecoverableBackendNode: address={}", address);
         let fut = TrackedFutureRegistry::wrap(self.future_registry.clone(), fut, desc);
         tokio::spawn(fut);
-        Self::Sender { address, node }
+        Self::Sender {
+            address,
+            node,
+            config: self.config.clone(),
+        }
     }
 }
 
@@ -159,6 +178,10 @@ impl<S: CmdTaskSender> CmdTaskSender for ReqAdaptorSender<S> {
             }
         }
     }
+
+    fn is_failing(&self) -> bool {
+        self.sender.is_failing()
+    }
 }
 
 pub struct ReqAdaptorSenderFactory<F: CmdTaskSenderFactory> {
@@ -191,12 +214,22 @@ impl<S: CmdTaskSender> CmdTaskSender for RoundRobinSenderGroup<S> {
 
     fn send(&self, cmd_task: Self::Task) -> Result<(), SenderBackendError<Self::Task>> {
         let index = self.cursor.fetch_add(1, Ordering::SeqCst);
-        let sender = match self.senders.get(index % self.senders.len()) {
+        let len = self.senders.len();
+        // Skip the failing members as long as there's any healthy one.
+        let sender = (0..len)
+            .filter_map(|i| self.senders.get((index + i) % len))
+            .find(|s| !s.is_failing())
+            .or_else(|| self.senders.get(index.checked_rem(len)?));
+        let sender = match sender {
             Some(s) => s,
             None => return Err(SenderBackendError::NodeNotFound),
         };
         sender.send(cmd_task)
     }
+
+    fn is_failing(&self) -> bool {
+        self.senders.iter().all(|s| s.is_failing())
+    }
 }
 
 pub struct RoundRobinSenderGroupFactory<F: CmdTaskSenderFactory> {
@@ -238,6 +271,10 @@ impl<S: CmdTaskSender> CmdTaskSender for CachedSender<S> {
     fn send(&self, cmd_task: Self::Task) -> Result<(), SenderBackendError<Self::Task>> {
         self.inner_sender.send(cmd_task)
     }
+
+    fn is_failing(&self) -> bool {
+        self.inner_sender.is_failing()
+    }
 }
 
 // TODO: support cleanup here to avoid memory leak.
@@ -294,6 +331,7 @@ pub fn gen_sender_factory<F: CmdTaskResultHandlerFactory, CF: ConnFactory>(
     conn_factory: Arc<CF>,
     future_registry: Arc<TrackedFutureRegistry>,
     batch_stats: Arc<BatchStats>,
+    conn_states: Arc<BackendConnStates>,
 ) -> BackendSenderFactory<F, CF>
 where
     <F::Handler as CmdTaskResultHandler>::Task: CmdTask<Pkt = CF::Pkt>,
@@ -307,6 +345,7 @@ where
             conn_factory,
             future_registry,
             batch_stats,
+            conn_states,
         ),
     ))
 }
@@ -320,6 +359,7 @@ pub fn gen_migration_sender_factory<F: CmdTaskResultHandlerFactory, CF: ConnFact
     conn_factory: Arc<CF>,
     future_registry: Arc<TrackedFutureRegistry>,
     batch_stats: Arc<BatchStats>,
+    conn_states: Arc<BackendConnStates>,
 ) -> MigrationBackendSenderFactory<F, CF>
 where
     <F::Handler as CmdTaskResultHandler>::Task: CmdTask<Pkt = CF::Pkt>,
@@ -333,6 +373,7 @@ where
             conn_factory,
             future_registry,
             batch_stats,
+            conn_states,
         )),
     )
 }
@@ -1,23 +1,46 @@
+use super::command::{new_command_pair, Command};
+use super::rename::CommandRenames;
 use super::session::CmdCtxHandler;
-use super::session::{handle_session, Session};
+use super::session::{
+    handle_session, AuthState, ClientInfo, CmdCtx, OutputBufferLimits, Session, SessionError,
+};
 use super::slowlog::SlowRequestLogger;
+use super::stats::{DbConnCounter, ProxyStats};
 use crate::common::batch::BatchStrategy;
 use crate::common::config::ConfigError;
+use crate::common::response::OK_REPLY;
 use crate::common::track::TrackedFutureRegistry;
-use crate::common::utils::{resolve_first_address, ThreadSafe};
+use crate::common::utils::{
+    resolve_first_address, HashTagDelimiters, SocketBufferSizes, ThreadSafe,
+};
+use crate::protocol::{Array, BulkStr, ProtocolLimits, Resp, RespPacket};
 use futures::channel::mpsc;
 use futures::{select, FutureExt, StreamExt};
 use std::error::Error;
-use std::num::NonZeroUsize;
-use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::fmt;
+use std::io;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use string_error::into_err;
-use tokio::net::TcpListener;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+
+// Same as `TcpListener::bind`.
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
 
 #[derive(Debug)]
 pub struct ServerProxyConfig {
     pub address: String,
+    // The clients could also connect to these addresses besides `address`.
+    pub extra_listen_addresses: Vec<String>,
+    // The backlog of the pending connections of all the listeners.
+    pub listen_backlog: NonZeroU32,
+    // Set `SO_REUSEPORT` so that multiple proxy processes could share the same port.
+    pub listen_reuseport: bool,
+    // Set on the listeners and inherited by the client connections.
+    pub client_socket_buffer: SocketBufferSizes,
     pub announce_address: String,
     pub announce_host: String,
     pub slowlog_len: NonZeroUsize,
@@ -25,6 +48,7 @@ pub struct ServerProxyConfig {
     pub slowlog_sample_rate: AtomicU64,
     pub thread_number: NonZeroUsize,
     pub backend_conn_num: NonZeroUsize,
+    pub backend_socket_buffer: SocketBufferSizes,
     pub active_redirection: bool,
     pub max_redirections: Option<NonZeroUsize>,
     pub default_redirection_address: Option<String>,
@@ -34,7 +58,57 @@ pub struct ServerProxyConfig {
     pub backend_high_flush_interval: Duration,
     pub backend_timeout: Duration,
     pub password: Option<String>,
+    pub auth_admin_commands: bool,
+    pub admin_password: Option<String>,
     pub command_cluster_nodes_version: ClusterNodesVersion,
+    pub proto_max_bulk_len: usize,
+    pub proto_max_multibulk_len: usize,
+    pub proto_max_inline_len: usize,
+    pub session_idle_timeout: Option<Duration>,
+    // In bytes. The connections whose pending replies exceed the hard limit,
+    // or keep exceeding the soft limit for `client_output_buffer_soft_seconds`, are closed.
+    // 0 disables the limit.
+    pub client_output_buffer_hard_limit: usize,
+    pub client_output_buffer_soft_limit: usize,
+    pub client_output_buffer_soft_seconds: u64,
+    // In bytes. The replies not smaller than it are written to the clients
+    // from their own buffers instead of being copied. 0 disables it.
+    pub reply_stream_threshold: usize,
+    pub unknown_command_policy: UnknownCommandPolicy,
+    pub select_policy: SelectPolicy,
+    // 0 means unlimited.
+    pub max_connections_per_db: AtomicUsize,
+    // The maximum number of client connections of the whole proxy
+    // like `maxclients` of Redis. 0 means unlimited.
+    pub maxclients: AtomicUsize,
+    // Include the backend addresses in the error replies.
+    // This discloses the topology so it should be disabled in production.
+    pub expose_backend_address: AtomicBool,
+    // Send PINGs to the backends right after UMCTL SETCLUSTER to establish the connections.
+    pub backend_warmup: bool,
+    // `CLUSTER RESET HARD` also resets the epochs. Only for testing.
+    pub cluster_reset_hard: bool,
+    // Reply PING with a LOADING error before any cluster metadata is set.
+    pub ping_requires_meta: bool,
+    // Enable `DEBUG SLEEP` for latency testing. Only for testing.
+    pub enable_debug: bool,
+    // The `redis_version` in INFO for the clients checking the Redis version.
+    pub redis_version: String,
+    // Replied to the data commands after `UMCTL MAINTENANCE ON`.
+    pub maintenance_error: String,
+    // Like `rename-command` of Redis. Kept secret like the passwords.
+    pub rename_command: CommandRenames,
+    // In milliseconds. 0 means no timeout.
+    // Blocking commands are not limited by it.
+    pub command_timeout: AtomicU64,
+    // Only applied by `set_hash_tag_delimiters` at startup.
+    pub hash_tag: HashTagDelimiters,
+    // The `UMCTL SETCLUSTER` and `UMCTL SETREPL` commands run before serving the clients.
+    pub init_meta_file: Option<String>,
+    // The `UMCTL SETCLUSTER` and `UMCTL SETREPL` commands with more elements
+    // or bytes than these are rejected before being parsed.
+    pub umctl_max_meta_args: usize,
+    pub umctl_max_meta_bytes: usize,
 }
 
 impl ServerProxyConfig {
@@ -54,17 +128,77 @@ impl ServerProxyConfig {
         self.slowlog_sample_rate
             .store(slowlog_sample_rate, Ordering::Relaxed)
     }
+
+    pub fn get_max_connections_per_db(&self) -> usize {
+        self.max_connections_per_db.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_connections_per_db(&self, n: usize) {
+        self.max_connections_per_db.store(n, Ordering::Relaxed)
+    }
+
+    pub fn get_maxclients(&self) -> usize {
+        self.maxclients.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxclients(&self, n: usize) {
+        self.maxclients.store(n, Ordering::Relaxed)
+    }
+
+    pub fn get_expose_backend_address(&self) -> bool {
+        self.expose_backend_address.load(Ordering::Relaxed)
+    }
+
+    pub fn set_expose_backend_address(&self, enabled: bool) {
+        self.expose_backend_address
+            .store(enabled, Ordering::Relaxed)
+    }
+
+    pub fn get_command_timeout(&self) -> Option<Duration> {
+        match self.command_timeout.load(Ordering::Relaxed) {
+            0 => None,
+            t => Some(Duration::from_millis(t)),
+        }
+    }
+
+    pub fn set_command_timeout(&self, timeout_ms: u64) {
+        self.command_timeout.store(timeout_ms, Ordering::Relaxed)
+    }
+
+    pub fn get_output_buffer_limits(&self) -> OutputBufferLimits {
+        OutputBufferLimits {
+            hard_limit: self.client_output_buffer_hard_limit,
+            soft_limit: self.client_output_buffer_soft_limit,
+            soft_duration: Duration::from_secs(self.client_output_buffer_soft_seconds),
+            stream_threshold: self.reply_stream_threshold,
+        }
+    }
+
+    pub fn get_protocol_limits(&self) -> ProtocolLimits {
+        ProtocolLimits {
+            max_bulk_len: self.proto_max_bulk_len,
+            max_multibulk_len: self.proto_max_multibulk_len,
+            max_inline_len: self.proto_max_inline_len,
+        }
+    }
 }
 
 impl ServerProxyConfig {
     pub fn get_field(&self, field: &str) -> Result<String, ConfigError> {
         match field.to_lowercase().as_ref() {
             "address" => Ok(self.address.clone()),
+            "extra_listen_addresses" => Ok(self.extra_listen_addresses.join(",")),
+            "listen_backlog" => Ok(self.listen_backlog.to_string()),
+            "listen_reuseport" => Ok(self.listen_reuseport.to_string()),
+            "client_send_buffer_size" => Ok(self.client_socket_buffer.get_send().to_string()),
+            "client_recv_buffer_size" => Ok(self.client_socket_buffer.get_recv().to_string()),
             "announce_address" => Ok(self.announce_address.clone()),
             "announce_host" => Ok(self.announce_host.clone()),
             "slowlog_len" => Ok(self.slowlog_len.to_string()),
             "thread_number" => Ok(self.thread_number.to_string()),
             "backend_conn_num" => Ok(self.backend_conn_num.to_string()),
+            "backend_send_buffer_size" => Ok(self.backend_socket_buffer.get_send().to_string()),
+            "backend_recv_buffer_size" => Ok(self.backend_socket_buffer.get_recv().to_string()),
             "slowlog_log_slower_than" => Ok(self.get_slowlog_log_slower_than().to_string()),
             "slowlog_sample_rate" => Ok(self.get_slowlog_sample_rate().to_string()),
             "active_redirection" => Ok(self.active_redirection.to_string()),
@@ -72,7 +206,42 @@ impl ServerProxyConfig {
                 .max_redirections
                 .map(|n| n.get().to_string())
                 .unwrap_or_else(|| "none".to_string())),
+            "proto_max_bulk_len" => Ok(self.proto_max_bulk_len.to_string()),
+            "proto_max_multibulk_len" => Ok(self.proto_max_multibulk_len.to_string()),
+            "proto_max_inline_len" => Ok(self.proto_max_inline_len.to_string()),
+            "session_idle_timeout" => Ok(self
+                .session_idle_timeout
+                .map(|t| t.as_secs())
+                .unwrap_or(0)
+                .to_string()),
+            "client_output_buffer_hard_limit" => {
+                Ok(self.client_output_buffer_hard_limit.to_string())
+            }
+            "client_output_buffer_soft_limit" => {
+                Ok(self.client_output_buffer_soft_limit.to_string())
+            }
+            "client_output_buffer_soft_seconds" => {
+                Ok(self.client_output_buffer_soft_seconds.to_string())
+            }
+            "reply_stream_threshold" => Ok(self.reply_stream_threshold.to_string()),
+            "unknown_command_policy" => Ok(self.unknown_command_policy.to_string()),
+            "select_policy" => Ok(self.select_policy.to_string()),
+            "maintenance_error" => Ok(self.maintenance_error.clone()),
+            "init_meta_file" => Ok(self.init_meta_file.clone().unwrap_or_default()),
+            "umctl_max_meta_args" => Ok(self.umctl_max_meta_args.to_string()),
+            "umctl_max_meta_bytes" => Ok(self.umctl_max_meta_bytes.to_string()),
+            "auth_admin_commands" => Ok(self.auth_admin_commands.to_string()),
+            "max_connections_per_db" => Ok(self.get_max_connections_per_db().to_string()),
+            "maxclients" => Ok(self.get_maxclients().to_string()),
+            "expose_backend_address" => Ok(self.get_expose_backend_address().to_string()),
+            "backend_warmup" => Ok(self.backend_warmup.to_string()),
+            "cluster_reset_hard" => Ok(self.cluster_reset_hard.to_string()),
+            "ping_requires_meta" => Ok(self.ping_requires_meta.to_string()),
+            "command_timeout" => Ok(self.command_timeout.load(Ordering::Relaxed).to_string()),
+            "hash_tag" => Ok(self.hash_tag.to_string()),
             "password" => Err(ConfigError::Forbidden),
+            "admin_password" => Err(ConfigError::Forbidden),
+            "rename_command" => Err(ConfigError::Forbidden),
             _ => Err(ConfigError::FieldNotFound),
         }
     }
@@ -80,11 +249,18 @@ impl ServerProxyConfig {
     pub fn set_value(&self, field: &str, value: &str) -> Result<(), ConfigError> {
         match field.to_lowercase().as_ref() {
             "address" => Err(ConfigError::ReadonlyField),
+            "extra_listen_addresses" => Err(ConfigError::ReadonlyField),
+            "listen_backlog" => Err(ConfigError::ReadonlyField),
+            "listen_reuseport" => Err(ConfigError::ReadonlyField),
+            "client_send_buffer_size" => Err(ConfigError::ReadonlyField),
+            "client_recv_buffer_size" => Err(ConfigError::ReadonlyField),
             "announce_address" => Err(ConfigError::ReadonlyField),
             "announce_host" => Err(ConfigError::ReadonlyField),
             "slowlog_len" => Err(ConfigError::ReadonlyField),
             "thread_number" => Err(ConfigError::ReadonlyField),
             "backend_conn_num" => Err(ConfigError::ReadonlyField),
+            "backend_send_buffer_size" => Err(ConfigError::ReadonlyField),
+            "backend_recv_buffer_size" => Err(ConfigError::ReadonlyField),
             "slowlog_log_slower_than" => {
                 let int_value = value
                     .parse::<i64>()
@@ -101,7 +277,56 @@ impl ServerProxyConfig {
             }
             "active_redirection" => Err(ConfigError::ReadonlyField),
             "max_redirections" => Err(ConfigError::ReadonlyField),
+            "proto_max_bulk_len" => Err(ConfigError::ReadonlyField),
+            "proto_max_multibulk_len" => Err(ConfigError::ReadonlyField),
+            "proto_max_inline_len" => Err(ConfigError::ReadonlyField),
+            "session_idle_timeout" => Err(ConfigError::ReadonlyField),
+            "init_meta_file" => Err(ConfigError::ReadonlyField),
+            "umctl_max_meta_args" => Err(ConfigError::ReadonlyField),
+            "umctl_max_meta_bytes" => Err(ConfigError::ReadonlyField),
+            "client_output_buffer_hard_limit" => Err(ConfigError::ReadonlyField),
+            "client_output_buffer_soft_limit" => Err(ConfigError::ReadonlyField),
+            "client_output_buffer_soft_seconds" => Err(ConfigError::ReadonlyField),
+            "reply_stream_threshold" => Err(ConfigError::ReadonlyField),
+            "unknown_command_policy" => Err(ConfigError::ReadonlyField),
+            "select_policy" => Err(ConfigError::ReadonlyField),
+            "maintenance_error" => Err(ConfigError::ReadonlyField),
+            "auth_admin_commands" => Err(ConfigError::ReadonlyField),
+            "max_connections_per_db" => {
+                let int_value = value
+                    .parse::<usize>()
+                    .map_err(|_| ConfigError::InvalidValue)?;
+                self.set_max_connections_per_db(int_value);
+                Ok(())
+            }
+            "maxclients" => {
+                let int_value = value
+                    .parse::<usize>()
+                    .map_err(|_| ConfigError::InvalidValue)?;
+                self.set_maxclients(int_value);
+                Ok(())
+            }
+            "expose_backend_address" => {
+                let enabled = value
+                    .parse::<bool>()
+                    .map_err(|_| ConfigError::InvalidValue)?;
+                self.set_expose_backend_address(enabled);
+                Ok(())
+            }
+            "backend_warmup" => Err(ConfigError::ReadonlyField),
+            "cluster_reset_hard" => Err(ConfigError::ReadonlyField),
+            "ping_requires_meta" => Err(ConfigError::ReadonlyField),
+            "command_timeout" => {
+                let int_value = value
+                    .parse::<u64>()
+                    .map_err(|_| ConfigError::InvalidValue)?;
+                self.set_command_timeout(int_value);
+                Ok(())
+            }
+            "hash_tag" => Err(ConfigError::ReadonlyField),
             "password" => Err(ConfigError::ReadonlyField),
+            "admin_password" => Err(ConfigError::ReadonlyField),
+            "rename_command" => Err(ConfigError::ReadonlyField),
             _ => Err(ConfigError::FieldNotFound),
         }
     }
@@ -117,12 +342,51 @@ pub enum ClusterNodesVersion {
     V2,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UnknownCommandPolicy {
+    // Forward the commands not in the command table to the backend.
+    Forward,
+    // Reply an `unknown command` error like Redis.
+    Reject,
+}
+
+impl fmt::Display for UnknownCommandPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Forward => write!(f, "forward-unknown"),
+            Self::Reject => write!(f, "reject-unknown"),
+        }
+    }
+}
+
+// The proxy serves only one cluster, so the clients can only select
+// the index 0 or the name of the cluster they are connected to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SelectPolicy {
+    // Only `SELECT 0` is allowed.
+    IndexOnly,
+    // `SELECT <cluster name>` is also allowed for the clients
+    // that are configured with the cluster names.
+    ClusterName,
+}
+
+impl fmt::Display for SelectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IndexOnly => write!(f, "index-only"),
+            Self::ClusterName => write!(f, "cluster-name"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ServerProxyService<H: CmdCtxHandler + ThreadSafe + Clone> {
     config: Arc<ServerProxyConfig>,
     cmd_ctx_handler: H,
     slow_request_logger: Arc<SlowRequestLogger>,
     future_registry: Arc<TrackedFutureRegistry>,
+    stats: Arc<ProxyStats>,
+    db_conn_counter: Arc<DbConnCounter>,
 }
 
 impl<H: CmdCtxHandler + ThreadSafe + Clone> ServerProxyService<H> {
@@ -131,12 +395,16 @@ impl<H: CmdCtxHandler + ThreadSafe + Clone> ServerProxyService<H> {
         cmd_ctx_handler: H,
         slow_request_logger: Arc<SlowRequestLogger>,
         future_registry: Arc<TrackedFutureRegistry>,
+        stats: Arc<ProxyStats>,
+        db_conn_counter: Arc<DbConnCounter>,
     ) -> Self {
         Self {
             config,
             cmd_ctx_handler,
             slow_request_logger,
             future_registry,
+            stats,
+            db_conn_counter,
         }
     }
 
@@ -144,27 +412,46 @@ impl<H: CmdCtxHandler + ThreadSafe + Clone> ServerProxyService<H> {
         &self,
         mut stopped: mpsc::UnboundedReceiver<()>,
     ) -> Result<(), Box<dyn Error>> {
-        let address = self.config.address.clone();
-        let address = resolve_first_address(&address).await.ok_or_else(|| {
-            let err_str = format!("failed to resolve address: {}", address);
-            error!("{}", err_str);
-            into_err(err_str)
-        })?;
-
-        let listener = TcpListener::bind(&address).await.map_err(|err| {
-            error!("unable to bind address: {} {:?}", address, err);
-            err
-        })?;
+        let mut listeners = vec![];
+        let addresses =
+            std::iter::once(&self.config.address).chain(self.config.extra_listen_addresses.iter());
+        for address in addresses {
+            let listener = bind_listener(
+                address,
+                self.config.listen_backlog.get(),
+                self.config.listen_reuseport,
+                self.config.client_socket_buffer,
+            )
+            .await?;
+            listeners.push(tokio_stream::wrappers::TcpListenerStream::new(listener));
+        }
 
         let forward_handler = self.cmd_ctx_handler.clone();
         let slow_request_logger = self.slow_request_logger.clone();
 
+        if let Some(path) = self.config.init_meta_file.as_ref() {
+            let content = tokio::fs::read_to_string(path).await.map_err(|err| {
+                error!("failed to read init_meta_file {}: {:?}", path, err);
+                err
+            })?;
+            load_init_meta(&forward_handler, &content)
+                .await
+                .map_err(|err| {
+                    error!("failed to load init_meta_file {}: {}", path, err);
+                    into_err(err)
+                })?;
+            info!("loaded init_meta_file {}", path);
+        }
+
         let session_id = AtomicUsize::new(0);
         let config = self.config.clone();
+        let protocol_limits = config.get_protocol_limits();
+        let idle_timeout = config.session_idle_timeout;
+        let output_buffer_limits = config.get_output_buffer_limits();
 
         let future_registry = self.future_registry.clone();
 
-        let mut s = tokio_stream::wrappers::TcpListenerStream::new(listener);
+        let mut s = futures::stream::select_all(listeners);
         // For `select!`
         #[allow(clippy::panic)]
         loop {
@@ -195,23 +482,55 @@ impl<H: CmdCtxHandler + ThreadSafe + Clone> ServerProxyService<H> {
             };
             debug!("accept conn: {}", peer);
 
+            // The accept loop is the only place acquiring the connections
+            // so the total could not exceed `maxclients`.
+            let maxclients = config.get_maxclients();
+            if maxclients != 0 && self.db_conn_counter.get_total() >= maxclients {
+                warn!("max number of clients reached: {}", peer);
+                self.stats
+                    .maxclients_rejected
+                    .fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(reject_session(sock, MAX_CLIENTS_ERR));
+                continue;
+            }
+
+            let db = forward_handler.get_db_name();
+            let max_conn = config.get_max_connections_per_db();
+            let db_conn_guard = match self.db_conn_counter.try_acquire(db.clone(), max_conn) {
+                Some(guard) => guard,
+                None => {
+                    warn!("max connections for database {} reached: {}", db, peer);
+                    self.stats
+                        .db_max_conn_rejected
+                        .fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(reject_session(sock, MAX_DB_CONN_ERR));
+                    continue;
+                }
+            };
+
             let curr_session_id = session_id.fetch_add(1, Ordering::SeqCst);
 
             let handle_clone = forward_handler.clone();
             let session_handler = handle_session(
                 Arc::new(Session::new(
                     curr_session_id,
+                    peer.clone(),
                     handle_clone,
                     slow_request_logger.clone(),
                     config.clone(),
                 )),
                 sock,
+                protocol_limits,
+                idle_timeout,
+                output_buffer_limits,
             );
 
             let desc = format!("session: session_id={} peer={}", curr_session_id, peer);
-            let fut = session_handler.map(move |res| match res {
-                Ok(()) => debug!("session IO closed {}", peer),
-                Err(err) => error!("session IO error {:?} {}", err, peer),
+            let stats = self.stats.clone();
+            let fut = session_handler.map(move |res| {
+                // Keep the connection counted until the session is closed.
+                let _db_conn_guard = db_conn_guard;
+                record_session_result(&stats, res, &peer);
             });
             let fut = TrackedFutureRegistry::wrap(future_registry.clone(), fut, desc);
             tokio::spawn(fut);
@@ -219,3 +538,166 @@ impl<H: CmdCtxHandler + ThreadSafe + Clone> ServerProxyService<H> {
         Ok(())
     }
 }
+
+fn record_session_result(stats: &ProxyStats, res: Result<(), SessionError>, peer: &str) {
+    match res {
+        Ok(()) => debug!("session IO closed {}", peer),
+        Err(SessionError::IdleTimeout) => {
+            stats.session_idle_closed.fetch_add(1, Ordering::Relaxed);
+            debug!("session closed for idle timeout {}", peer);
+        }
+        Err(SessionError::OutputBufferLimit) => {
+            stats
+                .session_output_buffer_closed
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        Err(err) => error!("session IO error {:?} {}", err, peer),
+    }
+}
+
+// Runs the `UMCTL SETCLUSTER` and `UMCTL SETREPL` commands in `content`, one command per line,
+// through the same path as the coordinator so the epoch and the metadata are checked the same way.
+// Empty lines and the lines starting with `#` are skipped.
+pub async fn load_init_meta<H: CmdCtxHandler>(handler: &H, content: &str) -> Result<(), String> {
+    let auth_state = AuthState::default();
+    auth_state.set_admin();
+    let client_info = ClientInfo::new(0, "init_meta_file".to_string());
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let is_meta_cmd = match args.as_slice() {
+            [cmd, sub_cmd, ..] => {
+                cmd.eq_ignore_ascii_case("UMCTL")
+                    && (sub_cmd.eq_ignore_ascii_case("SETCLUSTER")
+                        || sub_cmd.eq_ignore_ascii_case("SETREPL"))
+            }
+            _ => false,
+        };
+        if !is_meta_cmd {
+            return Err(format!("unsupported command: {}", line));
+        }
+
+        let resp = RespPacket::Data(Resp::Arr(Array::Arr(
+            args.into_iter()
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+                .collect(),
+        )));
+        let command = Command::new(Box::new(resp));
+        let (reply_sender, reply_receiver) = new_command_pair(&command);
+        let cmd_ctx = CmdCtx::new(command, reply_sender, 0, false);
+        let reply = handler
+            .handle_cmd_ctx(cmd_ctx, reply_receiver, &auth_state, &client_info)
+            .await
+            .map_err(|err| format!("{:?}: {}", err, line))?;
+        let (_, response, _) = reply.into_inner();
+        match response.into_resp_vec() {
+            Resp::Error(err) => {
+                return Err(format!("{}: {}", String::from_utf8_lossy(&err), line));
+            }
+            Resp::Simple(s) if s != OK_REPLY.as_bytes() => {
+                warn!("{}: {}", String::from_utf8_lossy(&s), line);
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+const MAX_DB_CONN_ERR: &[u8] = b"-ERR max connections for database reached\r\n";
+const MAX_CLIENTS_ERR: &[u8] = b"-ERR max number of clients reached\r\n";
+
+// Same as `TcpListener::bind` except for the configurable backlog, `SO_REUSEPORT`,
+// and the socket buffer sizes.
+async fn bind_listener(
+    address: &str,
+    backlog: u32,
+    reuseport: bool,
+    socket_buffer: SocketBufferSizes,
+) -> Result<TcpListener, Box<dyn Error>> {
+    let socket_address = resolve_first_address(address).await.ok_or_else(|| {
+        let err_str = format!("failed to resolve address: {}", address);
+        error!("{}", err_str);
+        into_err(err_str)
+    })?;
+
+    let bind = || {
+        let socket = socket_buffer.new_socket(socket_address)?;
+        // `TcpListener::bind` also sets it on unix so that the restarted proxy
+        // could bind the address with the connections in TIME_WAIT.
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+        if reuseport {
+            set_reuseport(&socket)?;
+        }
+        socket.bind(socket_address)?;
+        socket.listen(backlog)
+    };
+    bind().map_err(|err| {
+        error!("unable to bind address: {} {:?}", address, err);
+        err.into()
+    })
+}
+
+#[cfg(all(unix, not(target_os = "solaris"), not(target_os = "illumos")))]
+fn set_reuseport(socket: &TcpSocket) -> io::Result<()> {
+    socket.set_reuseport(true)
+}
+
+#[cfg(not(all(unix, not(target_os = "solaris"), not(target_os = "illumos"))))]
+fn set_reuseport(_socket: &TcpSocket) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "SO_REUSEPORT is not supported on this platform",
+    ))
+}
+
+async fn reject_session(mut sock: TcpStream, err: &'static [u8]) {
+    if let Err(err) = sock.write_all(err).await {
+        debug!("failed to send error to rejected session: {:?}", err);
+        return;
+    }
+    if let Err(err) = sock.shutdown().await {
+        debug!("failed to shutdown rejected session: {:?}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_session_result() {
+        let stats = ProxyStats::default();
+        record_session_result(&stats, Ok(()), "127.0.0.1:7000");
+        record_session_result(&stats, Err(SessionError::Canceled), "127.0.0.1:7000");
+        assert_eq!(stats.session_idle_closed.load(Ordering::Relaxed), 0);
+
+        record_session_result(&stats, Err(SessionError::IdleTimeout), "127.0.0.1:7000");
+        assert_eq!(stats.session_idle_closed.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            stats.session_output_buffer_closed.load(Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_bind_listener_reuseport() {
+        let listener = bind_listener("127.0.0.1:0", 16, true, SocketBufferSizes::default())
+            .await
+            .unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // The port could only be shared by the listeners all with SO_REUSEPORT.
+        let _listener = bind_listener(&address, 16, true, SocketBufferSizes::default())
+            .await
+            .unwrap();
+        assert!(
+            bind_listener(&address, 16, false, SocketBufferSizes::default())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -3,15 +3,16 @@ use super::command::{
     new_command_pair, CmdReplyReceiver, CmdReplySender, CmdType, Command, CommandError,
     CommandResult, DataCmdType, TaskReply, TaskResult,
 };
+use super::pause::DeferredCounter;
 use super::service::ServerProxyConfig;
 use super::slowlog::{SlowRequestLogger, Slowlog, TaskEvent};
+use crate::common::response::{ERR_COMMAND_TIMEOUT, ERR_PROTOCOL};
 use crate::protocol::{
-    new_simple_packet_codec, BinSafeStr, DecodeError, EncodeError, Resp, RespCodec, RespPacket,
-    RespVec,
+    encode_resp, new_limited_packet_codec, BinSafeStr, DecodeError, EncodeError, IndexedResp,
+    PacketSizeHint, ProtocolLimits, Resp, RespCodec, RespPacket, RespVec,
 };
 use futures::task::{Context, Poll};
 use futures::{future, Future, Sink, Stream, TryFutureExt};
-use futures::{StreamExt, TryStreamExt};
 use std::boxed::Box;
 use std::collections::VecDeque;
 use std::error::Error;
@@ -19,9 +20,12 @@ use std::fmt;
 use std::io;
 use std::pin::Pin;
 use std::sync;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWrite;
 use tokio::net::TcpStream;
+use tokio::time::Instant;
 use tokio_util::codec::Decoder;
 
 // CmdReplyReceiver is the fast path without heap allocation.
@@ -31,6 +35,8 @@ pub type CmdReplyFuture<'a> =
 pub trait CmdHandler {
     fn handle_cmd(&self, cmd: Command) -> CmdReplyFuture;
     fn handle_slowlog(&self, request: Box<RespPacket>, slowlog: Slowlog);
+    // The bytes of the replies not written to the client yet.
+    fn set_output_buffer_size(&self, size: usize);
 }
 
 pub trait CmdCtxHandler {
@@ -38,8 +44,127 @@ pub trait CmdCtxHandler {
         &self,
         cmd_ctx: CmdCtx,
         result_receiver: CmdReplyReceiver,
-        authenticated: &AtomicBool,
+        auth_state: &AuthState,
+        client_info: &ClientInfo,
     ) -> CmdReplyFuture;
+    // The database which the new sessions belong to.
+    fn get_db_name(&self) -> String;
+    // The commands deferred by `CLIENT PAUSE` should not time out during the pause.
+    fn get_pause_remaining(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+}
+
+// Command handler does not run in parallel for the same session.
+#[derive(Debug, Default)]
+pub struct AuthState {
+    authenticated: AtomicBool,
+    admin: AtomicBool,
+    // One-shot flag set by ASKING for the next command.
+    asking: AtomicBool,
+}
+
+impl AuthState {
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::Relaxed)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin.load(Ordering::Relaxed)
+    }
+
+    pub fn set_authenticated(&self) {
+        self.authenticated.store(true, Ordering::Relaxed);
+        self.admin.store(false, Ordering::Relaxed);
+    }
+
+    // An admin session can also run data commands.
+    pub fn set_admin(&self) {
+        self.authenticated.store(true, Ordering::Relaxed);
+        self.admin.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_asking(&self) {
+        self.asking.store(true, Ordering::Relaxed);
+    }
+
+    // Returns whether the last command is ASKING and clears the flag.
+    pub fn take_asking(&self) -> bool {
+        self.asking.swap(false, Ordering::Relaxed)
+    }
+}
+
+// The connection information shown in CLIENT INFO.
+#[derive(Debug)]
+pub struct ClientInfo {
+    id: usize,
+    addr: String,
+    created: Instant,
+    name: parking_lot::Mutex<String>,
+    // The lowercase name of the latest command.
+    last_cmd: parking_lot::Mutex<String>,
+    output_buffer_size: AtomicUsize,
+    deferred: DeferredCounter,
+}
+
+impl ClientInfo {
+    pub fn new(id: usize, addr: String) -> Self {
+        Self {
+            id,
+            addr,
+            created: Instant::now(),
+            name: parking_lot::Mutex::new(String::new()),
+            last_cmd: parking_lot::Mutex::new(String::new()),
+            output_buffer_size: AtomicUsize::new(0),
+            deferred: DeferredCounter::default(),
+        }
+    }
+
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    // Empty name means the name is not set.
+    pub fn get_name(&self) -> String {
+        self.name.lock().clone()
+    }
+
+    pub fn set_name(&self, name: String) {
+        *self.name.lock() = name;
+    }
+
+    pub fn set_last_cmd(&self, cmd_name: &str) {
+        let mut last_cmd = self.last_cmd.lock();
+        // Reuse the buffer to avoid allocation for every command.
+        last_cmd.clear();
+        last_cmd.extend(cmd_name.chars().map(|c| c.to_ascii_lowercase()));
+    }
+
+    pub fn get_output_buffer_size(&self) -> usize {
+        self.output_buffer_size.load(Ordering::Relaxed)
+    }
+
+    pub fn set_output_buffer_size(&self, size: usize) {
+        self.output_buffer_size.store(size, Ordering::Relaxed)
+    }
+
+    pub(crate) fn get_deferred_counter(&self) -> &DeferredCounter {
+        &self.deferred
+    }
+
+    // Follows the field order of Redis.
+    pub fn to_info_line(&self, db: usize) -> String {
+        format!(
+            "id={} addr={} name={} age={} omem={} db={} cmd={}\n",
+            self.id,
+            self.addr,
+            self.name.lock(),
+            self.created.elapsed().as_secs(),
+            self.get_output_buffer_size(),
+            db,
+            self.last_cmd.lock(),
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -48,6 +173,7 @@ pub struct CmdCtx {
     reply_sender: CmdReplySender,
     slowlog: Slowlog,
     redirection_times: Option<usize>,
+    asking: bool,
 }
 
 impl CmdCtx {
@@ -63,6 +189,7 @@ impl CmdCtx {
             reply_sender,
             slowlog,
             redirection_times: None,
+            asking: false,
         }
     }
 
@@ -78,6 +205,10 @@ impl CmdCtx {
         self.cmd.change_element(index, data)
     }
 
+    pub fn refresh_cmd_info(&mut self) {
+        self.cmd.refresh_info()
+    }
+
     // Returns remaining elements
     pub fn extract_inner_cmd(&mut self, removed_num: usize) -> Option<usize> {
         self.cmd.extract_inner_cmd(removed_num)
@@ -102,11 +233,16 @@ impl CmdCtx {
     pub fn get_redirection_times(&self) -> Option<usize> {
         self.redirection_times
     }
+
+    pub fn set_asking(&mut self, asking: bool) {
+        self.asking = asking
+    }
 }
 
 pub struct SessionContext {
     session_id: usize,
     slowlog_enabled: bool,
+    asking: bool,
 }
 
 impl CmdTask for CmdCtx {
@@ -131,9 +267,11 @@ impl CmdTask for CmdCtx {
         } = self;
         let task_result =
             result.map(|packet| Box::new(TaskReply::new(cmd.into_packet(), packet, slowlog)));
-        let res = reply_sender.send(task_result);
-        if let Err(e) = res {
-            error!("Failed to send result: {:?}", e);
+        match reply_sender.send(task_result) {
+            Ok(()) => (),
+            // The session is closed or the command has timed out.
+            Err(CommandError::Canceled) => debug!("result is discarded"),
+            Err(e) => error!("Failed to send result: {:?}", e),
         }
     }
 
@@ -149,6 +287,7 @@ impl CmdTask for CmdCtx {
         SessionContext {
             session_id: self.get_session_id(),
             slowlog_enabled: self.slowlog.is_enabled(),
+            asking: self.asking,
         }
     }
 
@@ -162,6 +301,10 @@ impl CmdTask for CmdCtx {
     fn log_event(&mut self, event: TaskEvent) {
         self.slowlog.log_event(event);
     }
+
+    fn is_asking(&self) -> bool {
+        self.asking
+    }
 }
 
 pub struct CmdCtxFactory;
@@ -189,8 +332,10 @@ impl CmdTaskFactory for CmdCtxFactory {
         let SessionContext {
             session_id,
             slowlog_enabled,
+            asking,
         } = context;
-        let cmd_ctx = CmdCtx::new(cmd, reply_sender, session_id, slowlog_enabled);
+        let mut cmd_ctx = CmdCtx::new(cmd, reply_sender, session_id, slowlog_enabled);
+        cmd_ctx.set_asking(asking);
         let fut = reply_receiver.map_ok(|reply| reply.into_resp_vec());
         (cmd_ctx, Box::pin(fut))
     }
@@ -198,7 +343,8 @@ impl CmdTaskFactory for CmdCtxFactory {
 
 pub struct Session<H: CmdCtxHandler> {
     session_id: usize,
-    authenticated: AtomicBool,
+    auth_state: AuthState,
+    client_info: ClientInfo,
     cmd_ctx_handler: H,
     slow_request_logger: sync::Arc<SlowRequestLogger>,
     config: Arc<ServerProxyConfig>,
@@ -207,13 +353,15 @@ pub struct Session<H: CmdCtxHandler> {
 impl<H: CmdCtxHandler> Session<H> {
     pub fn new(
         session_id: usize,
+        peer: String,
         cmd_ctx_handler: H,
         slow_request_logger: sync::Arc<SlowRequestLogger>,
         config: Arc<ServerProxyConfig>,
     ) -> Self {
         Session {
             session_id,
-            authenticated: AtomicBool::new(false),
+            auth_state: AuthState::default(),
+            client_info: ClientInfo::new(session_id, peer),
             cmd_ctx_handler,
             slow_request_logger,
             config,
@@ -223,40 +371,212 @@ impl<H: CmdCtxHandler> Session<H> {
 
 impl<H: CmdCtxHandler> CmdHandler for Session<H> {
     fn handle_cmd(&self, cmd: Command) -> CmdReplyFuture {
+        if let Some(cmd_name) = cmd.get_command_name() {
+            self.client_info.set_last_cmd(cmd_name);
+        }
         let (reply_sender, reply_receiver) = new_command_pair(&cmd);
 
         let slowlog_enabled = self
             .slow_request_logger
             .limit_rate(self.config.get_slowlog_sample_rate());
+        // The timeout starts from the arrival of the command
+        // instead of the first polling of the reply future.
+        let arrival = Instant::now();
+        let timeout = if cmd.get_data_cmd_type().is_blocking_cmd() {
+            None
+        } else {
+            self.config.get_command_timeout()
+        };
         let mut cmd_ctx = CmdCtx::new(cmd, reply_sender, self.session_id, slowlog_enabled);
         cmd_ctx.log_event(TaskEvent::Created);
-        self.cmd_ctx_handler
-            .handle_cmd_ctx(cmd_ctx, reply_receiver, &self.authenticated)
+        let fut = self.cmd_ctx_handler.handle_cmd_ctx(
+            cmd_ctx,
+            reply_receiver,
+            &self.auth_state,
+            &self.client_info,
+        );
+        let deadline = timeout.and_then(|timeout| {
+            let timeout = timeout.saturating_add(self.cmd_ctx_handler.get_pause_remaining());
+            arrival.checked_add(timeout)
+        });
+        match deadline {
+            Some(deadline) => with_deadline(fut, deadline),
+            None => fut,
+        }
     }
 
     fn handle_slowlog(&self, request: Box<RespPacket>, slowlog: Slowlog) {
         self.slow_request_logger.add_slow_log(request, slowlog)
     }
+
+    fn set_output_buffer_size(&self, size: usize) {
+        self.client_info.set_output_buffer_size(size)
+    }
+}
+
+// Stops waiting for the reply after `deadline`.
+// The late reply will be discarded by the `CmdReplySender`.
+fn with_deadline(fut: CmdReplyFuture, deadline: Instant) -> CmdReplyFuture {
+    let fut = async move {
+        tokio::time::timeout_at(deadline, fut)
+            .await
+            .unwrap_or(Err(CommandError::Timeout))
+    };
+    future::Either::Right(Box::pin(fut))
+}
+
+// Like `client-output-buffer-limit` of Redis. 0 disables the limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputBufferLimits {
+    pub hard_limit: usize,
+    pub soft_limit: usize,
+    pub soft_duration: Duration,
+    // The replies not smaller than it are written to the socket directly
+    // without being copied into the write buffer. 0 disables it.
+    pub stream_threshold: usize,
+}
+
+// Tracks the replies waiting to be written to the slow clients.
+struct OutputBuffer {
+    limits: OutputBufferLimits,
+    size: usize,
+    // Since when the size has kept exceeding the soft limit.
+    soft_limit_reached: Option<Instant>,
+}
+
+impl OutputBuffer {
+    fn new(limits: OutputBufferLimits) -> Self {
+        Self {
+            limits,
+            size: 0,
+            soft_limit_reached: None,
+        }
+    }
+
+    fn get_size(&self) -> usize {
+        self.size
+    }
+
+    fn push(&mut self, size: usize) {
+        self.size = self.size.saturating_add(size);
+    }
+
+    fn pop(&mut self, size: usize) {
+        self.size = self.size.saturating_sub(size);
+        if self.size <= self.limits.soft_limit {
+            self.soft_limit_reached = None;
+        }
+    }
+
+    fn exceeds_limits(&mut self, now: Instant) -> bool {
+        let OutputBufferLimits {
+            hard_limit,
+            soft_limit,
+            soft_duration,
+            ..
+        } = self.limits;
+        if hard_limit != 0 && self.size > hard_limit {
+            return true;
+        }
+        if soft_limit == 0 || self.size <= soft_limit {
+            return false;
+        }
+        let reached = *self.soft_limit_reached.get_or_insert(now);
+        now.saturating_duration_since(reached) >= soft_duration
+    }
 }
 
-pub async fn handle_session<H>(handler: sync::Arc<H>, sock: TcpStream) -> Result<(), SessionError>
+enum StreamingData {
+    Indexed(IndexedResp),
+    Encoded(Vec<u8>),
+}
+
+// A large reply being written from its own buffer.
+// The write buffer of the codec keeps its capacity after sending a large reply,
+// so copying the large replies into it would double the memory for every connection.
+struct StreamingReply {
+    size: usize,
+    data: StreamingData,
+    written: usize,
+}
+
+impl StreamingReply {
+    fn new(size: usize, packet: RespPacket) -> io::Result<Self> {
+        let data = match packet {
+            RespPacket::Indexed(indexed_resp) => StreamingData::Indexed(indexed_resp),
+            RespPacket::Data(resp) => {
+                let mut buf = Vec::with_capacity(size);
+                encode_resp(&mut buf, &resp)?;
+                StreamingData::Encoded(buf)
+            }
+        };
+        Ok(Self {
+            size,
+            data,
+            written: 0,
+        })
+    }
+
+    fn poll_write<W: AsyncWrite + Unpin>(
+        &mut self,
+        writer: &mut W,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let data = match &self.data {
+            StreamingData::Indexed(indexed_resp) => indexed_resp.get_data(),
+            StreamingData::Encoded(buf) => buf.as_slice(),
+        };
+        while let Some(remaining) = data.get(self.written..).filter(|d| !d.is_empty()) {
+            match Pin::new(&mut *writer).poll_write(cx, remaining) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)))
+                }
+                Poll::Ready(Ok(n)) => self.written += n,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub async fn handle_session<H>(
+    handler: sync::Arc<H>,
+    sock: TcpStream,
+    limits: ProtocolLimits,
+    idle_timeout: Option<Duration>,
+    output_buffer_limits: OutputBufferLimits,
+) -> Result<(), SessionError>
 where
     H: CmdHandler + Send + Sync + 'static,
 {
-    let (encoder, decoder) = new_simple_packet_codec::<Box<RespPacket>, Box<RespPacket>>();
-    let (mut writer, reader) = RespCodec::new(encoder, decoder).framed(sock).split();
-    let mut reader = reader.map_err(|e| match e {
-        DecodeError::Io(e) => SessionError::Io(e),
-        DecodeError::InvalidProtocol => SessionError::Canceled,
-    });
+    let (encoder, decoder) = new_limited_packet_codec(limits);
+    // Not split so that the large replies could be written to the socket directly.
+    let mut framed = RespCodec::new(encoder, decoder).framed(sock);
 
     const SESSION_BATCH_BUF: usize = 64;
     let mut reply_receiver_list = VecDeque::<CmdReplyFuture>::with_capacity(SESSION_BATCH_BUF);
-    let mut replies = VecDeque::<Box<RespPacket>>::with_capacity(SESSION_BATCH_BUF);
+    // (size, reply)
+    let mut replies = VecDeque::<(usize, Box<RespPacket>)>::with_capacity(SESSION_BATCH_BUF);
+    let mut output_buffer = OutputBuffer::new(output_buffer_limits);
+    let stream_threshold = output_buffer_limits.stream_threshold;
+    let mut streaming_reply: Option<StreamingReply> = None;
+
+    // When disabled, the timer will never be polled.
+    let mut idle_timer = Box::pin(tokio::time::sleep(idle_timeout.unwrap_or_default()));
+    // Same as Redis, the commands after QUIT are ignored
+    // and the connection is closed after the OK reply is flushed.
+    let mut quitting = false;
+    // Same as Redis, the protocol error is replied after the replies of the former commands
+    // and then the connection is closed.
+    let mut protocol_error = false;
+    let mut protocol_error_replied = false;
 
     future::poll_fn(|cx: &mut Context<'_>| -> Poll<Result<(), SessionError>> {
-        loop {
-            match Pin::new(&mut reader).poll_next(cx) {
+        let mut active = false;
+
+        while !quitting {
+            match Pin::new(&mut framed).poll_next(cx) {
                 Poll::Ready(None) => {
                     debug!("Session is closed by peer");
                     return Poll::Ready(Ok(()));
@@ -264,15 +584,27 @@ where
                 Poll::Ready(Some(req)) => {
                     let packet = match req {
                         Ok(packet) => packet,
-                        Err(err) => {
+                        Err(DecodeError::InvalidProtocol) => {
+                            warn!("session protocol error");
+                            protocol_error = true;
+                            quitting = true;
+                            break;
+                        }
+                        Err(DecodeError::Io(err)) => {
                             error!("session reader error {:?}", err);
-                            return Poll::Ready(Err(err));
+                            return Poll::Ready(Err(SessionError::Io(err)));
                         }
                     };
+                    // Same as Redis, empty commands are ignored without any reply.
+                    if is_empty_command(&packet) {
+                        continue;
+                    }
                     let cmd = Command::new(packet);
+                    quitting = cmd.get_type() == CmdType::Quit;
 
                     let fut = handler.handle_cmd(cmd);
                     reply_receiver_list.push_back(fut);
+                    active = true;
                 }
                 Poll::Pending => {
                     break;
@@ -299,6 +631,10 @@ where
                             handler.handle_slowlog(request, slowlog);
                             packet
                         }
+                        Err(CommandError::Timeout) => {
+                            let resp = Resp::Error(ERR_COMMAND_TIMEOUT.as_bytes().to_vec());
+                            Box::new(RespPacket::from_resp_vec(resp))
+                        }
                         Err(e) => {
                             let err_msg = format!("Err cmd error {:?}", e);
                             error!("{}", err_msg);
@@ -307,21 +643,71 @@ where
                         }
                     };
 
-                    replies.push_back(packet);
+                    let size = packet.get_size_hint().unwrap_or(0);
+                    output_buffer.push(size);
+                    replies.push_back((size, packet));
+                    active = true;
                 }
             }
         }
 
+        if protocol_error && !protocol_error_replied && reply_receiver_list.is_empty() {
+            let resp = Resp::Error(ERR_PROTOCOL.as_bytes().to_vec());
+            let packet = Box::new(RespPacket::from_resp_vec(resp));
+            let size = packet.get_size_hint().unwrap_or(0);
+            output_buffer.push(size);
+            replies.push_back((size, packet));
+            protocol_error_replied = true;
+        }
+
+        if let Some(timeout) = idle_timeout {
+            // The sessions waiting for the replies such as
+            // the ones running blocking commands are not idle.
+            let waiting = !reply_receiver_list.is_empty() || !replies.is_empty();
+            if active || waiting {
+                idle_timer.as_mut().reset(Instant::now() + timeout);
+            }
+            // Always poll the timer to get woken up later.
+            if idle_timer.as_mut().poll(cx).is_ready() && !waiting {
+                debug!("Session is closed for being idle");
+                return Poll::Ready(Err(SessionError::IdleTimeout));
+            }
+        }
+
         let poll_res = loop {
-            match Pin::new(&mut writer).poll_ready(cx) {
+            if let Some(reply) = streaming_reply.as_mut() {
+                // The replies before it in the write buffer need to be sent first.
+                match Pin::new(&mut framed).poll_flush(cx) {
+                    Poll::Pending => break Poll::Pending,
+                    Poll::Ready(Ok(())) => (),
+                    Poll::Ready(Err(err)) => break Poll::Ready(Err(err)),
+                }
+                match reply.poll_write(framed.get_mut(), cx) {
+                    Poll::Pending => break Poll::Pending,
+                    Poll::Ready(Ok(())) => {
+                        output_buffer.pop(reply.size);
+                        streaming_reply = None;
+                    }
+                    Poll::Ready(Err(err)) => break Poll::Ready(Err(EncodeError::Io(err))),
+                }
+            }
+
+            match Pin::new(&mut framed).poll_ready(cx) {
                 Poll::Pending => break Poll::Pending,
                 Poll::Ready(Ok(())) => (),
                 Poll::Ready(Err(err)) => break Poll::Ready(Err(err)),
             }
 
             match replies.pop_front() {
-                Some(reply) => {
-                    if let Err(err) = Pin::new(&mut writer).start_send(reply) {
+                Some((size, reply)) if stream_threshold != 0 && size >= stream_threshold => {
+                    match StreamingReply::new(size, *reply) {
+                        Ok(reply) => streaming_reply = Some(reply),
+                        Err(err) => break Poll::Ready(Err(EncodeError::Io(err))),
+                    }
+                }
+                Some((size, reply)) => {
+                    output_buffer.pop(size);
+                    if let Err(err) = Pin::new(&mut framed).start_send(reply) {
                         break Poll::Ready(Err(err));
                     }
                 }
@@ -329,11 +715,39 @@ where
                     // Even we don't call `start_send` this time,
                     // the former execution of this polling function may have
                     // a Pending result for poll_flush. We need to flush anyway.
-                    break Pin::new(&mut writer).poll_flush(cx);
+                    break Pin::new(&mut framed).poll_flush(cx);
                 }
             };
         };
 
+        handler.set_output_buffer_size(output_buffer.get_size());
+        if output_buffer.get_size() > 0 && output_buffer.exceeds_limits(Instant::now()) {
+            warn!(
+                "Session is closed for exceeding the output buffer limits: {} bytes",
+                output_buffer.get_size()
+            );
+            return Poll::Ready(Err(SessionError::OutputBufferLimit));
+        }
+
+        let all_sent =
+            reply_receiver_list.is_empty() && replies.is_empty() && streaming_reply.is_none();
+        if quitting && all_sent {
+            if let Poll::Ready(Ok(())) = poll_res {
+                let closed = Pin::new(&mut framed)
+                    .poll_close(cx)
+                    .map_err(|err| match err {
+                        EncodeError::Io(err) => SessionError::Io(err),
+                        EncodeError::NotReady(_) => SessionError::InvalidState,
+                    });
+                if protocol_error {
+                    debug!("Session is closed for the protocol error");
+                    return closed.map(|res| res.and(Err(SessionError::InvalidProtocol)));
+                }
+                debug!("Session is closed by QUIT");
+                return closed;
+            }
+        }
+
         match poll_res {
             Poll::Pending | Poll::Ready(Ok(())) => Poll::Pending,
             Poll::Ready(Err(err)) => {
@@ -355,6 +769,8 @@ pub enum SessionError {
     InvalidProtocol,
     Canceled,
     InvalidState,
+    IdleTimeout,
+    OutputBufferLimit,
 }
 
 impl fmt::Display for SessionError {
@@ -377,11 +793,26 @@ impl Error for SessionError {
     }
 }
 
+fn is_empty_command(packet: &RespPacket) -> bool {
+    packet.get_array_len() == Some(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::protocol::{Array, BulkStr, Resp};
+    use crate::protocol::{Array, BulkStr, DecodedPacket, Resp};
+    use bytes::BytesMut;
     use tokio;
+    #[test]
+    fn test_empty_command() {
+        let mut buf = BytesMut::from(&b"*0\r\n*1\r\n$0\r\n\r\n"[..]);
+        let packet = RespPacket::decode(&mut buf, ()).unwrap().unwrap();
+        assert!(is_empty_command(&packet));
+        // The command with an empty name is not ignored and will get an error.
+        let packet = RespPacket::decode(&mut buf, ()).unwrap().unwrap();
+        assert!(!is_empty_command(&packet));
+        assert_eq!(Command::new(Box::new(packet)).get_type(), CmdType::Invalid);
+    }
 
     #[tokio::test]
     async fn test_cmd_ctx_auto_send() {
@@ -398,4 +829,384 @@ mod tests {
         };
         assert!(matches!(err, CommandError::Dropped));
     }
+
+    #[tokio::test]
+    async fn test_cmd_timeout() {
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+            b"GET".to_vec(),
+        ))])));
+        let cmd = Command::new(Box::new(request));
+        let (sender, receiver) = new_command_pair(&cmd);
+        let cmd_ctx = CmdCtx::new(cmd, sender, 7799, true);
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        let fut = with_deadline(future::Either::Left(receiver), deadline);
+        let err = match fut.await {
+            Ok(_) => panic!(),
+            Err(err) => err,
+        };
+        assert!(matches!(err, CommandError::Timeout));
+        // The late reply is discarded.
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(b"OK".to_vec())));
+
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+            b"GET".to_vec(),
+        ))])));
+        let cmd = Command::new(Box::new(request));
+        let (sender, receiver) = new_command_pair(&cmd);
+        let cmd_ctx = CmdCtx::new(cmd, sender, 7799, true);
+        cmd_ctx.set_resp_result(Ok(Resp::Simple(b"OK".to_vec())));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let fut = with_deadline(future::Either::Left(receiver), deadline);
+        assert!(fut.await.is_ok());
+
+        // The time before the first polling also counts.
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+            b"GET".to_vec(),
+        ))])));
+        let cmd = Command::new(Box::new(request));
+        let (sender, receiver) = new_command_pair(&cmd);
+        let _cmd_ctx = CmdCtx::new(cmd, sender, 7799, true);
+        let deadline = Instant::now() + Duration::from_millis(300);
+        let fut = with_deadline(future::Either::Left(receiver), deadline);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let start = Instant::now();
+        assert!(matches!(fut.await, Err(CommandError::Timeout)));
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+
+    struct OkHandler;
+
+    impl CmdHandler for OkHandler {
+        fn handle_cmd(&self, cmd: Command) -> CmdReplyFuture {
+            let (sender, receiver) = new_command_pair(&cmd);
+            let cmd_ctx = CmdCtx::new(cmd, sender, 7799, false);
+            cmd_ctx.set_resp_result(Ok(Resp::Simple(b"OK".to_vec())));
+            future::Either::Left(receiver)
+        }
+
+        fn handle_slowlog(&self, _request: Box<RespPacket>, _slowlog: Slowlog) {}
+
+        fn set_output_buffer_size(&self, _size: usize) {}
+    }
+
+    #[tokio::test]
+    async fn test_quit_closes_session() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let session = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            handle_session(
+                Arc::new(OkHandler),
+                sock,
+                ProtocolLimits::default(),
+                None,
+                OutputBufferLimits::default(),
+            )
+            .await
+        });
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        // The command after QUIT is ignored.
+        client.write_all(b"PING\r\nQUIT\r\nPING\r\n").await.unwrap();
+        let mut buf = vec![];
+        // Returns only after the session closes the connection.
+        tokio::time::timeout(Duration::from_secs(3), client.read_to_end(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(buf, b"+OK\r\n+OK\r\n".to_vec());
+        assert!(session.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error_reply() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let limits = ProtocolLimits {
+            max_bulk_len: 16,
+            ..ProtocolLimits::default()
+        };
+        let session = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            handle_session(
+                Arc::new(OkHandler),
+                sock,
+                limits,
+                None,
+                OutputBufferLimits::default(),
+            )
+            .await
+        });
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        // The commands after the invalid one are ignored.
+        client
+            .write_all(b"PING\r\n*2\r\n$3\r\nGET\r\n$1024\r\nPING\r\n")
+            .await
+            .unwrap();
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(3), client.read_to_end(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        let expected = format!("+OK\r\n-{}\r\n", ERR_PROTOCOL);
+        assert_eq!(buf, expected.into_bytes());
+        assert!(matches!(
+            session.await.unwrap(),
+            Err(SessionError::InvalidProtocol)
+        ));
+    }
+
+    // Never replies.
+    struct PendingHandler;
+
+    impl CmdHandler for PendingHandler {
+        fn handle_cmd(&self, _cmd: Command) -> CmdReplyFuture {
+            future::Either::Right(Box::pin(future::pending()))
+        }
+
+        fn handle_slowlog(&self, _request: Box<RespPacket>, _slowlog: Slowlog) {}
+
+        fn set_output_buffer_size(&self, _size: usize) {}
+    }
+
+    async fn start_idle_session<H: CmdHandler + Send + Sync + 'static>(
+        handler: H,
+        idle_timeout: Duration,
+    ) -> (TcpStream, tokio::task::JoinHandle<Result<(), SessionError>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (sock, _) = listener.accept().await.unwrap();
+        let session = tokio::spawn(handle_session(
+            Arc::new(handler),
+            sock,
+            ProtocolLimits::default(),
+            Some(idle_timeout),
+            OutputBufferLimits::default(),
+        ));
+        (client, session)
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut client, session) = start_idle_session(OkHandler, Duration::from_millis(100)).await;
+        client.write_all(b"PING\r\n").await.unwrap();
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"+OK\r\n");
+
+        let res = tokio::time::timeout(Duration::from_secs(3), session)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(res, Err(SessionError::IdleTimeout)));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_waiting_for_reply() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut client, mut session) =
+            start_idle_session(PendingHandler, Duration::from_millis(50)).await;
+        client.write_all(b"BLPOP key 0\r\n").await.unwrap();
+        // Still waiting for the reply long after the idle timeout.
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), &mut session)
+                .await
+                .is_err()
+        );
+
+        drop(client);
+        let res = tokio::time::timeout(Duration::from_secs(3), session)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(res.is_ok());
+    }
+
+    // Replies large values to GET and ECHO, and OK to the others.
+    struct LargeReplyHandler;
+
+    impl LargeReplyHandler {
+        fn gen_value(len: usize) -> Vec<u8> {
+            (0..len).map(|i| b'a' + (i % 26) as u8).collect()
+        }
+
+        fn gen_encoded_reply(len: usize) -> Vec<u8> {
+            let mut buf = format!("${}\r\n", len).into_bytes();
+            buf.extend(Self::gen_value(len));
+            buf.extend_from_slice(b"\r\n");
+            buf
+        }
+    }
+
+    impl CmdHandler for LargeReplyHandler {
+        fn handle_cmd(&self, cmd: Command) -> CmdReplyFuture {
+            let cmd_type = cmd.get_type();
+            let (sender, receiver) = new_command_pair(&cmd);
+            let cmd_ctx = CmdCtx::new(cmd, sender, 7799, false);
+            match cmd_type {
+                // Decoded from the backend.
+                CmdType::Others => {
+                    let mut buf =
+                        BytesMut::from(Self::gen_encoded_reply(3 * 1024 * 1024).as_slice());
+                    let packet = RespPacket::decode(&mut buf, ()).unwrap().unwrap();
+                    cmd_ctx.set_result(Ok(Box::new(packet)));
+                }
+                // Generated by the proxy.
+                CmdType::Echo => {
+                    let value = Self::gen_value(2 * 1024 * 1024);
+                    cmd_ctx.set_resp_result(Ok(Resp::Bulk(BulkStr::Str(value))));
+                }
+                _ => cmd_ctx.set_resp_result(Ok(Resp::Simple(b"OK".to_vec()))),
+            }
+            future::Either::Left(receiver)
+        }
+
+        fn handle_slowlog(&self, _request: Box<RespPacket>, _slowlog: Slowlog) {}
+
+        fn set_output_buffer_size(&self, _size: usize) {}
+    }
+
+    #[tokio::test]
+    async fn test_stream_large_replies() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let session = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            handle_session(
+                Arc::new(LargeReplyHandler),
+                sock,
+                ProtocolLimits::default(),
+                None,
+                OutputBufferLimits {
+                    stream_threshold: 1024,
+                    ..Default::default()
+                },
+            )
+            .await
+        });
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client
+            .write_all(b"PING\r\nGET key\r\nPING\r\nECHO value\r\nQUIT\r\n")
+            .await
+            .unwrap();
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(10), client.read_to_end(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The large replies are not mixed up with the small ones.
+        let mut expected = b"+OK\r\n".to_vec();
+        expected.extend(LargeReplyHandler::gen_encoded_reply(3 * 1024 * 1024));
+        expected.extend_from_slice(b"+OK\r\n");
+        expected.extend(LargeReplyHandler::gen_encoded_reply(2 * 1024 * 1024));
+        expected.extend_from_slice(b"+OK\r\n");
+        assert_eq!(buf.len(), expected.len());
+        assert!(buf == expected);
+        assert!(session.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_output_buffer_hard_limit() {
+        let mut output_buffer = OutputBuffer::new(OutputBufferLimits {
+            hard_limit: 100,
+            soft_limit: 0,
+            soft_duration: Duration::from_secs(10),
+            stream_threshold: 0,
+        });
+        let now = Instant::now();
+        output_buffer.push(60);
+        output_buffer.push(40);
+        assert!(!output_buffer.exceeds_limits(now));
+        output_buffer.push(1);
+        assert!(output_buffer.exceeds_limits(now));
+        output_buffer.pop(60);
+        assert_eq!(output_buffer.get_size(), 41);
+        assert!(!output_buffer.exceeds_limits(now));
+    }
+
+    #[test]
+    fn test_output_buffer_soft_limit() {
+        let mut output_buffer = OutputBuffer::new(OutputBufferLimits {
+            hard_limit: 0,
+            soft_limit: 100,
+            soft_duration: Duration::from_secs(10),
+            stream_threshold: 0,
+        });
+        let now = Instant::now();
+        output_buffer.push(200);
+        assert!(!output_buffer.exceeds_limits(now));
+        assert!(!output_buffer.exceeds_limits(now + Duration::from_secs(9)));
+        assert!(output_buffer.exceeds_limits(now + Duration::from_secs(10)));
+
+        // Dropping below the soft limit restarts the timing.
+        output_buffer.pop(150);
+        output_buffer.push(150);
+        assert!(!output_buffer.exceeds_limits(now + Duration::from_secs(10)));
+        assert!(output_buffer.exceeds_limits(now + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_output_buffer_no_limit() {
+        let mut output_buffer = OutputBuffer::new(OutputBufferLimits::default());
+        output_buffer.push(usize::MAX);
+        assert!(!output_buffer.exceeds_limits(Instant::now()));
+    }
+
+    #[test]
+    fn test_auth_state() {
+        let auth_state = AuthState::default();
+        assert!(!auth_state.is_authenticated());
+        assert!(!auth_state.is_admin());
+
+        auth_state.set_admin();
+        assert!(auth_state.is_authenticated());
+        assert!(auth_state.is_admin());
+
+        auth_state.set_authenticated();
+        assert!(auth_state.is_authenticated());
+        assert!(!auth_state.is_admin());
+    }
+
+    #[test]
+    fn test_asking() {
+        let auth_state = AuthState::default();
+        assert!(!auth_state.take_asking());
+        auth_state.set_asking();
+        assert!(auth_state.take_asking());
+        assert!(!auth_state.take_asking());
+
+        let request = RespPacket::Data(Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"MGET".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"key".to_vec())),
+        ])));
+        let cmd = Command::new(Box::new(request));
+        let (sender, _receiver) = new_command_pair(&cmd);
+        let mut cmd_ctx = CmdCtx::new(cmd, sender, 7799, true);
+        assert!(!cmd_ctx.is_asking());
+        cmd_ctx.set_asking(true);
+        assert!(cmd_ctx.is_asking());
+
+        // The sub commands inherit the flag.
+        let resp = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"GET".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"key".to_vec())),
+        ]));
+        let (sub_cmd_ctx, _fut) = CmdCtxFactory.create_with_ctx(cmd_ctx.get_context(), resp);
+        assert!(sub_cmd_ctx.is_asking());
+    }
 }
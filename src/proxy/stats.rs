@@ -0,0 +1,442 @@
+use super::command::DataCmdType;
+use crate::common::response::ERR_MOVED;
+use crate::common::stats::atomic_usize_stats;
+use crate::protocol::{BulkStr, Resp, RespPacket};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+atomic_usize_stats! {
+    pub struct ProxyStats {
+        pub session_idle_closed: AtomicUsize,
+        pub session_output_buffer_closed: AtomicUsize,
+        pub db_max_conn_rejected: AtomicUsize,
+        pub maxclients_rejected: AtomicUsize,
+    }
+}
+
+// Tracks the client connections of each database.
+#[derive(Debug, Default)]
+pub struct DbConnCounter {
+    counts: parking_lot::Mutex<HashMap<String, usize>>,
+}
+
+impl DbConnCounter {
+    // `max_conn` of 0 means unlimited.
+    // The connection is counted until the returned guard is dropped.
+    pub fn try_acquire(self: &Arc<Self>, db: String, max_conn: usize) -> Option<DbConnGuard> {
+        {
+            let mut counts = self.counts.lock();
+            let count = counts.entry(db.clone()).or_insert(0);
+            if max_conn != 0 && *count >= max_conn {
+                return None;
+            }
+            *count += 1;
+        }
+        Some(DbConnGuard {
+            counter: self.clone(),
+            db,
+        })
+    }
+
+    pub fn get_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = self
+            .counts
+            .lock()
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(db, count)| (db.clone(), *count))
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    // The client connections of all the databases.
+    pub fn get_total(&self) -> usize {
+        self.counts.lock().values().sum()
+    }
+
+    fn release(&self, db: &str) {
+        let mut counts = self.counts.lock();
+        if let Some(count) = counts.get_mut(db) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                counts.remove(db);
+            }
+        }
+    }
+}
+
+// The results of warming up the connections of each backend after UMCTL SETCLUSTER.
+#[derive(Debug, Default)]
+pub struct BackendWarmupStats {
+    // address => (success, failure)
+    counts: parking_lot::Mutex<HashMap<String, (usize, usize)>>,
+}
+
+impl BackendWarmupStats {
+    pub fn record(&self, address: String, success: bool) {
+        let mut counts = self.counts.lock();
+        let count = counts.entry(address).or_insert((0, 0));
+        if success {
+            count.0 += 1;
+        } else {
+            count.1 += 1;
+        }
+    }
+
+    // Returns (success, failure)
+    pub fn get(&self, address: &str) -> (usize, usize) {
+        self.counts.lock().get(address).cloned().unwrap_or((0, 0))
+    }
+
+    // Removes the backends no longer owned by this proxy.
+    pub fn retain(&self, addresses: &[String]) {
+        self.counts
+            .lock()
+            .retain(|address, _| addresses.contains(address));
+    }
+}
+
+// The MOVED replies from the backends owned by this proxy within one second
+// to be considered as a storm, which indicates stale metadata.
+const MOVED_STORM_THRESHOLD: usize = 64;
+// Log the storm at most once within this interval.
+const MOVED_STORM_LOG_INTERVAL_SECS: u64 = 10;
+
+// The stats of the commands shown in the `stats` section of INFO.
+pub struct CommandStats {
+    start: coarsetime::Instant,
+    total_commands: AtomicUsize,
+    // The command count of `current_sec` since `start`.
+    current_sec: AtomicU64,
+    current_count: AtomicUsize,
+    // The command count of the second before `current_sec`.
+    last_count: AtomicUsize,
+    keyspace_hits: AtomicUsize,
+    keyspace_misses: AtomicUsize,
+    moved_replies: AtomicUsize,
+    // The MOVED count of `moved_sec` since `start`.
+    moved_sec: AtomicU64,
+    moved_sec_count: AtomicUsize,
+    moved_storms: AtomicUsize,
+    // The second of the last logged storm plus one. 0 means never.
+    moved_storm_logged_sec: AtomicU64,
+}
+
+impl Default for CommandStats {
+    fn default() -> Self {
+        Self {
+            start: coarsetime::Instant::now(),
+            total_commands: AtomicUsize::new(0),
+            current_sec: AtomicU64::new(0),
+            current_count: AtomicUsize::new(0),
+            last_count: AtomicUsize::new(0),
+            keyspace_hits: AtomicUsize::new(0),
+            keyspace_misses: AtomicUsize::new(0),
+            moved_replies: AtomicUsize::new(0),
+            moved_sec: AtomicU64::new(0),
+            moved_sec_count: AtomicUsize::new(0),
+            moved_storms: AtomicUsize::new(0),
+            moved_storm_logged_sec: AtomicU64::new(0),
+        }
+    }
+}
+
+impl CommandStats {
+    fn now_sec(&self) -> u64 {
+        self.start.elapsed().as_secs()
+    }
+
+    pub fn incr_commands(&self) {
+        self.incr_commands_at(self.now_sec())
+    }
+
+    // The per-second counts are best-effort under concurrent updates.
+    fn incr_commands_at(&self, now_sec: u64) {
+        self.total_commands.fetch_add(1, Ordering::Relaxed);
+        let sec = self.current_sec.load(Ordering::Relaxed);
+        if sec != now_sec
+            && self
+                .current_sec
+                .compare_exchange(sec, now_sec, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let count = self.current_count.swap(0, Ordering::Relaxed);
+            let last_count = if sec + 1 == now_sec { count } else { 0 };
+            self.last_count.store(last_count, Ordering::Relaxed);
+        }
+        self.current_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_total_commands(&self) -> usize {
+        self.total_commands.load(Ordering::Relaxed)
+    }
+
+    // Returns the command count of the last whole second.
+    pub fn get_ops_per_sec(&self) -> usize {
+        self.get_ops_per_sec_at(self.now_sec())
+    }
+
+    fn get_ops_per_sec_at(&self, now_sec: u64) -> usize {
+        let sec = self.current_sec.load(Ordering::Relaxed);
+        if sec == now_sec {
+            self.last_count.load(Ordering::Relaxed)
+        } else if sec + 1 == now_sec {
+            self.current_count.load(Ordering::Relaxed)
+        } else {
+            0
+        }
+    }
+
+    // Only the common read commands with cheap classification are counted.
+    // MGET is counted by its sub GET commands.
+    pub fn record_keyspace(&self, cmd_type: DataCmdType, reply: &RespPacket) {
+        let hit = match cmd_type {
+            DataCmdType::Get | DataCmdType::Getex | DataCmdType::Getdel => {
+                match reply.to_resp_slice() {
+                    Resp::Bulk(BulkStr::Str(_)) => true,
+                    Resp::Bulk(BulkStr::Nil) => false,
+                    _ => return,
+                }
+            }
+            // Multi-key EXISTS is split into single-key EXISTS.
+            DataCmdType::Exists => match reply.to_resp_slice() {
+                Resp::Integer(b"1") => true,
+                Resp::Integer(b"0") => false,
+                _ => return,
+            },
+            _ => return,
+        };
+        let counter = if hit {
+            &self.keyspace_hits
+        } else {
+            &self.keyspace_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_keyspace_hits(&self) -> usize {
+        self.keyspace_hits.load(Ordering::Relaxed)
+    }
+
+    pub fn get_keyspace_misses(&self) -> usize {
+        self.keyspace_misses.load(Ordering::Relaxed)
+    }
+
+    // The backends owned by this proxy should never reply MOVED
+    // unless the metadata of this proxy or the backends is stale.
+    // This is only observational. The coordinator will resync the metadata.
+    pub fn record_redirection(&self, reply: &RespPacket) {
+        let is_moved = reply
+            .get_error()
+            .and_then(|err| err.split(|b| *b == b' ').next())
+            .map(|prefix| prefix == ERR_MOVED.as_bytes())
+            .unwrap_or(false);
+        if is_moved && self.incr_moved_at(self.now_sec()) {
+            warn!(
+                "found MOVED storm from backends, metadata may be stale threshold={}/s total={}",
+                MOVED_STORM_THRESHOLD,
+                self.get_moved_replies(),
+            );
+        }
+    }
+
+    // Returns true if a storm should be logged.
+    fn incr_moved_at(&self, now_sec: u64) -> bool {
+        self.moved_replies.fetch_add(1, Ordering::Relaxed);
+        let sec = self.moved_sec.load(Ordering::Relaxed);
+        if sec != now_sec
+            && self
+                .moved_sec
+                .compare_exchange(sec, now_sec, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.moved_sec_count.store(0, Ordering::Relaxed);
+        }
+        // Only the request reaching the threshold counts the storm.
+        let count = self.moved_sec_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if count != MOVED_STORM_THRESHOLD {
+            return false;
+        }
+        self.moved_storms.fetch_add(1, Ordering::Relaxed);
+
+        let logged_sec = self.moved_storm_logged_sec.load(Ordering::Relaxed);
+        if logged_sec != 0 && logged_sec - 1 + MOVED_STORM_LOG_INTERVAL_SECS > now_sec {
+            return false;
+        }
+        self.moved_storm_logged_sec
+            .compare_exchange(
+                logged_sec,
+                now_sec + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    pub fn get_moved_replies(&self) -> usize {
+        self.moved_replies.load(Ordering::Relaxed)
+    }
+
+    pub fn get_moved_storms(&self) -> usize {
+        self.moved_storms.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.total_commands.store(0, Ordering::Relaxed);
+        self.current_count.store(0, Ordering::Relaxed);
+        self.last_count.store(0, Ordering::Relaxed);
+        self.keyspace_hits.store(0, Ordering::Relaxed);
+        self.keyspace_misses.store(0, Ordering::Relaxed);
+        self.moved_replies.store(0, Ordering::Relaxed);
+        self.moved_sec_count.store(0, Ordering::Relaxed);
+        self.moved_storms.store(0, Ordering::Relaxed);
+    }
+}
+
+pub struct DbConnGuard {
+    counter: Arc<DbConnCounter>,
+    db: String,
+}
+
+impl Drop for DbConnGuard {
+    fn drop(&mut self) {
+        self.counter.release(&self.db);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_db_conn_counter() {
+        let counter = Arc::new(DbConnCounter::default());
+        let guard1 = counter.try_acquire("db1".to_string(), 2).unwrap();
+        let guard2 = counter.try_acquire("db1".to_string(), 2).unwrap();
+        assert!(counter.try_acquire("db1".to_string(), 2).is_none());
+
+        let guard3 = counter.try_acquire("db2".to_string(), 2).unwrap();
+        assert_eq!(
+            counter.get_counts(),
+            vec![("db1".to_string(), 2), ("db2".to_string(), 1)]
+        );
+
+        assert_eq!(counter.get_total(), 3);
+
+        drop(guard1);
+        let guard4 = counter.try_acquire("db1".to_string(), 2).unwrap();
+        drop(guard2);
+        drop(guard3);
+        drop(guard4);
+        assert!(counter.get_counts().is_empty());
+
+        // Unlimited
+        let guards: Vec<_> = (0..10)
+            .map(|_| counter.try_acquire("db1".to_string(), 0).unwrap())
+            .collect();
+        assert_eq!(counter.get_counts(), vec![("db1".to_string(), 10)]);
+        drop(guards);
+        assert!(counter.get_counts().is_empty());
+        assert_eq!(counter.get_total(), 0);
+    }
+
+    #[test]
+    fn test_backend_warmup_stats() {
+        let stats = BackendWarmupStats::default();
+        stats.record("127.0.0.1:6379".to_string(), true);
+        stats.record("127.0.0.1:6379".to_string(), false);
+        stats.record("127.0.0.1:6379".to_string(), true);
+        stats.record("127.0.0.1:7000".to_string(), false);
+        assert_eq!(stats.get("127.0.0.1:6379"), (2, 1));
+        assert_eq!(stats.get("127.0.0.1:7000"), (0, 1));
+        assert_eq!(stats.get("127.0.0.1:7001"), (0, 0));
+
+        stats.retain(&["127.0.0.1:6379".to_string()]);
+        assert_eq!(stats.get("127.0.0.1:6379"), (2, 1));
+        assert_eq!(stats.get("127.0.0.1:7000"), (0, 0));
+    }
+
+    #[test]
+    fn test_command_ops_per_sec() {
+        let stats = CommandStats::default();
+        for _ in 0..3 {
+            stats.incr_commands_at(1);
+        }
+        assert_eq!(stats.get_ops_per_sec_at(1), 0);
+        assert_eq!(stats.get_ops_per_sec_at(2), 3);
+
+        stats.incr_commands_at(2);
+        assert_eq!(stats.get_ops_per_sec_at(2), 3);
+        assert_eq!(stats.get_ops_per_sec_at(3), 1);
+        assert_eq!(stats.get_ops_per_sec_at(4), 0);
+
+        // Skip some seconds without any command.
+        stats.incr_commands_at(5);
+        assert_eq!(stats.get_ops_per_sec_at(5), 0);
+        assert_eq!(stats.get_total_commands(), 5);
+
+        stats.reset();
+        assert_eq!(stats.get_total_commands(), 0);
+        assert_eq!(stats.get_ops_per_sec_at(6), 0);
+    }
+
+    #[test]
+    fn test_keyspace_stats() {
+        let stats = CommandStats::default();
+        let value = RespPacket::from_resp_vec(Resp::Bulk(BulkStr::Str(b"v".to_vec())));
+        let nil = RespPacket::from_resp_vec(Resp::Bulk(BulkStr::Nil));
+        let exists = RespPacket::from_resp_vec(Resp::Integer(b"1".to_vec()));
+        let not_exists = RespPacket::from_resp_vec(Resp::Integer(b"0".to_vec()));
+        let err = RespPacket::from_resp_vec(Resp::Error(b"ERR".to_vec()));
+
+        stats.record_keyspace(DataCmdType::Get, &value);
+        stats.record_keyspace(DataCmdType::Getex, &nil);
+        stats.record_keyspace(DataCmdType::Exists, &exists);
+        stats.record_keyspace(DataCmdType::Exists, &not_exists);
+        stats.record_keyspace(DataCmdType::Get, &err);
+        stats.record_keyspace(DataCmdType::Set, &value);
+        assert_eq!(stats.get_keyspace_hits(), 2);
+        assert_eq!(stats.get_keyspace_misses(), 2);
+
+        stats.reset();
+        assert_eq!(stats.get_keyspace_hits(), 0);
+        assert_eq!(stats.get_keyspace_misses(), 0);
+    }
+
+    #[test]
+    fn test_moved_storm() {
+        let stats = CommandStats::default();
+        let moved = RespPacket::from_resp_vec(Resp::Error(b"MOVED 233 127.0.0.1:6000".to_vec()));
+        let ask = RespPacket::from_resp_vec(Resp::Error(b"ASK 233 127.0.0.1:6000".to_vec()));
+        let err = RespPacket::from_resp_vec(Resp::Error(b"ERR MOVED".to_vec()));
+        stats.record_redirection(&moved);
+        stats.record_redirection(&ask);
+        stats.record_redirection(&err);
+        assert_eq!(stats.get_moved_replies(), 1);
+        stats.reset();
+
+        // Below the threshold in different seconds.
+        for sec in 1..4 {
+            for _ in 1..MOVED_STORM_THRESHOLD {
+                assert!(!stats.incr_moved_at(sec));
+            }
+        }
+        assert_eq!(stats.get_moved_storms(), 0);
+
+        let storm = |sec| {
+            (0..MOVED_STORM_THRESHOLD * 2)
+                .filter(|_| stats.incr_moved_at(sec))
+                .count()
+        };
+        assert_eq!(storm(5), 1);
+        assert_eq!(stats.get_moved_storms(), 1);
+        // The logging is rate limited.
+        assert_eq!(storm(6), 0);
+        assert_eq!(storm(5 + MOVED_STORM_LOG_INTERVAL_SECS - 1), 0);
+        assert_eq!(stats.get_moved_storms(), 3);
+        assert_eq!(storm(5 + MOVED_STORM_LOG_INTERVAL_SECS), 1);
+        assert_eq!(stats.get_moved_storms(), 4);
+    }
+}
@@ -1,13 +1,18 @@
+use super::command::Command;
+use crate::protocol::{Array, BulkStr, Resp, RespVec};
+use btoi::btou;
 use std::collections::HashSet;
 
 pub struct CommandTable {
     supported_commands: HashSet<&'static [u8]>,
+    write_commands: HashSet<&'static [u8]>,
 }
 
 impl Default for CommandTable {
     fn default() -> Self {
         Self {
             supported_commands: SUPPORTED_COMMANDS.iter().cloned().collect(),
+            write_commands: WRITE_COMMANDS.iter().cloned().collect(),
         }
     }
 }
@@ -16,9 +21,277 @@ impl CommandTable {
     pub fn is_supported(&self, cmd: &[u8]) -> bool {
         self.supported_commands.contains(cmd)
     }
+
+    // `cmd` should be in lowercase.
+    pub fn is_write(&self, cmd: &[u8]) -> bool {
+        self.write_commands.contains(cmd)
+    }
+
+    // The entries of these commands in the `COMMAND` reply
+    // should come from `gen_proxy_command_infos` instead of the backend.
+    pub fn is_proxy_command(&self, cmd: &[u8]) -> bool {
+        PROXY_COMMANDS
+            .iter()
+            .any(|info| info.name.as_bytes().eq_ignore_ascii_case(cmd))
+    }
+
+    pub fn gen_proxy_command_infos(&self) -> Vec<RespVec> {
+        PROXY_COMMANDS
+            .iter()
+            .map(ProxyCommandInfo::to_resp)
+            .collect()
+    }
+
+    // Returns all the keys of the commands in `get_key_specs`.
+    // Returns None for the other commands or invalid arguments,
+    // which are left to the backend.
+    pub fn get_keys<'a>(&self, cmd: &'a Command) -> Option<Vec<&'a [u8]>> {
+        self.get_key_indices(cmd)?
+            .into_iter()
+            .map(|i| cmd.get_command_element(i))
+            .collect()
+    }
+
+    // Same as `get_keys` but returns the argument indices of the keys.
+    pub fn get_key_indices(&self, cmd: &Command) -> Option<Vec<usize>> {
+        let cmd_name = cmd.get_command_name()?.to_lowercase();
+        let key_specs = get_key_specs(cmd_name.as_bytes())?;
+        let mut indices = vec![];
+        for key_spec in key_specs.iter() {
+            key_spec.extract_key_indices(cmd, &mut indices)?;
+        }
+        Some(indices)
+    }
+
+    // `cmd` should be in lowercase.
+    pub fn has_key_specs(&self, cmd: &[u8]) -> bool {
+        get_key_specs(cmd).is_some()
+    }
+
+    // Case-insensitive since it's checked for every data command before lowering the name.
+    pub fn get_no_key_routing(&self, cmd: &[u8]) -> Option<NoKeyRouting> {
+        NO_KEY_COMMANDS
+            .iter()
+            .find(|(name, _)| name.as_bytes().eq_ignore_ascii_case(cmd))
+            .map(|(_, routing)| *routing)
+    }
+}
+
+// The commands without any key still need to reach all the backends of this proxy,
+// and the replies of the backends are merged into one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoKeyRouting {
+    // A random one of the non-nil replies.
+    Random,
+    // The first error or OK.
+    AllOk,
+    // The sum of the integer replies.
+    SumIntegers,
+    // All the elements of the array replies.
+    ConcatArrays,
+    // The backends are scanned one by one with the index of the backend kept in the cursor.
+    Scan,
 }
 
-const SUPPORTED_COMMANDS: [&[u8]; 137] = [
+const NO_KEY_COMMANDS: [(&str, NoKeyRouting); 6] = [
+    ("randomkey", NoKeyRouting::Random),
+    ("flushall", NoKeyRouting::AllOk),
+    ("flushdb", NoKeyRouting::AllOk),
+    ("dbsize", NoKeyRouting::SumIntegers),
+    ("keys", NoKeyRouting::ConcatArrays),
+    ("scan", NoKeyRouting::Scan),
+];
+
+// Similar to the key specs in the `COMMAND` reply of Redis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeySpec {
+    // The keys from `first` to `last` with `step`.
+    // Negative `last` counts from the end, e.g. -1 means the last argument.
+    Range {
+        first: usize,
+        last: isize,
+        step: usize,
+    },
+    // <numkeys> <key> [key ...]
+    NumKeys {
+        numkeys_index: usize,
+    },
+    // The argument after any of the keywords since `start`, e.g. `STORE <key>`.
+    Keyword {
+        keywords: &'static [&'static str],
+        start: usize,
+    },
+}
+
+impl KeySpec {
+    fn extract_key_indices(&self, cmd: &Command, indices: &mut Vec<usize>) -> Option<()> {
+        let arg_len = cmd.get_command_len()?;
+        match *self {
+            KeySpec::Range { first, last, step } => {
+                let last = if last < 0 {
+                    arg_len.checked_sub(last.unsigned_abs())?
+                } else {
+                    last as usize
+                };
+                if first > last || last >= arg_len {
+                    return None;
+                }
+                indices.extend((first..=last).step_by(step));
+            }
+            KeySpec::NumKeys { numkeys_index } => {
+                let key_num: usize = btou(cmd.get_command_element(numkeys_index)?).ok()?;
+                let first = numkeys_index + 1;
+                if first.checked_add(key_num)? > arg_len {
+                    return None;
+                }
+                indices.extend(first..(first + key_num));
+            }
+            KeySpec::Keyword { keywords, start } => {
+                let mut i = start;
+                while i + 1 < arg_len {
+                    let arg = cmd.get_command_element(i)?;
+                    if keywords
+                        .iter()
+                        .any(|keyword| keyword.as_bytes().eq_ignore_ascii_case(arg))
+                    {
+                        indices.push(i + 1);
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+const ALL_KEYS: KeySpec = KeySpec::Range {
+    first: 1,
+    last: -1,
+    step: 1,
+};
+const FIRST_KEY: KeySpec = KeySpec::Range {
+    first: 1,
+    last: 1,
+    step: 1,
+};
+
+// The supported commands with multiple keys which are not at fixed positions,
+// excluding the ones split into single-key commands like MGET and DEL.
+// Only the keys in the same slot are allowed for them.
+fn get_key_specs(cmd_name: &[u8]) -> Option<&'static [KeySpec]> {
+    let key_specs: &'static [KeySpec] = match cmd_name {
+        b"sdiff" | b"sinter" | b"sunion" | b"sdiffstore" | b"sinterstore" | b"pfcount"
+        | b"pfmerge" | b"touch" | b"unlink" => &[ALL_KEYS],
+        b"rename" | b"smove" | b"rpoplpush" => &[KeySpec::Range {
+            first: 1,
+            last: 2,
+            step: 1,
+        }],
+        b"eval" | b"evalsha" => &[KeySpec::NumKeys { numkeys_index: 2 }],
+        // SINTERCARD numkeys key [key ...] [LIMIT limit]
+        // LMPOP numkeys key [key ...] LEFT|RIGHT [COUNT count]
+        b"sintercard" | b"zintercard" | b"lmpop" | b"zmpop" => {
+            &[KeySpec::NumKeys { numkeys_index: 1 }]
+        }
+        // ZUNIONSTORE destination numkeys key [key ...]
+        b"zunionstore" | b"zinterstore" => &[FIRST_KEY, KeySpec::NumKeys { numkeys_index: 2 }],
+        // SORT key [BY pattern] [LIMIT offset count] [GET pattern ...] [ASC|DESC] [ALPHA] [STORE destination]
+        b"sort" => &[
+            FIRST_KEY,
+            KeySpec::Keyword {
+                keywords: &["STORE"],
+                start: 2,
+            },
+        ],
+        // GEORADIUS key longitude latitude radius unit [...] [STORE key] [STOREDIST key]
+        b"georadius" => &[
+            FIRST_KEY,
+            KeySpec::Keyword {
+                keywords: &["STORE", "STOREDIST"],
+                start: 6,
+            },
+        ],
+        // GEORADIUSBYMEMBER key member radius unit [...] [STORE key] [STOREDIST key]
+        b"georadiusbymember" => &[
+            FIRST_KEY,
+            KeySpec::Keyword {
+                keywords: &["STORE", "STOREDIST"],
+                start: 5,
+            },
+        ],
+        _ => return None,
+    };
+    Some(key_specs)
+}
+
+// The commands handled by the server proxy itself.
+// Their shapes follow the `COMMAND` reply of Redis:
+// name, arity, flags, first key, last key, step, and ACL categories.
+struct ProxyCommandInfo {
+    name: &'static str,
+    arity: i64,
+    flags: &'static [&'static str],
+    acl_categories: &'static [&'static str],
+}
+
+impl ProxyCommandInfo {
+    fn to_resp(&self) -> RespVec {
+        let to_simple_arr = |strs: &[&str]| {
+            Resp::Arr(Array::Arr(
+                strs.iter()
+                    .map(|s| Resp::Simple(s.as_bytes().to_vec()))
+                    .collect(),
+            ))
+        };
+        let to_integer = |n: i64| Resp::Integer(n.to_string().into_bytes());
+        Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(self.name.as_bytes().to_vec())),
+            to_integer(self.arity),
+            to_simple_arr(self.flags),
+            // None of them has keys.
+            to_integer(0),
+            to_integer(0),
+            to_integer(0),
+            to_simple_arr(self.acl_categories),
+        ]))
+    }
+}
+
+const PROXY_COMMANDS: [ProxyCommandInfo; 3] = [
+    // AUTH password
+    // AUTH username password
+    ProxyCommandInfo {
+        name: "auth",
+        arity: -2,
+        flags: &[
+            "noscript",
+            "loading",
+            "stale",
+            "skip_monitor",
+            "skip_slowlog",
+            "fast",
+            "no_auth",
+        ],
+        acl_categories: &["@fast", "@connection"],
+    },
+    ProxyCommandInfo {
+        name: "cluster",
+        arity: -2,
+        flags: &["admin", "random", "stale"],
+        acl_categories: &["@admin", "@slow", "@dangerous"],
+    },
+    // UMCTL accepts variable sub commands and arguments.
+    ProxyCommandInfo {
+        name: "umctl",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        acl_categories: &["@admin", "@slow", "@dangerous"],
+    },
+];
+
+const SUPPORTED_COMMANDS: [&[u8]; 161] = [
+    b"object",
     b"restore",
     b"zrangebylex",
     b"zcount",
@@ -156,4 +429,294 @@ const SUPPORTED_COMMANDS: [&[u8]; 137] = [
     b"xtrim",
     b"llen",
     b"command",
+    b"lolwut",
+    b"time",
+    b"lastsave",
+    b"role",
+    b"client",
+    b"debug",
+    b"memory",
+    b"watch",
+    b"unwatch",
+    b"getdel",
+    b"getex",
+    b"copy",
+    b"sintercard",
+    b"lmpop",
+    b"zmpop",
+    b"zintercard",
+    b"lpos",
+    b"dbsize",
+    b"flushall",
+    b"flushdb",
+    b"keys",
+    b"randomkey",
+    b"scan",
 ];
+
+// The supported commands with the `write` flag in Redis.
+// EVAL and EVALSHA are also included since the proxy can't tell whether the script writes.
+const WRITE_COMMANDS: [&[u8]; 85] = [
+    b"append",
+    b"bitfield",
+    b"blpop",
+    b"brpop",
+    b"brpoplpush",
+    b"bzpopmax",
+    b"bzpopmin",
+    b"copy",
+    b"decr",
+    b"decrby",
+    b"del",
+    b"eval",
+    b"evalsha",
+    b"expire",
+    b"expireat",
+    b"flushall",
+    b"flushdb",
+    b"geoadd",
+    b"georadius",
+    b"georadiusbymember",
+    b"getdel",
+    b"getex",
+    b"getset",
+    b"hdel",
+    b"hincrby",
+    b"hincrbyfloat",
+    b"hmset",
+    b"hset",
+    b"hsetnx",
+    b"incr",
+    b"incrby",
+    b"incrbyfloat",
+    b"linsert",
+    b"lmpop",
+    b"lpop",
+    b"lpush",
+    b"lpushx",
+    b"lrem",
+    b"lset",
+    b"ltrim",
+    b"mset",
+    b"msetnx",
+    b"persist",
+    b"pexpire",
+    b"pexpireat",
+    b"pfadd",
+    b"pfmerge",
+    b"psetex",
+    b"rename",
+    b"renamenx",
+    b"restore",
+    b"rpop",
+    b"rpoplpush",
+    b"rpush",
+    b"rpushx",
+    b"sadd",
+    b"sdiffstore",
+    b"set",
+    b"setbit",
+    b"setex",
+    b"setnx",
+    b"setrange",
+    b"sinterstore",
+    b"smove",
+    b"sort",
+    b"spop",
+    b"srem",
+    b"sunionstore",
+    b"unlink",
+    b"xack",
+    b"xadd",
+    b"xclaim",
+    b"xdel",
+    b"xtrim",
+    b"zadd",
+    b"zincrby",
+    b"zinterstore",
+    b"zmpop",
+    b"zpopmax",
+    b"zpopmin",
+    b"zrem",
+    b"zremrangebylex",
+    b"zremrangebyrank",
+    b"zremrangebyscore",
+    b"zunionstore",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RespPacket;
+
+    fn gen_cmd(args: &str) -> Command {
+        let resp = Resp::Arr(Array::Arr(
+            args.split(' ')
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+                .collect(),
+        ));
+        Command::new(Box::new(RespPacket::from_resp_vec(resp)))
+    }
+
+    fn get_keys(args: &str) -> Option<Vec<String>> {
+        let cmd = gen_cmd(args);
+        CommandTable::default().get_keys(&cmd).map(|keys| {
+            keys.into_iter()
+                .map(|key| String::from_utf8(key.to_vec()).unwrap())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_range_key_spec() {
+        assert_eq!(
+            get_keys("SINTER a b c"),
+            Some(vec!["a".into(), "b".into(), "c".into()])
+        );
+        assert_eq!(get_keys("sinter a"), Some(vec!["a".into()]));
+        assert_eq!(get_keys("SINTER"), None);
+        assert_eq!(get_keys("RENAME a b"), Some(vec!["a".into(), "b".into()]));
+        assert_eq!(
+            get_keys("SMOVE a b member"),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(get_keys("RENAME a"), None);
+        assert_eq!(get_keys("GET a"), None);
+    }
+
+    #[test]
+    fn test_numkeys_key_spec() {
+        assert_eq!(
+            get_keys("EVALSHA sha 2 a b arg"),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(get_keys("EVAL script 0 arg"), Some(vec![]));
+        assert_eq!(get_keys("EVAL script 3 a b"), None);
+        assert_eq!(get_keys("EVAL script x a"), None);
+        assert_eq!(
+            get_keys("ZUNIONSTORE dest 2 a b WEIGHTS 1 2"),
+            Some(vec!["dest".into(), "a".into(), "b".into()])
+        );
+        // The option tails are not keys.
+        assert_eq!(
+            get_keys("SINTERCARD 2 a b LIMIT 1"),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(
+            get_keys("ZINTERCARD 1 LIMIT LIMIT 1"),
+            Some(vec!["LIMIT".into()])
+        );
+        assert_eq!(
+            get_keys("LMPOP 2 a b LEFT COUNT 2"),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(get_keys("ZMPOP 4 a b MIN"), None);
+        // Single-key commands with option tails have no key specs.
+        assert_eq!(get_keys("LPOS a elem RANK 1 COUNT 2"), None);
+        for args in &[
+            "EXPIRE a 10 NX",
+            "PEXPIRE a 10000",
+            "EXPIREAT a 1700000000",
+            "PEXPIREAT a 1700000000000 GT",
+            "PERSIST a",
+            "TTL a",
+            "PTTL a",
+        ] {
+            assert_eq!(get_keys(args), None, "{}", args);
+        }
+    }
+
+    #[test]
+    fn test_keyword_key_spec() {
+        assert_eq!(get_keys("SORT a"), Some(vec!["a".into()]));
+        assert_eq!(
+            get_keys("SORT a BY w_* LIMIT 0 10 store dest"),
+            Some(vec!["a".into(), "dest".into()])
+        );
+        assert_eq!(
+            get_keys("GEORADIUS a 15 37 200 km STORE b STOREDIST c"),
+            Some(vec!["a".into(), "b".into(), "c".into()])
+        );
+        // The member is not a keyword.
+        assert_eq!(
+            get_keys("GEORADIUSBYMEMBER a STORE 200 km STORE b"),
+            Some(vec!["a".into(), "b".into()])
+        );
+        // Missing the key after the keyword.
+        assert_eq!(get_keys("SORT a STORE"), Some(vec!["a".into()]));
+    }
+
+    fn get_elements(resp: &RespVec) -> &[RespVec] {
+        match resp {
+            Resp::Arr(Array::Arr(elements)) => elements.as_slice(),
+            other => panic!("unexpected reply {:?}", other),
+        }
+    }
+
+    fn get_flags(resp: &RespVec) -> Vec<&[u8]> {
+        get_elements(resp)
+            .iter()
+            .map(|flag| match flag {
+                Resp::Simple(s) => s.as_slice(),
+                other => panic!("unexpected flag {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_key_routing() {
+        let table = CommandTable::default();
+        assert_eq!(
+            table.get_no_key_routing(b"DBSIZE"),
+            Some(NoKeyRouting::SumIntegers)
+        );
+        assert_eq!(table.get_no_key_routing(b"scan"), Some(NoKeyRouting::Scan));
+        assert_eq!(table.get_no_key_routing(b"get"), None);
+        for (name, _) in NO_KEY_COMMANDS.iter() {
+            assert!(table.is_supported(name.as_bytes()));
+            assert!(!table.has_key_specs(name.as_bytes()));
+        }
+        assert!(table.is_write(b"flushall"));
+        assert!(!table.is_write(b"keys"));
+    }
+
+    #[test]
+    fn test_proxy_command_infos() {
+        let table = CommandTable::default();
+        assert!(table.is_proxy_command(b"umctl"));
+        assert!(table.is_proxy_command(b"AUTH"));
+        assert!(table.is_proxy_command(b"cluster"));
+        assert!(!table.is_proxy_command(b"get"));
+
+        let infos = table.gen_proxy_command_infos();
+        let mut names = vec![];
+        for info in infos.iter() {
+            let elements = get_elements(info);
+            assert_eq!(elements.len(), 7);
+            let name = match &elements[0] {
+                Resp::Bulk(BulkStr::Str(name)) => name.clone(),
+                other => panic!("unexpected name {:?}", other),
+            };
+            // All of them are variadic.
+            assert_eq!(elements[1], Resp::Integer(b"-2".to_vec()));
+            for key_spec in elements.iter().skip(3).take(3) {
+                assert_eq!(*key_spec, Resp::Integer(b"0".to_vec()));
+            }
+            let flags = get_flags(&elements[2]);
+            match name.as_slice() {
+                b"umctl" | b"cluster" => assert!(flags.contains(&b"admin".as_ref())),
+                b"auth" => {
+                    assert!(flags.contains(&b"no_auth".as_ref()));
+                    assert!(!flags.contains(&b"admin".as_ref()));
+                }
+                _ => panic!("unexpected command {:?}", name),
+            }
+            names.push(name);
+        }
+        names.sort();
+        assert_eq!(
+            names,
+            vec![b"auth".to_vec(), b"cluster".to_vec(), b"umctl".to_vec()]
+        );
+    }
+}
@@ -10,12 +10,14 @@ use crate::protocol::{Array, BulkStr, RedisClientFactory, Resp, RespVec};
 use crate::proxy::cluster::ClusterMetaError;
 use itertools::Either;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{atomic, Arc};
 
 type ReplicatorRecord = Either<Arc<dyn MasterReplicator>, Arc<dyn ReplicaReplicator>>;
 type ReplicatorMap = HashMap<(ClusterName, String), (ReplicatorRecord, Arc<FutureAutoStopHandle>)>;
 
+// The replicator metadata is optional. `UMCTL SETREPL` could arrive after `UMCTL SETCLUSTER`
+// or never, so the consumers treat the missing metadata as a master without any replica.
 pub struct ReplicatorManager<F: RedisClientFactory> {
     updating_epoch: atomic::AtomicU64,
     replicators: RwLock<(u64, ReplicatorMap)>,
@@ -297,4 +299,309 @@ impl<F: RedisClientFactory> ReplicatorManager<F> {
 
         Resp::Arr(Array::Arr(reports))
     }
+
+    pub fn get_role_report(&self) -> RespVec {
+        let (master_metadata, replica_metadata) = self.get_metadata();
+        gen_role_report(&master_metadata, &replica_metadata)
+    }
+
+    pub fn get_info_replication(&self) -> String {
+        let (master_metadata, replica_metadata) = self.get_metadata();
+        gen_info_replication(&master_metadata, &replica_metadata)
+    }
+
+    // Dropping the handles stops all the spawned replicator futures.
+    // Stops all the replicators. The epoch will also be reset if `reset_epoch` is true.
+    pub fn reset(&self, reset_epoch: bool) {
+        let mut replicators = self.replicators.write();
+        if !replicators.1.is_empty() {
+            info!("reset {} replicators", replicators.1.len());
+        }
+        replicators.1.clear();
+        if reset_epoch {
+            replicators.0 = 0;
+            self.updating_epoch.store(0, atomic::Ordering::SeqCst);
+        }
+    }
+
+    pub fn stop(&self) {
+        let mut replicators = self.replicators.write();
+        if !replicators.1.is_empty() {
+            info!("stop {} replicators", replicators.1.len());
+        }
+        replicators.1.clear();
+    }
+}
+
+impl<F: RedisClientFactory> Drop for ReplicatorManager<F> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// The role of the whole proxy reported by ROLE and INFO.
+// The clients only talk to the proxies, so the peers are reported by their proxy addresses.
+// The proxy does not track the replication offsets so they are always 0.
+enum ProxyRole<'a> {
+    // The distinct proxies of the replicas of all the masters.
+    Master(BTreeSet<&'a str>),
+    // The proxy of the first master.
+    Replica(&'a str),
+}
+
+// A proxy with any master, or without any replication metadata, is a master.
+// A proxy with only replicas is a replica of the proxy of the first master.
+fn get_proxy_role<'a>(
+    master_metadata: &'a [MasterMeta],
+    replica_metadata: &'a [ReplicaMeta],
+) -> ProxyRole<'a> {
+    let master_proxy = replica_metadata
+        .iter()
+        .flat_map(|meta| meta.masters.iter())
+        .map(|master| master.proxy_address.as_str())
+        .next();
+    if let (true, Some(master_proxy)) = (master_metadata.is_empty(), master_proxy) {
+        return ProxyRole::Replica(master_proxy);
+    }
+
+    let replica_proxies = master_metadata
+        .iter()
+        .flat_map(|meta| meta.replicas.iter())
+        .map(|replica| replica.proxy_address.as_str())
+        .collect();
+    ProxyRole::Master(replica_proxies)
+}
+
+fn gen_role_report(master_metadata: &[MasterMeta], replica_metadata: &[ReplicaMeta]) -> RespVec {
+    let replica_proxies = match get_proxy_role(master_metadata, replica_metadata) {
+        ProxyRole::Replica(master_proxy) => {
+            let (host, port) = split_address(master_proxy);
+            return Resp::Arr(Array::Arr(vec![
+                Resp::Bulk(BulkStr::Str(b"slave".to_vec())),
+                Resp::Bulk(BulkStr::Str(host.as_bytes().to_vec())),
+                Resp::Integer(port.as_bytes().to_vec()),
+                Resp::Bulk(BulkStr::Str(b"connected".to_vec())),
+                Resp::Integer(b"0".to_vec()),
+            ]));
+        }
+        ProxyRole::Master(replica_proxies) => replica_proxies,
+    };
+
+    let replicas = replica_proxies
+        .into_iter()
+        .map(|proxy_address| {
+            let (host, port) = split_address(proxy_address);
+            Resp::Arr(Array::Arr(vec![
+                Resp::Bulk(BulkStr::Str(host.as_bytes().to_vec())),
+                Resp::Bulk(BulkStr::Str(port.as_bytes().to_vec())),
+                Resp::Bulk(BulkStr::Str(b"0".to_vec())),
+            ]))
+        })
+        .collect();
+    Resp::Arr(Array::Arr(vec![
+        Resp::Bulk(BulkStr::Str(b"master".to_vec())),
+        Resp::Integer(b"0".to_vec()),
+        Resp::Arr(Array::Arr(replicas)),
+    ]))
+}
+
+// The `Replication` section of INFO in the same format as Redis.
+fn gen_info_replication(
+    master_metadata: &[MasterMeta],
+    replica_metadata: &[ReplicaMeta],
+) -> String {
+    let mut lines = vec![];
+    match get_proxy_role(master_metadata, replica_metadata) {
+        ProxyRole::Replica(master_proxy) => {
+            let (host, port) = split_address(master_proxy);
+            lines.push("role:slave".to_string());
+            lines.push(format!("master_host:{}", host));
+            lines.push(format!("master_port:{}", port));
+            lines.push("master_link_status:up".to_string());
+            lines.push("slave_repl_offset:0".to_string());
+            lines.push("connected_slaves:0".to_string());
+        }
+        ProxyRole::Master(replica_proxies) => {
+            lines.push("role:master".to_string());
+            lines.push(format!("connected_slaves:{}", replica_proxies.len()));
+            for (i, proxy_address) in replica_proxies.into_iter().enumerate() {
+                let (host, port) = split_address(proxy_address);
+                lines.push(format!(
+                    "slave{}:ip={},port={},state=online,offset=0,lag=0",
+                    i, host, port
+                ));
+            }
+            lines.push("master_repl_offset:0".to_string());
+        }
+    }
+    lines.into_iter().map(|line| line + "\r\n").collect()
+}
+
+fn split_address(address: &str) -> (&str, &str) {
+    address.rsplit_once(':').unwrap_or((address, "0"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::cluster::ReplPeer;
+    use crate::common::proto::ClusterMapFlags;
+    use crate::protocol::{MockRedisClientFactory, RedisClientError};
+    use std::convert::TryFrom;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_drop_replicator_manager() {
+        let mut client_factory = MockRedisClientFactory::new();
+        client_factory
+            .expect_create_client()
+            .times(0..)
+            .returning(|_| Box::pin(async { Err(RedisClientError::InitError) }));
+
+        let future_registry = Arc::new(TrackedFutureRegistry::default());
+        let manager = ReplicatorManager::new(Arc::new(client_factory), future_registry.clone());
+
+        let meta = ReplicatorMeta {
+            epoch: 1,
+            flags: ClusterMapFlags {
+                force: false,
+                compress: false,
+            },
+            masters: vec![MasterMeta {
+                cluster_name: ClusterName::try_from("mycluster").unwrap(),
+                master_node_address: "127.0.0.1:6379".to_string(),
+                replicas: vec![],
+            }],
+            replicas: vec![],
+        };
+        manager
+            .update_replicators(meta, "127.0.0.1".to_string())
+            .unwrap();
+        assert_eq!(manager.get_role_num(), (1, 0));
+        assert_eq!(future_registry.get_all_futures().len(), 1);
+
+        drop(manager);
+        for _ in 0..100 {
+            if future_registry.get_all_futures().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(future_registry.get_all_futures().is_empty());
+    }
+
+    fn gen_peer(node_address: &str, proxy_address: &str) -> ReplPeer {
+        ReplPeer {
+            node_address: node_address.to_string(),
+            proxy_address: proxy_address.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_role_report() {
+        let cluster_name = ClusterName::try_from("mycluster").unwrap();
+        let master = Resp::Bulk(BulkStr::Str(b"master".to_vec()));
+        let zero = Resp::Integer(b"0".to_vec());
+
+        let report = gen_role_report(&[], &[]);
+        let expected = Resp::Arr(Array::Arr(vec![
+            master.clone(),
+            zero.clone(),
+            Resp::Arr(Array::Arr(vec![])),
+        ]));
+        assert_eq!(report, expected);
+
+        let masters = vec![
+            MasterMeta {
+                cluster_name: cluster_name.clone(),
+                master_node_address: "127.0.0.1:6379".to_string(),
+                replicas: vec![gen_peer("127.0.0.1:7001", "127.0.0.1:5300")],
+            },
+            MasterMeta {
+                cluster_name: cluster_name.clone(),
+                master_node_address: "127.0.0.1:6380".to_string(),
+                replicas: vec![gen_peer("127.0.0.1:7002", "127.0.0.1:5300")],
+            },
+        ];
+        let report = gen_role_report(&masters, &[]);
+        let replica = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"127.0.0.1".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"5300".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"0".to_vec())),
+        ]));
+        let expected = Resp::Arr(Array::Arr(vec![
+            master,
+            zero.clone(),
+            Resp::Arr(Array::Arr(vec![replica])),
+        ]));
+        assert_eq!(report, expected);
+
+        let replicas = vec![ReplicaMeta {
+            cluster_name,
+            replica_node_address: "127.0.0.1:7001".to_string(),
+            masters: vec![gen_peer("127.0.0.1:6379", "127.0.0.1:5299")],
+        }];
+        let report = gen_role_report(&[], &replicas);
+        let expected = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"slave".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"127.0.0.1".to_vec())),
+            Resp::Integer(b"5299".to_vec()),
+            Resp::Bulk(BulkStr::Str(b"connected".to_vec())),
+            zero,
+        ]));
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn test_info_replication() {
+        let cluster_name = ClusterName::try_from("mycluster").unwrap();
+
+        let info = gen_info_replication(&[], &[]);
+        assert_eq!(
+            info,
+            "role:master\r\nconnected_slaves:0\r\nmaster_repl_offset:0\r\n"
+        );
+
+        // The replicas in the same proxy are reported once.
+        let masters = vec![
+            MasterMeta {
+                cluster_name: cluster_name.clone(),
+                master_node_address: "127.0.0.1:6379".to_string(),
+                replicas: vec![
+                    gen_peer("127.0.0.1:7001", "127.0.0.1:5300"),
+                    gen_peer("127.0.0.2:7001", "127.0.0.2:5301"),
+                ],
+            },
+            MasterMeta {
+                cluster_name: cluster_name.clone(),
+                master_node_address: "127.0.0.1:6380".to_string(),
+                replicas: vec![gen_peer("127.0.0.1:7002", "127.0.0.1:5300")],
+            },
+        ];
+        let info = gen_info_replication(&masters, &[]);
+        let expected = concat!(
+            "role:master\r\n",
+            "connected_slaves:2\r\n",
+            "slave0:ip=127.0.0.1,port=5300,state=online,offset=0,lag=0\r\n",
+            "slave1:ip=127.0.0.2,port=5301,state=online,offset=0,lag=0\r\n",
+            "master_repl_offset:0\r\n",
+        );
+        assert_eq!(info, expected);
+
+        let replicas = vec![ReplicaMeta {
+            cluster_name,
+            replica_node_address: "127.0.0.1:7001".to_string(),
+            masters: vec![gen_peer("127.0.0.1:6379", "127.0.0.1:5299")],
+        }];
+        let info = gen_info_replication(&[], &replicas);
+        let expected = concat!(
+            "role:slave\r\n",
+            "master_host:127.0.0.1\r\n",
+            "master_port:5299\r\n",
+            "master_link_status:up\r\n",
+            "slave_repl_offset:0\r\n",
+            "connected_slaves:0\r\n",
+        );
+        assert_eq!(info, expected);
+    }
 }
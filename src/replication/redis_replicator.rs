@@ -4,7 +4,7 @@ use super::replicator::{
 use crate::common::resp_execution::{retry_handle_func, I64Retriever};
 use crate::common::utils::resolve_first_address;
 use crate::protocol::{
-    OptionalMulti, RedisClient, RedisClientError, RedisClientFactory, Resp, RespVec,
+    CmdBuilder, OptionalMulti, RedisClient, RedisClientError, RedisClientFactory, Resp, RespVec,
 };
 use futures::{future, Future};
 use futures::{FutureExt, TryFutureExt};
@@ -54,7 +54,7 @@ impl<F: RedisClientFactory> MasterReplicator for RedisMasterReplicator<F> {
         let meta = self.meta.clone();
         let address = meta.master_node_address.clone();
         let interval = Duration::new(5, 0);
-        let cmd = vec!["SLAVEOF".to_string(), "NO".to_string(), "ONE".to_string()];
+        let cmd = CmdBuilder::new("SLAVEOF").arg("NO").arg("ONE").build();
         self.role_sync
             .start(Self::handle_result, address, cmd, interval)
             .map(|f| {
@@ -144,11 +144,10 @@ impl<F: RedisClientFactory> RedisReplicaReplicator<F> {
             }
 
             let cmd = match resolve_first_address(master_node_address.as_str()).await {
-                Some(address) => {
-                    let host = address.ip().to_string();
-                    let port = address.port().to_string();
-                    vec![b"SLAVEOF".to_vec(), host.into_bytes(), port.into_bytes()]
-                }
+                Some(address) => CmdBuilder::new("SLAVEOF")
+                    .arg(address.ip().to_string())
+                    .arg(address.port().to_string())
+                    .build(),
                 None => {
                     error!(
                         "failed to resolve master node address in replica replicator: {}",
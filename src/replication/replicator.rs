@@ -1,6 +1,6 @@
 use crate::common::cluster::{ClusterName, ReplPeer};
 use crate::common::proto::ClusterMapFlags;
-use crate::common::utils::{CmdParseError, ThreadSafe};
+use crate::common::utils::{is_valid_address, CmdParseError, ThreadSafe};
 use crate::protocol::{Array, BulkStr, RedisClientError, Resp};
 use futures::Future;
 use std::convert::TryFrom;
@@ -70,12 +70,12 @@ fn parse_repl_meta<T: AsRef<[u8]>>(resp: &Resp<T>) -> Result<ReplicatorMeta, Cmd
     });
     let mut it = it.peekable();
 
-    let epoch_str = it.next().ok_or(CmdParseError::InvalidEpoch)?;
+    let epoch_str = it.next().ok_or(CmdParseError::MissingField("epoch"))?;
     let epoch = epoch_str
         .parse::<u64>()
         .map_err(|_e| CmdParseError::InvalidEpoch)?;
 
-    let flags = ClusterMapFlags::from_arg(&it.next().ok_or(CmdParseError::InvalidArgs)?);
+    let flags = ClusterMapFlags::from_arg(&it.next().ok_or(CmdParseError::MissingField("flags"))?);
 
     let mut master_meta_array = Vec::new();
     let mut replica_meta_array = Vec::new();
@@ -83,19 +83,21 @@ fn parse_repl_meta<T: AsRef<[u8]>>(resp: &Resp<T>) -> Result<ReplicatorMeta, Cmd
     while it.peek().is_some() {
         let mut peers = Vec::new();
 
-        let role = it.next().ok_or(CmdParseError::InvalidRole)?;
-        let cluster_name = it.next().ok_or(CmdParseError::InvalidClusterName)?;
+        let role = it.next().ok_or(CmdParseError::MissingField("role"))?;
+        let cluster_name = it
+            .next()
+            .ok_or(CmdParseError::MissingField("cluster name"))?;
         let cluster_name = ClusterName::try_from(cluster_name.as_str())
             .map_err(|_| CmdParseError::InvalidClusterName)?;
-        let node_address = it.next().ok_or(CmdParseError::InvalidArgs)?;
+        let node_address = parse_address(&mut it, "node address")?;
         let peer_num = it
             .next()
-            .ok_or(CmdParseError::InvalidArgs)?
+            .ok_or(CmdParseError::MissingField("peer number"))?
             .parse::<usize>()
             .map_err(|_| CmdParseError::InvalidArgs)?;
         for _ in 0..peer_num {
-            let node_address = it.next().ok_or(CmdParseError::InvalidArgs)?;
-            let proxy_address = it.next().ok_or(CmdParseError::InvalidArgs)?;
+            let node_address = parse_address(&mut it, "peer node address")?;
+            let proxy_address = parse_address(&mut it, "peer proxy address")?;
             peers.push(ReplPeer {
                 node_address,
                 proxy_address,
@@ -128,6 +130,17 @@ fn parse_repl_meta<T: AsRef<[u8]>>(resp: &Resp<T>) -> Result<ReplicatorMeta, Cmd
     })
 }
 
+fn parse_address<It>(it: &mut It, field: &'static str) -> Result<String, CmdParseError>
+where
+    It: Iterator<Item = String>,
+{
+    let address = it.next().ok_or(CmdParseError::MissingField(field))?;
+    if !is_valid_address(&address) {
+        return Err(CmdParseError::InvalidAddress(address));
+    }
+    Ok(address)
+}
+
 pub fn encode_repl_meta(meta: ReplicatorMeta) -> Vec<String> {
     let ReplicatorMeta {
         epoch,
@@ -264,4 +277,36 @@ mod tests {
         let args = encode_repl_meta(meta.clone()).join(" ");
         assert_eq!(args, "233 NOFLAG master testcluster localhost:6000 1 localhost:6001 localhost:5299 replica testcluster localhost:6001 1 localhost:6000 localhost:5299")
     }
+
+    #[test]
+    fn test_parse_invalid_replicator_meta() {
+        let parse = |s: &str| {
+            let arguments = s
+                .split(' ')
+                .map(|s| Resp::Bulk(BulkStr::Str(s.to_string().into_bytes())))
+                .collect();
+            parse_repl_meta(&Resp::Arr(Array::Arr(arguments)))
+        };
+        assert_eq!(
+            parse("UMCTL SETREPL").unwrap_err(),
+            CmdParseError::MissingField("epoch")
+        );
+        assert_eq!(
+            parse("UMCTL SETREPL abc force").unwrap_err(),
+            CmdParseError::InvalidEpoch
+        );
+        assert_eq!(
+            parse("UMCTL SETREPL 233 force leader testcluster localhost:6000 0").unwrap_err(),
+            CmdParseError::InvalidRole
+        );
+        assert_eq!(
+            parse("UMCTL SETREPL 233 force master testcluster localhost 0").unwrap_err(),
+            CmdParseError::InvalidAddress("localhost".to_string())
+        );
+        assert_eq!(
+            parse("UMCTL SETREPL 233 force master testcluster localhost:6000 1 localhost:6001")
+                .unwrap_err(),
+            CmdParseError::MissingField("peer proxy address")
+        );
+    }
 }
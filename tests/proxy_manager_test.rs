@@ -9,41 +9,64 @@ mod tests {
 
     use arc_swap::ArcSwap;
     use connection::DummyOkConnFactory;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
     use redis_client::DummyClientFactory;
+    use std::collections::HashMap;
     use std::convert::TryFrom;
-    use std::num::NonZeroUsize;
+    use std::num::{NonZeroU32, NonZeroUsize};
     use std::str;
-    use std::sync::atomic::{AtomicI64, AtomicU64};
+    use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
     use std::sync::Arc;
-    use std::time::Duration;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
     use tokio;
     use undermoon::common::batch::BatchStrategy;
     use undermoon::common::cluster::{
-        ClusterName, MigrationMeta, MigrationTaskMeta, Range, RangeList, SlotRange, SlotRangeTag,
+        ClusterName, MigrationMeta, MigrationTaskMeta, Range, RangeList, ReplPeer, SlotRange,
+        SlotRangeTag,
     };
     use undermoon::common::proto::{ClusterMapFlags, ProxyClusterMeta, SET_CLUSTER_API_VERSION};
     use undermoon::common::response::{
-        ERR_BACKEND_CONNECTION, ERR_CLUSTER_NOT_FOUND, ERR_MOVED, ERR_TOO_MANY_REDIRECTIONS,
-        OK_REPLY,
+        ERR_BACKEND_CONNECTION, ERR_CLUSTER_NOT_FOUND, ERR_INVALID_COMMAND, ERR_LOADING_META,
+        ERR_MAINTENANCE, ERR_MOVED, ERR_NOAUTH, ERR_NOT_THE_SAME_SLOT, ERR_READONLY,
+        ERR_TOO_MANY_REDIRECTIONS, NOT_READY_FOR_SWITCHING_REPLY, OK_REPLY, OLD_EPOCH_REPLY,
+        PONG_REPLY,
     };
     use undermoon::common::track::TrackedFutureRegistry;
-    use undermoon::common::utils::pretty_print_bytes;
-    use undermoon::common::version::UNDERMOON_MIGRATION_VERSION;
-    use undermoon::migration::task::{MgrSubCmd, MigrationState, SwitchArg};
-    use undermoon::protocol::{Array, BinSafeStr, BulkStr, Resp, RespPacket, RespVec, VFunctor};
+    use undermoon::common::utils::{
+        parse_redirection, pretty_print_bytes, HashTagDelimiters, SocketBufferSizes,
+    };
+    use undermoon::common::version::{UNDERMOON_MIGRATION_VERSION, UNDERMOON_VERSION};
+    use undermoon::migration::task::{MgrSubCmd, MigrationState, MigrationTaskStatus, SwitchArg};
+    use undermoon::protocol::{
+        encode_resp, Array, BinSafeStr, BulkStr, Resp, RespPacket, RespVec, VFunctor,
+    };
     use undermoon::proxy::command::{new_command_pair, CmdReplyReceiver, Command};
+    use undermoon::proxy::executor::ForwardHandler;
+    use undermoon::proxy::health::{HealthStatus, ProxyHealth, PROXY_HEALTH_VERSION};
     use undermoon::proxy::manager::MetaManager;
     use undermoon::proxy::manager::MetaMap;
-    use undermoon::proxy::service::{ClusterNodesVersion, ServerProxyConfig};
-    use undermoon::proxy::session::CmdCtx;
+    use undermoon::proxy::rename::CommandRenames;
+    use undermoon::proxy::sender::CmdTaskSender;
+    use undermoon::proxy::service::{
+        load_init_meta, ClusterNodesVersion, SelectPolicy, ServerProxyConfig, UnknownCommandPolicy,
+    };
+    use undermoon::proxy::session::{AuthState, ClientInfo, CmdCtx, CmdCtxHandler};
+    use undermoon::proxy::slowlog::SlowRequestLogger;
+    use undermoon::proxy::stats::{DbConnCounter, ProxyStats};
     use undermoon::replication::replicator::{MasterMeta, ReplicaMeta, ReplicatorMeta};
 
     const TEST_CLUSTER: &str = "test_cluster";
     type TestMetaManager = MetaManager<DummyClientFactory, DummyOkConnFactory>;
+    type TestForwardHandler = ForwardHandler<DummyClientFactory, DummyOkConnFactory>;
 
     fn gen_config() -> ServerProxyConfig {
         ServerProxyConfig {
             address: "127.0.0.1:5299".to_string(),
+            extra_listen_addresses: vec![],
+            listen_backlog: NonZeroU32::new(1024).unwrap(),
+            listen_reuseport: false,
+            client_socket_buffer: SocketBufferSizes::default(),
             announce_address: "127.0.0.1:5299".to_string(),
             announce_host: "127.0.0.1".to_string(),
             slowlog_len: NonZeroUsize::new(1024).unwrap(),
@@ -53,6 +76,7 @@ mod tests {
             // Should only be 1 so that when `wait_backend_ready` is done,
             // the whole backend is ready.
             backend_conn_num: NonZeroUsize::new(1).unwrap(),
+            backend_socket_buffer: SocketBufferSizes::default(),
             active_redirection: false,
             max_redirections: None,
             default_redirection_address: None,
@@ -62,7 +86,34 @@ mod tests {
             backend_high_flush_interval: Duration::from_nanos(800_000),
             backend_timeout: Duration::from_secs(3),
             password: None,
+            auth_admin_commands: false,
+            admin_password: None,
             command_cluster_nodes_version: ClusterNodesVersion::V2,
+            proto_max_bulk_len: 512 * 1024 * 1024,
+            proto_max_multibulk_len: 1024 * 1024,
+            proto_max_inline_len: 64 * 1024,
+            session_idle_timeout: None,
+            client_output_buffer_hard_limit: 0,
+            client_output_buffer_soft_limit: 0,
+            client_output_buffer_soft_seconds: 0,
+            reply_stream_threshold: 64 * 1024,
+            init_meta_file: None,
+            umctl_max_meta_args: 100_000,
+            umctl_max_meta_bytes: 64 * 1024 * 1024,
+            unknown_command_policy: UnknownCommandPolicy::Forward,
+            select_policy: SelectPolicy::IndexOnly,
+            max_connections_per_db: AtomicUsize::new(0),
+            maxclients: AtomicUsize::new(0),
+            expose_backend_address: AtomicBool::new(false),
+            backend_warmup: false,
+            cluster_reset_hard: false,
+            ping_requires_meta: false,
+            enable_debug: false,
+            redis_version: "7.0.0".to_string(),
+            maintenance_error: ERR_MAINTENANCE.to_string(),
+            rename_command: CommandRenames::default(),
+            command_timeout: AtomicU64::new(0),
+            hash_tag: HashTagDelimiters::default(),
         }
     }
 
@@ -70,7 +121,13 @@ mod tests {
         handle_func: Arc<dyn Fn(Vec<String>) -> RespVec + Send + Sync + 'static>,
         config: ServerProxyConfig,
     ) -> TestMetaManager {
-        let config = Arc::new(config);
+        gen_testing_manager_with_shared_config(handle_func, Arc::new(config))
+    }
+
+    fn gen_testing_manager_with_shared_config(
+        handle_func: Arc<dyn Fn(Vec<String>) -> RespVec + Send + Sync + 'static>,
+        config: Arc<ServerProxyConfig>,
+    ) -> TestMetaManager {
         let client_factory = Arc::new(DummyClientFactory::new(handle_func.clone()));
         let conn_factory = Arc::new(DummyOkConnFactory::new(handle_func));
         let meta_map = Arc::new(ArcSwap::new(Arc::new(MetaMap::empty())));
@@ -84,6 +141,1085 @@ mod tests {
         )
     }
 
+    fn gen_testing_forward_handler(config: ServerProxyConfig) -> TestForwardHandler {
+        gen_testing_forward_handler_with_func(Arc::new(always_ok), config)
+    }
+
+    fn gen_testing_forward_handler_with_func(
+        handle_func: Arc<dyn Fn(Vec<String>) -> RespVec + Send + Sync + 'static>,
+        config: ServerProxyConfig,
+    ) -> TestForwardHandler {
+        let config = Arc::new(config);
+        let manager = gen_testing_manager_with_shared_config(handle_func, config.clone());
+        gen_testing_forward_handler_with_manager(manager, config)
+    }
+
+    fn gen_testing_forward_handler_with_manager(
+        manager: TestMetaManager,
+        config: Arc<ServerProxyConfig>,
+    ) -> TestForwardHandler {
+        let future_registry = Arc::new(TrackedFutureRegistry::default());
+        let (stopped, _) = mpsc::unbounded();
+        ForwardHandler::with_manager(
+            config.clone(),
+            manager,
+            Arc::new(SlowRequestLogger::new(config)),
+            future_registry,
+            stopped,
+            Arc::new(ProxyStats::default()),
+            Arc::new(DbConnCounter::default()),
+        )
+    }
+
+    async fn send_to_handler(handler: &TestForwardHandler, args: &str) -> RespVec {
+        let auth_state = AuthState::default();
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+        send_to_session(handler, &auth_state, &client_info, args).await
+    }
+
+    fn gen_cmd_ctx(args: &str) -> (CmdCtx, CmdReplyReceiver) {
+        let resp = RespPacket::Data(Resp::Arr(Array::Arr(
+            args.split(' ')
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+                .collect(),
+        )));
+        let command = Command::new(Box::new(resp));
+        let (s, r) = new_command_pair(&command);
+        (CmdCtx::new(command, s, 233, true), r)
+    }
+
+    // Keeps the session states among the commands.
+    async fn send_to_session(
+        handler: &TestForwardHandler,
+        auth_state: &AuthState,
+        client_info: &ClientInfo,
+        args: &str,
+    ) -> RespVec {
+        client_info.set_last_cmd(args.split(' ').next().unwrap_or(""));
+        let (cmd_ctx, r) = gen_cmd_ctx(args);
+        let (_, response, _) = handler
+            .handle_cmd_ctx(cmd_ctx, r, auth_state, client_info)
+            .await
+            .unwrap()
+            .into_inner();
+        response.into_resp_vec()
+    }
+
+    #[tokio::test]
+    async fn test_ping_requires_meta() {
+        let mut config = gen_config();
+        config.ping_requires_meta = true;
+        let handler = gen_testing_forward_handler(config);
+
+        let resp = send_to_handler(&handler, "PING").await;
+        assert_eq!(resp, Resp::Error(ERR_LOADING_META.as_bytes().to_vec()));
+
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+
+        let resp = send_to_handler(&handler, "PING").await;
+        assert_eq!(resp, Resp::Simple(PONG_REPLY.as_bytes().to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_ping_without_meta() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let resp = send_to_handler(&handler, "PING").await;
+        assert_eq!(resp, Resp::Simple(PONG_REPLY.as_bytes().to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_simple_reply_bytes() {
+        let handler = gen_testing_forward_handler(gen_config());
+        for (cmd, expected) in &[
+            ("PING", &b"+PONG\r\n"[..]),
+            ("ping hello", &b"$5\r\nhello\r\n"[..]),
+            (
+                "PING a b",
+                &b"-ERR wrong number of arguments for 'ping' command\r\n"[..],
+            ),
+            ("SELECT 0", &b"+OK\r\n"[..]),
+            ("QUIT", &b"+OK\r\n"[..]),
+        ] {
+            let resp = send_to_handler(&handler, cmd).await;
+            let mut buf = vec![];
+            encode_resp(&mut buf, &resp).unwrap();
+            assert_eq!(buf.as_slice(), *expected, "{}", cmd);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debug_sleep() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let resp = send_to_handler(&handler, "DEBUG SLEEP 0").await;
+        assert_eq!(resp, Resp::Error(b"ERR DEBUG is disabled".to_vec()));
+
+        let mut config = gen_config();
+        config.enable_debug = true;
+        let handler = gen_testing_forward_handler(config);
+
+        let start = Instant::now();
+        let resp = send_to_handler(&handler, "DEBUG SLEEP 0.1").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        for args in &["DEBUG SLEEP -1", "DEBUG SLEEP abc", "DEBUG SLEEP"] {
+            let resp = send_to_handler(&handler, args).await;
+            assert_eq!(resp, Resp::Error(b"ERR invalid sleep seconds".to_vec()));
+        }
+        let resp = send_to_handler(&handler, "DEBUG OBJECT key").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR only DEBUG SLEEP is supported".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_command() {
+        let handler = gen_testing_forward_handler(gen_config());
+
+        let resp = send_to_handler(&handler, "MEMORY DOCTOR").await;
+        assert!(matches!(resp, Resp::Bulk(BulkStr::Str(_))));
+
+        // Routed to the backends but no cluster is set yet.
+        let cluster_not_found = Resp::Error(ERR_CLUSTER_NOT_FOUND.as_bytes().to_vec());
+        let resp = send_to_handler(&handler, "MEMORY USAGE key").await;
+        assert_eq!(resp, cluster_not_found);
+        let resp = send_to_handler(&handler, "MEMORY STATS").await;
+        assert_eq!(resp, cluster_not_found);
+
+        let resp = send_to_handler(&handler, "MEMORY USAGE").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR: Missing key for MEMORY USAGE".to_vec())
+        );
+        let resp = send_to_handler(&handler, "MEMORY MALLOC-STATS").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR: Unsupported MEMORY sub command MALLOC-STATS".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_info_redis_version() {
+        let mut config = gen_config();
+        config.redis_version = "6.2.0".to_string();
+        let handler = gen_testing_forward_handler(config);
+        let info = match send_to_handler(&handler, "INFO").await {
+            Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert!(info.starts_with("# Server\r\nredis_version:6.2.0\r\n"));
+        // No cluster is set yet.
+        assert!(info.ends_with("# Keyspace\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_maxclients() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let resp = send_to_handler(&handler, "CONFIG SET maxclients 10").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "CONFIG SET maxclients -1").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        let resp = send_to_handler(&handler, "CONFIG GET maxclients").await;
+        assert_eq!(resp, Resp::Bulk(BulkStr::Str(b"10".to_vec())));
+
+        let info = match send_to_handler(&handler, "INFO").await {
+            Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert!(info.contains("# Clients\r\nconnected_clients:0\r\nmaxclients:10\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_umctl_dbmode() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let resp = send_to_handler(&handler, "UMCTL DBMODE test_cluster READONLY").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let readonly = Resp::Error(ERR_READONLY.as_bytes().to_vec());
+        for args in &[
+            "SET key value",
+            "del a",
+            "EVAL script 1 a",
+            "MSET {t}a 1 {t}b 2",
+        ] {
+            let resp = send_to_handler(&handler, args).await;
+            assert_eq!(resp, readonly, "{}", args);
+        }
+        for args in &["GET key", "EXISTS a", "MGET {t}a {t}b"] {
+            let resp = send_to_handler(&handler, args).await;
+            assert_ne!(resp, readonly, "{}", args);
+            assert!(!matches!(resp, Resp::Error(_)), "{}", args);
+        }
+
+        let resp = send_to_handler(&handler, "UMCTL DBSTATS").await;
+        let expected = "test_cluster connections:0 max_connections:0 mode:readonly";
+        assert_eq!(
+            resp,
+            Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+                expected.as_bytes().to_vec()
+            ))]))
+        );
+
+        let resp = send_to_handler(&handler, "UMCTL DBMODE test_cluster readwrite").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "SET key value").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "UMCTL DBSTATS").await;
+        assert_eq!(resp, Resp::Arr(Array::Arr(vec![])));
+
+        let resp = send_to_handler(&handler, "UMCTL DBMODE test_cluster WRITEONLY").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+
+        let resp = send_to_handler(&handler, "WATCH {tag}a {tag}b").await;
+        assert_eq!(resp, ok);
+        let resp = send_to_handler(&handler, "UNWATCH").await;
+        assert_eq!(resp, ok);
+
+        let resp = send_to_handler(&handler, "WATCH a b").await;
+        assert_eq!(resp, Resp::Error(ERR_NOT_THE_SAME_SLOT.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "WATCH").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_umctl_health() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+
+        let health = match send_to_handler(&handler, "UMCTL HEALTH").await {
+            Resp::Bulk(BulkStr::Str(health)) => health,
+            other => panic!("unexpected reply {:?}", other),
+        };
+        let health: ProxyHealth = serde_json::from_slice(&health).unwrap();
+        assert_eq!(health.version, PROXY_HEALTH_VERSION);
+        assert_eq!(health.status, HealthStatus::Ok);
+        assert_eq!(health.cluster, "test_cluster");
+        assert_eq!(health.epoch, 1);
+        assert!(health.migrations.is_empty());
+        assert!(health.replication.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_umctl_mgrconfig() {
+        let handler = gen_testing_forward_handler(gen_config());
+
+        let resp = send_to_handler(&handler, "UMCTL MGRCONFIG SET scan_count 32").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "UMCTL MGRCONFIG SET scan_count 0").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR invalid migration config scan_count: INVALID_VALUE".to_vec())
+        );
+        let resp = send_to_handler(&handler, "UMCTL MGRCONFIG SET concurrency 2").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR invalid migration config concurrency: FIELD_NOT_FOUND".to_vec())
+        );
+
+        let fields = match send_to_handler(&handler, "UMCTL MGRCONFIG GET").await {
+            Resp::Arr(Array::Arr(fields)) => fields,
+            other => panic!("unexpected reply {:?}", other),
+        };
+        let scan_count_index = fields
+            .iter()
+            .position(|field| field == &Resp::Bulk(BulkStr::Str(b"scan_count".to_vec())))
+            .unwrap();
+        assert_eq!(
+            fields[scan_count_index + 1],
+            Resp::Bulk(BulkStr::Str(b"32".to_vec()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_noauth() {
+        let mut config = gen_config();
+        config.password = Some("pwd".to_string());
+        let handler = gen_testing_forward_handler(config);
+        let auth_state = AuthState::default();
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+        let noauth = Resp::Error(b"NOAUTH Authentication required.".to_vec());
+
+        for cmd in &["GET a", "SET a b", "WATCH a"] {
+            let resp = send_to_session(&handler, &auth_state, &client_info, cmd).await;
+            assert_eq!(resp, noauth);
+        }
+        let resp = send_to_session(&handler, &auth_state, &client_info, "PING").await;
+        assert_eq!(resp, Resp::Simple(PONG_REPLY.as_bytes().to_vec()));
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "AUTH pwd").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "WATCH a").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_auth_admin_commands() {
+        let mut config = gen_config();
+        config.password = Some("pwd".to_string());
+        config.auth_admin_commands = true;
+        let handler = gen_testing_forward_handler(config);
+        let auth_state = AuthState::default();
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+        let admin_cmds = ["CLUSTER NODES", "UMCTL DBSTATS"];
+
+        for args in admin_cmds.iter() {
+            let resp = send_to_session(&handler, &auth_state, &client_info, args).await;
+            assert_eq!(
+                resp,
+                Resp::Error(ERR_NOAUTH.as_bytes().to_vec()),
+                "{}",
+                args
+            );
+        }
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "AUTH pwd").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        for args in admin_cmds.iter() {
+            let resp = send_to_session(&handler, &auth_state, &client_info, args).await;
+            assert_ne!(
+                resp,
+                Resp::Error(ERR_NOAUTH.as_bytes().to_vec()),
+                "{}",
+                args
+            );
+        }
+
+        // Without `auth_admin_commands`, only the data commands require AUTH.
+        let mut config = gen_config();
+        config.password = Some("pwd".to_string());
+        let handler = gen_testing_forward_handler(config);
+        let auth_state = AuthState::default();
+        for args in admin_cmds.iter() {
+            let resp = send_to_session(&handler, &auth_state, &client_info, args).await;
+            assert_ne!(
+                resp,
+                Resp::Error(ERR_NOAUTH.as_bytes().to_vec()),
+                "{}",
+                args
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_umctl_permission() {
+        let mut config = gen_config();
+        config.password = Some("pwd".to_string());
+        config.admin_password = Some("admin".to_string());
+        let handler = gen_testing_forward_handler(config);
+        let auth_state = AuthState::default();
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "UMCTL DBSTATS").await;
+        assert_eq!(resp, Resp::Error(ERR_NOAUTH.as_bytes().to_vec()));
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "AUTH pwd").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "UMCTL DBSTATS").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"NOPERM this session has no permissions to run UMCTL commands".to_vec())
+        );
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "AUTH admin").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "UMCTL DBSTATS").await;
+        assert!(!matches!(resp, Resp::Error(_)));
+        // The admin session can also run the data commands.
+        let resp = send_to_session(&handler, &auth_state, &client_info, "WATCH a").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_multi_key_cross_slot() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let cross_slot = Resp::Error(ERR_NOT_THE_SAME_SLOT.as_bytes().to_vec());
+        for args in &[
+            "RENAME a b",
+            "SINTER a b",
+            "EVALSHA sha 2 a b",
+            "ZUNIONSTORE a 1 b",
+            "SORT a STORE b",
+            "GEORADIUS a 15 37 200 km STOREDIST b",
+            "SINTERCARD 2 a b LIMIT 1",
+            "ZINTERCARD 2 a b LIMIT 1",
+            "LMPOP 2 a b LEFT COUNT 2",
+        ] {
+            let resp = send_to_handler(&handler, args).await;
+            assert_eq!(resp, cross_slot, "{}", args);
+        }
+
+        // Routed to the backends but no cluster is set yet.
+        let cluster_not_found = Resp::Error(ERR_CLUSTER_NOT_FOUND.as_bytes().to_vec());
+        for args in &[
+            "RENAME {t}a {t}b",
+            "EVALSHA sha 2 {t}a {t}b",
+            "SORT a",
+            "SINTERCARD 2 {t}a {t}b LIMIT 1",
+            "LPOS a b RANK 1 COUNT 2",
+        ] {
+            let resp = send_to_handler(&handler, args).await;
+            assert_eq!(resp, cluster_not_found, "{}", args);
+        }
+    }
+
+    async fn test_multi_int_cmd_with_mode(active_redirection: bool) {
+        let sent = Arc::new(parking_lot::Mutex::new(vec![]));
+        let sent_clone = sent.clone();
+        let handle_func = move |cmd: Vec<String>| match cmd[0].to_uppercase().as_str() {
+            "DEL" | "EXISTS" | "TOUCH" | "UNLINK" => {
+                let key_num = cmd.len() - 1;
+                sent_clone.lock().push(cmd.join(" "));
+                Resp::Integer(key_num.to_string().into_bytes())
+            }
+            _ => Resp::Simple(b"OK".to_vec()),
+        };
+        let mut config = gen_config();
+        config.active_redirection = active_redirection;
+        let handler = gen_testing_forward_handler_with_func(Arc::new(handle_func), config);
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        for cmd_name in &["DEL", "EXISTS", "TOUCH", "UNLINK"] {
+            sent.lock().clear();
+            // Forwarded as a whole in both modes.
+            let cmd = format!("{} {{t}}a {{t}}b {{t}}c", cmd_name);
+            let resp = send_to_handler(&handler, &cmd).await;
+            assert_eq!(resp, Resp::Integer(b"3".to_vec()), "{}", cmd);
+            assert_eq!(sent.lock().clone(), vec![cmd.clone()]);
+
+            sent.lock().clear();
+            let cmd = format!("{} a b c", cmd_name);
+            let resp = send_to_handler(&handler, &cmd).await;
+            if !active_redirection {
+                let cross_slot = Resp::Error(ERR_NOT_THE_SAME_SLOT.as_bytes().to_vec());
+                assert_eq!(resp, cross_slot, "{}", cmd);
+                assert!(sent.lock().is_empty());
+                continue;
+            }
+            assert_eq!(resp, Resp::Integer(b"3".to_vec()), "{}", cmd);
+            let mut sent_cmds = sent.lock().clone();
+            sent_cmds.sort();
+            let expected: Vec<String> = ["a", "b", "c"]
+                .iter()
+                .map(|key| format!("{} {}", cmd_name, key))
+                .collect();
+            assert_eq!(sent_cmds, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_int_cmd() {
+        test_multi_int_cmd_with_mode(false).await;
+    }
+
+    #[tokio::test]
+    async fn test_multi_int_cmd_split() {
+        test_multi_int_cmd_with_mode(true).await;
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, ok);
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let get_info = |handler| async move {
+            match send_to_handler(handler, "INFO").await {
+                Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+                other => panic!("unexpected reply {:?}", other),
+            }
+        };
+        assert!(get_info(&handler).await.contains("\r\nmaintenance:0\r\n"));
+
+        let resp = send_to_handler(&handler, "UMCTL MAINTENANCE ON").await;
+        assert_eq!(resp, ok);
+        let err = Resp::Error(ERR_MAINTENANCE.as_bytes().to_vec());
+        for cmd in &["GET key", "SET key value", "MGET a b", "EVAL script 0"] {
+            assert_eq!(send_to_handler(&handler, cmd).await, err, "{}", cmd);
+        }
+        // The admin commands still work.
+        let resp = send_to_handler(&handler, "PING").await;
+        assert_eq!(resp, Resp::Simple(PONG_REPLY.as_bytes().to_vec()));
+        assert!(matches!(
+            send_to_handler(&handler, "UMCTL INFO").await,
+            Resp::Arr(_)
+        ));
+        assert!(get_info(&handler).await.contains("\r\nmaintenance:1\r\n"));
+
+        let resp = send_to_handler(&handler, "UMCTL MAINTENANCE maybe").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        let resp = send_to_handler(&handler, "UMCTL MAINTENANCE").await;
+        assert!(matches!(resp, Resp::Error(_)));
+
+        let resp = send_to_handler(&handler, "umctl maintenance off").await;
+        assert_eq!(resp, ok);
+        assert_eq!(send_to_handler(&handler, "SET key value").await, ok);
+
+        let mut config = gen_config();
+        config.maintenance_error = "ERR custom maintenance".to_string();
+        let handler = gen_testing_forward_handler(config);
+        let resp = send_to_handler(&handler, "UMCTL MAINTENANCE ON").await;
+        assert_eq!(resp, ok);
+        let resp = send_to_handler(&handler, "GET key").await;
+        assert_eq!(resp, Resp::Error(b"ERR custom maintenance".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_expire_and_copy_cmds() {
+        let sent = Arc::new(parking_lot::Mutex::new(vec![]));
+        let sent_clone = sent.clone();
+        let handle_func = move |cmd: Vec<String>| {
+            sent_clone.lock().push(cmd.join(" "));
+            match cmd[0].to_uppercase().as_str() {
+                "TTL" | "PTTL" => Resp::Integer(b"-1".to_vec()),
+                "SET" => Resp::Simple(b"OK".to_vec()),
+                _ => Resp::Integer(b"1".to_vec()),
+            }
+        };
+        let handler = gen_testing_forward_handler_with_func(Arc::new(handle_func), gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        for (cmd, expected) in &[
+            ("EXPIRE a 10", Resp::Integer(b"1".to_vec())),
+            ("PEXPIRE a 10000 NX", Resp::Integer(b"1".to_vec())),
+            ("EXPIREAT a 1700000000", Resp::Integer(b"1".to_vec())),
+            ("PEXPIREAT a 1700000000000", Resp::Integer(b"1".to_vec())),
+            ("PERSIST a", Resp::Integer(b"1".to_vec())),
+            ("TTL a", Resp::Integer(b"-1".to_vec())),
+            ("PTTL a", Resp::Integer(b"-1".to_vec())),
+            ("COPY {a}1 {a}2", Resp::Integer(b"1".to_vec())),
+            ("COPY {a}1 {a}2 DB 0 REPLACE", Resp::Integer(b"1".to_vec())),
+        ] {
+            sent.lock().clear();
+            let resp = send_to_handler(&handler, cmd).await;
+            assert_eq!(&resp, expected, "{}", cmd);
+            assert_eq!(sent.lock().clone(), vec![cmd.to_string()]);
+        }
+
+        sent.lock().clear();
+        let resp = send_to_handler(&handler, "COPY a b").await;
+        assert_eq!(resp, Resp::Error(ERR_NOT_THE_SAME_SLOT.as_bytes().to_vec()));
+        for cmd in &["COPY {a}1 {a}2 DB 1", "COPY {a}1 {a}2 REPLACE db -1"] {
+            let resp = send_to_handler(&handler, cmd).await;
+            let err = b"ERR Copying to another database is not allowed in cluster mode".to_vec();
+            assert_eq!(resp, Resp::Error(err), "{}", cmd);
+        }
+        assert!(sent.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rename_command() {
+        let sent = Arc::new(parking_lot::Mutex::new(vec![]));
+        let sent_clone = sent.clone();
+        let handle_func = move |cmd: Vec<String>| {
+            sent_clone.lock().push(cmd.join(" "));
+            Resp::Simple(b"OK".to_vec())
+        };
+        let mut config = gen_config();
+        let renames: HashMap<String, String> =
+            vec![("FLUSHALL", ""), ("set", "my-set"), ("config", "my-config")]
+                .into_iter()
+                .map(|(original, alias)| (original.to_string(), alias.to_string()))
+                .collect();
+        config.rename_command = CommandRenames::new(renames).unwrap();
+        let handler = gen_testing_forward_handler_with_func(Arc::new(handle_func), config);
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, ok);
+        while let Resp::Error(_) = send_to_handler(&handler, "MY-SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        sent.lock().clear();
+        assert_eq!(send_to_handler(&handler, "my-set key value").await, ok);
+        assert_eq!(sent.lock().clone(), vec!["set key value".to_string()]);
+
+        for cmd in &[
+            "FLUSHALL",
+            "flushall async",
+            "SET key value",
+            "CONFIG GET maxclients",
+        ] {
+            match send_to_handler(&handler, cmd).await {
+                Resp::Error(err) => {
+                    assert!(err.starts_with(b"ERR unknown command"), "{}", cmd);
+                }
+                other => panic!("unexpected reply {:?}", other),
+            }
+        }
+        assert_eq!(sent.lock().len(), 1);
+
+        let resp = send_to_handler(&handler, "MY-CONFIG GET maxclients").await;
+        assert!(matches!(resp, Resp::Bulk(BulkStr::Str(_))));
+        let resp = send_to_handler(&handler, "MY-CONFIG GET rename_command").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        // The commands not renamed still work.
+        let resp = send_to_handler(&handler, "GET key").await;
+        assert_eq!(resp, ok);
+    }
+
+    #[tokio::test]
+    async fn test_lolwut_time_lastsave() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let resp = send_to_handler(&handler, "LOLWUT").await;
+        let expected = format!("undermoon ver. {}\n", UNDERMOON_VERSION);
+        assert_eq!(resp, Resp::Bulk(BulkStr::Str(expected.into_bytes())));
+
+        let parse_time = |resp: RespVec| -> (u64, u64) {
+            match resp {
+                Resp::Arr(Array::Arr(arr)) => match arr.as_slice() {
+                    [Resp::Bulk(BulkStr::Str(secs)), Resp::Bulk(BulkStr::Str(micros))] => (
+                        str::from_utf8(secs).unwrap().parse().unwrap(),
+                        str::from_utf8(micros).unwrap().parse().unwrap(),
+                    ),
+                    other => panic!("unexpected TIME reply {:?}", other),
+                },
+                other => panic!("unexpected TIME reply {:?}", other),
+            }
+        };
+        let (secs, micros) = parse_time(send_to_handler(&handler, "TIME").await);
+        assert!(secs >= now && secs <= now + 60);
+        assert!(micros < 1_000_000);
+        // TIME never goes backward.
+        let next = parse_time(send_to_handler(&handler, "TIME").await);
+        assert!(next >= (secs, micros));
+
+        let lastsave = match send_to_handler(&handler, "LASTSAVE").await {
+            Resp::Integer(n) => str::from_utf8(&n).unwrap().parse::<u64>().unwrap(),
+            other => panic!("unexpected LASTSAVE reply {:?}", other),
+        };
+        assert!(lastsave <= secs && lastsave + 60 >= now);
+        // The start time of the proxy doesn't change.
+        let resp = send_to_handler(&handler, "LASTSAVE").await;
+        assert_eq!(resp, Resp::Integer(lastsave.to_string().into_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_reject_unknown_command() {
+        let mut config = gen_config();
+        config.unknown_command_policy = UnknownCommandPolicy::Reject;
+        let handler = gen_testing_forward_handler(config);
+
+        let resp = send_to_handler(&handler, "NOSUCHCMD a b").await;
+        assert_eq!(
+            resp,
+            Resp::Error(
+                b"ERR unknown command `NOSUCHCMD`, with args beginning with: `a`, `b`, ".to_vec()
+            )
+        );
+
+        // A long argument is cut at 128 characters.
+        let long_arg = "x".repeat(200);
+        let resp = send_to_handler(&handler, &format!("NOSUCHCMD {} b", long_arg)).await;
+        let expected = format!(
+            "ERR unknown command `NOSUCHCMD`, with args beginning with: `{}`, ",
+            "x".repeat(128)
+        );
+        assert_eq!(resp, Resp::Error(expected.into_bytes()));
+
+        // The arguments after the first 128 characters are not shown
+        // and the last shown one is also cut.
+        let args = vec!["abc"; 100].join(" ");
+        let resp = send_to_handler(&handler, &format!("NOSUCHCMD {}", args)).await;
+        let expected = format!(
+            "ERR unknown command `NOSUCHCMD`, with args beginning with: {}`ab`, ",
+            "`abc`, ".repeat(18)
+        );
+        assert_eq!(resp, Resp::Error(expected.into_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_key_prefix() {
+        let sent = Arc::new(parking_lot::Mutex::new(vec![]));
+        let sent_clone = sent.clone();
+        let handle_func = move |cmd: Vec<String>| {
+            sent_clone.lock().push(cmd.join(" "));
+            match cmd[0].to_uppercase().as_str() {
+                "LMPOP" => Resp::Arr(Array::Arr(vec![
+                    Resp::Bulk(BulkStr::Str(cmd[2].clone().into_bytes())),
+                    Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(b"v1".to_vec()))])),
+                ])),
+                _ => Resp::Simple(b"OK".to_vec()),
+            }
+        };
+        let handler = gen_testing_forward_handler_with_func(Arc::new(handle_func), gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383 CONFIG key_prefix t1:",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        sent.lock().clear();
+        let resp = send_to_handler(&handler, "MSET {a}1 v1 {a}2 v2").await;
+        assert_eq!(resp, Resp::Simple(b"OK".to_vec()));
+        let resp = send_to_handler(&handler, "LMPOP 2 {a}1 {a}2 LEFT").await;
+        let expected = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"{a}1".to_vec())),
+            Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(b"v1".to_vec()))])),
+        ]));
+        assert_eq!(resp, expected);
+        assert_eq!(
+            sent.lock().clone(),
+            vec![
+                "SET t1:{a}1 v1".to_string(),
+                "SET t1:{a}2 v2".to_string(),
+                "LMPOP 2 t1:{a}1 t1:{a}2 LEFT".to_string(),
+            ]
+        );
+
+        sent.lock().clear();
+        for cmd in &["KEYS *", "SCAN 0", "DBSIZE", "FLUSHALL", "SORT {a}1 BY w_*"] {
+            let resp = send_to_handler(&handler, cmd).await;
+            assert!(matches!(resp, Resp::Error(_)), "{}", cmd);
+        }
+        assert!(sent.lock().is_empty());
+
+        let info = match send_to_handler(&handler, "INFO").await {
+            Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert!(info.ends_with("# Keyspace\r\n"));
+        assert!(sent.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_no_key_commands() {
+        let handle_func = |cmd: Vec<String>| match cmd[0].to_uppercase().as_str() {
+            "DBSIZE" => Resp::Integer(b"42".to_vec()),
+            "KEYS" => Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(b"k".to_vec()))])),
+            "RANDOMKEY" => Resp::Bulk(BulkStr::Str(b"k".to_vec())),
+            // Each backend is scanned in two rounds.
+            "SCAN" => {
+                let (next, key) = if cmd[1] == "0" {
+                    ("5", "a")
+                } else {
+                    ("0", "b")
+                };
+                Resp::Arr(Array::Arr(vec![
+                    Resp::Bulk(BulkStr::Str(next.as_bytes().to_vec())),
+                    Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+                        key.as_bytes().to_vec(),
+                    ))])),
+                ]))
+            }
+            _ => Resp::Simple(b"OK".to_vec()),
+        };
+        let handler = gen_testing_forward_handler_with_func(Arc::new(handle_func), gen_config());
+        let resp = send_to_handler(&handler, "DBSIZE").await;
+        assert_eq!(resp, Resp::Error(ERR_LOADING_META.as_bytes().to_vec()));
+
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-8191 127.0.0.1:6380 1 8192-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        while let Resp::Error(_) = send_to_handler(&handler, "DBSIZE").await {
+            // The backend connections are not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let resp = send_to_handler(&handler, "dbsize").await;
+        assert_eq!(resp, Resp::Integer(b"84".to_vec()));
+        let resp = send_to_handler(&handler, "KEYS *").await;
+        let k = Resp::Bulk(BulkStr::Str(b"k".to_vec()));
+        assert_eq!(resp, Resp::Arr(Array::Arr(vec![k.clone(), k.clone()])));
+        let resp = send_to_handler(&handler, "RANDOMKEY").await;
+        assert_eq!(resp, k);
+        let resp = send_to_handler(&handler, "FLUSHALL").await;
+        assert_eq!(resp, Resp::Simple(b"OK".to_vec()));
+
+        let mut cursor = "0".to_string();
+        let mut keys = vec![];
+        loop {
+            let resp = send_to_handler(&handler, &format!("SCAN {} COUNT 10", cursor)).await;
+            let mut elements = match resp {
+                Resp::Arr(Array::Arr(elements)) => elements,
+                other => panic!("unexpected reply {:?}", other),
+            };
+            match elements.pop() {
+                Some(Resp::Arr(Array::Arr(scanned))) => keys.extend(scanned),
+                other => panic!("unexpected keys {:?}", other),
+            }
+            cursor = match elements.pop() {
+                Some(Resp::Bulk(BulkStr::Str(next))) => String::from_utf8(next).unwrap(),
+                other => panic!("unexpected cursor {:?}", other),
+            };
+            if cursor == "0" {
+                break;
+            }
+        }
+        let a = Resp::Bulk(BulkStr::Str(b"a".to_vec()));
+        let b = Resp::Bulk(BulkStr::Str(b"b".to_vec()));
+        assert_eq!(keys, vec![a.clone(), b.clone(), a, b]);
+
+        for cmd in &["SCAN x", &format!("SCAN {}", 2u64 << 54)] {
+            let resp = send_to_handler(&handler, cmd).await;
+            assert_eq!(resp, Resp::Error(b"ERR invalid cursor".to_vec()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_command_name() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let resp = send_to_handler(&handler, "").await;
+        assert_eq!(resp, Resp::Error(ERR_INVALID_COMMAND.as_bytes().to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_client_commands() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let auth_state = AuthState::default();
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT ID").await;
+        assert_eq!(resp, Resp::Integer(b"233".to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT GETNAME").await;
+        assert_eq!(resp, Resp::Bulk(BulkStr::Nil));
+
+        let resp =
+            send_to_session(&handler, &auth_state, &client_info, "CLIENT SETNAME conn1").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT GETNAME").await;
+        assert_eq!(resp, Resp::Bulk(BulkStr::Str(b"conn1".to_vec())));
+
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT INFO").await;
+        let line = match resp {
+            Resp::Bulk(BulkStr::Str(line)) => String::from_utf8(line).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert!(line.starts_with("id=233 addr=127.0.0.1:7000 name=conn1 age="));
+        assert!(line.ends_with(" db=0 cmd=client\n"));
+
+        let resp =
+            send_to_session(&handler, &auth_state, &client_info, "CLIENT SETNAME a\nb").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT UNKNOWN").await;
+        assert!(matches!(resp, Resp::Error(_)));
+
+        // Accepted but ignored.
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT NO-EVICT on").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp =
+            send_to_session(&handler, &auth_state, &client_info, "CLIENT NO-TOUCH OFF").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT NO-EVICT").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        let resp =
+            send_to_session(&handler, &auth_state, &client_info, "CLIENT NO-TOUCH maybe").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_select() {
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+
+        let handler = gen_testing_forward_handler(gen_config());
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, ok);
+        assert_eq!(send_to_handler(&handler, "SELECT 0").await, ok);
+        let resp = send_to_handler(&handler, "SELECT 1").await;
+        assert_eq!(resp, Resp::Error(b"ERR DB index is out of range".to_vec()));
+        let resp = send_to_handler(&handler, "SELECT test_cluster").await;
+        assert_eq!(resp, Resp::Error(b"ERR invalid DB index".to_vec()));
+        let resp = send_to_handler(&handler, "SELECT").await;
+        assert!(matches!(resp, Resp::Error(_)));
+
+        let mut config = gen_config();
+        config.select_policy = SelectPolicy::ClusterName;
+        let handler = gen_testing_forward_handler(config);
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, ok);
+        assert_eq!(send_to_handler(&handler, "SELECT 0").await, ok);
+        assert_eq!(send_to_handler(&handler, "SELECT test_cluster").await, ok);
+        let resp = send_to_handler(&handler, "SELECT -1").await;
+        assert_eq!(resp, Resp::Error(b"ERR DB index is out of range".to_vec()));
+        let resp = send_to_handler(&handler, "SELECT other_cluster").await;
+        assert_eq!(resp, Resp::Error(ERR_CLUSTER_NOT_FOUND.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "SELECT bad:name").await;
+        assert_eq!(resp, Resp::Error(b"ERR invalid cluster name".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_client_pause() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        for args in &[
+            "CLIENT PAUSE",
+            "CLIENT PAUSE -1",
+            "CLIENT PAUSE 10 READ",
+            "CLIENT PAUSE 10 ALL more",
+            "CLIENT PAUSE 18446744073709551615",
+            "CLIENT PAUSE 18446744073709551616",
+        ] {
+            let resp = send_to_handler(&handler, args).await;
+            assert!(matches!(resp, Resp::Error(_)), "{}", args);
+        }
+        assert_eq!(handler.get_pause_remaining(), Duration::from_millis(0));
+
+        let pause = Duration::from_millis(200);
+        let resp = send_to_handler(&handler, "CLIENT PAUSE 200 WRITE").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let start = Instant::now();
+        let resp = send_to_handler(&handler, "GET key").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        assert!(start.elapsed() < pause);
+
+        // The following commands of the session with a paused command are also deferred.
+        let auth_state = AuthState::default();
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+        let (set_cmd_ctx, set_receiver) = gen_cmd_ctx("SET key value");
+        let set_fut = handler.handle_cmd_ctx(set_cmd_ctx, set_receiver, &auth_state, &client_info);
+        let (get_cmd_ctx, get_receiver) = gen_cmd_ctx("GET key");
+        let get_fut = handler.handle_cmd_ctx(get_cmd_ctx, get_receiver, &auth_state, &client_info);
+        assert!(matches!(get_fut, futures::future::Either::Right(_)));
+        assert!(handler.get_pause_remaining() > Duration::from_millis(0));
+        assert!(set_fut.await.is_ok());
+        assert!(start.elapsed() >= pause);
+        assert!(get_fut.await.is_ok());
+
+        let resp = send_to_handler(&handler, "CLIENT PAUSE 100 ALL").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let start = Instant::now();
+        let resp = send_to_handler(&handler, "GET key").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(handler.get_pause_remaining(), Duration::from_millis(0));
+
+        let resp = send_to_handler(&handler, "CLIENT PAUSE 60000 ALL").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let (get_cmd_ctx, get_receiver) = gen_cmd_ctx("GET key");
+        let get_fut = handler.handle_cmd_ctx(get_cmd_ctx, get_receiver, &auth_state, &client_info);
+        let resp = send_to_handler(&handler, "CLIENT UNPAUSE").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        assert_eq!(handler.get_pause_remaining(), Duration::from_millis(0));
+        let resp = tokio::time::timeout(Duration::from_secs(3), get_fut)
+            .await
+            .expect("the deferred command should be dispatched after CLIENT UNPAUSE");
+        assert!(resp.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_client_pause_noauth() {
+        let noauth = Resp::Error(ERR_NOAUTH.as_bytes().to_vec());
+        let client_info = ClientInfo::new(233, "127.0.0.1:7000".to_string());
+
+        // The data password is always required.
+        let mut config = gen_config();
+        config.password = Some("pwd".to_string());
+        let handler = gen_testing_forward_handler(config);
+        let auth_state = AuthState::default();
+        for args in &["CLIENT PAUSE 100", "CLIENT UNPAUSE"] {
+            let resp = send_to_session(&handler, &auth_state, &client_info, args).await;
+            assert_eq!(resp, noauth, "{}", args);
+        }
+        assert_eq!(handler.get_pause_remaining(), Duration::from_millis(0));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "AUTH pwd").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT PAUSE 100").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT UNPAUSE").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+
+        // The admin password is required when the admin commands need the authentication.
+        let mut config = gen_config();
+        config.auth_admin_commands = true;
+        config.admin_password = Some("admin".to_string());
+        let handler = gen_testing_forward_handler(config);
+        let auth_state = AuthState::default();
+        for args in &["CLIENT PAUSE 100", "CLIENT UNPAUSE"] {
+            let resp = send_to_session(&handler, &auth_state, &client_info, args).await;
+            assert_eq!(resp, noauth, "{}", args);
+        }
+        assert_eq!(handler.get_pause_remaining(), Duration::from_millis(0));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "AUTH admin").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_session(&handler, &auth_state, &client_info, "CLIENT PAUSE 100").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+    }
+
     fn gen_set_command(key: BinSafeStr) -> (CmdCtx, CmdReplyReceiver) {
         let resp = RespPacket::Data(Resp::Arr(Array::Arr(vec![
             Resp::Bulk(BulkStr::Str(b"SET".to_vec())),
@@ -265,6 +1401,264 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_forward_handler_with_manager() {
+        let manager = gen_testing_manager(Arc::new(always_ok), gen_config());
+        manager.set_meta(gen_proxy_cluster_meta()).unwrap();
+        wait_backend_ready(&manager).await;
+
+        // The metadata and backends of the manager are used without UMCTL SETCLUSTER.
+        let handler = gen_testing_forward_handler_with_manager(manager, Arc::new(gen_config()));
+        let resp = send_to_handler(&handler, "SET key value").await;
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+        let resp = send_to_handler(&handler, "UMCTL GETEPOCH").await;
+        assert_eq!(resp, Resp::Integer(b"1".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_load_init_meta() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let content = format!(
+            "# comment\n\nUMCTL SETCLUSTER {} 2 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383\n\
+            UMCTL SETREPL 2 NOFLAGS master test_cluster 127.0.0.1:6379 1 127.0.0.1:6380 127.0.0.1:7000\n",
+            SET_CLUSTER_API_VERSION
+        );
+        load_init_meta(&handler, &content).await.unwrap();
+        let resp = send_to_handler(&handler, "UMCTL GETEPOCH").await;
+        assert_eq!(resp, Resp::Integer(b"2".to_vec()));
+        let resp = send_to_handler(&handler, "UMCTL READY").await;
+        assert_eq!(resp, Resp::Integer(b"1".to_vec()));
+
+        // The epoch is checked just like the coordinator sending it.
+        let err = load_init_meta(&handler, &content).await.unwrap_err();
+        assert!(err.starts_with(OLD_EPOCH_REPLY));
+        let err = load_init_meta(&handler, "SET key value").await.unwrap_err();
+        assert!(err.starts_with("unsupported command"));
+    }
+
+    #[tokio::test]
+    async fn test_last_meta_epoch() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let get_info = |handler| async move {
+            match send_to_handler(handler, "INFO").await {
+                Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+                other => panic!("unexpected reply {:?}", other),
+            }
+        };
+        let get_detail = |handler| async move {
+            match send_to_handler(handler, "UMCTL GETEPOCH DETAIL").await {
+                Resp::Arr(Array::Arr(fields)) => fields
+                    .into_iter()
+                    .map(|field| match field {
+                        Resp::Bulk(BulkStr::Str(s)) => String::from_utf8(s).unwrap(),
+                        other => panic!("unexpected field {:?}", other),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                other => panic!("unexpected reply {:?}", other),
+            }
+        };
+
+        let expected = concat!(
+            "\r\n# Config\r\n",
+            "last_setcluster_epoch:0\r\nlast_setcluster_age_seconds:-1\r\n",
+            "last_setrepl_epoch:0\r\nlast_setrepl_age_seconds:-1\r\n",
+        );
+        let info = get_info(&handler).await;
+        assert!(info.contains(expected), "{}", info);
+
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 3 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+        assert_eq!(send_to_handler(&handler, &set_cluster).await, ok);
+        let set_repl = "UMCTL SETREPL 4 NOFLAGS master test_cluster 127.0.0.1:6379 0";
+        assert_eq!(send_to_handler(&handler, set_repl).await, ok);
+        // The failed updates are not recorded.
+        let old_set_cluster = set_cluster.replace(" 3 ", " 2 ");
+        let resp = send_to_handler(&handler, &old_set_cluster).await;
+        assert!(matches!(resp, Resp::Error(_)));
+
+        let expected = concat!(
+            "\r\n# Config\r\n",
+            "last_setcluster_epoch:3\r\nlast_setcluster_age_seconds:0\r\n",
+            "last_setrepl_epoch:4\r\nlast_setrepl_age_seconds:0\r\n",
+        );
+        let info = get_info(&handler).await;
+        assert!(info.contains(expected), "{}", info);
+        assert_eq!(
+            get_detail(&handler).await,
+            "epoch 3 last_setcluster_epoch 3 last_setcluster_age_seconds 0 \
+            last_setrepl_epoch 4 last_setrepl_age_seconds 0"
+        );
+
+        let resp = send_to_handler(&handler, "UMCTL GETEPOCH").await;
+        assert_eq!(resp, Resp::Integer(b"3".to_vec()));
+        let resp = send_to_handler(&handler, "UMCTL GETEPOCH ALL").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_set_cluster_async() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let get_token = |resp| match resp {
+            Resp::Integer(token) => String::from_utf8(token).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        let wait_for_status = |handler, token: String| async move {
+            let status_cmd = format!("UMCTL SETCLUSTER STATUS {}", token);
+            loop {
+                match send_to_handler(handler, &status_cmd).await {
+                    Resp::Simple(s) if s == b"PENDING" => {
+                        tokio::time::sleep(Duration::from_millis(1)).await
+                    }
+                    resp => return resp,
+                }
+            }
+        };
+        let set_cluster = |epoch: u64| {
+            format!(
+                "UMCTL SETCLUSTER ASYNC {} {} NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+                SET_CLUSTER_API_VERSION, epoch
+            )
+        };
+
+        let token1 = get_token(send_to_handler(&handler, &set_cluster(3)).await);
+        let token2 = get_token(send_to_handler(&handler, &set_cluster(2)).await);
+        let token3 = get_token(send_to_handler(&handler, &set_cluster(4)).await);
+        // Applied in order so only the older epoch fails.
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+        assert_eq!(wait_for_status(&handler, token1).await, ok);
+        assert_eq!(
+            wait_for_status(&handler, token2).await,
+            Resp::Error(OLD_EPOCH_REPLY.as_bytes().to_vec())
+        );
+        assert_eq!(wait_for_status(&handler, token3).await, ok);
+        let resp = send_to_handler(&handler, "UMCTL GETEPOCH").await;
+        assert_eq!(resp, Resp::Integer(b"4".to_vec()));
+
+        let resp = send_to_handler(&handler, "UMCTL SETCLUSTER STATUS 12345").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        let resp = send_to_handler(&handler, "UMCTL SETCLUSTER STATUS token").await;
+        assert!(matches!(resp, Resp::Error(_)));
+        // Invalid metadata is rejected without a token.
+        let resp = send_to_handler(&handler, "UMCTL SETCLUSTER ASYNC v0 5").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_umctl_invalid_meta_reply() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"Failed to parse args: invalid address: 127.0.0.1".to_vec())
+        );
+        let resp = send_to_handler(&handler, "UMCTL SETREPL abc NOFLAGS").await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"Invalid arguments: invalid epoch".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_umctl_oversized_meta() {
+        let mut config = gen_config();
+        config.umctl_max_meta_args = 100;
+        config.umctl_max_meta_bytes = 1024;
+        let handler = gen_testing_forward_handler(config);
+
+        let nodes: Vec<String> = (0..100)
+            .map(|i| format!("127.0.0.1:{} 1 {}-{}", 6000 + i, i, i))
+            .collect();
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster {}",
+            SET_CLUSTER_API_VERSION,
+            nodes.join(" ")
+        );
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR metadata payload too large: 306 elements exceeds 100".to_vec())
+        );
+
+        let set_repl = format!("UMCTL SETREPL 1 NOFLAGS {}", "a".repeat(1024));
+        let resp = send_to_handler(&handler, &set_repl).await;
+        assert_eq!(
+            resp,
+            Resp::Error(b"ERR metadata payload too large: 1044 bytes exceeds 1024".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_umctl_mgr_status() {
+        let manager = gen_testing_manager(Arc::new(handle_migration_command), gen_config());
+        manager.set_meta(gen_migration_cluster_meta(false)).unwrap();
+        let handler = gen_testing_forward_handler_with_manager(manager, Arc::new(gen_config()));
+
+        let migration_meta = "127.0.0.1:5299 127.0.0.1:6379 127.0.0.1:6000 127.0.0.1:7000";
+        let cmd = format!(
+            "UMCTL MGRSTATUS test_cluster migrating 1 8001-16383 233 {}",
+            migration_meta
+        );
+        let resp = send_to_handler(&handler, &cmd).await;
+        assert_eq!(
+            resp,
+            Resp::Arr(Array::Arr(vec![
+                Resp::Bulk(BulkStr::Str(b"switching".to_vec())),
+                Resp::Bulk(BulkStr::Str(b"1 8001-16383".to_vec())),
+            ]))
+        );
+
+        let cmd = format!(
+            "UMCTL MGRSTATUS test_cluster importing 1 0-8000 233 {}",
+            migration_meta
+        );
+        let resp = send_to_handler(&handler, &cmd).await;
+        assert_eq!(
+            resp,
+            Resp::Arr(Array::Arr(vec![
+                Resp::Bulk(BulkStr::Str(b"failed".to_vec())),
+                Resp::Bulk(BulkStr::Str(b"1 0-8000".to_vec())),
+            ]))
+        );
+
+        let cmd = format!(
+            "UMCTL MGRSTATUS test_cluster migrating 1 8001-16383 234 {}",
+            migration_meta
+        );
+        let resp = send_to_handler(&handler, &cmd).await;
+        assert_eq!(
+            resp,
+            Resp::Error(NOT_READY_FOR_SWITCHING_REPLY.as_bytes().to_vec())
+        );
+
+        let resp = send_to_handler(&handler, "UMCTL MGRSTATUS test_cluster").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_umctl_routes() {
+        let manager = gen_testing_manager(Arc::new(always_ok), gen_config());
+        manager.set_meta(gen_proxy_cluster_meta()).unwrap();
+        let handler = gen_testing_forward_handler_with_manager(manager, Arc::new(gen_config()));
+
+        let expected = Resp::Arr(Array::Arr(vec![Resp::Bulk(BulkStr::Str(
+            b"0-16383 -> 127.0.0.1:6379 local".to_vec(),
+        ))]));
+        let resp = send_to_handler(&handler, "UMCTL ROUTES").await;
+        assert_eq!(resp, expected);
+        let resp = send_to_handler(&handler, "UMCTL ROUTES test_cluster").await;
+        assert_eq!(resp, expected);
+        let resp = send_to_handler(&handler, "UMCTL ROUTES other_cluster").await;
+        assert!(matches!(resp, Resp::Error(_)));
+    }
+
     #[tokio::test]
     async fn test_data_command() {
         let meta = gen_proxy_cluster_meta();
@@ -279,6 +1673,143 @@ mod tests {
         assert_ok_reply(reply_receiver).await;
     }
 
+    #[tokio::test]
+    async fn test_dbsize() {
+        let meta = gen_proxy_cluster_meta();
+        let handle_func = |cmd: Vec<String>| match cmd[0].to_uppercase().as_str() {
+            "DBSIZE" => Resp::Integer(b"42".to_vec()),
+            _ => Resp::Simple(b"OK".to_vec()),
+        };
+        let manager = gen_testing_manager(Arc::new(handle_func), gen_config());
+        assert_eq!(manager.get_dbsize().await, Ok(0));
+
+        manager.set_meta(meta).unwrap();
+        wait_backend_ready(&manager).await;
+        assert_eq!(manager.get_dbsize().await, Ok(42));
+
+        let manager = gen_testing_manager(Arc::new(always_ok), gen_config());
+        manager.set_meta(gen_proxy_cluster_meta()).unwrap();
+        wait_backend_ready(&manager).await;
+        assert!(manager.get_dbsize().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redirection_sender() {
+        let manager = gen_testing_manager(Arc::new(always_ok), gen_config());
+
+        // Not any peer in the metadata.
+        let target = parse_redirection(b"MOVED 866 127.0.0.1:7001").unwrap();
+        let sender = manager.create_redirection_sender(&target);
+        let (cmd_ctx, reply_receiver) = gen_set_command(b"key".to_vec());
+        sender.send(cmd_ctx).unwrap();
+
+        assert_ok_reply(reply_receiver).await;
+    }
+
+    #[tokio::test]
+    async fn test_backend_warmup() {
+        let meta = gen_proxy_cluster_meta();
+        let mut config = gen_config();
+        config.backend_warmup = true;
+        let manager = gen_testing_manager(Arc::new(always_ok), config);
+
+        manager.set_meta(meta).unwrap();
+
+        let address = "127.0.0.1:6379";
+        let stats = manager.get_backend_warmup_stats().clone();
+        while stats.get(address) == (0, 0) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(stats.get(address), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_cluster_reset() {
+        let manager = gen_testing_manager(Arc::new(handle_migration_command), gen_config());
+
+        manager.set_meta(gen_migration_cluster_meta(true)).unwrap();
+        assert!(!manager.get_cluster().is_empty());
+        assert!(resp_contains(&manager.info(), "test_cluster"));
+
+        manager.reset(false);
+        assert!(manager.get_cluster().is_empty());
+        assert!(!resp_contains(&manager.info(), "test_cluster"));
+        // Soft reset keeps the epoch.
+        assert!(manager.set_meta(gen_migration_cluster_meta(true)).is_err());
+
+        manager.reset(true);
+        manager.set_meta(gen_migration_cluster_meta(true)).unwrap();
+        assert!(!manager.get_cluster().is_empty());
+    }
+
+    // The slots are local in the even epochs and owned by a peer in the odd ones.
+    fn gen_alternating_cluster_meta(epoch: u64) -> ProxyClusterMeta {
+        let args = match epoch % 2 {
+            0 => format!(
+                "{} {} NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+                SET_CLUSTER_API_VERSION, epoch
+            ),
+            _ => format!(
+                "{} {} NOFLAGS test_cluster PEER 127.0.0.1:7001 1 0-16383",
+                SET_CLUSTER_API_VERSION, epoch
+            ),
+        };
+        let mut iter = args.split(' ').map(|s| s.to_string()).peekable();
+        let (meta, extended_args) = ProxyClusterMeta::parse(&mut iter).unwrap();
+        assert!(extended_args.is_ok());
+        meta
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_set_meta_racing_with_data_commands() {
+        const LAST_EPOCH: u64 = 300;
+
+        let manager = Arc::new(gen_testing_manager(Arc::new(always_ok), gen_config()));
+        manager.set_meta(gen_alternating_cluster_meta(2)).unwrap();
+        wait_backend_ready(&manager).await;
+
+        let setter = {
+            let manager = manager.clone();
+            let runtime = tokio::runtime::Handle::current();
+            std::thread::spawn(move || {
+                let _guard = runtime.enter();
+                for epoch in 3..=LAST_EPOCH {
+                    manager
+                        .set_meta(gen_alternating_cluster_meta(epoch))
+                        .unwrap();
+                }
+            })
+        };
+
+        while !setter.is_finished() {
+            // The epoch always matches the topology in the same snapshot.
+            let meta_map = manager.get_meta_map().load();
+            let has_local_slots = !meta_map.get_cluster_map().get_local_nodes().is_empty();
+            let expected_parity = if has_local_slots { 0 } else { 1 };
+            assert_eq!(meta_map.get_epoch() % 2, expected_parity);
+
+            let (cmd_ctx, reply_receiver) = gen_set_command(b"key".to_vec());
+            manager.send(cmd_ctx);
+            let (_, response, _) = reply_receiver.await.unwrap().into_inner();
+            match response.into_resp_vec() {
+                Resp::Simple(s) => assert_eq!(s, OK_REPLY.as_bytes()),
+                Resp::Error(err) => {
+                    let err = str::from_utf8(&err).unwrap();
+                    // The connections to the backend may be re-established in the new epoch.
+                    assert!(
+                        err.starts_with("MOVED") && err.ends_with("127.0.0.1:7001")
+                            || err.starts_with(ERR_BACKEND_CONNECTION),
+                        "unexpected error {}",
+                        err
+                    );
+                }
+                other => panic!("unexpected reply {:?}", other),
+            }
+        }
+        setter.join().unwrap();
+        assert_eq!(manager.get_epoch(), LAST_EPOCH);
+    }
+
     fn gen_migration_cluster_meta(is_source_proxy: bool) -> ProxyClusterMeta {
         gen_migration_cluster_meta_helper(is_source_proxy, 233, "127.0.0.1:5299", "127.0.0.1:6000")
     }
@@ -519,6 +2050,75 @@ mod tests {
         check_dst_request(&dst_manager).await;
         assert!(src_manager.get_finished_migration_tasks().is_empty());
         assert!(dst_manager.get_finished_migration_tasks().is_empty());
+        let task_meta = gen_switch_arg().meta;
+        assert_eq!(
+            src_manager.get_migration_task_status(&task_meta).unwrap(),
+            MigrationTaskStatus::Source
+        );
+        assert_eq!(
+            dst_manager.get_migration_task_status(&task_meta).unwrap(),
+            MigrationTaskStatus::Switching
+        );
+    }
+
+    // The destination never acknowledges PRESWITCH so the source is forced to go ahead
+    // after `migration_max_blocking_time`.
+    #[tokio::test]
+    async fn test_manager_migration_with_unconfirmed_switch() {
+        let precheck_ready = Arc::new(AtomicBool::new(false));
+        let ready = precheck_ready.clone();
+        let handle_func = move |cmd: Vec<String>| {
+            if cmd[0].eq_ignore_ascii_case("UMCTL") {
+                let sub_cmd = cmd[1].to_uppercase();
+                if sub_cmd == "PRESWITCH"
+                    || (sub_cmd == "PRECHECK" && !ready.load(Ordering::SeqCst))
+                {
+                    return Resp::Error(NOT_READY_FOR_SWITCHING_REPLY.as_bytes().to_vec());
+                }
+            }
+            handle_migration_command(cmd)
+        };
+        let src_manager = gen_testing_manager(Arc::new(handle_func), gen_config());
+        let s = format!("{version} 233 NOFLAGS test_cluster \
+            127.0.0.1:6379 1 0-8000 \
+            127.0.0.1:6379 migrating 1 8001-16383 233 127.0.0.1:5299 127.0.0.1:6379 127.0.0.1:6000 127.0.0.1:7000 \
+            PEER \
+            127.0.0.1:6000 importing 1 8001-16383 233 127.0.0.1:5299 127.0.0.1:6379 127.0.0.1:6000 127.0.0.1:7000 \
+            CONFIG migration_max_blocking_time 10",
+            version=SET_CLUSTER_API_VERSION);
+        let mut iter = s.split(' ').map(|s| s.to_string()).peekable();
+        let (meta, extended_args) = ProxyClusterMeta::parse(&mut iter).unwrap();
+        assert!(extended_args.is_ok());
+        src_manager.set_meta(meta).unwrap();
+        wait_backend_ready(&src_manager).await;
+
+        // The slot of key `a` is 15495.
+        // Before the switch, the source still owns the migrating slots.
+        let (cmd_ctx, reply_receiver) = gen_set_command(b"a".to_vec());
+        src_manager.send(cmd_ctx);
+        let resp = reply_receiver.await.unwrap().into_resp_vec();
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
+
+        precheck_ready.store(true, Ordering::SeqCst);
+        loop {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let info = src_manager.info();
+            if resp_contains(&info, MigrationState::Scanning.to_string().as_str()) {
+                break;
+            }
+        }
+
+        // The keys could have been moved to the destination which only accepts them after ASKING.
+        let (cmd_ctx, reply_receiver) = gen_set_command(b"a".to_vec());
+        src_manager.send(cmd_ctx);
+        let resp = reply_receiver.await.unwrap().into_resp_vec();
+        assert_eq!(resp, Resp::Error(b"ASK 15495 127.0.0.1:6000".to_vec()));
+
+        // The slot of key `b` is 3300 which is not migrating.
+        let (cmd_ctx, reply_receiver) = gen_set_command(b"b".to_vec());
+        src_manager.send(cmd_ctx);
+        let resp = reply_receiver.await.unwrap().into_resp_vec();
+        assert_eq!(resp, Resp::Simple(OK_REPLY.as_bytes().to_vec()));
     }
 
     #[tokio::test]
@@ -543,6 +2143,12 @@ mod tests {
         wait_backend_ready(&dst_manager).await;
 
         let switch_arg = gen_switch_arg();
+        let task_meta = switch_arg.meta.clone();
+        assert_eq!(
+            dst_manager.get_migration_task_status(&task_meta).unwrap(),
+            MigrationTaskStatus::Switching
+        );
+
         dst_manager
             .handle_switch(switch_arg.clone(), MgrSubCmd::PreCheck)
             .unwrap();
@@ -572,6 +2178,14 @@ mod tests {
 
         assert_eq!(src_manager.get_finished_migration_tasks().len(), 1);
         assert_eq!(dst_manager.get_finished_migration_tasks().len(), 1);
+        assert_eq!(
+            src_manager.get_migration_task_status(&task_meta).unwrap(),
+            MigrationTaskStatus::Committed
+        );
+        assert_eq!(
+            dst_manager.get_migration_task_status(&task_meta).unwrap(),
+            MigrationTaskStatus::Committed
+        );
 
         // dst will be set first.
         dst_manager
@@ -585,6 +2199,11 @@ mod tests {
         check_dst_request(&dst_manager).await;
         assert!(src_manager.get_finished_migration_tasks().is_empty());
         assert!(dst_manager.get_finished_migration_tasks().is_empty());
+        // The task is dropped along with the migrating slot range.
+        assert_eq!(
+            dst_manager.get_migration_task_status(&task_meta).unwrap(),
+            MigrationTaskStatus::Failed
+        );
     }
 
     #[tokio::test]
@@ -871,6 +2490,163 @@ mod tests {
         assert_eq!(s, ERR_TOO_MANY_REDIRECTIONS.as_bytes());
     }
 
+    #[tokio::test]
+    async fn test_role() {
+        let handler = gen_testing_forward_handler(gen_config());
+        let resp = send_to_handler(&handler, "ROLE").await;
+        let expected = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"master".to_vec())),
+            Resp::Integer(b"0".to_vec()),
+            Resp::Arr(Array::Arr(vec![])),
+        ]));
+        assert_eq!(resp, expected);
+
+        let config = Arc::new(gen_config());
+        let manager = gen_testing_manager_with_shared_config(Arc::new(always_ok), config.clone());
+        let mut meta = gen_repl_meta_with_all_replicas();
+        meta.replicas[0].masters.push(ReplPeer {
+            node_address: "127.0.0.1:6379".to_string(),
+            proxy_address: "127.0.0.1:5299".to_string(),
+        });
+        manager.update_replicators(meta).unwrap();
+        let handler = gen_testing_forward_handler_with_manager(manager, config);
+        let resp = send_to_handler(&handler, "ROLE").await;
+        let expected = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"slave".to_vec())),
+            Resp::Bulk(BulkStr::Str(b"127.0.0.1".to_vec())),
+            Resp::Integer(b"5299".to_vec()),
+            Resp::Bulk(BulkStr::Str(b"connected".to_vec())),
+            Resp::Integer(b"0".to_vec()),
+        ]));
+        assert_eq!(resp, expected);
+    }
+
+    #[tokio::test]
+    async fn test_info_replication() {
+        let config = Arc::new(gen_config());
+        let manager = gen_testing_manager_with_shared_config(Arc::new(always_ok), config.clone());
+        let mut meta = gen_repl_meta_with_all_masters();
+        meta.masters[0].replicas = vec![
+            ReplPeer {
+                node_address: "127.0.0.1:7001".to_string(),
+                proxy_address: "127.0.0.1:5300".to_string(),
+            },
+            ReplPeer {
+                node_address: "127.0.0.2:7001".to_string(),
+                proxy_address: "127.0.0.2:5300".to_string(),
+            },
+        ];
+        manager.update_replicators(meta).unwrap();
+        let handler = gen_testing_forward_handler_with_manager(manager, config);
+
+        let info = match send_to_handler(&handler, "INFO").await {
+            Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        let expected = concat!(
+            "\r\n# Replication\r\n",
+            "role:master\r\n",
+            "connected_slaves:2\r\n",
+            "slave0:ip=127.0.0.1,port=5300,state=online,offset=0,lag=0\r\n",
+            "slave1:ip=127.0.0.2,port=5300,state=online,offset=0,lag=0\r\n",
+            "master_repl_offset:0\r\n",
+            "\r\n# Keyspace\r\n",
+        );
+        assert!(info.contains(expected), "{}", info);
+    }
+
+    // SETCLUSTER could arrive before SETREPL during the initial bring-up.
+    #[tokio::test]
+    async fn test_setcluster_before_setrepl() {
+        let handle_func = |cmd: Vec<String>| match cmd[0].to_uppercase().as_str() {
+            "GET" => Resp::Bulk(BulkStr::Str(b"value".to_vec())),
+            _ => Resp::Simple(b"OK".to_vec()),
+        };
+        let handler = gen_testing_forward_handler_with_func(Arc::new(handle_func), gen_config());
+        let set_cluster = format!(
+            "UMCTL SETCLUSTER {} 1 NOFLAGS test_cluster 127.0.0.1:6379 1 0-16383",
+            SET_CLUSTER_API_VERSION
+        );
+        let ok = Resp::Simple(OK_REPLY.as_bytes().to_vec());
+        let resp = send_to_handler(&handler, &set_cluster).await;
+        assert_eq!(resp, ok);
+        while let Resp::Error(_) = send_to_handler(&handler, "SET key value").await {
+            // The backend connection is not ready.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let value = Resp::Bulk(BulkStr::Str(b"value".to_vec()));
+        assert_eq!(send_to_handler(&handler, "GET key").await, value);
+        let resp = send_to_handler(&handler, "UMCTL READY").await;
+        assert_eq!(resp, Resp::Integer(b"1".to_vec()));
+
+        // Without the replicator metadata, the proxy is a master without replicas.
+        let resp = send_to_handler(&handler, "UMCTL INFOREPL").await;
+        assert_eq!(resp, Resp::Arr(Array::Arr(vec![])));
+        let resp = send_to_handler(&handler, "ROLE").await;
+        let expected = Resp::Arr(Array::Arr(vec![
+            Resp::Bulk(BulkStr::Str(b"master".to_vec())),
+            Resp::Integer(b"0".to_vec()),
+            Resp::Arr(Array::Arr(vec![])),
+        ]));
+        assert_eq!(resp, expected);
+        let info = match send_to_handler(&handler, "INFO").await {
+            Resp::Bulk(BulkStr::Str(info)) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        let expected =
+            "# Replication\r\nrole:master\r\nconnected_slaves:0\r\nmaster_repl_offset:0\r\n";
+        assert!(info.contains(expected), "{}", info);
+        assert!(matches!(
+            send_to_handler(&handler, "UMCTL INFO").await,
+            Resp::Arr(_)
+        ));
+
+        let set_repl = "UMCTL SETREPL 2 NOFLAGS master test_cluster 127.0.0.1:6379 1 127.0.0.1:6380 127.0.0.1:7000";
+        assert_eq!(send_to_handler(&handler, set_repl).await, ok);
+        assert_eq!(send_to_handler(&handler, "GET key").await, value);
+        let resp = send_to_handler(&handler, "UMCTL INFOREPL").await;
+        assert!(matches!(resp, Resp::Arr(Array::Arr(reports)) if reports.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_slot_ownership_events() {
+        let manager = gen_testing_manager(Arc::new(always_ok), gen_config());
+        let mut events = manager.subscribe_slot_changes();
+
+        let gen_meta = |epoch: u64, slots: &str| {
+            let args = format!(
+                "{} {} NOFLAGS test_cluster 127.0.0.1:6379 {}",
+                SET_CLUSTER_API_VERSION, epoch, slots
+            );
+            let mut iter = args.split(' ').map(|s| s.to_string()).peekable();
+            ProxyClusterMeta::parse(&mut iter).unwrap().0
+        };
+        let range_list = |s: &str| RangeList::try_from(s).unwrap();
+
+        manager.set_meta(gen_meta(1, "1 0-8191")).unwrap();
+        let event = events.next().await.unwrap();
+        assert_eq!(event.epoch, 1);
+        assert_eq!(event.added, range_list("1 0-8191"));
+        assert_eq!(event.removed, range_list("0"));
+
+        // The unchanged slots are not published.
+        manager.set_meta(gen_meta(2, "1 0-8191")).unwrap();
+        manager
+            .set_meta(gen_meta(3, "2 100-200 8192-9000"))
+            .unwrap();
+        let event = events.next().await.unwrap();
+        assert_eq!(event.epoch, 3);
+        assert_eq!(event.added, range_list("1 8192-9000"));
+        assert_eq!(event.removed, range_list("2 0-99 201-8191"));
+
+        manager.reset(true);
+        let event = events.next().await.unwrap();
+        assert_eq!(event.epoch, 0);
+        assert_eq!(event.added, range_list("0"));
+        assert_eq!(event.removed, range_list("2 100-200 8192-9000"));
+    }
+
     #[tokio::test]
     async fn test_readiness_stable_slots() {
         let manager = gen_testing_manager(Arc::new(always_ok), gen_config());
@@ -0,0 +1,39 @@
+extern crate undermoon;
+
+#[cfg(test)]
+mod tests {
+    use undermoon::common::utils::{generate_slot, same_slot, set_slot_hasher, SlotHasher};
+    use undermoon::protocol::{Array, BulkStr, Resp, RespPacket};
+    use undermoon::proxy::command::Command;
+
+    // Maps the keys to the slots by the key length for deterministic tests.
+    struct KeyLenSlotHasher;
+
+    impl SlotHasher for KeyLenSlotHasher {
+        fn hash_slot(&self, key: &[u8]) -> usize {
+            key.len()
+        }
+    }
+
+    fn gen_cmd(args: &[&str]) -> Command {
+        let resp = Resp::Arr(Array::Arr(
+            args.iter()
+                .map(|arg| Resp::Bulk(BulkStr::Str(arg.as_bytes().to_vec())))
+                .collect(),
+        ));
+        Command::new(Box::new(RespPacket::from_resp_vec(resp)))
+    }
+
+    // Lives in its own test binary since the slot hasher is global.
+    #[test]
+    fn test_custom_slot_hasher() {
+        assert_eq!(generate_slot(b"foo"), 12182);
+
+        set_slot_hasher(Box::new(KeyLenSlotHasher));
+        assert_eq!(generate_slot(b"foo"), 3);
+        assert!(same_slot(
+            vec![b"foo".as_ref(), b"bar".as_ref()].into_iter()
+        ));
+        assert_eq!(gen_cmd(&["GET", "somekey"]).get_slot(), Some(7));
+    }
+}